@@ -113,11 +113,16 @@ class AuthService {
     }
 
     try {
-      const response = await apiClient.post<{ access_token: string }>('/auth/refresh', {
-        refresh_token: this.refreshToken,
-      })
+      // Refresh tokens are single-use: keep the replacement that comes back
+      const response = await apiClient.post<{ access_token: string; refresh_token: string }>(
+        '/auth/refresh',
+        {
+          refresh_token: this.refreshToken,
+        },
+      )
 
       this.accessToken = response.access_token
+      this.refreshToken = response.refresh_token
       this.saveTokensToStorage()
       return true
     } catch {
@@ -0,0 +1,54 @@
+-- One row per successful pronunciation evaluation
+CREATE TABLE speech_attempts (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    provider TEXT NOT NULL,
+    ref_text TEXT NOT NULL,
+    ref_pinyin TEXT,
+    lang TEXT NOT NULL,
+    core TEXT NOT NULL,
+    duration_ms INTEGER,
+    overall_score REAL,
+    pronunciation_score REAL,
+    fluency_score REAL,
+    tone_score REAL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+-- Word-level breakdown of an attempt, in reading order
+CREATE TABLE speech_word_scores (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    attempt_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    word TEXT NOT NULL,
+    pinyin TEXT,
+    tone TEXT,
+    overall REAL NOT NULL,
+    pronunciation REAL NOT NULL,
+    tone_score REAL,
+    prominence REAL,
+    read_type INTEGER NOT NULL,
+    span_start INTEGER,
+    span_end INTEGER,
+    FOREIGN KEY (attempt_id) REFERENCES speech_attempts (id) ON DELETE CASCADE
+);
+
+-- Phoneme-level breakdown of a word, in reading order
+CREATE TABLE speech_phoneme_scores (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    word_score_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    phoneme TEXT NOT NULL,
+    phone TEXT,
+    pronunciation REAL NOT NULL,
+    tone_index INTEGER,
+    span_start INTEGER,
+    span_end INTEGER,
+    FOREIGN KEY (word_score_id) REFERENCES speech_word_scores (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_speech_attempts_user_created ON speech_attempts (user_id, created_at);
+CREATE INDEX idx_speech_word_scores_attempt_id ON speech_word_scores (attempt_id);
+CREATE INDEX idx_speech_word_scores_word ON speech_word_scores (word);
+CREATE INDEX idx_speech_phoneme_scores_word_score_id ON speech_phoneme_scores (word_score_id);
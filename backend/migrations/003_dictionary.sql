@@ -0,0 +1,46 @@
+-- Dictionary headwords imported from CC-CEDICT, one row per headword and reading
+CREATE TABLE dictionary_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    traditional TEXT NOT NULL,
+    simplified TEXT NOT NULL,
+    -- Numbered pinyin as CC-CEDICT writes it, e.g. "Bei3 jing1"
+    pinyin TEXT NOT NULL,
+    -- Lowercase numbered syllables with ü as v, e.g. "bei3 jing1"
+    pinyin_numbered TEXT NOT NULL,
+    -- Syllables run together without tones, e.g. "beijing"
+    pinyin_toneless TEXT NOT NULL,
+    -- Measure words from the entry's CL: sense, e.g. "個|个[ge4],位[wei4]"
+    classifiers TEXT
+);
+
+CREATE INDEX idx_dictionary_entries_simplified ON dictionary_entries (simplified);
+CREATE INDEX idx_dictionary_entries_traditional ON dictionary_entries (traditional);
+CREATE INDEX idx_dictionary_entries_pinyin_toneless ON dictionary_entries (pinyin_toneless);
+
+-- The senses of an entry, in CC-CEDICT order
+CREATE TABLE dictionary_senses (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    entry_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    meaning TEXT NOT NULL,
+    part_of_speech TEXT,
+    FOREIGN KEY (entry_id) REFERENCES dictionary_entries (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_dictionary_senses_entry ON dictionary_senses (entry_id, position);
+
+-- Full-text index over sense meanings for English to Chinese lookup
+CREATE VIRTUAL TABLE dictionary_senses_fts USING fts5 (
+    meaning,
+    content = 'dictionary_senses',
+    content_rowid = 'id'
+);
+
+CREATE TRIGGER dictionary_senses_fts_insert AFTER INSERT ON dictionary_senses BEGIN
+    INSERT INTO dictionary_senses_fts (rowid, meaning) VALUES (new.id, new.meaning);
+END;
+
+CREATE TRIGGER dictionary_senses_fts_delete AFTER DELETE ON dictionary_senses BEGIN
+    INSERT INTO dictionary_senses_fts (dictionary_senses_fts, rowid, meaning)
+    VALUES ('delete', old.id, old.meaning);
+END;
@@ -0,0 +1,31 @@
+-- A learner's own vocabulary, mirroring the app's Word model
+CREATE TABLE user_words (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    chinese TEXT NOT NULL,
+    pinyin TEXT,
+    definition TEXT NOT NULL,
+    difficulty TEXT NOT NULL DEFAULT 'beginner'
+        CHECK (difficulty IN ('beginner', 'intermediate', 'advanced')),
+    added_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_reviewed_at DATETIME,
+    next_review_at DATETIME,
+    strength INTEGER NOT NULL DEFAULT 0 CHECK (strength BETWEEN 0 AND 100),
+    correct_attempts INTEGER NOT NULL DEFAULT 0,
+    total_attempts INTEGER NOT NULL DEFAULT 0,
+    -- Per-task progress
+    speaking_completed BOOLEAN NOT NULL DEFAULT FALSE,
+    speaking_last_score REAL,
+    speaking_attempts INTEGER NOT NULL DEFAULT 0,
+    reading_completed BOOLEAN NOT NULL DEFAULT FALSE,
+    reading_times_encountered INTEGER NOT NULL DEFAULT 0,
+    writing_completed BOOLEAN NOT NULL DEFAULT FALSE,
+    writing_last_score REAL,
+    writing_attempts INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (user_id, chinese),
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_user_words_next_review ON user_words (user_id, next_review_at);
+CREATE INDEX idx_user_words_difficulty ON user_words (user_id, difficulty);
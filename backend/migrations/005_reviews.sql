@@ -0,0 +1,49 @@
+-- Spaced-repetition state of each word; next_review_at and last_reviewed_at
+-- on user_words are its due date and last review
+ALTER TABLE user_words ADD COLUMN review_state TEXT NOT NULL DEFAULT 'new'
+    CHECK (review_state IN ('new', 'review', 'relearning'));
+-- FSRS memory: stability in days and difficulty from 1 to 10
+ALTER TABLE user_words ADD COLUMN stability REAL;
+ALTER TABLE user_words ADD COLUMN review_difficulty REAL;
+-- SM-2 ease factor
+ALTER TABLE user_words ADD COLUMN ease_factor REAL NOT NULL DEFAULT 2.5;
+-- Successful reviews in a row
+ALTER TABLE user_words ADD COLUMN repetitions INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE user_words ADD COLUMN lapses INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE user_words ADD COLUMN interval_days INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX idx_user_words_review_queue ON user_words (user_id, review_state, next_review_at);
+
+-- One row per graded review, with the card's memory after it
+CREATE TABLE review_logs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    word_id TEXT NOT NULL,
+    reviewed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    grade INTEGER NOT NULL CHECK (grade BETWEEN 1 AND 4),
+    -- State before the review, so 'new' marks a card's first review
+    state TEXT NOT NULL,
+    algorithm TEXT NOT NULL,
+    -- Days since the previous review
+    elapsed_days REAL,
+    interval_days INTEGER NOT NULL,
+    stability REAL,
+    difficulty REAL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    FOREIGN KEY (word_id) REFERENCES user_words (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_review_logs_user_reviewed ON review_logs (user_id, reviewed_at);
+CREATE INDEX idx_review_logs_word ON review_logs (word_id, reviewed_at);
+
+-- Per-user scheduling preferences; users without a row get the defaults
+CREATE TABLE review_settings (
+    user_id TEXT PRIMARY KEY,
+    algorithm TEXT NOT NULL DEFAULT 'fsrs' CHECK (algorithm IN ('fsrs', 'sm2')),
+    desired_retention REAL NOT NULL DEFAULT 0.9
+        CHECK (desired_retention BETWEEN 0.7 AND 0.99),
+    new_cards_per_day INTEGER NOT NULL DEFAULT 20 CHECK (new_cards_per_day >= 0),
+    maximum_interval_days INTEGER NOT NULL DEFAULT 36500 CHECK (maximum_interval_days >= 1),
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
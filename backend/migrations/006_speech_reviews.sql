@@ -0,0 +1,6 @@
+-- Where a review's grade came from: the learner grading themselves, or a
+-- speech evaluation that scored the word
+ALTER TABLE review_logs ADD COLUMN source TEXT NOT NULL DEFAULT 'manual'
+    CHECK (source IN ('manual', 'speech'));
+ALTER TABLE review_logs ADD COLUMN speech_attempt_id TEXT
+    REFERENCES speech_attempts (id) ON DELETE SET NULL;
@@ -0,0 +1,28 @@
+-- HSK vocabulary lists, one row per word of each standard. HSK 2.0 has levels
+-- 1 to 6; HSK 3.0 has levels 1 to 6 and a combined 7-9 band stored as level 7
+CREATE TABLE hsk_words (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    standard TEXT NOT NULL CHECK (standard IN ('2.0', '3.0')),
+    level INTEGER NOT NULL CHECK (level BETWEEN 1 AND 7),
+    -- Order within the level's published list
+    position INTEGER NOT NULL,
+    word TEXT NOT NULL,
+    -- Tone-marked, e.g. "Běijīng"
+    pinyin TEXT NOT NULL,
+    english TEXT NOT NULL,
+    UNIQUE (standard, word)
+);
+
+CREATE INDEX idx_hsk_words_level ON hsk_words (standard, level, position);
+
+-- The characters of each standard, at the lowest level of a word using them
+CREATE TABLE hsk_characters (
+    standard TEXT NOT NULL CHECK (standard IN ('2.0', '3.0')),
+    character TEXT NOT NULL,
+    level INTEGER NOT NULL CHECK (level BETWEEN 1 AND 7),
+    -- Order of first appearance in the word list
+    position INTEGER NOT NULL,
+    PRIMARY KEY (standard, character)
+);
+
+CREATE INDEX idx_hsk_characters_level ON hsk_characters (standard, level, position);
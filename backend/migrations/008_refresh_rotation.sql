@@ -0,0 +1,22 @@
+-- Refresh-token rotation. A session's refresh tokens form one family, named by
+-- a random secret kept here only as its SHA-256 hash; each refresh moves the
+-- session to the next generation, and only that generation's token is accepted.
+-- Sessions from before rotation have no family and must sign in again
+ALTER TABLE sessions ADD COLUMN refresh_family_hash TEXT;
+ALTER TABLE sessions ADD COLUMN refresh_generation INTEGER NOT NULL DEFAULT 0;
+
+-- Security-relevant account events
+CREATE TABLE audit_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT,
+    -- Not a foreign key: the session is often revoked by the event itself
+    session_id TEXT,
+    event TEXT NOT NULL,
+    ip_address TEXT,
+    user_agent TEXT,
+    details TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_audit_events_user ON audit_events (user_id, created_at);
@@ -0,0 +1,4 @@
+-- When the session last moved to a new refresh generation. The generation just
+-- rotated out is still accepted for a few seconds after, so that two tabs
+-- refreshing with the same token at once are not taken for a stolen token
+ALTER TABLE sessions ADD COLUMN refresh_rotated_at DATETIME;
@@ -0,0 +1,8 @@
+-- Where each standard's HSK lists were imported from. A standard without a row
+-- holds the bundled lists, which cover only some levels; one with a row was
+-- replaced from list files, with or without the standard's character list
+CREATE TABLE hsk_lists (
+    standard TEXT PRIMARY KEY CHECK (standard IN ('2.0', '3.0')),
+    character_list BOOLEAN NOT NULL,
+    imported_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,4 @@
+-- Word frequency of an entry's simplified form in the segmentation lexicon, 0
+-- if it has none, so that searches can rank entries in SQL. Set on import:
+-- entries imported before this are 0 until the dictionary is imported again
+ALTER TABLE dictionary_entries ADD COLUMN frequency INTEGER NOT NULL DEFAULT 0;
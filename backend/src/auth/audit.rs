@@ -0,0 +1,66 @@
+use serde::Serialize;
+
+use crate::{
+    db::Database,
+    error::Result,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum AuditEventKind {
+    /// A rotated-out refresh token was presented and its session revoked
+    RefreshTokenReuse,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    pub kind: AuditEventKind,
+    pub user_id: Option<String>,
+    pub session_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: Option<String>,
+}
+
+/// Records security-relevant events, both in the `audit_events` table and in
+/// the log under the `audit` target.
+#[derive(Clone)]
+pub struct AuditService {
+    db: Database,
+}
+
+impl AuditService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub async fn record(&self, event: &AuditEvent) -> Result<()> {
+        tracing::warn!(
+            target: "audit",
+            kind = ?event.kind,
+            user_id = event.user_id.as_deref(),
+            session_id = event.session_id.as_deref(),
+            ip_address = event.ip_address.as_deref(),
+            details = event.details.as_deref(),
+            "Audit event"
+        );
+
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_events (user_id, session_id, event, ip_address, user_agent, details)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+            event.user_id,
+            event.session_id,
+            event.kind,
+            event.ip_address,
+            event.user_agent,
+            event.details
+        )
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+}
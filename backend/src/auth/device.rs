@@ -0,0 +1,59 @@
+//! Naming the device behind a session from its user agent.
+//!
+//! Only the browsers and systems people commonly sign in from are told apart;
+//! the point is a recognisable label like "Firefox on Windows" for a list of
+//! signed-in devices, not an exhaustive user-agent parser.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Device {
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    /// e.g. `Chrome on macOS`
+    pub name: String,
+}
+
+/// Browsers by a token of their user agent. Order matters: most browsers also
+/// claim to be Safari, and Chromium-based ones to be Chrome.
+const BROWSERS: &[(&str, &str)] = &[
+    ("Edg", "Edge"),
+    ("OPR/", "Opera"),
+    ("SamsungBrowser/", "Samsung Internet"),
+    ("Firefox/", "Firefox"),
+    ("FxiOS/", "Firefox"),
+    ("CriOS/", "Chrome"),
+    ("Chrome/", "Chrome"),
+    ("Safari/", "Safari"),
+];
+
+/// Systems by a token of their user agent, in the same spirit.
+const SYSTEMS: &[(&str, &str)] = &[
+    ("iPhone", "iOS"),
+    ("iPad", "iPadOS"),
+    ("Android", "Android"),
+    ("CrOS", "ChromeOS"),
+    ("Windows", "Windows"),
+    ("Macintosh", "macOS"),
+    ("Linux", "Linux"),
+];
+
+fn find(user_agent: &str, known: &[(&str, &str)]) -> Option<String> {
+    known
+        .iter()
+        .find(|(token, _)| user_agent.contains(token))
+        .map(|(_, name)| name.to_string())
+}
+
+/// The browser and system named by a user agent, if any.
+pub fn describe(user_agent: Option<&str>) -> Device {
+    let user_agent = user_agent.unwrap_or_default();
+    let browser = find(user_agent, BROWSERS);
+    let os = find(user_agent, SYSTEMS);
+    let name = match (&browser, &os) {
+        (Some(browser), Some(os)) => format!("{} on {}", browser, os),
+        (Some(only), None) | (None, Some(only)) => only.clone(),
+        (None, None) => "Unknown device".to_string(),
+    };
+    Device { browser, os, name }
+}
@@ -0,0 +1,87 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{
+        request::Parts,
+        HeaderMap,
+    },
+};
+
+use super::{
+    jwt::JwtService,
+    session::SessionService,
+};
+use crate::error::AppError;
+
+/// The signed-in caller: a valid bearer access token whose session is still
+/// live and belongs to the token's user. Rejects with 401 otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthUser {
+    pub user_id: String,
+    pub session_id: String,
+}
+
+/// The caller if the request carries a bearer token, for endpoints that also
+/// serve anonymous callers. A token that is present but invalid still gets 401.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionalAuthUser(pub Option<AuthUser>);
+
+/// The token of an `Authorization: Bearer` header, if there is one.
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
+
+async fn authenticate(parts: &Parts) -> Result<Option<AuthUser>, AppError> {
+    let Some(token) = bearer_token(&parts.headers) else {
+        return Ok(None);
+    };
+
+    let (Some(jwt_service), Some(session_service)) = (
+        parts.extensions.get::<JwtService>(),
+        parts.extensions.get::<SessionService>(),
+    ) else {
+        return Err(AppError::InternalServerError(
+            "Authentication services are not configured".to_string(),
+        ));
+    };
+
+    let claims = jwt_service.verify_access_token(token)?;
+    if !session_service
+        .validate_session(&claims.session_id, &claims.sub)
+        .await?
+    {
+        return Err(AppError::Unauthorized);
+    }
+
+    Ok(Some(AuthUser {
+        user_id: claims.sub,
+        session_id: claims.session_id,
+    }))
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        authenticate(parts).await?.ok_or(AppError::Unauthorized)
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for OptionalAuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(authenticate(parts).await?))
+    }
+}
@@ -17,7 +17,10 @@ use crate::{
         AppError,
         Result,
     },
-    models::Claims,
+    models::{
+        Claims,
+        RefreshClaims,
+    },
 };
 
 #[derive(Clone)]
@@ -44,12 +47,19 @@ impl JwtService {
             exp: expires_at.timestamp(),
             iat: now.timestamp(),
             session_id: session_id.to_string(),
+            refresh: None,
         };
 
-        encode(&Header::default(), &claims, &self.encoding_key).map_err(|e| AppError::Jwt(e))
+        encode(&Header::default(), &claims, &self.encoding_key).map_err(AppError::Jwt)
     }
 
-    pub fn create_refresh_token(&self, user_id: &str, session_id: &str) -> Result<String> {
+    /// A refresh token for one generation of the session's refresh family.
+    pub fn create_refresh_token(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        refresh: &RefreshClaims,
+    ) -> Result<String> {
         let now = Utc::now();
         let expires_at = now + Duration::days(30); // Long-lived refresh token
 
@@ -58,25 +68,35 @@ impl JwtService {
             exp: expires_at.timestamp(),
             iat: now.timestamp(),
             session_id: session_id.to_string(),
+            refresh: Some(refresh.clone()),
         };
 
-        encode(&Header::default(), &claims, &self.encoding_key).map_err(|e| AppError::Jwt(e))
+        encode(&Header::default(), &claims, &self.encoding_key).map_err(AppError::Jwt)
     }
 
+    /// Check a token's signature and expiry, whichever kind it is.
     pub fn verify_token(&self, token: &str) -> Result<Claims> {
         let validation = Validation::default();
 
         decode::<Claims>(token, &self.decoding_key, &validation)
             .map(|data| data.claims)
-            .map_err(|e| AppError::Jwt(e))
+            .map_err(AppError::Jwt)
     }
 
-    pub fn refresh_access_token(&self, refresh_token: &str) -> Result<(String, Claims)> {
-        let claims = self.verify_token(refresh_token)?;
-
-        // Create new access token with same session
-        let new_access_token = self.create_access_token(&claims.sub, &claims.session_id)?;
+    /// Verify a token presented as an access token; refresh tokens are refused.
+    pub fn verify_access_token(&self, token: &str) -> Result<Claims> {
+        let claims = self.verify_token(token)?;
+        if claims.refresh.is_some() {
+            return Err(AppError::Unauthorized);
+        }
+        Ok(claims)
+    }
 
-        Ok((new_access_token, claims))
+    /// Verify a refresh token. Whether it is still its session's current
+    /// generation is for the session store to decide.
+    pub fn verify_refresh_token(&self, token: &str) -> Result<(Claims, RefreshClaims)> {
+        let mut claims = self.verify_token(token)?;
+        let refresh = claims.refresh.take().ok_or(AppError::Unauthorized)?;
+        Ok((claims, refresh))
     }
 }
@@ -1,3 +1,6 @@
+pub mod audit;
+pub mod device;
+pub mod extractor;
 pub mod jwt;
 pub mod password;
 pub mod session;
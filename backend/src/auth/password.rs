@@ -189,8 +189,8 @@ impl PasswordAuthService {
             "INSERT INTO users (id, created_at, updated_at, email, display_name, password_hash) VALUES (?, ?, ?, ?, ?, ?)",
         )
         .bind(&user.id)
-        .bind(&user.created_at)
-        .bind(&user.updated_at)
+        .bind(user.created_at)
+        .bind(user.updated_at)
         .bind(&user.email)
         .bind(&user.display_name)
         .bind(&user.password_hash)
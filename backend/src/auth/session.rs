@@ -1,22 +1,90 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::{
+        Duration as StdDuration,
+        Instant,
+    },
+};
+
 use chrono::{
     Duration,
+    NaiveDateTime,
     Utc,
 };
+use rand::RngCore;
+use sha2::{
+    Digest,
+    Sha256,
+};
 
 use crate::{
     db::Database,
     error::Result,
-    models::Session,
+    models::{
+        RefreshClaims,
+        Session,
+    },
 };
 
+/// The outcome of presenting a refresh token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefreshRotation {
+    /// The token was current and is now spent, or was spent moments ago by
+    /// another tab; these are the claims of the token to use next
+    Rotated(RefreshClaims),
+    /// An already rotated-out token was presented, and the session was revoked
+    Reused { user_id: String },
+    /// The session has ended, or the token is not of its family
+    Invalid,
+}
+
+/// How long a session found live is trusted without asking the database again.
+/// Revocations through this service take effect at once; this only bounds how
+/// long another process's revocation can go unnoticed.
+const SESSION_CACHE_TTL: StdDuration = StdDuration::from_secs(30);
+/// How often a session in use has its `last_used_at` written.
+const LAST_USED_INTERVAL_SECONDS: i64 = 300;
+/// How long after a rotation the token it spent is still accepted, for tabs
+/// that refreshed with the same token at once.
+const REFRESH_GRACE_SECONDS: i64 = 10;
+
+fn hash_family(family: &str) -> String {
+    hex::encode(Sha256::digest(family.as_bytes()))
+}
+
+/// A live session as last read from the database.
+#[derive(Debug, Clone)]
+struct CachedSession {
+    user_id: String,
+    expires_at: NaiveDateTime,
+    last_used_at: NaiveDateTime,
+    checked_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct SessionCache {
+    sessions: HashMap<String, CachedSession>,
+    /// Counts revocations, so that a session read from the database before one
+    /// is not cached after it
+    revocations: u64,
+}
+
 #[derive(Clone)]
 pub struct SessionService {
     db: Database,
+    cache: Arc<Mutex<SessionCache>>,
 }
 
 impl SessionService {
     pub fn new(db: Database) -> Self {
-        Self { db }
+        Self {
+            db,
+            cache: Arc::new(Mutex::new(SessionCache::default())),
+        }
     }
 
     pub async fn create_session(
@@ -49,6 +117,181 @@ impl SessionService {
         Ok(session)
     }
 
+    /// Start a new refresh-token family for the session, returning the claims of
+    /// its first token. Any earlier refresh token of the session stops working.
+    pub async fn start_refresh_family(&self, session_id: &str) -> Result<RefreshClaims> {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let family = hex::encode(secret);
+        let family_hash = hash_family(&family);
+
+        sqlx::query!(
+            r#"
+            UPDATE sessions SET refresh_family_hash = ?1, refresh_generation = 0
+            WHERE id = ?2
+            "#,
+            family_hash,
+            session_id
+        )
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(RefreshClaims {
+            family,
+            generation: 0,
+        })
+    }
+
+    /// Spend a refresh token of the user's session. Only the token of the
+    /// session's current generation is accepted, or the one before it for a few
+    /// seconds after it was spent, which gets the current generation again.
+    /// Presenting any other earlier token means it was copied, so the whole
+    /// session is revoked.
+    pub async fn rotate_refresh_token(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        presented: &RefreshClaims,
+    ) -> Result<RefreshRotation> {
+        let now = Utc::now().naive_utc();
+        let family_hash = hash_family(&presented.family);
+
+        let rotated = sqlx::query!(
+            r#"
+            UPDATE sessions SET refresh_generation = refresh_generation + 1,
+                                refresh_rotated_at = ?1, last_used_at = ?1
+            WHERE id = ?2 AND user_id = ?3 AND refresh_family_hash = ?4
+              AND refresh_generation = ?5 AND expires_at > ?1
+            "#,
+            now,
+            session_id,
+            user_id,
+            family_hash,
+            presented.generation
+        )
+        .execute(self.db.pool())
+        .await?
+        .rows_affected();
+        if rotated == 1 {
+            return Ok(RefreshRotation::Rotated(RefreshClaims {
+                family: presented.family.clone(),
+                generation: presented.generation + 1,
+            }));
+        }
+
+        let current = sqlx::query!(
+            r#"
+            SELECT refresh_generation, refresh_rotated_at as "refresh_rotated_at: NaiveDateTime"
+            FROM sessions
+            WHERE id = ?1 AND user_id = ?2 AND refresh_family_hash = ?3 AND expires_at > ?4
+            "#,
+            session_id,
+            user_id,
+            family_hash,
+            now
+        )
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        match current {
+            Some(current)
+                if presented.generation + 1 == current.refresh_generation
+                    && current.refresh_rotated_at.is_some_and(|rotated_at| {
+                        now - rotated_at < Duration::seconds(REFRESH_GRACE_SECONDS)
+                    }) =>
+            {
+                Ok(RefreshRotation::Rotated(RefreshClaims {
+                    family: presented.family.clone(),
+                    generation: current.refresh_generation,
+                }))
+            }
+            Some(current) if presented.generation < current.refresh_generation => {
+                self.revoke_session(session_id).await?;
+                Ok(RefreshRotation::Reused {
+                    user_id: user_id.to_string(),
+                })
+            }
+            _ => Ok(RefreshRotation::Invalid),
+        }
+    }
+
+    /// Whether the session is live and belongs to the user, for checking an
+    /// access token on each request. Answers come from a short-lived cache, and
+    /// the session's `last_used_at` is written at most every few minutes.
+    pub async fn validate_session(&self, session_id: &str, user_id: &str) -> Result<bool> {
+        let now = Utc::now().naive_utc();
+        let (cached, revocations) = self
+            .cache
+            .lock()
+            .map(|cache| {
+                let cached = cache
+                    .sessions
+                    .get(session_id)
+                    .filter(|cached| cached.checked_at.elapsed() < SESSION_CACHE_TTL)
+                    .cloned();
+                (cached, cache.revocations)
+            })
+            .unwrap_or_default();
+
+        let (mut session, fetched) = match cached {
+            Some(cached) => (cached, false),
+            None => {
+                let Some(session) = self.get_session(session_id).await? else {
+                    self.forget(|id, _| id == session_id);
+                    return Ok(false);
+                };
+                let session = CachedSession {
+                    user_id: session.user_id,
+                    expires_at: session.expires_at,
+                    last_used_at: session.last_used_at,
+                    checked_at: Instant::now(),
+                };
+                (session, true)
+            }
+        };
+        if session.expires_at <= now || session.user_id != user_id {
+            return Ok(false);
+        }
+
+        if (now - session.last_used_at).num_seconds() >= LAST_USED_INTERVAL_SECONDS {
+            self.update_session_activity(session_id).await?;
+            session.last_used_at = now;
+        }
+        if fetched {
+            self.remember(session_id, session, revocations);
+        } else if let Ok(mut cache) = self.cache.lock() {
+            if let Some(cached) = cache.sessions.get_mut(session_id) {
+                // Only if it was not revoked meanwhile
+                cached.last_used_at = session.last_used_at;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Cache a session read from the database while `revocations` was the
+    /// revocation count. If a revocation happened since, the read may predate
+    /// it, so the session is left for the next request to read again.
+    fn remember(&self, session_id: &str, session: CachedSession, revocations: u64) {
+        let Ok(mut cache) = self.cache.lock() else {
+            return;
+        };
+        if cache.revocations != revocations {
+            return;
+        }
+        cache
+            .sessions
+            .retain(|_, cached| cached.checked_at.elapsed() < SESSION_CACHE_TTL);
+        cache.sessions.insert(session_id.to_string(), session);
+    }
+
+    /// Drop cached sessions matching `revoked`.
+    fn forget(&self, revoked: impl Fn(&str, &CachedSession) -> bool) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.revocations += 1;
+            cache.sessions.retain(|id, cached| !revoked(id, cached));
+        }
+    }
+
     pub async fn get_session(&self, session_id: &str) -> Result<Option<Session>> {
         let now = Utc::now().naive_utc();
         let session = sqlx::query_as!(
@@ -67,6 +310,26 @@ impl SessionService {
         Ok(session)
     }
 
+    /// The user's live sessions, most recently used first.
+    pub async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<Session>> {
+        let now = Utc::now().naive_utc();
+        let sessions = sqlx::query_as!(
+            Session,
+            r#"
+            SELECT id as "id!", user_id as "user_id!", created_at, expires_at, last_used_at, ip_address, user_agent
+            FROM sessions
+            WHERE user_id = ?1 AND expires_at > ?2
+            ORDER BY last_used_at DESC, created_at DESC
+            "#,
+            user_id,
+            now
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(sessions)
+    }
+
     pub async fn update_session_activity(&self, session_id: &str) -> Result<()> {
         let now = Utc::now().naive_utc();
         sqlx::query!(
@@ -84,15 +347,100 @@ impl SessionService {
         sqlx::query!("DELETE FROM sessions WHERE id = ?1", session_id)
             .execute(self.db.pool())
             .await?;
+        self.forget(|id, _| id == session_id);
 
         Ok(())
     }
 
+    /// Revoke one of the user's sessions; `false` if they have no such session.
+    pub async fn revoke_user_session(&self, user_id: &str, session_id: &str) -> Result<bool> {
+        let revoked = sqlx::query!(
+            "DELETE FROM sessions WHERE id = ?1 AND user_id = ?2",
+            session_id,
+            user_id
+        )
+        .execute(self.db.pool())
+        .await?
+        .rows_affected();
+        self.forget(|id, _| id == session_id);
+
+        Ok(revoked > 0)
+    }
+
     pub async fn revoke_all_user_sessions(&self, user_id: &str) -> Result<()> {
         sqlx::query!("DELETE FROM sessions WHERE user_id = ?1", user_id)
             .execute(self.db.pool())
             .await?;
+        self.forget(|_, cached| cached.user_id == user_id);
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn session_service() -> SessionService {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        let now = Utc::now().naive_utc();
+        sqlx::query(
+            "INSERT INTO users (id, created_at, updated_at, email, password_hash) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind("user-1")
+        .bind(now)
+        .bind(now)
+        .bind("user@example.com")
+        .bind("hash")
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        SessionService::new(db)
+    }
+
+    #[tokio::test]
+    async fn test_revocation_during_cache_fill_is_not_undone() {
+        let service = session_service().await;
+        let session = service.create_session("user-1", None, None).await.unwrap();
+
+        // A validation reads the session, then a revocation lands before the
+        // validation gets to cache what it read
+        let revocations = service.cache.lock().unwrap().revocations;
+        let read = service.get_session(&session.id).await.unwrap().unwrap();
+        service.revoke_session(&session.id).await.unwrap();
+        service.remember(
+            &session.id,
+            CachedSession {
+                user_id: read.user_id,
+                expires_at: read.expires_at,
+                last_used_at: read.last_used_at,
+                checked_at: Instant::now(),
+            },
+            revocations,
+        );
+
+        assert!(!service
+            .validate_session(&session.id, "user-1")
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_validate_and_revoke() {
+        let service = session_service().await;
+
+        for _ in 0..20 {
+            let session = service.create_session("user-1", None, None).await.unwrap();
+            let (_, revoked) = tokio::join!(
+                service.validate_session(&session.id, "user-1"),
+                service.revoke_session(&session.id),
+            );
+            revoked.unwrap();
+            assert!(!service
+                .validate_session(&session.id, "user-1")
+                .await
+                .unwrap());
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Load a CC-CEDICT file into the dictionary tables, replacing their contents:
+//!
+//! ```text
+//! cargo run --bin import_cedict -- cedict_ts.u8
+//! ```
+
+use std::path::PathBuf;
+
+use anyhow::{
+    Context,
+    Result,
+};
+use clap::Parser;
+use mandarinpath_backend::{
+    db::Database,
+    dictionary::DictionaryService,
+};
+
+#[derive(Parser, Debug)]
+#[command(about = "Import a CC-CEDICT file into the dictionary")]
+struct Args {
+    /// CC-CEDICT file, e.g. cedict_ts.u8
+    path: PathBuf,
+
+    /// Database URL
+    #[arg(
+        short,
+        long,
+        env = "DATABASE_URL",
+        default_value = "sqlite:./mandarinpath.db"
+    )]
+    database_url: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let text = std::fs::read_to_string(&args.path)
+        .with_context(|| format!("Failed to read {}", args.path.display()))?;
+    let db = Database::new(&args.database_url).await?;
+    let summary = DictionaryService::new(db).import_cedict(&text).await?;
+
+    for error in &summary.skipped {
+        eprintln!("Skipped {}", error);
+    }
+    println!(
+        "Imported {} entries with {} senses from {}",
+        summary.entries,
+        summary.senses,
+        args.path.display()
+    );
+    Ok(())
+}
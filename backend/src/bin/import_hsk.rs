@@ -0,0 +1,104 @@
+//! Load HSK word lists into the curriculum tables. With no arguments the
+//! bundled lists of both standards are loaded; given a list file, it replaces
+//! that standard's lists, with the characters taken from a character list if
+//! one is given:
+//!
+//! ```text
+//! cargo run --bin import_hsk
+//! cargo run --bin import_hsk -- --standard 3.0 hsk3_full.tsv --characters hsk3_characters.tsv
+//! ```
+
+use std::path::PathBuf;
+
+use anyhow::{
+    Context,
+    Result,
+};
+use clap::Parser;
+use mandarinpath_backend::{
+    curriculum::{
+        CurriculumService,
+        ImportSummary,
+        Standard,
+    },
+    db::Database,
+};
+
+#[derive(Parser, Debug)]
+#[command(about = "Import HSK word lists into the curriculum")]
+struct Args {
+    /// Tab-separated list of level, word, pinyin and English; the bundled lists if omitted
+    path: Option<PathBuf>,
+
+    /// Tab-separated list of level and character; otherwise characters are taken from the words
+    #[arg(short, long, requires = "path")]
+    characters: Option<PathBuf>,
+
+    /// Standard the list belongs to, 2.0 or 3.0
+    #[arg(short, long, default_value = "2.0", value_parser = parse_standard)]
+    standard: Standard,
+
+    /// Database URL
+    #[arg(
+        short,
+        long,
+        env = "DATABASE_URL",
+        default_value = "sqlite:./mandarinpath.db"
+    )]
+    database_url: String,
+}
+
+fn parse_standard(value: &str) -> Result<Standard, String> {
+    match value {
+        "2.0" | "2" => Ok(Standard::Hsk2),
+        "3.0" | "3" => Ok(Standard::Hsk3),
+        _ => Err("expected 2.0 or 3.0".to_string()),
+    }
+}
+
+fn report(standard: Standard, summary: &ImportSummary) {
+    for error in &summary.skipped {
+        eprintln!("HSK {}: skipped {}", standard.as_str(), error);
+    }
+    for error in &summary.skipped_characters {
+        eprintln!(
+            "HSK {}: skipped character list {}",
+            standard.as_str(),
+            error
+        );
+    }
+    println!(
+        "Imported {} words and {} characters into HSK {}",
+        summary.words,
+        summary.characters,
+        standard.as_str()
+    );
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let db = Database::new(&args.database_url).await?;
+    let curriculum = CurriculumService::new(db);
+    match &args.path {
+        Some(path) => {
+            let read = |path: &PathBuf| {
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))
+            };
+            let words = read(path)?;
+            let characters = args.characters.as_ref().map(read).transpose()?;
+            let summary = curriculum
+                .import_lists(args.standard, &words, characters.as_deref())
+                .await?;
+            report(args.standard, &summary);
+        }
+        None => {
+            for (standard, summary) in curriculum.import_bundled().await? {
+                report(standard, &summary);
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,10 +1,31 @@
+use std::{
+    net::IpAddr,
+    time::Duration,
+};
+
 use anyhow::Result;
-use clap::Parser;
+use clap::{
+    Parser,
+    ValueEnum,
+};
 use secrecy::{
     ExposeSecret,
     Secret,
 };
 
+const AUTH_RATE_LIMIT_PER_IP: u32 = 30;
+const AUTH_RATE_LIMIT_PER_EMAIL: u32 = 10;
+const AUTH_RATE_LIMIT_WINDOW_SECS: u64 = 900;
+const SPEECH_RATE_LIMIT_PER_USER: u32 = 200;
+const SPEECH_RATE_LIMIT_WINDOW_SECS: u64 = 3600;
+const SPEECH_MAX_AUDIO_SECS: u64 = 120;
+const SPEECH_CONNECT_TIMEOUT_SECS: u64 = 5;
+const SPEECH_READ_TIMEOUT_SECS: u64 = 10;
+const SPEECH_REQUEST_TIMEOUT_SECS: u64 = 30;
+const SPEECH_MAX_RETRIES: u32 = 2;
+const SPEECH_BREAKER_THRESHOLD: u32 = 5;
+const SPEECH_BREAKER_COOLDOWN_SECS: u64 = 30;
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
@@ -45,6 +66,194 @@ pub struct Args {
     /// Increase logging verbosity (-v, -vv, -vvv)
     #[arg(short, long, action = clap::ArgAction::Count)]
     pub verbose: u8,
+
+    /// Speech evaluation backend
+    #[arg(long, env = "SPEECH_PROVIDER", value_enum, default_value = "iflytek")]
+    pub speech_provider: SpeechProvider,
+
+    /// Longest recording accepted for speech evaluation, in seconds
+    #[arg(long, env = "SPEECH_MAX_AUDIO_SECS", default_value_t = SPEECH_MAX_AUDIO_SECS)]
+    pub speech_max_audio_secs: u64,
+
+    /// Deadline for connecting to the speech provider, in seconds
+    #[arg(long, env = "SPEECH_CONNECT_TIMEOUT_SECS", default_value_t = SPEECH_CONNECT_TIMEOUT_SECS)]
+    pub speech_connect_timeout_secs: u64,
+
+    /// Longest wait for the provider's result once all audio is sent, in seconds
+    #[arg(long, env = "SPEECH_READ_TIMEOUT_SECS", default_value_t = SPEECH_READ_TIMEOUT_SECS)]
+    pub speech_read_timeout_secs: u64,
+
+    /// Overall deadline for an evaluation on top of the recording's length, in seconds
+    #[arg(long, env = "SPEECH_REQUEST_TIMEOUT_SECS", default_value_t = SPEECH_REQUEST_TIMEOUT_SECS)]
+    pub speech_request_timeout_secs: u64,
+
+    /// Reconnection attempts after a transient connection failure
+    #[arg(long, env = "SPEECH_MAX_RETRIES", default_value_t = SPEECH_MAX_RETRIES)]
+    pub speech_max_retries: u32,
+
+    /// Consecutive provider failures that open the circuit breaker
+    #[arg(long, env = "SPEECH_BREAKER_THRESHOLD", default_value_t = SPEECH_BREAKER_THRESHOLD)]
+    pub speech_breaker_threshold: u32,
+
+    /// How long an open circuit breaker rejects requests, in seconds
+    #[arg(long, env = "SPEECH_BREAKER_COOLDOWN_SECS", default_value_t = SPEECH_BREAKER_COOLDOWN_SECS)]
+    pub speech_breaker_cooldown_secs: u64,
+
+    /// Sign-in and registration attempts allowed per client IP in a window (0 disables)
+    #[arg(long, env = "AUTH_RATE_LIMIT_PER_IP", default_value_t = AUTH_RATE_LIMIT_PER_IP)]
+    pub auth_rate_limit_per_ip: u32,
+
+    /// Sign-in and registration attempts allowed per email address in a window (0 disables)
+    #[arg(long, env = "AUTH_RATE_LIMIT_PER_EMAIL", default_value_t = AUTH_RATE_LIMIT_PER_EMAIL)]
+    pub auth_rate_limit_per_email: u32,
+
+    /// Length of the sign-in and registration rate limit window, in seconds
+    #[arg(long, env = "AUTH_RATE_LIMIT_WINDOW_SECS", default_value_t = AUTH_RATE_LIMIT_WINDOW_SECS)]
+    pub auth_rate_limit_window_secs: u64,
+
+    /// Speech evaluations allowed per user, or per IP when anonymous, in a window (0 disables)
+    #[arg(long, env = "SPEECH_RATE_LIMIT_PER_USER", default_value_t = SPEECH_RATE_LIMIT_PER_USER)]
+    pub speech_rate_limit_per_user: u32,
+
+    /// Length of the speech evaluation rate limit window, in seconds
+    #[arg(long, env = "SPEECH_RATE_LIMIT_WINDOW_SECS", default_value_t = SPEECH_RATE_LIMIT_WINDOW_SECS)]
+    pub speech_rate_limit_window_secs: u64,
+
+    /// Addresses of reverse proxies whose X-Forwarded-For / X-Real-IP are believed, comma-separated
+    #[arg(long, env = "TRUSTED_PROXIES", value_delimiter = ',')]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+/// Which implementation scores `/speech/evaluate` requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SpeechProvider {
+    /// iFlytek ISE over WebSocket (requires `IFLYTEK_*` credentials)
+    #[default]
+    Iflytek,
+    /// Deterministic offline scorer for development and CI
+    Mock,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpeechConfig {
+    pub provider: SpeechProvider,
+    pub max_audio_duration: Duration,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    /// Allowance for one evaluation beyond the time spent streaming the recording
+    pub request_timeout: Duration,
+    pub max_retries: u32,
+    pub breaker_failure_threshold: u32,
+    pub breaker_cooldown: Duration,
+}
+
+impl SpeechConfig {
+    /// Largest accepted upload body: the maximum duration of 48 kHz stereo 16-bit PCM,
+    /// the most verbose format a browser will produce, plus room for the form fields.
+    pub fn max_upload_bytes(&self) -> usize {
+        const BYTES_PER_SECOND: u64 = 48_000 * 2 * 2;
+        (self.max_audio_duration.as_secs() * BYTES_PER_SECOND) as usize + 64 * 1024
+    }
+}
+
+impl Default for SpeechConfig {
+    fn default() -> Self {
+        Self {
+            provider: SpeechProvider::default(),
+            max_audio_duration: Duration::from_secs(SPEECH_MAX_AUDIO_SECS),
+            connect_timeout: Duration::from_secs(SPEECH_CONNECT_TIMEOUT_SECS),
+            read_timeout: Duration::from_secs(SPEECH_READ_TIMEOUT_SECS),
+            request_timeout: Duration::from_secs(SPEECH_REQUEST_TIMEOUT_SECS),
+            max_retries: SPEECH_MAX_RETRIES,
+            breaker_failure_threshold: SPEECH_BREAKER_THRESHOLD,
+            breaker_cooldown: Duration::from_secs(SPEECH_BREAKER_COOLDOWN_SECS),
+        }
+    }
+}
+
+/// What a rate limit counts requests by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// The client's IP address
+    Ip,
+    /// The signed-in user, or the client's IP address for anonymous requests
+    User,
+    /// The `email` field of a JSON body; requests without one are not counted
+    Email,
+}
+
+impl RateLimitKey {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitKey::Ip => "ip",
+            RateLimitKey::User => "user",
+            RateLimitKey::Email => "email",
+        }
+    }
+}
+
+/// At most `max_requests` per key in each fixed window of `window`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub key: RateLimitKey,
+    pub max_requests: u32,
+    pub window: Duration,
+}
+
+impl RateLimit {
+    /// The limit, or none when `max_requests` is zero.
+    fn enabled(key: RateLimitKey, max_requests: u32, window: Duration) -> Option<Self> {
+        (max_requests > 0).then_some(Self {
+            key,
+            max_requests,
+            window,
+        })
+    }
+}
+
+/// Rate limits by route group; a request must stay within every limit of its group.
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// `/auth/register` and `/auth/login`, against password guessing
+    pub auth: Vec<RateLimit>,
+    /// `/speech/evaluate` and `/speech/stream`, which spend provider quota
+    pub speech: Vec<RateLimit>,
+}
+
+impl RateLimitConfig {
+    /// Limits from per-key request counts (0 disables a limit) and window lengths.
+    fn new(
+        auth_per_ip: u32,
+        auth_per_email: u32,
+        auth_window: Duration,
+        speech_per_user: u32,
+        speech_window: Duration,
+    ) -> Self {
+        Self {
+            auth: [
+                RateLimit::enabled(RateLimitKey::Ip, auth_per_ip, auth_window),
+                RateLimit::enabled(RateLimitKey::Email, auth_per_email, auth_window),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            speech: RateLimit::enabled(RateLimitKey::User, speech_per_user, speech_window)
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self::new(
+            AUTH_RATE_LIMIT_PER_IP,
+            AUTH_RATE_LIMIT_PER_EMAIL,
+            Duration::from_secs(AUTH_RATE_LIMIT_WINDOW_SECS),
+            SPEECH_RATE_LIMIT_PER_USER,
+            Duration::from_secs(SPEECH_RATE_LIMIT_WINDOW_SECS),
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -55,6 +264,11 @@ pub struct Config {
     pub port: u16,
     pub debug_mode: bool,
     pub verbosity: u8,
+    pub speech: SpeechConfig,
+    pub rate_limits: RateLimitConfig,
+    /// Reverse proxies whose `X-Forwarded-For` / `X-Real-IP` name the client
+    /// when they are the peer
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 impl Config {
@@ -72,6 +286,24 @@ impl Config {
             port: args.port,
             debug_mode: args.debug,
             verbosity: args.verbose,
+            speech: SpeechConfig {
+                provider: args.speech_provider,
+                max_audio_duration: Duration::from_secs(args.speech_max_audio_secs),
+                connect_timeout: Duration::from_secs(args.speech_connect_timeout_secs),
+                read_timeout: Duration::from_secs(args.speech_read_timeout_secs),
+                request_timeout: Duration::from_secs(args.speech_request_timeout_secs),
+                max_retries: args.speech_max_retries,
+                breaker_failure_threshold: args.speech_breaker_threshold,
+                breaker_cooldown: Duration::from_secs(args.speech_breaker_cooldown_secs),
+            },
+            rate_limits: RateLimitConfig::new(
+                args.auth_rate_limit_per_ip,
+                args.auth_rate_limit_per_email,
+                Duration::from_secs(args.auth_rate_limit_window_secs),
+                args.speech_rate_limit_per_user,
+                Duration::from_secs(args.speech_rate_limit_window_secs),
+            ),
+            trusted_proxies: args.trusted_proxies,
         })
     }
 
@@ -0,0 +1,97 @@
+//! Reading HSK word and character lists.
+//!
+//! Word lists are tab-separated, one word per line: `level word pinyin english`,
+//! with tone-marked pinyin and `#` starting a comment line. This is the format
+//! of the lists bundled under `data/hsk`; fuller lists can be converted to it
+//! and loaded with the `import_hsk` binary. Character lists, such as the one
+//! published with HSK 3.0, have one `level character` pair per line.
+
+use crate::{
+    pinyin::is_hanzi,
+    records::{
+        self,
+        RecordError,
+    },
+};
+
+/// The bundled part of HSK 2.0: levels 1 to 3, without a character list.
+pub const BUNDLED_2_0: &str = include_str!("../../data/hsk/hsk2.tsv");
+/// The bundled part of HSK 3.0: level 1, without a character list.
+pub const BUNDLED_3_0: &str = include_str!("../../data/hsk/hsk3.tsv");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HskListEntry {
+    pub level: i64,
+    pub word: String,
+    pub pinyin: String,
+    pub english: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HskCharacterEntry {
+    pub level: i64,
+    pub character: char,
+}
+
+/// Parse a whole word list, skipping blank and comment lines.
+pub fn parse(text: &str) -> impl Iterator<Item = Result<(usize, HskListEntry), RecordError>> + '_ {
+    records::parse(text, parse_entry)
+}
+
+/// Parse one line of a word list; `None` for blank and comment lines.
+pub fn parse_line(line: &str) -> Option<Result<HskListEntry, &'static str>> {
+    records::parse_line(line, parse_entry)
+}
+
+/// Parse a whole character list, skipping blank and comment lines.
+pub fn parse_characters(
+    text: &str,
+) -> impl Iterator<Item = Result<(usize, HskCharacterEntry), RecordError>> + '_ {
+    records::parse(text, parse_character)
+}
+
+fn parse_entry(line: &str) -> Result<HskListEntry, &'static str> {
+    let mut fields = line.split('\t').map(str::trim);
+    let level = fields
+        .next()
+        .and_then(|level| level.parse().ok())
+        .ok_or("invalid level")?;
+    let word = fields
+        .next()
+        .filter(|word| !word.is_empty())
+        .ok_or("missing word")?;
+    let pinyin = fields
+        .next()
+        .filter(|pinyin| !pinyin.is_empty())
+        .ok_or("missing pinyin")?;
+    let english = fields.next().unwrap_or_default();
+    if fields.next().is_some() {
+        return Err("too many fields");
+    }
+
+    Ok(HskListEntry {
+        level,
+        word: word.to_string(),
+        pinyin: pinyin.to_string(),
+        english: english.to_string(),
+    })
+}
+
+fn parse_character(line: &str) -> Result<HskCharacterEntry, &'static str> {
+    let mut fields = line.split('\t').map(str::trim);
+    let level = fields
+        .next()
+        .and_then(|level| level.parse().ok())
+        .ok_or("invalid level")?;
+    let mut characters = fields.next().unwrap_or_default().chars();
+    let character = match (characters.next(), characters.next()) {
+        (Some(character), None) if is_hanzi(character) => character,
+        (None, _) => return Err("missing character"),
+        _ => return Err("not a single character"),
+    };
+    if fields.next().is_some() {
+        return Err("too many fields");
+    }
+
+    Ok(HskCharacterEntry { level, character })
+}
@@ -0,0 +1,524 @@
+//! HSK word and character lists, and how much of them a learner knows.
+//!
+//! Both the 2.0 standard (levels 1 to 6) and the 3.0 standard (levels 1 to 6
+//! and a combined 7-9 band) are kept. Lists are loaded into the `hsk_*` tables
+//! with the `import_hsk` binary, which replaces one standard at a time, and the
+//! bundled lists in [`hsk`] are loaded into a fresh database at startup. A
+//! standard's characters come from its published character list when one is
+//! imported with the words; otherwise each character is assigned the lowest
+//! level of a word that uses it.
+//!
+//! The bundled lists are only a part of each standard, so every answer about a
+//! standard comes with its [`ListStatus`]: which levels are loaded, and whether
+//! the lists were imported from files rather than bundled.
+//!
+//! Coverage is measured against the learner's vocabulary: a word is in their
+//! vocabulary if they have added it, and known once it passed its last review.
+
+pub mod hsk;
+
+use std::collections::HashSet;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    db::Database,
+    error::Result,
+    pinyin::is_hanzi,
+    records::RecordError,
+};
+
+/// Stored level of HSK 3.0's 7-9 band.
+const BAND_7_9: i64 = 7;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+pub enum Standard {
+    /// The 2012 standard, with six levels
+    #[default]
+    #[serde(rename = "2.0")]
+    #[sqlx(rename = "2.0")]
+    Hsk2,
+    /// The 2021 standard, with six levels and a 7-9 band
+    #[serde(rename = "3.0")]
+    #[sqlx(rename = "3.0")]
+    Hsk3,
+}
+
+impl Standard {
+    /// Highest level that can be asked for; HSK 3.0's 8 and 9 are its 7-9 band.
+    pub fn max_level(self) -> i64 {
+        match self {
+            Self::Hsk2 => 6,
+            Self::Hsk3 => 9,
+        }
+    }
+
+    /// The level a requested level is stored as, if the standard has it.
+    pub fn stored_level(self, level: i64) -> Option<i64> {
+        match self {
+            _ if !(1..=self.max_level()).contains(&level) => None,
+            Self::Hsk3 if level > BAND_7_9 => Some(BAND_7_9),
+            _ => Some(level),
+        }
+    }
+
+    /// The levels as stored, lowest first.
+    pub fn stored_levels(self) -> impl Iterator<Item = i64> {
+        let highest = self.stored_level(self.max_level()).unwrap_or(1);
+        1..=highest
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Hsk2 => "2.0",
+            Self::Hsk3 => "3.0",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HskWord {
+    pub id: i64,
+    pub word: String,
+    /// Tone-marked, e.g. `Běijīng`
+    pub pinyin: String,
+    pub english: String,
+    pub level: i64,
+    /// Whether the word is in the learner's vocabulary, when there is a learner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_vocabulary: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HskCharacter {
+    pub character: String,
+    pub level: i64,
+}
+
+/// How much of one level a learner has covered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LevelCoverage {
+    pub level: i64,
+    /// Words in the level's list; 0 if it has not been imported
+    pub words: i64,
+    pub words_in_vocabulary: i64,
+    pub words_known: i64,
+    pub characters: i64,
+    /// Characters used by any word in the learner's vocabulary
+    pub characters_in_vocabulary: i64,
+    /// Characters used by any known word
+    pub characters_known: i64,
+}
+
+/// What is loaded of a standard's lists.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListStatus {
+    /// Whether the lists were imported from files rather than bundled
+    pub imported: bool,
+    /// Whether the characters come from the standard's character list
+    pub character_list: bool,
+    /// Stored levels with any words, lowest first
+    pub levels: Vec<i64>,
+    /// Imported from files with the character list, and every level loaded
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportSummary {
+    pub words: usize,
+    pub characters: usize,
+    /// Lines that could not be read or repeat a word, which are left out
+    pub skipped: Vec<RecordError>,
+    /// Lines of the character list left out, likewise
+    pub skipped_characters: Vec<RecordError>,
+}
+
+#[derive(Clone)]
+pub struct CurriculumService {
+    db: Database,
+}
+
+impl CurriculumService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Replace a standard's word and character lists with those of an HSK list
+    /// file, in one transaction. A word listed twice is kept at its lower level.
+    pub async fn import(&self, standard: Standard, text: &str) -> Result<ImportSummary> {
+        self.import_lists(standard, text, None).await
+    }
+
+    /// Replace a standard's lists with its bundled one.
+    async fn import_bundled_list(&self, standard: Standard) -> Result<ImportSummary> {
+        let text = match standard {
+            Standard::Hsk2 => hsk::BUNDLED_2_0,
+            Standard::Hsk3 => hsk::BUNDLED_3_0,
+        };
+        self.replace_lists(standard, text, None, true).await
+    }
+
+    /// [`import`](Self::import), taking the characters and their levels from
+    /// the standard's character list if there is one rather than from the words.
+    pub async fn import_lists(
+        &self,
+        standard: Standard,
+        words_text: &str,
+        characters_text: Option<&str>,
+    ) -> Result<ImportSummary> {
+        self.replace_lists(standard, words_text, characters_text, false)
+            .await
+    }
+
+    async fn replace_lists(
+        &self,
+        standard: Standard,
+        words_text: &str,
+        characters_text: Option<&str>,
+        bundled: bool,
+    ) -> Result<ImportSummary> {
+        let mut summary = ImportSummary {
+            words: 0,
+            characters: 0,
+            skipped: Vec::new(),
+            skipped_characters: Vec::new(),
+        };
+
+        let mut entries = Vec::new();
+        for entry in hsk::parse(words_text) {
+            match entry {
+                Ok((line, mut entry)) => match standard.stored_level(entry.level) {
+                    Some(level) => {
+                        entry.level = level;
+                        entries.push((line, entry));
+                    }
+                    None => summary.skipped.push(RecordError {
+                        line,
+                        reason: "level out of range",
+                    }),
+                },
+                Err(e) => summary.skipped.push(e),
+            }
+        }
+        // Stable, so each level keeps the list's order
+        entries.sort_by_key(|(_, entry)| entry.level);
+
+        let mut tx = self.db.pool().begin().await?;
+        sqlx::query!("DELETE FROM hsk_words WHERE standard = ?1", standard)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM hsk_characters WHERE standard = ?1", standard)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM hsk_lists WHERE standard = ?1", standard)
+            .execute(&mut *tx)
+            .await?;
+        if !bundled {
+            let character_list = characters_text.is_some();
+            sqlx::query!(
+                "INSERT INTO hsk_lists (standard, character_list) VALUES (?1, ?2)",
+                standard,
+                character_list
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let mut words = HashSet::new();
+        let mut character_levels = Vec::new();
+        for (line, entry) in entries {
+            if !words.insert(entry.word.clone()) {
+                summary.skipped.push(RecordError {
+                    line,
+                    reason: "word listed twice",
+                });
+                continue;
+            }
+            let position = summary.words as i64;
+            sqlx::query!(
+                r#"
+                INSERT INTO hsk_words (standard, level, position, word, pinyin, english)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+                standard,
+                entry.level,
+                position,
+                entry.word,
+                entry.pinyin,
+                entry.english
+            )
+            .execute(&mut *tx)
+            .await?;
+            summary.words += 1;
+
+            if characters_text.is_none() {
+                character_levels.extend(entry.word.chars().filter(|&c| is_hanzi(c)).map(
+                    |character| {
+                        (
+                            line,
+                            hsk::HskCharacterEntry {
+                                level: entry.level,
+                                character,
+                            },
+                        )
+                    },
+                ));
+            }
+        }
+
+        if let Some(text) = characters_text {
+            for entry in hsk::parse_characters(text) {
+                match entry {
+                    Ok((line, mut entry)) => match standard.stored_level(entry.level) {
+                        Some(level) => {
+                            entry.level = level;
+                            character_levels.push((line, entry));
+                        }
+                        None => summary.skipped_characters.push(RecordError {
+                            line,
+                            reason: "level out of range",
+                        }),
+                    },
+                    Err(e) => summary.skipped_characters.push(e),
+                }
+            }
+            character_levels.sort_by_key(|(_, entry)| entry.level);
+        }
+
+        let mut characters = HashSet::new();
+        for (line, entry) in character_levels {
+            if !characters.insert(entry.character) {
+                // Repeats are expected when characters come from the words
+                if characters_text.is_some() {
+                    summary.skipped_characters.push(RecordError {
+                        line,
+                        reason: "character listed twice",
+                    });
+                }
+                continue;
+            }
+            let character = entry.character.to_string();
+            let position = summary.characters as i64;
+            sqlx::query!(
+                r#"
+                INSERT INTO hsk_characters (standard, character, level, position)
+                VALUES (?1, ?2, ?3, ?4)
+                "#,
+                standard,
+                character,
+                entry.level,
+                position
+            )
+            .execute(&mut *tx)
+            .await?;
+            summary.characters += 1;
+        }
+
+        tx.commit().await?;
+        Ok(summary)
+    }
+
+    /// Load the bundled lists of the standards that have no words yet, as on a
+    /// fresh database.
+    pub async fn import_bundled_if_empty(&self) -> Result<Vec<(Standard, ImportSummary)>> {
+        let mut imported = Vec::new();
+        for standard in [Standard::Hsk2, Standard::Hsk3] {
+            let words = sqlx::query_scalar!(
+                "SELECT COUNT(*) FROM hsk_words WHERE standard = ?1",
+                standard
+            )
+            .fetch_one(self.db.pool())
+            .await?;
+            if words == 0 {
+                imported.push((standard, self.import_bundled_list(standard).await?));
+            }
+        }
+        Ok(imported)
+    }
+
+    /// Load the bundled lists of both standards.
+    pub async fn import_bundled(&self) -> Result<Vec<(Standard, ImportSummary)>> {
+        Ok(vec![
+            (
+                Standard::Hsk2,
+                self.import_bundled_list(Standard::Hsk2).await?,
+            ),
+            (
+                Standard::Hsk3,
+                self.import_bundled_list(Standard::Hsk3).await?,
+            ),
+        ])
+    }
+
+    /// Which of a standard's levels are loaded and where its lists came from.
+    pub async fn list_status(&self, standard: Standard) -> Result<ListStatus> {
+        let levels = sqlx::query_scalar!(
+            "SELECT DISTINCT level FROM hsk_words WHERE standard = ?1 ORDER BY level",
+            standard
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+        let character_list = sqlx::query_scalar!(
+            r#"SELECT character_list as "character_list: bool" FROM hsk_lists WHERE standard = ?1"#,
+            standard
+        )
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        let imported = character_list.is_some();
+        let character_list = character_list.unwrap_or(false);
+        let complete =
+            imported && character_list && levels.iter().copied().eq(standard.stored_levels());
+        Ok(ListStatus {
+            imported,
+            character_list,
+            levels,
+            complete,
+        })
+    }
+
+    /// A page of a level's words in list order, with the total. With a user,
+    /// each word says whether it is in their vocabulary.
+    pub async fn level_words(
+        &self,
+        standard: Standard,
+        level: i64,
+        user_id: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<HskWord>, i64)> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT h.id as "id!", h.word, h.pinyin, h.english, h.level,
+                   w.id IS NOT NULL as "in_vocabulary!: bool"
+            FROM hsk_words h
+            LEFT JOIN user_words w ON w.user_id = ?3 AND w.chinese = h.word
+            WHERE h.standard = ?1 AND h.level = ?2
+            ORDER BY h.position
+            LIMIT ?4 OFFSET ?5
+            "#,
+            standard,
+            level,
+            user_id,
+            limit,
+            offset
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let total = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM hsk_words WHERE standard = ?1 AND level = ?2",
+            standard,
+            level
+        )
+        .fetch_one(self.db.pool())
+        .await?;
+
+        let words = rows
+            .into_iter()
+            .map(|row| HskWord {
+                id: row.id,
+                word: row.word,
+                pinyin: row.pinyin,
+                english: row.english,
+                level: row.level,
+                in_vocabulary: user_id.map(|_| row.in_vocabulary),
+            })
+            .collect();
+        Ok((words, total as i64))
+    }
+
+    /// A level's characters, in order of first appearance in its word list.
+    pub async fn level_characters(
+        &self,
+        standard: Standard,
+        level: i64,
+    ) -> Result<Vec<HskCharacter>> {
+        let characters = sqlx::query_as!(
+            HskCharacter,
+            r#"
+            SELECT character, level
+            FROM hsk_characters
+            WHERE standard = ?1 AND level = ?2
+            ORDER BY position
+            "#,
+            standard,
+            level
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(characters)
+    }
+
+    /// The user's coverage of every level of a standard, lowest first.
+    pub async fn coverage(&self, user_id: &str, standard: Standard) -> Result<Vec<LevelCoverage>> {
+        let words = sqlx::query!(
+            r#"
+            SELECT h.level, COUNT(*) as "words!: i64", COUNT(w.id) as "in_vocabulary!: i64",
+                   COUNT(CASE WHEN w.review_state = 'review' THEN 1 END) as "known!: i64"
+            FROM hsk_words h
+            LEFT JOIN user_words w ON w.user_id = ?2 AND w.chinese = h.word
+            WHERE h.standard = ?1
+            GROUP BY h.level
+            "#,
+            standard,
+            user_id
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let characters = sqlx::query!(
+            r#"
+            SELECT c.level, COUNT(*) as "characters!: i64",
+                   COUNT(CASE WHEN EXISTS (
+                       SELECT 1 FROM user_words w
+                       WHERE w.user_id = ?2 AND instr(w.chinese, c.character) > 0
+                   ) THEN 1 END) as "in_vocabulary!: i64",
+                   COUNT(CASE WHEN EXISTS (
+                       SELECT 1 FROM user_words w
+                       WHERE w.user_id = ?2 AND w.review_state = 'review'
+                         AND instr(w.chinese, c.character) > 0
+                   ) THEN 1 END) as "known!: i64"
+            FROM hsk_characters c
+            WHERE c.standard = ?1
+            GROUP BY c.level
+            "#,
+            standard,
+            user_id
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(standard
+            .stored_levels()
+            .map(|level| {
+                let mut coverage = LevelCoverage {
+                    level,
+                    words: 0,
+                    words_in_vocabulary: 0,
+                    words_known: 0,
+                    characters: 0,
+                    characters_in_vocabulary: 0,
+                    characters_known: 0,
+                };
+                if let Some(row) = words.iter().find(|row| row.level == level) {
+                    coverage.words = row.words;
+                    coverage.words_in_vocabulary = row.in_vocabulary;
+                    coverage.words_known = row.known;
+                }
+                if let Some(row) = characters.iter().find(|row| row.level == level) {
+                    coverage.characters = row.characters;
+                    coverage.characters_in_vocabulary = row.in_vocabulary;
+                    coverage.characters_known = row.known;
+                }
+                coverage
+            })
+            .collect())
+    }
+}
@@ -1,7 +1,15 @@
-use std::time::Duration;
+use std::{
+    collections::HashSet,
+    time::Duration,
+};
 
 use anyhow::Result;
+use serde::Serialize;
 use sqlx::{
+    migrate::{
+        Migrate,
+        Migrator,
+    },
     sqlite::{
         SqliteConnectOptions,
         SqlitePoolOptions,
@@ -9,6 +17,25 @@ use sqlx::{
     SqlitePool,
 };
 
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// How the database's schema compares to the migrations built into this binary.
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    pub applied: usize,
+    pub expected: usize,
+    /// Versions not yet applied
+    pub pending: Vec<i64>,
+    /// A migration that started but did not finish
+    pub dirty: Option<i64>,
+}
+
+impl MigrationStatus {
+    pub fn is_current(&self) -> bool {
+        self.pending.is_empty() && self.dirty.is_none()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Database {
     pool: SqlitePool,
@@ -29,13 +56,22 @@ impl Database {
             .filename(db_path)
             .create_if_missing(true);
 
-        let pool = SqlitePoolOptions::new()
-            .max_connections(10)
+        // Every connection to `:memory:` opens its own empty database, so keep
+        // exactly one alive for the pool's lifetime
+        let pool_options = if db_path == ":memory:" {
+            SqlitePoolOptions::new()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+        } else {
+            SqlitePoolOptions::new().max_connections(10)
+        };
+        let pool = pool_options
             .acquire_timeout(Duration::from_secs(3))
             .connect_with(connect_options)
             .await?;
 
-        sqlx::migrate!("./migrations").run(&pool).await?;
+        MIGRATOR.run(&pool).await?;
 
         Ok(Self { pool })
     }
@@ -43,4 +79,39 @@ impl Database {
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
     }
+
+    /// Round trip a trivial query through the pool.
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    pub async fn migration_status(&self) -> Result<MigrationStatus> {
+        let mut conn = self.pool.acquire().await?;
+        let dirty = conn.dirty_version().await?;
+        let applied: HashSet<i64> = conn
+            .list_applied_migrations()
+            .await?
+            .into_iter()
+            .map(|migration| migration.version)
+            .collect();
+
+        let expected: Vec<i64> = MIGRATOR
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| migration.version)
+            .collect();
+        let pending = expected
+            .iter()
+            .copied()
+            .filter(|version| !applied.contains(version))
+            .collect();
+
+        Ok(MigrationStatus {
+            applied: applied.len(),
+            expected: expected.len(),
+            pending,
+            dirty,
+        })
+    }
 }
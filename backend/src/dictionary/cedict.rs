@@ -0,0 +1,140 @@
+//! Reading CC-CEDICT's text format.
+//!
+//! Each line is `traditional simplified [pin1 yin1] /sense/sense/`, with `#`
+//! starting a comment line. A sense beginning `CL:` lists the word's measure
+//! words (`CL:個|个[ge4],位[wei4]`) rather than a meaning.
+
+use crate::{
+    pinyin::parse,
+    records::{
+        self,
+        RecordError,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CedictEntry {
+    pub traditional: String,
+    pub simplified: String,
+    /// Numbered pinyin as written, e.g. `Bei3 jing1`
+    pub pinyin: String,
+    pub senses: Vec<String>,
+    /// The entry's `CL:` sense without its prefix
+    pub classifiers: Option<String>,
+}
+
+/// Parse a whole file, skipping blank and comment lines.
+pub fn parse(text: &str) -> impl Iterator<Item = Result<(usize, CedictEntry), RecordError>> + '_ {
+    records::parse(text, parse_entry)
+}
+
+/// Parse one line; `None` for blank and comment lines.
+pub fn parse_line(line: &str) -> Option<Result<CedictEntry, &'static str>> {
+    records::parse_line(line, parse_entry)
+}
+
+fn parse_entry(line: &str) -> Result<CedictEntry, &'static str> {
+    let (traditional, rest) = line.split_once(' ').ok_or("missing simplified form")?;
+    let (simplified, rest) = rest.split_once(' ').ok_or("missing pinyin")?;
+    let rest = rest
+        .trim_start()
+        .strip_prefix('[')
+        .ok_or("missing pinyin")?;
+    let (pinyin, rest) = rest.split_once(']').ok_or("unterminated pinyin")?;
+    let senses = rest
+        .trim()
+        .strip_prefix('/')
+        .and_then(|senses| senses.strip_suffix('/'))
+        .ok_or("missing senses")?;
+
+    let mut classifiers = None;
+    let mut meanings = Vec::new();
+    for sense in senses
+        .split('/')
+        .map(str::trim)
+        .filter(|sense| !sense.is_empty())
+    {
+        match sense.strip_prefix("CL:") {
+            Some(list) => classifiers = Some(list.to_string()),
+            None => meanings.push(sense.to_string()),
+        }
+    }
+    if meanings.is_empty() && classifiers.is_none() {
+        return Err("no senses");
+    }
+
+    Ok(CedictEntry {
+        traditional: traditional.to_string(),
+        simplified: simplified.to_string(),
+        pinyin: pinyin.trim().to_string(),
+        senses: meanings,
+        classifiers,
+    })
+}
+
+/// Search keys for CC-CEDICT pinyin: lowercase numbered syllables with ü as
+/// `v` (`lv4 se4`), and the syllables run together without tones (`lvse`).
+/// Tokens that are not syllables, such as the latin letters of `卡拉OK` and the
+/// `·` of names, are kept lowercased; CC-CEDICT writes a tone on every syllable.
+pub fn search_keys(pinyin: &str) -> (String, String) {
+    let mut numbered = Vec::new();
+    let mut toneless = String::new();
+    for token in pinyin.split_whitespace() {
+        let syllables = parse::parse(token)
+            .ok()
+            .filter(|syllables| syllables.iter().all(|syllable| syllable.tone.is_some()));
+        match syllables {
+            Some(syllables) => {
+                for syllable in syllables {
+                    toneless.push_str(&syllable.spelling);
+                    numbered.push(syllable.numbered());
+                }
+            }
+            None => {
+                let token = token.to_lowercase();
+                toneless.extend(token.chars().filter(|c| !c.is_ascii_digit()));
+                numbered.push(token);
+            }
+        }
+    }
+    (numbered.join(" "), toneless)
+}
+
+/// Tone-marked form of CC-CEDICT pinyin, keeping the capital of proper nouns.
+pub fn marked(pinyin: &str) -> String {
+    pinyin
+        .split_whitespace()
+        .map(|token| {
+            let marked = token
+                .ends_with(|c: char| c.is_ascii_digit())
+                .then(|| parse::to_marked(token).ok())
+                .flatten();
+            match marked {
+                Some(marked) if token.starts_with(|c: char| c.is_uppercase()) => {
+                    let mut chars = marked.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_uppercase().chain(chars).collect())
+                        .unwrap_or_default()
+                }
+                Some(marked) => marked,
+                None => token.replace("u:", "ü"),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A loose part of speech read off the wording of a sense; CC-CEDICT itself
+/// does not record one.
+pub fn part_of_speech(meaning: &str) -> Option<&'static str> {
+    if meaning.starts_with("to ") {
+        Some("verb")
+    } else if meaning.starts_with("classifier for") {
+        Some("classifier")
+    } else if meaning.starts_with("surname ") {
+        Some("surname")
+    } else {
+        None
+    }
+}
@@ -0,0 +1,420 @@
+//! Chinese-English dictionary backed by CC-CEDICT.
+//!
+//! The dictionary is not bundled: a CC-CEDICT file is loaded into the
+//! `dictionary_*` tables with the `import_cedict` binary, replacing whatever was
+//! there. Entries can then be looked up by hanzi (simplified or traditional),
+//! by pinyin with or without tones, by prefix, and from English.
+
+pub mod cedict;
+
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+};
+
+use serde::Serialize;
+
+use crate::{
+    db::Database,
+    error::Result,
+    pinyin::parse::PinyinSyllable,
+    records::RecordError,
+    text::Segmenter,
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DictionaryEntry {
+    pub id: i64,
+    pub traditional: String,
+    pub simplified: String,
+    /// Numbered pinyin as CC-CEDICT writes it, e.g. `Bei3 jing1`
+    pub pinyin: String,
+    /// e.g. `Běi jīng`
+    pub pinyin_marked: String,
+    pub definitions: Vec<Definition>,
+    pub classifiers: Vec<Classifier>,
+}
+
+/// One sense of an entry, in the shape of the app's `WordDefinition`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Definition {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part_of_speech: Option<String>,
+    pub meaning: String,
+}
+
+/// A measure word used with an entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Classifier {
+    pub traditional: String,
+    pub simplified: String,
+    pub pinyin: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportSummary {
+    pub entries: usize,
+    pub senses: usize,
+    /// Lines that could not be read, which are left out
+    pub skipped: Vec<RecordError>,
+}
+
+#[derive(sqlx::FromRow)]
+struct EntryRow {
+    id: i64,
+    traditional: String,
+    simplified: String,
+    pinyin: String,
+    pinyin_numbered: String,
+    classifiers: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SenseRow {
+    entry_id: i64,
+    meaning: String,
+    part_of_speech: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct DictionaryService {
+    db: Database,
+}
+
+impl DictionaryService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Replace the dictionary with the entries of a CC-CEDICT file, in one transaction.
+    pub async fn import_cedict(&self, text: &str) -> Result<ImportSummary> {
+        let mut summary = ImportSummary {
+            entries: 0,
+            senses: 0,
+            skipped: Vec::new(),
+        };
+        let mut tx = self.db.pool().begin().await?;
+
+        sqlx::query!("DELETE FROM dictionary_senses")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM dictionary_entries")
+            .execute(&mut *tx)
+            .await?;
+
+        let segmenter = Segmenter::bundled();
+        for entry in cedict::parse(text) {
+            let entry = match entry {
+                Ok((_, entry)) => entry,
+                Err(e) => {
+                    summary.skipped.push(e);
+                    continue;
+                }
+            };
+            let (pinyin_numbered, pinyin_toneless) = cedict::search_keys(&entry.pinyin);
+            let frequency = segmenter.frequency(&entry.simplified).unwrap_or(0);
+            let entry_id = sqlx::query!(
+                r#"
+                INSERT INTO dictionary_entries (traditional, simplified, pinyin, pinyin_numbered,
+                                                pinyin_toneless, classifiers, frequency)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                "#,
+                entry.traditional,
+                entry.simplified,
+                entry.pinyin,
+                pinyin_numbered,
+                pinyin_toneless,
+                entry.classifiers,
+                frequency
+            )
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+
+            for (position, meaning) in entry.senses.iter().enumerate() {
+                let position = position as i64;
+                let part_of_speech = cedict::part_of_speech(meaning);
+                sqlx::query!(
+                    r#"
+                    INSERT INTO dictionary_senses (entry_id, position, meaning, part_of_speech)
+                    VALUES (?1, ?2, ?3, ?4)
+                    "#,
+                    entry_id,
+                    position,
+                    meaning,
+                    part_of_speech
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            summary.entries += 1;
+            summary.senses += entry.senses.len();
+        }
+
+        tx.commit().await?;
+        Ok(summary)
+    }
+
+    pub async fn entry_count(&self) -> Result<i64> {
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM dictionary_entries")
+            .fetch_one(self.db.pool())
+            .await?;
+        Ok(count as i64)
+    }
+
+    /// Entries written exactly `hanzi`, in simplified or traditional characters.
+    pub async fn lookup(&self, hanzi: &str, limit: usize) -> Result<Vec<DictionaryEntry>> {
+        let rows = sqlx::query_as!(
+            EntryRow,
+            r#"
+            SELECT id as "id!", traditional, simplified, pinyin, pinyin_numbered, classifiers
+            FROM dictionary_entries
+            WHERE simplified = ?1 OR traditional = ?1
+            "#,
+            hanzi
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        self.entries(by_frequency(rows, limit)).await
+    }
+
+    /// Entries by id, in the order given; ids with no entry are left out.
+    pub async fn get_entries(&self, ids: &[i64]) -> Result<Vec<DictionaryEntry>> {
+        let mut rows = self.entry_rows(ids).await?;
+        rows.sort_by_key(|row| ids.iter().position(|&id| id == row.id));
+        self.entries(rows).await
+    }
+
+    /// Entries starting with `prefix`, in simplified or traditional characters,
+    /// shortest first and then the most common.
+    pub async fn search_prefix(&self, prefix: &str, limit: usize) -> Result<Vec<DictionaryEntry>> {
+        // GLOB rather than LIKE so the indexes apply; its wildcards are escaped
+        // by putting each in a bracket expression
+        let mut pattern = String::new();
+        for c in prefix.chars() {
+            match c {
+                '*' | '?' | '[' | ']' => {
+                    pattern.push('[');
+                    pattern.push(c);
+                    pattern.push(']');
+                }
+                _ => pattern.push(c),
+            }
+        }
+        pattern.push('*');
+        let limit = limit as i64;
+
+        // Shorter words first, so the prefix itself leads, then as by_frequency
+        let rows = sqlx::query_as!(
+            EntryRow,
+            r#"
+            SELECT id as "id!", traditional, simplified, pinyin, pinyin_numbered, classifiers
+            FROM dictionary_entries
+            WHERE simplified GLOB ?1 OR traditional GLOB ?1
+            ORDER BY LENGTH(simplified), frequency DESC, pinyin GLOB '[A-Z]*', id
+            LIMIT ?2
+            "#,
+            pattern,
+            limit
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+        self.entries(rows).await
+    }
+
+    /// Entries read as `syllables`. Syllables without a tone match any tone;
+    /// when none has one, the syllables only need to run together the same way,
+    /// so `xian` also finds 西安.
+    pub async fn search_pinyin(
+        &self,
+        syllables: &[PinyinSyllable],
+        limit: usize,
+    ) -> Result<Vec<DictionaryEntry>> {
+        let toneless: String = syllables
+            .iter()
+            .map(|syllable| syllable.spelling.as_str())
+            .collect();
+        let rows = sqlx::query_as!(
+            EntryRow,
+            r#"
+            SELECT id as "id!", traditional, simplified, pinyin, pinyin_numbered, classifiers
+            FROM dictionary_entries
+            WHERE pinyin_toneless = ?1
+            "#,
+            toneless
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let rows = if syllables.iter().any(|syllable| syllable.tone.is_some()) {
+            rows.into_iter()
+                .filter(|row| reads_as(&row.pinyin_numbered, syllables))
+                .collect()
+        } else {
+            rows
+        };
+        self.entries(by_frequency(rows, limit)).await
+    }
+
+    /// Entries with a sense matching every word of `query`, best match first.
+    pub async fn search_english(&self, query: &str, limit: usize) -> Result<Vec<DictionaryEntry>> {
+        // Each word quoted, so FTS5 syntax in the query is taken literally
+        let words: Vec<String> = query
+            .split_whitespace()
+            .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+            .collect();
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+        let fts_query = words.join(" ");
+        let limit = limit as i64;
+
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT entry_id as "entry_id!"
+            FROM (
+                SELECT s.entry_id, dictionary_senses_fts.rank AS rank
+                FROM dictionary_senses_fts
+                JOIN dictionary_senses s ON s.id = dictionary_senses_fts.rowid
+                WHERE dictionary_senses_fts MATCH ?1
+            )
+            GROUP BY entry_id
+            ORDER BY MIN(rank), entry_id
+            LIMIT ?2
+            "#,
+            fts_query,
+            limit
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut rows = self.entry_rows(&ids).await?;
+        let position: HashMap<i64, usize> =
+            ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        rows.sort_by_key(|row| position[&row.id]);
+        let mut entries = self.entries(rows).await?;
+
+        // A sense that is the query itself ("eat", "to eat") beats one merely containing it
+        let query = query.trim().to_lowercase();
+        let exact = |entry: &DictionaryEntry| {
+            entry.definitions.iter().any(|definition| {
+                let meaning = definition.meaning.to_lowercase();
+                meaning == query || meaning.strip_prefix("to ") == Some(query.as_str())
+            })
+        };
+        entries.sort_by_key(|entry| !exact(entry));
+        Ok(entries)
+    }
+
+    async fn entry_rows(&self, ids: &[i64]) -> Result<Vec<EntryRow>> {
+        let ids = serde_json::to_string(ids)?;
+        let rows = sqlx::query_as!(
+            EntryRow,
+            r#"
+            SELECT id as "id!", traditional, simplified, pinyin, pinyin_numbered, classifiers
+            FROM dictionary_entries
+            WHERE id IN (SELECT value FROM json_each(?1))
+            "#,
+            ids
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+        Ok(rows)
+    }
+
+    /// Attach senses to entry rows, keeping their order.
+    async fn entries(&self, rows: Vec<EntryRow>) -> Result<Vec<DictionaryEntry>> {
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<i64> = rows.iter().map(|row| row.id).collect();
+        let ids = serde_json::to_string(&ids)?;
+        let senses = sqlx::query_as!(
+            SenseRow,
+            r#"
+            SELECT entry_id, meaning, part_of_speech
+            FROM dictionary_senses
+            WHERE entry_id IN (SELECT value FROM json_each(?1))
+            ORDER BY entry_id, position
+            "#,
+            ids
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut definitions: HashMap<i64, Vec<Definition>> = HashMap::new();
+        for sense in senses {
+            definitions
+                .entry(sense.entry_id)
+                .or_default()
+                .push(Definition {
+                    part_of_speech: sense.part_of_speech,
+                    meaning: sense.meaning,
+                });
+        }
+
+        Ok(rows
+            .into_iter()
+            .map(|row| DictionaryEntry {
+                pinyin_marked: cedict::marked(&row.pinyin),
+                definitions: definitions.remove(&row.id).unwrap_or_default(),
+                classifiers: row
+                    .classifiers
+                    .as_deref()
+                    .map(parse_classifiers)
+                    .unwrap_or_default(),
+                id: row.id,
+                traditional: row.traditional,
+                simplified: row.simplified,
+                pinyin: row.pinyin,
+            })
+            .collect())
+    }
+}
+
+/// The `limit` most common entries, by the segmentation lexicon's word
+/// frequencies, with lowercase readings ahead of proper nouns.
+fn by_frequency(mut rows: Vec<EntryRow>, limit: usize) -> Vec<EntryRow> {
+    let segmenter = Segmenter::bundled();
+    rows.sort_by_key(|row| {
+        (
+            Reverse(segmenter.frequency(&row.simplified).unwrap_or(0)),
+            row.pinyin.starts_with(|c: char| c.is_uppercase()),
+            row.id,
+        )
+    });
+    rows.truncate(limit);
+    rows
+}
+
+/// Whether numbered pinyin (`bei3 jing1`) reads as `syllables`, any tone
+/// matching a syllable given without one.
+fn reads_as(numbered: &str, syllables: &[PinyinSyllable]) -> bool {
+    let entry: Vec<&str> = numbered.split(' ').collect();
+    entry.len() == syllables.len()
+        && entry.iter().zip(syllables).all(|(written, syllable)| {
+            let (spelling, tone) = written.split_at(written.len().saturating_sub(1));
+            spelling == syllable.spelling
+                && syllable
+                    .tone
+                    .is_none_or(|expected| tone == expected.to_string())
+        })
+}
+
+/// `個|个[ge4],位[wei4]` into its measure words.
+fn parse_classifiers(list: &str) -> Vec<Classifier> {
+    list.split(',')
+        .filter_map(|item| {
+            let (hanzi, pinyin) = item.trim().split_once('[')?;
+            let pinyin = pinyin.strip_suffix(']')?;
+            let (traditional, simplified) = hanzi.split_once('|').unwrap_or((hanzi, hanzi));
+            Some(Classifier {
+                traditional: traditional.to_string(),
+                simplified: simplified.to_string(),
+                pinyin: pinyin.to_string(),
+            })
+        })
+        .collect()
+}
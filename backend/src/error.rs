@@ -1,5 +1,9 @@
 use axum::{
-    http::StatusCode,
+    http::{
+        header,
+        HeaderValue,
+        StatusCode,
+    },
     response::{
         IntoResponse,
         Response,
@@ -9,6 +13,11 @@ use axum::{
 use serde_json::json;
 use thiserror::Error;
 
+use crate::speech::{
+    audio::AudioError,
+    SpeechError,
+};
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
@@ -26,6 +35,24 @@ pub enum AppError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Too many requests, retry after {retry_after_secs} seconds")]
+    RateLimited { retry_after_secs: u64 },
+
+    #[error("Invalid audio: {0}")]
+    Audio(AudioError),
+
+    #[error("Speech evaluation failed: {0}")]
+    Speech(#[from] SpeechError),
+
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
 
@@ -42,6 +69,60 @@ impl IntoResponse for AppError {
                 "UNAUTHORIZED",
             ),
             AppError::BadRequest(_) => (StatusCode::BAD_REQUEST, "Bad request", "BAD_REQUEST"),
+            AppError::NotFound(_) => (StatusCode::NOT_FOUND, "Not found", "NOT_FOUND"),
+            AppError::Conflict(_) => (StatusCode::CONFLICT, "Conflict", "CONFLICT"),
+            AppError::PayloadTooLarge(_) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Payload too large",
+                "PAYLOAD_TOO_LARGE",
+            ),
+            AppError::RateLimited { .. } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests",
+                "RATE_LIMITED",
+            ),
+            AppError::Audio(AudioError::UnknownFormat | AudioError::UnsupportedCodec(_)) => (
+                StatusCode::BAD_REQUEST,
+                "Unsupported audio format",
+                "UNSUPPORTED_AUDIO_FORMAT",
+            ),
+            AppError::Audio(_) => (StatusCode::BAD_REQUEST, "Invalid audio", "INVALID_AUDIO"),
+            AppError::Speech(e) => {
+                let (status, message) = match e {
+                    SpeechError::AudioTooShort(_) => (
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "Audio too short or silent",
+                    ),
+                    SpeechError::UnsupportedEncoding(_) => {
+                        (StatusCode::BAD_REQUEST, "Unsupported audio format")
+                    }
+                    SpeechError::InvalidRequest(_) => {
+                        (StatusCode::BAD_REQUEST, "Invalid evaluation request")
+                    }
+                    SpeechError::QuotaExceeded(_) | SpeechError::Unavailable(_) => (
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "Speech service unavailable",
+                    ),
+                    SpeechError::Timeout(_) => {
+                        (StatusCode::GATEWAY_TIMEOUT, "Speech service timed out")
+                    }
+                    // Our credentials or the provider's output, not the caller's request
+                    SpeechError::Auth(_)
+                    | SpeechError::MalformedResponse(_)
+                    | SpeechError::Provider { .. } => {
+                        tracing::error!("Speech provider error: {}", e);
+                        (StatusCode::BAD_GATEWAY, "Speech evaluation failed")
+                    }
+                    SpeechError::Internal(_) => {
+                        tracing::error!("Speech evaluation error: {}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            "Speech evaluation failed",
+                        )
+                    }
+                };
+                (status, message, e.code())
+            }
             AppError::Database(_) => {
                 tracing::error!("Database error: {}", self);
                 (
@@ -88,13 +169,39 @@ impl IntoResponse for AppError {
             "error": error_message,
             "code": error_code,
             "details": match &self {
-                AppError::BadRequest(msg) => Some(msg.as_str()),
-                AppError::InternalServerError(msg) => Some(msg.as_str()),
+                AppError::BadRequest(msg) => Some(msg.clone()),
+                AppError::NotFound(msg) => Some(msg.clone()),
+                AppError::Conflict(msg) => Some(msg.clone()),
+                AppError::PayloadTooLarge(msg) => Some(msg.clone()),
+                AppError::RateLimited { retry_after_secs } => {
+                    Some(format!("Try again in {} seconds", retry_after_secs))
+                }
+                AppError::Audio(e) => Some(e.to_string()),
+                // Provider credential problems are ours to fix, not the caller's to read
+                AppError::Speech(SpeechError::Auth(_) | SpeechError::Internal(_)) => None,
+                AppError::Speech(e) => Some(e.to_string()),
+                AppError::InternalServerError(msg) => Some(msg.clone()),
                 _ => None,
             }
         }));
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let AppError::RateLimited { retry_after_secs } = self {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+        }
+        response
+    }
+}
+
+impl From<AudioError> for AppError {
+    fn from(error: AudioError) -> Self {
+        match error {
+            // Over-length recordings are a plain bad request, as before decoding existed
+            AudioError::TooLong(_) => AppError::BadRequest(error.to_string()),
+            other => AppError::Audio(other),
+        }
     }
 }
 
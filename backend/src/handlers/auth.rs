@@ -2,6 +2,7 @@ use axum::{
     extract::{
         Extension,
         Json,
+        Path,
     },
     http::HeaderMap,
     response::Json as ResponseJson,
@@ -13,6 +14,13 @@ use serde_json::{
 
 use crate::{
     auth::{
+        audit::{
+            AuditEvent,
+            AuditEventKind,
+            AuditService,
+        },
+        device,
+        extractor::AuthUser,
         jwt::JwtService,
         password::{
             AuthResponse,
@@ -20,17 +28,55 @@ use crate::{
             PasswordAuthService,
             RegisterRequest,
         },
-        session::SessionService,
+        session::{
+            RefreshRotation,
+            SessionService,
+        },
     },
     config::Config,
     error::Result,
+    middleware::client_ip::ClientIp,
+    models::User,
 };
 
+/// The client's user agent, for session tracking.
+fn user_agent(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("user-agent")
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+/// Open a session for a user who just signed in, with its first tokens.
+async fn start_session(
+    jwt_service: &JwtService,
+    session_service: &SessionService,
+    user: User,
+    ip_address: Option<String>,
+    headers: &HeaderMap,
+) -> Result<AuthResponse> {
+    let session = session_service
+        .create_session(&user.id, ip_address, user_agent(headers))
+        .await?;
+    let refresh = session_service.start_refresh_family(&session.id).await?;
+
+    // Generate JWT tokens
+    let access_token = jwt_service.create_access_token(&user.id, &session.id)?;
+    let refresh_token = jwt_service.create_refresh_token(&user.id, &session.id, &refresh)?;
+
+    Ok(AuthResponse {
+        user: user.to_public(),
+        access_token,
+        refresh_token,
+    })
+}
+
 pub async fn register(
     Extension(password_auth): Extension<PasswordAuthService>,
     Extension(jwt_service): Extension<JwtService>,
     Extension(session_service): Extension<SessionService>,
     Extension(config): Extension<Config>,
+    ClientIp(ip_address): ClientIp,
     headers: HeaderMap,
     Json(request): Json<RegisterRequest>,
 ) -> Result<ResponseJson<AuthResponse>> {
@@ -39,78 +85,39 @@ pub async fn register(
         tracing::debug!("Request headers: {:?}", headers);
     }
 
-    // Get IP address and user agent for session tracking
-    let ip_address = headers
-        .get("x-forwarded-for")
-        .or_else(|| headers.get("x-real-ip"))
-        .and_then(|h| h.to_str().ok())
-        .map(|s| s.to_string());
-
-    let user_agent = headers
-        .get("user-agent")
-        .and_then(|h| h.to_str().ok())
-        .map(|s| s.to_string());
-
     // Register user
     let user = password_auth.register(request).await?;
 
-    // Create session
-    let session = session_service
-        .create_session(&user.id, ip_address, user_agent)
-        .await?;
-
-    // Generate JWT tokens
-    let access_token = jwt_service.create_access_token(&user.id, &session.id)?;
-    let refresh_token = jwt_service.create_refresh_token(&user.id, &session.id)?;
-
-    Ok(ResponseJson(AuthResponse {
-        user: user.to_public(),
-        access_token,
-        refresh_token,
-    }))
+    let response =
+        start_session(&jwt_service, &session_service, user, ip_address, &headers).await?;
+    Ok(ResponseJson(response))
 }
 
 pub async fn login(
     Extension(password_auth): Extension<PasswordAuthService>,
     Extension(jwt_service): Extension<JwtService>,
     Extension(session_service): Extension<SessionService>,
+    ClientIp(ip_address): ClientIp,
     headers: HeaderMap,
     Json(request): Json<LoginRequest>,
 ) -> Result<ResponseJson<AuthResponse>> {
-    // Get IP address and user agent for session tracking
-    let ip_address = headers
-        .get("x-forwarded-for")
-        .or_else(|| headers.get("x-real-ip"))
-        .and_then(|h| h.to_str().ok())
-        .map(|s| s.to_string());
-
-    let user_agent = headers
-        .get("user-agent")
-        .and_then(|h| h.to_str().ok())
-        .map(|s| s.to_string());
-
     // Authenticate user
     let user = password_auth.login(request).await?;
 
-    // Create session
-    let session = session_service
-        .create_session(&user.id, ip_address, user_agent)
-        .await?;
-
-    // Generate JWT tokens
-    let access_token = jwt_service.create_access_token(&user.id, &session.id)?;
-    let refresh_token = jwt_service.create_refresh_token(&user.id, &session.id)?;
-
-    Ok(ResponseJson(AuthResponse {
-        user: user.to_public(),
-        access_token,
-        refresh_token,
-    }))
+    let response =
+        start_session(&jwt_service, &session_service, user, ip_address, &headers).await?;
+    Ok(ResponseJson(response))
 }
 
+/// Exchange a refresh token for a new access token and a new refresh token.
+/// The presented refresh token is spent; presenting it again revokes the session,
+/// unless it is within seconds, as when two tabs refresh at once.
 pub async fn refresh_token(
     Extension(jwt_service): Extension<JwtService>,
     Extension(session_service): Extension<SessionService>,
+    Extension(audit): Extension<AuditService>,
+    ClientIp(ip_address): ClientIp,
+    headers: HeaderMap,
     Json(payload): Json<Value>,
 ) -> Result<ResponseJson<Value>> {
     let refresh_token = payload
@@ -118,68 +125,104 @@ pub async fn refresh_token(
         .and_then(|v| v.as_str())
         .ok_or_else(|| crate::error::AppError::BadRequest("Missing refresh token".to_string()))?;
 
-    let (new_access_token, claims) = jwt_service.refresh_access_token(refresh_token)?;
+    let (claims, refresh) = jwt_service.verify_refresh_token(refresh_token)?;
 
-    // Verify session still exists
-    let session = session_service.get_session(&claims.session_id).await?;
-    if session.is_none() {
-        return Err(crate::error::AppError::Unauthorized);
+    match session_service
+        .rotate_refresh_token(&claims.sub, &claims.session_id, &refresh)
+        .await?
+    {
+        RefreshRotation::Rotated(next) => Ok(ResponseJson(json!({
+            "access_token": jwt_service.create_access_token(&claims.sub, &claims.session_id)?,
+            "refresh_token": jwt_service.create_refresh_token(&claims.sub, &claims.session_id, &next)?,
+        }))),
+        RefreshRotation::Reused { user_id } => {
+            audit
+                .record(&AuditEvent {
+                    kind: AuditEventKind::RefreshTokenReuse,
+                    user_id: Some(user_id),
+                    session_id: Some(claims.session_id),
+                    ip_address,
+                    user_agent: user_agent(&headers),
+                    details: Some(format!("Generation {} presented again", refresh.generation)),
+                })
+                .await?;
+            Err(crate::error::AppError::Unauthorized)
+        }
+        RefreshRotation::Invalid => Err(crate::error::AppError::Unauthorized),
     }
+}
 
-    // Update session activity
+/// End the caller's current session.
+pub async fn logout(
+    Extension(session_service): Extension<SessionService>,
+    auth_user: AuthUser,
+) -> Result<ResponseJson<Value>> {
     session_service
-        .update_session_activity(&claims.session_id)
+        .revoke_session(&auth_user.session_id)
         .await?;
-
-    Ok(ResponseJson(json!({
-        "access_token": new_access_token
-    })))
+    Ok(ResponseJson(json!({"success": true})))
 }
 
-pub async fn logout(
+/// End every session of the caller, this one included.
+pub async fn logout_all(
     Extension(session_service): Extension<SessionService>,
-    Json(payload): Json<Value>,
+    auth_user: AuthUser,
 ) -> Result<ResponseJson<Value>> {
-    let session_id = payload
-        .get("session_id")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| crate::error::AppError::BadRequest("Missing session_id".to_string()))?;
-
-    session_service.revoke_session(session_id).await?;
+    session_service
+        .revoke_all_user_sessions(&auth_user.user_id)
+        .await?;
     Ok(ResponseJson(json!({"success": true})))
 }
 
-pub async fn logout_all(
+/// The caller's live sessions, most recently used first, with the current one
+/// marked and each one's device named from its user agent.
+pub async fn list_sessions(
     Extension(session_service): Extension<SessionService>,
-    Json(payload): Json<Value>,
+    auth_user: AuthUser,
 ) -> Result<ResponseJson<Value>> {
-    let user_id = payload
-        .get("user_id")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| crate::error::AppError::BadRequest("Missing user_id".to_string()))?;
+    let sessions: Vec<Value> = session_service
+        .list_user_sessions(&auth_user.user_id)
+        .await?
+        .into_iter()
+        .map(|session| {
+            json!({
+                "id": session.id,
+                "current": session.id == auth_user.session_id,
+                "device": device::describe(session.user_agent.as_deref()),
+                "ip_address": session.ip_address,
+                "created_at": session.created_at,
+                "last_used_at": session.last_used_at,
+                "expires_at": session.expires_at,
+            })
+        })
+        .collect();
+
+    Ok(ResponseJson(json!({"sessions": sessions})))
+}
 
-    session_service.revoke_all_user_sessions(user_id).await?;
+/// End one of the caller's sessions, such as a device they no longer use.
+pub async fn revoke_session(
+    Extension(session_service): Extension<SessionService>,
+    auth_user: AuthUser,
+    Path(session_id): Path<String>,
+) -> Result<ResponseJson<Value>> {
+    if !session_service
+        .revoke_user_session(&auth_user.user_id, &session_id)
+        .await?
+    {
+        return Err(crate::error::AppError::NotFound(
+            "Session not found".to_string(),
+        ));
+    }
     Ok(ResponseJson(json!({"success": true})))
 }
 
 pub async fn me(
     Extension(password_auth): Extension<PasswordAuthService>,
-    Extension(jwt_service): Extension<JwtService>,
-    headers: HeaderMap,
+    auth_user: AuthUser,
 ) -> Result<ResponseJson<Value>> {
-    // Extract token from Authorization header
-    let auth_header = headers
-        .get("authorization")
-        .and_then(|h| h.to_str().ok())
-        .and_then(|h| h.strip_prefix("Bearer "))
-        .ok_or_else(|| crate::error::AppError::Unauthorized)?;
-
-    // Verify token
-    let claims = jwt_service.verify_token(auth_header)?;
-
-    // Get user
     let user = password_auth
-        .get_user_by_id(&claims.sub)
+        .get_user_by_id(&auth_user.user_id)
         .await?
         .ok_or_else(|| crate::error::AppError::Unauthorized)?;
 
@@ -0,0 +1,140 @@
+use axum::{
+    extract::{
+        Extension,
+        Path,
+        Query,
+    },
+    response::Result as AxumResult,
+    Json,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    auth::extractor::{
+        AuthUser,
+        OptionalAuthUser,
+    },
+    curriculum::{
+        CurriculumService,
+        HskCharacter,
+        HskWord,
+        LevelCoverage,
+        ListStatus,
+        Standard,
+    },
+    error::AppError,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct StandardQuery {
+    /// `2.0` (the default) or `3.0`
+    #[serde(default)]
+    pub standard: Standard,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LevelWordsQuery {
+    #[serde(default)]
+    pub standard: Standard,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LevelWords {
+    pub standard: Standard,
+    pub level: i64,
+    pub words: Vec<HskWord>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub list: ListStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LevelCharacters {
+    pub standard: Standard,
+    pub level: i64,
+    pub characters: Vec<HskCharacter>,
+    pub list: ListStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Coverage {
+    pub standard: Standard,
+    pub levels: Vec<LevelCoverage>,
+    pub list: ListStatus,
+}
+
+/// The stored level for a requested one, or a 400 naming the standard's levels.
+fn stored_level(standard: Standard, level: i64) -> AxumResult<i64, AppError> {
+    standard.stored_level(level).ok_or_else(|| {
+        AppError::BadRequest(format!(
+            "HSK {} has levels 1 to {}",
+            standard.as_str(),
+            standard.max_level()
+        ))
+    })
+}
+
+pub async fn level_words(
+    OptionalAuthUser(auth_user): OptionalAuthUser,
+    Extension(curriculum): Extension<CurriculumService>,
+    Path(level): Path<i64>,
+    Query(query): Query<LevelWordsQuery>,
+) -> AxumResult<Json<LevelWords>, AppError> {
+    let user_id = auth_user.map(|auth_user| auth_user.user_id);
+    let stored = stored_level(query.standard, level)?;
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let (words, total) = curriculum
+        .level_words(query.standard, stored, user_id.as_deref(), limit, offset)
+        .await?;
+    let list = curriculum.list_status(query.standard).await?;
+
+    Ok(Json(LevelWords {
+        standard: query.standard,
+        level,
+        words,
+        total,
+        limit,
+        offset,
+        list,
+    }))
+}
+
+pub async fn level_characters(
+    Extension(curriculum): Extension<CurriculumService>,
+    Path(level): Path<i64>,
+    Query(query): Query<StandardQuery>,
+) -> AxumResult<Json<LevelCharacters>, AppError> {
+    let stored = stored_level(query.standard, level)?;
+    let characters = curriculum.level_characters(query.standard, stored).await?;
+    let list = curriculum.list_status(query.standard).await?;
+
+    Ok(Json(LevelCharacters {
+        standard: query.standard,
+        level,
+        characters,
+        list,
+    }))
+}
+
+pub async fn coverage(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(curriculum): Extension<CurriculumService>,
+    Query(query): Query<StandardQuery>,
+) -> AxumResult<Json<Coverage>, AppError> {
+    let levels = curriculum.coverage(&user_id, query.standard).await?;
+    let list = curriculum.list_status(query.standard).await?;
+
+    Ok(Json(Coverage {
+        standard: query.standard,
+        levels,
+        list,
+    }))
+}
@@ -0,0 +1,74 @@
+use axum::{
+    extract::{
+        Extension,
+        Query,
+    },
+    response::Result as AxumResult,
+    Json,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    dictionary::{
+        DictionaryEntry,
+        DictionaryService,
+    },
+    error::AppError,
+    pinyin::parse,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct DictionarySearchQuery {
+    /// Exact simplified or traditional word
+    pub hanzi: Option<String>,
+    /// Pinyin in any style, with or without tones
+    pub pinyin: Option<String>,
+    /// English words all found in one sense
+    pub english: Option<String>,
+    /// Start of a simplified or traditional word
+    pub prefix: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DictionarySearchResponse {
+    pub entries: Vec<DictionaryEntry>,
+}
+
+pub async fn search(
+    Extension(dictionary): Extension<DictionaryService>,
+    Query(query): Query<DictionarySearchQuery>,
+) -> AxumResult<Json<DictionarySearchResponse>, AppError> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let given = |value: Option<String>| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let entries = match (
+        given(query.hanzi),
+        given(query.pinyin),
+        given(query.english),
+        given(query.prefix),
+    ) {
+        (Some(hanzi), None, None, None) => dictionary.lookup(&hanzi, limit).await?,
+        (None, Some(pinyin), None, None) => {
+            let syllables = parse::parse(&pinyin)
+                .map_err(|e| AppError::BadRequest(format!("Invalid pinyin: {}", e)))?;
+            dictionary.search_pinyin(&syllables, limit).await?
+        }
+        (None, None, Some(english), None) => dictionary.search_english(&english, limit).await?,
+        (None, None, None, Some(prefix)) => dictionary.search_prefix(&prefix, limit).await?,
+        _ => {
+            return Err(AppError::BadRequest(
+                "Give exactly one of hanzi, pinyin, english or prefix".to_string(),
+            ))
+        }
+    };
+
+    Ok(Json(DictionarySearchResponse { entries }))
+}
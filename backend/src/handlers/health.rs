@@ -1,12 +1,24 @@
+use std::{
+    collections::BTreeMap,
+    time::Instant,
+};
+
 use axum::{
     http::StatusCode,
     response::Json,
+    Extension,
 };
+use serde::Serialize;
 use serde_json::{
     json,
     Value,
 };
 
+use crate::{
+    db::Database,
+    speech::SharedSpeechEvaluator,
+};
+
 pub async fn health_check() -> Result<Json<Value>, StatusCode> {
     Ok(Json(json!({
         "status": "healthy",
@@ -14,10 +26,124 @@ pub async fn health_check() -> Result<Json<Value>, StatusCode> {
     })))
 }
 
-pub async fn readiness_check() -> Result<Json<Value>, StatusCode> {
-    // In a real implementation, you'd check database connectivity, etc.
-    Ok(Json(json!({
-        "status": "ready",
-        "timestamp": chrono::Utc::now().to_rfc3339()
-    })))
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    /// Working, but some features may fail; does not affect readiness
+    Degraded,
+    /// Hard failure; the instance is not ready
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DependencyCheck {
+    pub status: CheckStatus,
+    /// Time the check took; for the speech provider, its most recent call
+    pub latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl DependencyCheck {
+    fn timed(started: Instant, status: CheckStatus) -> Self {
+        Self {
+            status,
+            latency_ms: Some((started.elapsed().as_secs_f64() * 1000.0 * 100.0).round() / 100.0),
+            error: None,
+            details: None,
+        }
+    }
+
+    fn failed(started: Instant, error: impl ToString) -> Self {
+        Self {
+            error: Some(error.to_string()),
+            ..Self::timed(started, CheckStatus::Failed)
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    /// `ready`, or `not_ready` if any check failed
+    pub status: &'static str,
+    pub timestamp: String,
+    pub checks: BTreeMap<&'static str, DependencyCheck>,
+}
+
+pub async fn readiness_check(
+    Extension(db): Extension<Database>,
+    Extension(evaluator): Extension<SharedSpeechEvaluator>,
+) -> (StatusCode, Json<Readiness>) {
+    let mut checks = BTreeMap::new();
+    checks.insert("database", check_database(&db).await);
+    checks.insert("migrations", check_migrations(&db).await);
+    checks.insert("speech_provider", check_speech_provider(&evaluator));
+
+    let ready = checks
+        .values()
+        .all(|check| check.status != CheckStatus::Failed);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(Readiness {
+            status: if ready { "ready" } else { "not_ready" },
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            checks,
+        }),
+    )
+}
+
+async fn check_database(db: &Database) -> DependencyCheck {
+    let started = Instant::now();
+    match db.ping().await {
+        Ok(()) => DependencyCheck::timed(started, CheckStatus::Ok),
+        Err(e) => DependencyCheck::failed(started, e),
+    }
+}
+
+async fn check_migrations(db: &Database) -> DependencyCheck {
+    let started = Instant::now();
+    let migrations = match db.migration_status().await {
+        Ok(migrations) => migrations,
+        Err(e) => return DependencyCheck::failed(started, e),
+    };
+
+    let mut check = if migrations.is_current() {
+        DependencyCheck::timed(started, CheckStatus::Ok)
+    } else {
+        DependencyCheck::failed(started, "database schema is behind this build")
+    };
+    check.details = serde_json::to_value(&migrations).ok();
+    check
+}
+
+/// Reported from what the evaluator has seen; the provider is not called.
+fn check_speech_provider(evaluator: &SharedSpeechEvaluator) -> DependencyCheck {
+    let health = evaluator.health();
+    // A provider outage only affects speech features, so it never fails readiness
+    let (status, error) = if !health.credentials_configured {
+        (
+            CheckStatus::Degraded,
+            Some("provider credentials are not configured"),
+        )
+    } else if !health.is_healthy() {
+        (CheckStatus::Degraded, Some("circuit breaker is not closed"))
+    } else {
+        (CheckStatus::Ok, None)
+    };
+
+    DependencyCheck {
+        status,
+        latency_ms: health.last_latency_ms.map(|latency| latency as f64),
+        error: error.map(str::to_string),
+        details: serde_json::to_value(&health).ok(),
+    }
 }
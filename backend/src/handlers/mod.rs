@@ -1,3 +1,8 @@
 pub mod auth;
+pub mod curriculum;
+pub mod dictionary;
 pub mod health;
+pub mod review;
 pub mod speech;
+pub mod text;
+pub mod vocabulary;
@@ -0,0 +1,94 @@
+use axum::{
+    extract::{
+        Extension,
+        Path,
+        Query,
+    },
+    response::Result as AxumResult,
+    Json,
+};
+use chrono::Utc;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    auth::extractor::AuthUser,
+    error::AppError,
+    vocabulary::{
+        review::{
+            GradedReview,
+            ReviewLog,
+            ReviewQueue,
+            ReviewService,
+            ReviewSettings,
+            ReviewSettingsUpdate,
+        },
+        scheduler::Grade,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct DueQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GradeRequest {
+    /// `again`, `hard`, `good` or `easy`
+    pub grade: Grade,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewLogList {
+    pub reviews: Vec<ReviewLog>,
+}
+
+pub async fn due(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(reviews): Extension<ReviewService>,
+    Query(query): Query<DueQuery>,
+) -> AxumResult<Json<ReviewQueue>, AppError> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    Ok(Json(reviews.queue(&user_id, Utc::now(), limit).await?))
+}
+
+pub async fn grade(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(reviews): Extension<ReviewService>,
+    Path(word_id): Path<String>,
+    Json(request): Json<GradeRequest>,
+) -> AxumResult<Json<GradedReview>, AppError> {
+    reviews
+        .grade(&user_id, &word_id, request.grade, Utc::now())
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("Word not found".to_string()))
+}
+
+pub async fn history(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(reviews): Extension<ReviewService>,
+    Path(word_id): Path<String>,
+) -> AxumResult<Json<ReviewLogList>, AppError> {
+    Ok(Json(ReviewLogList {
+        reviews: reviews.history(&user_id, &word_id).await?,
+    }))
+}
+
+pub async fn get_settings(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(reviews): Extension<ReviewService>,
+) -> AxumResult<Json<ReviewSettings>, AppError> {
+    Ok(Json(reviews.settings(&user_id).await?))
+}
+
+pub async fn update_settings(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(reviews): Extension<ReviewService>,
+    Json(update): Json<ReviewSettingsUpdate>,
+) -> AxumResult<Json<ReviewSettings>, AppError> {
+    Ok(Json(reviews.update_settings(&user_id, update).await?))
+}
@@ -1,12 +1,26 @@
 use axum::{
     extract::{
+        ws::{
+            Message,
+            WebSocket,
+            WebSocketUpgrade,
+        },
         Extension,
         Multipart,
+        Path,
+        Query,
+    },
+    response::{
+        Response,
+        Result as AxumResult,
     },
-    response::Result as AxumResult,
     Json,
 };
 use bytes::Bytes;
+use chrono::{
+    Duration as ChronoDuration,
+    Utc,
+};
 use serde::{
     Deserialize,
     Serialize,
@@ -18,11 +32,52 @@ use tracing::{
 };
 
 use crate::{
+    auth::extractor::{
+        AuthUser,
+        OptionalAuthUser,
+    },
+    config::Config,
     error::AppError,
+    pinyin::{
+        self,
+        parse,
+        PinyinConverter,
+        Tones,
+    },
     speech::{
-        IFlytekService,
+        analytics::{
+            SpeechAnalyticsService,
+            SpeechInsights,
+        },
+        audio,
+        drills::{
+            Drill,
+            DrillGenerator,
+            DrillTarget,
+        },
+        history::{
+            AttemptContext,
+            SpeechAttempt,
+            SpeechAttemptDetail,
+            SpeechHistoryService,
+            WordTrendPoint,
+            WordTrendSummary,
+        },
+        tone::{
+            self,
+            ToneAnalysis,
+        },
+        ProviderHealth,
+        SharedSpeechEvaluator,
+        SpeechError,
         SpeechEvaluationRequest,
         SpeechEvaluationResponse,
+        SpeechStream,
+        SpeechStreamEvent,
+    },
+    vocabulary::review::{
+        ReviewService,
+        SpeechReview,
     },
 };
 
@@ -33,6 +88,67 @@ pub struct EvaluateSpeechParams {
     pub core: Option<String>,
     pub ref_pinyin: Option<String>,
     pub phoneme_output: Option<bool>,
+    /// Count vocabulary words read in the recording as reviews; on by default
+    pub update_reviews: Option<bool>,
+}
+
+impl EvaluateSpeechParams {
+    fn into_request(
+        self,
+        audio_data: Bytes,
+        audio_encoding: String,
+        sample_rate: u32,
+        channels: u8,
+        bit_depth: u8,
+    ) -> Result<SpeechEvaluationRequest, SpeechError> {
+        let lang = self.lang.unwrap_or_else(|| "cn".to_string());
+        let ref_pinyin = match self.ref_pinyin.filter(|pinyin| !pinyin.trim().is_empty()) {
+            Some(pinyin) => Some(check_ref_pinyin(&pinyin, &self.ref_text)?),
+            None => (lang == "cn")
+                .then(|| generate_ref_pinyin(&self.ref_text))
+                .flatten(),
+        };
+
+        Ok(SpeechEvaluationRequest {
+            audio_data,
+            ref_text: self.ref_text,
+            lang,
+            core: self.core.unwrap_or_else(|| "sent".to_string()),
+            ref_pinyin,
+            phoneme_output: self.phoneme_output.unwrap_or(true),
+            audio_encoding,
+            sample_rate,
+            channels,
+            bit_depth,
+        })
+    }
+}
+
+/// Client-supplied pinyin rewritten as numbered syllables, rejected when it does
+/// not parse or has a different number of syllables than `ref_text` has hanzi.
+fn check_ref_pinyin(ref_pinyin: &str, ref_text: &str) -> Result<String, SpeechError> {
+    let invalid =
+        |reason: String| SpeechError::InvalidRequest(format!("Invalid ref_pinyin: {}", reason));
+    let numbered = parse::normalize_reference(ref_pinyin).map_err(|e| invalid(e.to_string()))?;
+
+    let syllables = numbered.split(' ').count();
+    let hanzi = ref_text.chars().filter(|&c| pinyin::is_hanzi(c)).count();
+    if hanzi > 0 && syllables != hanzi {
+        return Err(invalid(format!(
+            "{} syllables for {} characters of ref_text",
+            syllables, hanzi
+        )));
+    }
+    Ok(numbered)
+}
+
+/// Citation-tone pinyin for Chinese reference text the client sent without any,
+/// so polyphones are scored against their reading in context. Left unset when a
+/// character has no known reading, since misaligned pinyin is worse than none.
+fn generate_ref_pinyin(ref_text: &str) -> Option<String> {
+    let conversion = PinyinConverter::bundled().convert(ref_text);
+    (conversion.is_complete() && conversion.syllables().next().is_some())
+        .then(|| conversion.numbered(Tones::Citation))
 }
 
 #[derive(Debug, Serialize)]
@@ -40,17 +156,35 @@ pub struct ApiSpeechEvaluationResponse {
     pub success: bool,
     pub data: Option<SpeechEvaluationResponse>,
     pub error: Option<String>,
+    /// Set when the result was saved to the signed-in user's history
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempt_id: Option<String>,
+    /// Pitch contour and per-syllable tone scores measured from the recording
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tone_analysis: Option<ToneAnalysis>,
+    /// The signed-in user's vocabulary words read in the recording, each
+    /// reviewed with a grade from its scores
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reviews: Option<Vec<SpeechReview>>,
 }
 
 pub async fn evaluate_speech(
-    Extension(iflytek_service): Extension<IFlytekService>,
+    Extension(evaluator): Extension<SharedSpeechEvaluator>,
+    Extension(config): Extension<Config>,
+    OptionalAuthUser(auth_user): OptionalAuthUser,
+    Extension(history): Extension<SpeechHistoryService>,
+    Extension(reviews): Extension<ReviewService>,
     mut multipart: Multipart,
 ) -> AxumResult<Json<ApiSpeechEvaluationResponse>, AppError> {
     info!("Received speech evaluation request");
 
+    // Anonymous evaluations are allowed but only signed-in ones are recorded
+    let user_id = auth_user.map(|auth_user| auth_user.user_id);
+
     let mut params: Option<EvaluateSpeechParams> = None;
     let mut audio_data: Option<Bytes> = None;
-    let mut audio_encoding = "lame".to_string();
+    // Only consulted for headerless PCM; containers describe themselves
+    let mut audio_encoding = String::new();
     let mut sample_rate = 16000u32;
     let mut channels = 1u8;
     let mut bit_depth = 16u8;
@@ -85,8 +219,7 @@ pub async fn evaluate_speech(
                     .bytes()
                     .await
                     .map_err(|_| AppError::BadRequest("Failed to read encoding".to_string()))?;
-                audio_encoding = String::from_utf8(encoding_bytes.to_vec())
-                    .unwrap_or_else(|_| "lame".to_string());
+                audio_encoding = String::from_utf8(encoding_bytes.to_vec()).unwrap_or_default();
             }
             "sample_rate" => {
                 let sample_rate_bytes = field
@@ -133,66 +266,520 @@ pub async fn evaluate_speech(
 
     if audio_data.is_empty() {
         error!("Empty audio data received");
-        return Ok(Json(ApiSpeechEvaluationResponse {
-            success: false,
-            data: None,
-            error: Some("Empty audio data".to_string()),
-        }));
+        return Err(SpeechError::AudioTooShort("Empty audio data".to_string()).into());
     }
 
+    let upload_size = audio_data.len();
+    let max_duration = config.speech.max_audio_duration;
+    let normalized = tokio::task::spawn_blocking(move || {
+        if audio_encoding == "raw" && audio::Container::sniff(&audio_data).is_none() {
+            audio::normalize_raw(&audio_data, sample_rate, channels, bit_depth, max_duration)
+        } else {
+            audio::normalize(audio_data, max_duration)
+        }
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Audio decoding failed: {}", e)))?
+    .map_err(|e| {
+        warn!("Rejecting uploaded audio: {}", e);
+        AppError::from(e)
+    })?;
+
     info!(
-        "Processing speech evaluation for text: '{}', audio size: {} bytes",
+        "Processing speech evaluation for text: '{}', audio size: {} bytes ({:.1}s)",
         params.ref_text,
-        audio_data.len()
+        upload_size,
+        normalized.duration.as_secs_f32()
     );
 
-    // Create iFlytek request
-    let request = SpeechEvaluationRequest {
-        audio_data,
-        ref_text: params.ref_text,
-        lang: params.lang.unwrap_or_else(|| "cn".to_string()),
-        core: params.core.unwrap_or_else(|| "sent".to_string()),
-        ref_pinyin: params.ref_pinyin,
-        phoneme_output: params.phoneme_output.unwrap_or(true),
-        audio_encoding,
-        sample_rate,
-        channels,
-        bit_depth,
+    // Providers always receive 16 kHz mono 16-bit PCM
+    let duration = normalized.duration;
+    let pcm = normalized.pcm.clone();
+    let update_reviews = params.update_reviews.unwrap_or(true);
+    let request = params.into_request(
+        normalized.pcm,
+        "raw".to_string(),
+        audio::TARGET_SAMPLE_RATE,
+        1,
+        16,
+    )?;
+    let context = AttemptContext::new(&request, Some(duration));
+
+    // Call the configured speech evaluator
+    let response = evaluator.evaluate(request).await.map_err(|e| {
+        warn!("{} evaluation failed: {}", evaluator.name(), e);
+        e
+    })?;
+    info!("Speech evaluation completed successfully");
+
+    // Tones are only judged for Chinese, and need the provider's time spans
+    let tone_analysis = if context.lang == "cn" {
+        let words = response.words.clone();
+        tokio::task::spawn_blocking(move || tone::analyze(&pcm, audio::TARGET_SAMPLE_RATE, &words))
+            .await
+            .map_err(|e| error!("Tone analysis failed: {}", e))
+            .ok()
+    } else {
+        None
+    };
+
+    let attempt_id = match &user_id {
+        // A history write failure should not cost the learner their result
+        Some(user_id) => match history
+            .record_attempt(user_id, evaluator.name(), &context, &response)
+            .await
+        {
+            Ok(attempt) => Some(attempt.id),
+            Err(e) => {
+                error!("Failed to save speech attempt: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let reviews = match &user_id {
+        // Review scheduling is a side effect; the evaluation still stands without it
+        Some(user_id) if update_reviews && context.lang == "cn" => match reviews
+            .grade_speech(
+                user_id,
+                attempt_id.as_deref(),
+                &context.ref_text,
+                &response.words,
+                Utc::now(),
+            )
+            .await
+        {
+            Ok(reviews) => Some(reviews),
+            Err(e) => {
+                error!("Failed to review words from speech: {}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    Ok(Json(ApiSpeechEvaluationResponse {
+        success: true,
+        data: Some(response),
+        error: None,
+        attempt_id,
+        tone_analysis,
+        reviews,
+    }))
+}
+
+/// Messages the browser sends over `/speech/stream`.
+///
+/// A session is one `start` text message, any number of binary audio chunks,
+/// then `end` once the learner stops talking. Chunks are relayed to the
+/// provider as they arrive, so they must already be what providers take:
+/// 16 kHz mono 16-bit little-endian PCM. The format fields may be left out,
+/// and are refused if they declare anything else.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamClientMessage {
+    Start {
+        #[serde(flatten)]
+        params: EvaluateSpeechParams,
+        encoding: Option<String>,
+        sample_rate: Option<u32>,
+        channels: Option<u8>,
+        bit_depth: Option<u8>,
+    },
+    End,
+}
+
+/// Messages pushed back to the browser over `/speech/stream`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamServerMessage {
+    /// The provider session is open; audio may be sent
+    Ready,
+    Partial {
+        data: SpeechEvaluationResponse,
+    },
+    Final {
+        data: SpeechEvaluationResponse,
+    },
+    Error {
+        error: String,
+        /// Set for evaluation failures; the same values as the HTTP API's `code`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        code: Option<&'static str>,
+    },
+}
+
+impl From<SpeechError> for StreamServerMessage {
+    fn from(error: SpeechError) -> Self {
+        Self::Error {
+            code: Some(error.code()),
+            error: error.to_string(),
+        }
+    }
+}
+
+pub async fn stream_speech(
+    Extension(evaluator): Extension<SharedSpeechEvaluator>,
+    Extension(config): Extension<Config>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| run_speech_stream(socket, evaluator, config))
+}
+
+async fn run_speech_stream(
+    mut socket: WebSocket,
+    evaluator: SharedSpeechEvaluator,
+    config: Config,
+) {
+    info!("Speech stream connected");
+
+    let request = match socket.recv().await {
+        Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+            Ok(StreamClientMessage::Start {
+                params,
+                encoding,
+                sample_rate,
+                channels,
+                bit_depth,
+            }) => {
+                let request = check_stream_format(encoding, sample_rate, channels, bit_depth)
+                    .and_then(|()| {
+                        params.into_request(
+                            Bytes::new(),
+                            "raw".to_string(),
+                            audio::TARGET_SAMPLE_RATE,
+                            1,
+                            16,
+                        )
+                    });
+                match request {
+                    Ok(request) => request,
+                    Err(e) => {
+                        if send_stream_message(&mut socket, &e.into()).await {
+                            let _ = socket.send(Message::Close(None)).await;
+                        }
+                        return;
+                    }
+                }
+            }
+            _ => {
+                send_stream_error(&mut socket, "Expected a start message").await;
+                return;
+            }
+        },
+        _ => return,
     };
+    let SpeechStream { audio, mut events } = match evaluator.open_stream(request).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            error!("Failed to open {} speech stream: {}", evaluator.name(), e);
+            if send_stream_message(&mut socket, &e.into()).await {
+                let _ = socket.send(Message::Close(None)).await;
+            }
+            return;
+        }
+    };
+    if !send_stream_message(&mut socket, &StreamServerMessage::Ready).await {
+        return;
+    }
 
-    // Call iFlytek service
-    match iflytek_service.evaluate_speech(request).await {
-        Ok(response) => {
-            if let Some(error) = &response.error {
-                warn!("iFlytek API returned error: {}", error);
-                Ok(Json(ApiSpeechEvaluationResponse {
-                    success: false,
-                    data: None,
-                    error: Some(error.clone()),
-                }))
-            } else {
-                info!("Speech evaluation completed successfully");
-                Ok(Json(ApiSpeechEvaluationResponse {
-                    success: true,
-                    data: Some(response),
-                    error: None,
-                }))
+    let max_duration = config.speech.max_audio_duration;
+    let max_bytes = (max_duration.as_secs_f64() * STREAM_BYTES_PER_SECOND as f64) as u64;
+    let mut audio = Some(audio);
+    let mut received: u64 = 0;
+
+    loop {
+        tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Binary(chunk))) => {
+                    let Some(sender) = &audio else {
+                        continue;
+                    };
+                    received += chunk.len() as u64;
+                    if received > max_bytes {
+                        let message = format!(
+                            "Audio is longer than the {} second limit",
+                            max_duration.as_secs()
+                        );
+                        send_stream_error(&mut socket, &message).await;
+                        return;
+                    }
+                    if sender.send(Bytes::from(chunk)).await.is_err() {
+                        audio = None;
+                    }
+                }
+                Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                    // Dropping the sender tells the provider the recording is complete
+                    Ok(StreamClientMessage::End) => audio = None,
+                    _ => {
+                        send_stream_error(&mut socket, "Unexpected message").await;
+                        return;
+                    }
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                    info!("Speech stream closed by client");
+                    return;
+                }
+                Some(Ok(_)) => {}
+            },
+            event = events.recv() => {
+                let (message, done) = match event {
+                    Some(SpeechStreamEvent::Partial(data)) => {
+                        (StreamServerMessage::Partial { data }, false)
+                    }
+                    Some(SpeechStreamEvent::Final(data)) => {
+                        info!("Streaming speech evaluation completed successfully");
+                        (StreamServerMessage::Final { data }, true)
+                    }
+                    Some(SpeechStreamEvent::Failed(error)) => {
+                        warn!("{} stream returned error: {}", evaluator.name(), error);
+                        (error.into(), true)
+                    }
+                    None => (
+                        StreamServerMessage::Error {
+                            error: "Speech evaluation ended unexpectedly".to_string(),
+                            code: None,
+                        },
+                        true,
+                    ),
+                };
+                if !send_stream_message(&mut socket, &message).await || done {
+                    break;
+                }
             }
         }
+    }
+
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// Bytes of one second of streamed audio.
+const STREAM_BYTES_PER_SECOND: u64 = audio::TARGET_SAMPLE_RATE as u64 * 2;
+
+/// Refuse a stream declared as anything but 16 kHz mono 16-bit PCM.
+fn check_stream_format(
+    encoding: Option<String>,
+    sample_rate: Option<u32>,
+    channels: Option<u8>,
+    bit_depth: Option<u8>,
+) -> Result<(), SpeechError> {
+    let pcm = encoding.as_deref().unwrap_or("raw") == "raw"
+        && sample_rate.unwrap_or(audio::TARGET_SAMPLE_RATE) == audio::TARGET_SAMPLE_RATE
+        && channels.unwrap_or(1) == 1
+        && bit_depth.unwrap_or(16) == 16;
+    if pcm {
+        Ok(())
+    } else {
+        Err(SpeechError::InvalidRequest(format!(
+            "Streamed audio must be raw {} Hz mono 16-bit PCM",
+            audio::TARGET_SAMPLE_RATE
+        )))
+    }
+}
+
+async fn send_stream_message(socket: &mut WebSocket, message: &StreamServerMessage) -> bool {
+    match serde_json::to_string(message) {
+        Ok(text) => socket.send(Message::Text(text)).await.is_ok(),
         Err(e) => {
-            error!("Failed to evaluate speech: {}", e);
-            Ok(Json(ApiSpeechEvaluationResponse {
-                success: false,
-                data: None,
-                error: Some(format!("Speech evaluation failed: {}", e)),
-            }))
+            error!("Failed to serialize stream message: {}", e);
+            false
         }
     }
 }
 
-pub async fn health_check() -> AxumResult<Json<serde_json::Value>, AppError> {
-    Ok(Json(serde_json::json!({
-        "status": "ok",
-        "service": "speech_evaluation"
-    })))
+async fn send_stream_error(socket: &mut WebSocket, error: &str) {
+    let message = StreamServerMessage::Error {
+        error: error.to_string(),
+        code: None,
+    };
+    if send_stream_message(socket, &message).await {
+        let _ = socket.send(Message::Close(None)).await;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SpeechHealth {
+    /// `ok`, or `degraded` while credentials are missing or the breaker is not closed
+    pub status: &'static str,
+    pub service: &'static str,
+    #[serde(flatten)]
+    pub provider: ProviderHealth,
+}
+
+pub async fn health_check(
+    Extension(evaluator): Extension<SharedSpeechEvaluator>,
+) -> AxumResult<Json<SpeechHealth>, AppError> {
+    let provider = evaluator.health();
+    Ok(Json(SpeechHealth {
+        status: if provider.is_healthy() {
+            "ok"
+        } else {
+            "degraded"
+        },
+        service: "speech_evaluation",
+        provider,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListAttemptsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SpeechAttemptList {
+    pub attempts: Vec<SpeechAttempt>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WordTrendList {
+    pub words: Vec<WordTrendSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WordTrend {
+    pub word: String,
+    pub points: Vec<WordTrendPoint>,
+}
+
+pub async fn list_attempts(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(history): Extension<SpeechHistoryService>,
+    Query(query): Query<ListAttemptsQuery>,
+) -> AxumResult<Json<SpeechAttemptList>, AppError> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let (attempts, total) = history.list_attempts(&user_id, limit, offset).await?;
+
+    Ok(Json(SpeechAttemptList {
+        attempts,
+        total,
+        limit,
+        offset,
+    }))
+}
+
+pub async fn get_attempt(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(history): Extension<SpeechHistoryService>,
+    Path(attempt_id): Path<String>,
+) -> AxumResult<Json<SpeechAttemptDetail>, AppError> {
+    history
+        .get_attempt(&user_id, &attempt_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("Speech attempt not found".to_string()))
+}
+
+pub async fn word_trends(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(history): Extension<SpeechHistoryService>,
+) -> AxumResult<Json<WordTrendList>, AppError> {
+    Ok(Json(WordTrendList {
+        words: history.word_trends(&user_id).await?,
+    }))
+}
+
+pub async fn word_trend(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(history): Extension<SpeechHistoryService>,
+    Path(word): Path<String>,
+) -> AxumResult<Json<WordTrend>, AppError> {
+    let points = history.word_trend(&user_id, &word).await?;
+
+    Ok(Json(WordTrend { word, points }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InsightsQuery {
+    /// Only consider attempts from the last `days` days
+    pub days: Option<i64>,
+}
+
+pub async fn insights(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(analytics): Extension<SpeechAnalyticsService>,
+    Query(query): Query<InsightsQuery>,
+) -> AxumResult<Json<SpeechInsights>, AppError> {
+    let since = query
+        .days
+        .filter(|days| *days > 0)
+        .map(|days| (Utc::now() - ChronoDuration::days(days)).naive_utc());
+
+    Ok(Json(analytics.insights(&user_id, since).await?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DrillsQuery {
+    /// Comma-separated sounds (`zh,ang,tone3-tone3`); defaults to the user's weakest sounds
+    pub sounds: Option<String>,
+    /// Words and minimal pairs per drill
+    pub count: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DrillList {
+    pub drills: Vec<Drill>,
+}
+
+/// How many of the user's weakest sounds get a drill when none are requested.
+const WEAKEST_SOUND_DRILLS: usize = 3;
+
+pub async fn drills(
+    OptionalAuthUser(auth_user): OptionalAuthUser,
+    Extension(analytics): Extension<SpeechAnalyticsService>,
+    Query(query): Query<DrillsQuery>,
+) -> AxumResult<Json<DrillList>, AppError> {
+    let count = query.count.unwrap_or(10).clamp(1, 50);
+    let requested = query.sounds.filter(|sounds| !sounds.trim().is_empty());
+
+    let targets: Vec<DrillTarget> = match &requested {
+        Some(sounds) => sounds
+            .split(',')
+            .map(|label| {
+                DrillTarget::parse(label).ok_or_else(|| {
+                    AppError::BadRequest(format!("Unknown sound '{}'", label.trim()))
+                })
+            })
+            .collect::<Result<_, _>>()?,
+        None => {
+            let user_id = auth_user.ok_or(AppError::Unauthorized)?.user_id;
+            analytics
+                .insights(&user_id, None)
+                .await?
+                .weakest_sounds
+                .iter()
+                .filter_map(|sound| DrillTarget::parse(&sound.sound))
+                .collect()
+        }
+    };
+
+    let generator = DrillGenerator::bundled();
+    let mut rng = rand::thread_rng();
+    let mut drills = Vec::new();
+    for target in targets {
+        let drill = generator.drill(&target, count, &mut rng);
+        if drill.is_empty() {
+            // Weak sounds the word lists cannot exercise are passed over
+            if requested.is_some() {
+                return Err(AppError::BadRequest(format!(
+                    "No drill material for sound '{}'",
+                    target
+                )));
+            }
+            continue;
+        }
+        drills.push(drill);
+        if requested.is_none() && drills.len() == WEAKEST_SOUND_DRILLS {
+            break;
+        }
+    }
+
+    Ok(Json(DrillList { drills }))
 }
@@ -0,0 +1,65 @@
+use axum::{
+    response::Result as AxumResult,
+    Json,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    error::AppError,
+    text::{
+        Segment,
+        SegmentKind,
+        Segmenter,
+    },
+};
+
+/// Longest text accepted for segmentation, in characters.
+const MAX_SEGMENT_CHARS: usize = 20_000;
+
+#[derive(Debug, Deserialize)]
+pub struct SegmentRequest {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SegmentResponse {
+    pub segments: Vec<Segment>,
+    pub word_count: usize,
+    /// Words made of characters in no lexicon word
+    pub unknown_words: Vec<String>,
+}
+
+pub async fn segment(
+    Json(request): Json<SegmentRequest>,
+) -> AxumResult<Json<SegmentResponse>, AppError> {
+    if request.text.trim().is_empty() {
+        return Err(AppError::BadRequest("Text is empty".to_string()));
+    }
+    if request.text.chars().count() > MAX_SEGMENT_CHARS {
+        return Err(AppError::BadRequest(format!(
+            "Text is longer than {} characters",
+            MAX_SEGMENT_CHARS
+        )));
+    }
+
+    let segments = Segmenter::bundled().segment(&request.text);
+    let words = segments
+        .iter()
+        .filter(|segment| segment.kind == SegmentKind::Word);
+    let word_count = words.clone().count();
+    let mut unknown_words: Vec<String> = Vec::new();
+    for word in words.filter(|segment| !segment.in_lexicon) {
+        if !unknown_words.contains(&word.text) {
+            unknown_words.push(word.text.clone());
+        }
+    }
+
+    Ok(Json(SegmentResponse {
+        segments,
+        word_count,
+        unknown_words,
+    }))
+}
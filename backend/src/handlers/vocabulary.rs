@@ -0,0 +1,175 @@
+use axum::{
+    extract::{
+        Extension,
+        Path,
+        Query,
+    },
+    response::Result as AxumResult,
+    Json,
+};
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::{
+    json,
+    Value,
+};
+
+use crate::{
+    auth::extractor::AuthUser,
+    dictionary::DictionaryService,
+    error::AppError,
+    vocabulary::{
+        BulkAddition,
+        Difficulty,
+        NewWord,
+        VocabularyService,
+        Word,
+        WordFilter,
+        WordUpdate,
+    },
+};
+
+/// Most dictionary entries one bulk request may add.
+const MAX_BULK_ENTRIES: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct ListWordsQuery {
+    pub difficulty: Option<Difficulty>,
+    /// Only words due for review now
+    #[serde(default)]
+    pub due: bool,
+    /// Only words due for review by this time
+    pub due_before: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WordList {
+    pub words: Vec<Word>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkAddRequest {
+    /// Dictionary entry ids, as returned by `/dictionary/search`
+    pub entry_ids: Vec<i64>,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+}
+
+pub async fn list_words(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(vocabulary): Extension<VocabularyService>,
+    Query(query): Query<ListWordsQuery>,
+) -> AxumResult<Json<WordList>, AppError> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let filter = WordFilter {
+        difficulty: query.difficulty,
+        due_by: query.due_before.or(query.due.then(Utc::now)),
+    };
+
+    let (words, total) = vocabulary
+        .list_words(&user_id, &filter, limit, offset)
+        .await?;
+
+    Ok(Json(WordList {
+        words,
+        total,
+        limit,
+        offset,
+    }))
+}
+
+pub async fn get_word(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(vocabulary): Extension<VocabularyService>,
+    Path(word_id): Path<String>,
+) -> AxumResult<Json<Word>, AppError> {
+    vocabulary
+        .get_word(&user_id, &word_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("Word not found".to_string()))
+}
+
+pub async fn add_word(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(vocabulary): Extension<VocabularyService>,
+    Json(word): Json<NewWord>,
+) -> AxumResult<Json<Word>, AppError> {
+    Ok(Json(vocabulary.add_word(&user_id, word).await?))
+}
+
+pub async fn add_from_dictionary(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(vocabulary): Extension<VocabularyService>,
+    Extension(dictionary): Extension<DictionaryService>,
+    Json(request): Json<BulkAddRequest>,
+) -> AxumResult<Json<BulkAddition>, AppError> {
+    if request.entry_ids.is_empty() {
+        return Err(AppError::BadRequest(
+            "No dictionary entries given".to_string(),
+        ));
+    }
+    if request.entry_ids.len() > MAX_BULK_ENTRIES {
+        return Err(AppError::BadRequest(format!(
+            "At most {} dictionary entries can be added at once",
+            MAX_BULK_ENTRIES
+        )));
+    }
+
+    let entries = dictionary.get_entries(&request.entry_ids).await?;
+    let missing: Vec<String> = request
+        .entry_ids
+        .iter()
+        .filter(|&&id| !entries.iter().any(|entry| entry.id == id))
+        .map(i64::to_string)
+        .collect();
+    if !missing.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "No dictionary entries with ids {}",
+            missing.join(", ")
+        )));
+    }
+
+    Ok(Json(
+        vocabulary
+            .add_entries(&user_id, &entries, request.difficulty)
+            .await?,
+    ))
+}
+
+pub async fn update_word(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(vocabulary): Extension<VocabularyService>,
+    Path(word_id): Path<String>,
+    Json(update): Json<WordUpdate>,
+) -> AxumResult<Json<Word>, AppError> {
+    vocabulary
+        .update_word(&user_id, &word_id, update)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("Word not found".to_string()))
+}
+
+pub async fn delete_word(
+    AuthUser { user_id, .. }: AuthUser,
+    Extension(vocabulary): Extension<VocabularyService>,
+    Path(word_id): Path<String>,
+) -> AxumResult<Json<Value>, AppError> {
+    if !vocabulary.delete_word(&user_id, &word_id).await? {
+        return Err(AppError::NotFound("Word not found".to_string()));
+    }
+    Ok(Json(json!({"success": true})))
+}
@@ -1,9 +1,15 @@
 pub mod auth;
 pub mod config;
+pub mod curriculum;
 pub mod db;
+pub mod dictionary;
 pub mod error;
 pub mod handlers;
 pub mod middleware;
 pub mod models;
+pub mod pinyin;
+pub mod records;
 pub mod routes;
 pub mod speech;
+pub mod text;
+pub mod vocabulary;
@@ -1,13 +1,3 @@
-mod auth;
-mod config;
-mod db;
-mod error;
-mod handlers;
-mod middleware;
-mod models;
-mod routes;
-mod speech;
-
 use std::net::SocketAddr;
 
 use anyhow::Result;
@@ -15,6 +5,16 @@ use axum::{
     http::Method,
     Router,
 };
+use mandarinpath_backend::{
+    config::Config,
+    curriculum::CurriculumService,
+    db::Database,
+    middleware::{
+        csrf::CsrfLayer,
+        security::SecurityLayer,
+    },
+    routes::create_routes,
+};
 use tower::ServiceBuilder;
 use tower_http::{
     compression::CompressionLayer,
@@ -26,16 +26,6 @@ use tracing_subscriber::{
     util::SubscriberInitExt,
 };
 
-use crate::{
-    config::Config,
-    db::Database,
-    middleware::{
-        csrf::CsrfLayer,
-        security::SecurityLayer,
-    },
-    routes::create_routes,
-};
-
 #[tokio::main]
 async fn main() -> Result<()> {
     let config = Config::from_args()?;
@@ -62,6 +52,17 @@ async fn main() -> Result<()> {
     tracing::info!("Log level: {}", log_level);
 
     let db = Database::new(&config.database_url).await?;
+    for (standard, summary) in CurriculumService::new(db.clone())
+        .import_bundled_if_empty()
+        .await?
+    {
+        tracing::info!(
+            "Loaded the bundled HSK {} list: {} words, {} characters",
+            standard.as_str(),
+            summary.words,
+            summary.characters
+        );
+    }
 
     let cors = CorsLayer::new()
         .allow_origin(config.frontend_url.parse::<axum::http::HeaderValue>()?)
@@ -88,7 +89,12 @@ async fn main() -> Result<()> {
     tracing::info!("Starting server on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    // Peer addresses key the per-IP rate limits
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
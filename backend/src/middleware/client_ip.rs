@@ -0,0 +1,83 @@
+//! The address a request came from, for rate limits and session records.
+
+use std::net::{
+    IpAddr,
+    SocketAddr,
+};
+
+use axum::{
+    async_trait,
+    extract::{
+        ConnectInfo,
+        FromRequestParts,
+    },
+    http::{
+        request::Parts,
+        HeaderMap,
+    },
+};
+
+use crate::{
+    config::Config,
+    error::AppError,
+};
+
+/// The client's IP address: the peer address, unless the peer is one of
+/// `trusted_proxies`, in which case it is the address the proxy reports.
+/// `None` when the peer is not known.
+pub fn client_ip(parts: &Parts, trusted_proxies: &[IpAddr]) -> Option<String> {
+    let ConnectInfo(peer) = parts.extensions.get::<ConnectInfo<SocketAddr>>()?;
+    let peer = peer.ip();
+    let ip = if trusted_proxies.contains(&peer) {
+        forwarded_ip(&parts.headers, trusted_proxies).unwrap_or(peer)
+    } else {
+        peer
+    };
+    Some(ip.to_string())
+}
+
+/// The client address reported by a trusted proxy. Each hop appends the
+/// address it saw to `X-Forwarded-For`, so everything left of the last
+/// trusted proxy's entry is the client's to write; the list is read from the
+/// right, past any further trusted proxies.
+fn forwarded_ip(headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let forwarded = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .map(|value| value.to_str().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if forwarded.is_empty() {
+        let real_ip = headers.get("x-real-ip")?.to_str().ok()?;
+        return real_ip.trim().parse().ok();
+    }
+
+    for hop in forwarded.iter().rev().flat_map(|value| value.rsplit(',')) {
+        // An entry that is not an address cannot be told apart from a forgery
+        let ip: IpAddr = hop.trim().parse().ok()?;
+        if !trusted_proxies.contains(&ip) {
+            return Some(ip);
+        }
+    }
+    None
+}
+
+/// [`client_ip`] as an extractor, trusting the configured proxies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIp(pub Option<String>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let trusted_proxies = parts
+            .extensions
+            .get::<Config>()
+            .map(|config| config.trusted_proxies.as_slice())
+            .unwrap_or_default();
+        Ok(Self(client_ip(parts, trusted_proxies)))
+    }
+}
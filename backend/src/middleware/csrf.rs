@@ -74,6 +74,12 @@ impl CsrfLayer {
     }
 }
 
+impl Default for CsrfLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<S> Layer<S> for CsrfLayer {
     type Service = CsrfService<S>;
 
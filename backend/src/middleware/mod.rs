@@ -1,2 +1,4 @@
+pub mod client_ip;
 pub mod csrf;
+pub mod rate_limit;
 pub mod security;
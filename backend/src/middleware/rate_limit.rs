@@ -0,0 +1,275 @@
+//! Fixed-window rate limits counted in the `rate_limits` table, so they hold
+//! across restarts and are shared by every server on the same database.
+
+use std::net::IpAddr;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::request::Parts,
+    response::{
+        IntoResponse,
+        Response,
+    },
+};
+use chrono::{
+    NaiveDateTime,
+    Utc,
+};
+use tower::{
+    Layer,
+    Service,
+};
+
+use super::client_ip::client_ip;
+use crate::{
+    auth::{
+        extractor::bearer_token,
+        jwt::JwtService,
+    },
+    config::{
+        RateLimit,
+        RateLimitKey,
+    },
+    db::Database,
+    error::{
+        AppError,
+        Result,
+    },
+};
+
+/// Largest body read to find the email of an email-keyed request.
+const MAX_BUFFERED_BODY: usize = 64 * 1024;
+/// How often windows that have run out are deleted.
+const PURGE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Counts requests per key in the `rate_limits` table.
+#[derive(Clone)]
+pub struct RateLimiter {
+    db: Database,
+    trusted_proxies: Arc<[IpAddr]>,
+    last_purge: Arc<Mutex<Instant>>,
+}
+
+impl RateLimiter {
+    /// Requests from one of `trusted_proxies` are counted by the client IP the
+    /// proxy reports rather than by the peer address.
+    pub fn new(db: Database, trusted_proxies: Vec<IpAddr>) -> Self {
+        Self {
+            db,
+            trusted_proxies: trusted_proxies.into(),
+            last_purge: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// A layer holding the requests of one route group to `limits`.
+    pub fn layer(&self, group: &'static str, limits: Vec<RateLimit>) -> RateLimitLayer {
+        RateLimitLayer {
+            limiter: self.clone(),
+            group,
+            limits: limits.into(),
+        }
+    }
+
+    /// Count a request against `key`. Over the limit, returns how long until
+    /// the key's window ends.
+    pub async fn hit(&self, key: &str, limit: &RateLimit) -> Result<Option<Duration>> {
+        if self.purge_due() {
+            self.purge_expired().await?;
+        }
+
+        let now = Utc::now().naive_utc();
+        let expires_at = now + chrono::Duration::milliseconds(limit.window.as_millis() as i64);
+        // A window that has run out starts over with this request
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO rate_limits (key, count, window_start, expires_at)
+            VALUES (?1, 1, ?2, ?3)
+            ON CONFLICT(key) DO UPDATE SET
+                count = CASE WHEN rate_limits.expires_at <= ?2 THEN 1 ELSE rate_limits.count + 1 END,
+                window_start = CASE WHEN rate_limits.expires_at <= ?2 THEN ?2 ELSE rate_limits.window_start END,
+                expires_at = CASE WHEN rate_limits.expires_at <= ?2 THEN ?3 ELSE rate_limits.expires_at END
+            RETURNING count as "count!: i64", expires_at as "expires_at!: NaiveDateTime"
+            "#,
+            key,
+            now,
+            expires_at
+        )
+        .fetch_one(self.db.pool())
+        .await?;
+
+        if row.count <= i64::from(limit.max_requests) {
+            return Ok(None);
+        }
+        Ok(Some((row.expires_at - now).to_std().unwrap_or_default()))
+    }
+
+    /// Delete the windows that have run out, returning how many there were.
+    pub async fn purge_expired(&self) -> Result<u64> {
+        let now = Utc::now().naive_utc();
+        let purged = sqlx::query!("DELETE FROM rate_limits WHERE expires_at <= ?1", now)
+            .execute(self.db.pool())
+            .await?
+            .rows_affected();
+        Ok(purged)
+    }
+
+    fn purge_due(&self) -> bool {
+        let Ok(mut last_purge) = self.last_purge.lock() else {
+            return false;
+        };
+        if last_purge.elapsed() < PURGE_INTERVAL {
+            return false;
+        }
+        *last_purge = Instant::now();
+        true
+    }
+
+    fn client_ip(&self, parts: &Parts) -> String {
+        client_ip(parts, &self.trusted_proxies).unwrap_or_else(|| "unknown".to_string())
+    }
+}
+
+/// The signed-in user, going by the access token alone. Whether its session is
+/// still live is left to the handler; a stale token only counts against its
+/// own user.
+fn user_id(parts: &Parts) -> Option<String> {
+    let token = bearer_token(&parts.headers)?;
+    let jwt_service = parts.extensions.get::<JwtService>()?;
+    jwt_service
+        .verify_access_token(token)
+        .ok()
+        .map(|claims| claims.sub)
+}
+
+/// The `email` field of a JSON body, normalized so that case and surrounding
+/// whitespace do not make a new key.
+fn body_email(body: &[u8]) -> Option<String> {
+    let body: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let email = body.get("email")?.as_str()?.trim().to_lowercase();
+    (!email.is_empty()).then_some(email)
+}
+
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: RateLimiter,
+    group: &'static str,
+    limits: Arc<[RateLimit]>,
+}
+
+impl RateLimitLayer {
+    /// Count the request against every limit of the group, handing it back if
+    /// it is within all of them.
+    async fn check(&self, req: Request) -> Result<Request> {
+        let (parts, body) = req.into_parts();
+        let (email, body) = if self.limits.iter().any(|l| l.key == RateLimitKey::Email) {
+            let body = axum::body::to_bytes(body, MAX_BUFFERED_BODY)
+                .await
+                .map_err(|_| {
+                    AppError::PayloadTooLarge(format!(
+                        "Request body exceeds {} bytes",
+                        MAX_BUFFERED_BODY
+                    ))
+                })?;
+            (body_email(&body), Body::from(body))
+        } else {
+            (None, body)
+        };
+
+        let mut retry_after: Option<Duration> = None;
+        for limit in self.limits.iter() {
+            let identity = match limit.key {
+                RateLimitKey::Ip => self.limiter.client_ip(&parts),
+                RateLimitKey::User => user_id(&parts)
+                    .unwrap_or_else(|| format!("ip:{}", self.limiter.client_ip(&parts))),
+                RateLimitKey::Email => match &email {
+                    Some(email) => email.clone(),
+                    None => continue,
+                },
+            };
+            let key = format!("{}:{}:{}", self.group, limit.key.as_str(), identity);
+
+            // Every limit is counted, even once one has been exceeded
+            if let Some(wait) = self.limiter.hit(&key, limit).await? {
+                tracing::warn!(
+                    group = self.group,
+                    key = %key,
+                    max_requests = limit.max_requests,
+                    "Rate limit exceeded"
+                );
+                retry_after = retry_after.max(Some(wait));
+            }
+        }
+
+        match retry_after {
+            Some(wait) => Err(AppError::RateLimited {
+                // Whole seconds, rounded up so a retry is never early
+                retry_after_secs: (wait.as_secs() + u64::from(wait.subsec_nanos() > 0)).max(1),
+            }),
+            None => Ok(Request::from_parts(parts, body)),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            rate_limit_layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    rate_limit_layer: RateLimitLayer,
+}
+
+impl<S> Service<Request> for RateLimitService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = std::pin::Pin<
+        Box<
+            dyn std::future::Future<Output = std::result::Result<Self::Response, Self::Error>>
+                + Send,
+        >,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let rate_limit_layer = self.rate_limit_layer.clone();
+        let mut inner = self.inner.clone();
+
+        Box::pin(async move {
+            if rate_limit_layer.limits.is_empty() {
+                return inner.call(req).await;
+            }
+
+            match rate_limit_layer.check(req).await {
+                Ok(req) => inner.call(req).await,
+                Err(error) => Ok(error.into_response()),
+            }
+        })
+    }
+}
@@ -20,6 +20,12 @@ impl SecurityLayer {
     }
 }
 
+impl Default for SecurityLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<S> Layer<S> for SecurityLayer {
     type Service = SecurityService<S>;
 
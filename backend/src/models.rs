@@ -84,4 +84,15 @@ pub struct Claims {
     pub exp: i64,
     pub iat: i64,
     pub session_id: String,
+    /// Present on refresh tokens only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh: Option<RefreshClaims>,
+}
+
+/// Where a refresh token stands in its session's rotation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefreshClaims {
+    /// Secret shared by every refresh token of the session
+    pub family: String,
+    pub generation: i64,
 }
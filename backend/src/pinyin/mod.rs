@@ -0,0 +1,351 @@
+//! Hanzi to pinyin.
+//!
+//! Readings come from a bundled dictionary (`data/pinyin/`): a default reading
+//! for each character, and words whose characters read differently in context
+//! (银行 yínháng, 长大 zhǎngdà) or carry a neutral tone. Text is matched
+//! longest word first, falling back to each character's default reading, and
+//! every syllable carries both its citation tone and its tone after sandhi.
+
+pub mod parse;
+pub mod sandhi;
+
+use std::{
+    collections::HashMap,
+    sync::OnceLock,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use self::sandhi::SandhiSyllable;
+
+const READINGS: &str = include_str!("../../data/pinyin/readings.tsv");
+const WORDS: &str = include_str!("../../data/pinyin/words.tsv");
+
+/// Which tone to write for a syllable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tones {
+    /// The dictionary tone, as reference pinyin is written
+    #[default]
+    Citation,
+    /// The tone as spoken, after 不/一 and third-tone sandhi
+    Sandhi,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Syllable {
+    pub hanzi: char,
+    /// Toneless spelling with ü written as `v`, e.g. `lv`
+    pub spelling: String,
+    /// 1-4, or 5 for the neutral tone
+    pub citation_tone: u8,
+    pub sandhi_tone: u8,
+}
+
+impl Syllable {
+    pub fn tone(&self, tones: Tones) -> u8 {
+        match tones {
+            Tones::Citation => self.citation_tone,
+            Tones::Sandhi => self.sandhi_tone,
+        }
+    }
+
+    /// Numbered pinyin as providers expect it, e.g. `lv4`, `ma5`.
+    pub fn numbered(&self, tones: Tones) -> String {
+        format!("{}{}", self.spelling, self.tone(tones))
+    }
+
+    /// Pinyin with a tone mark, e.g. `lǜ`; neutral tones are unmarked.
+    pub fn marked(&self, tones: Tones) -> String {
+        mark_tone(&self.spelling, self.tone(tones))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Token {
+    Syllable(Syllable),
+    /// A character missing from the dictionary
+    Unknown {
+        text: String,
+    },
+    /// Anything that is not hanzi: punctuation, spaces, latin letters, digits
+    Other {
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Conversion {
+    pub tokens: Vec<Token>,
+}
+
+impl Conversion {
+    pub fn syllables(&self) -> impl Iterator<Item = &Syllable> {
+        self.tokens.iter().filter_map(|token| match token {
+            Token::Syllable(syllable) => Some(syllable),
+            _ => None,
+        })
+    }
+
+    /// Whether every hanzi in the text has a reading.
+    pub fn is_complete(&self) -> bool {
+        !self
+            .tokens
+            .iter()
+            .any(|token| matches!(token, Token::Unknown { .. }))
+    }
+
+    /// Space-separated numbered pinyin for the hanzi, e.g. `ni3 hao3`.
+    pub fn numbered(&self, tones: Tones) -> String {
+        self.syllables()
+            .map(|syllable| syllable.numbered(tones))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Space-separated tone-marked pinyin for the hanzi, e.g. `ní hǎo`.
+    pub fn marked(&self, tones: Tones) -> String {
+        self.syllables()
+            .map(|syllable| syllable.marked(tones))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Reading {
+    spelling: String,
+    tone: u8,
+}
+
+impl Reading {
+    /// Parse numbered pinyin; the dictionary always writes the tone, even a neutral 5.
+    fn parse(numbered: &str) -> Option<Self> {
+        if !numbered.ends_with(|c: char| ('1'..='5').contains(&c)) {
+            return None;
+        }
+        match parse::parse(numbered).ok()?.as_slice() {
+            [syllable] => Some(Self {
+                spelling: syllable.spelling.clone(),
+                tone: syllable.tone?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Converts hanzi text to pinyin using a dictionary of character and word readings.
+pub struct PinyinConverter {
+    chars: HashMap<char, Reading>,
+    /// Words of two or more characters
+    words: HashMap<Vec<char>, Vec<Reading>>,
+    longest_word: usize,
+}
+
+impl PinyinConverter {
+    /// The converter over the dictionary compiled into the binary.
+    pub fn bundled() -> &'static Self {
+        static CONVERTER: OnceLock<PinyinConverter> = OnceLock::new();
+        CONVERTER.get_or_init(|| Self::from_tsv(READINGS, &[WORDS]))
+    }
+
+    /// Build a converter from tab-separated readings.
+    ///
+    /// `readings` lines are `pinyin, characters` giving each character's default
+    /// reading. Word lists are `hanzi, pinyin` with one syllable per character;
+    /// further columns are ignored, and where lists disagree the first wins.
+    /// Single-character entries override the default reading. Lines starting
+    /// with `#` are comments; malformed lines are skipped.
+    pub fn from_tsv(readings: &str, word_lists: &[&str]) -> Self {
+        let mut chars = HashMap::new();
+        for fields in data_lines(readings) {
+            let [pinyin, hanzi, ..] = fields[..] else {
+                continue;
+            };
+            let Some(reading) = Reading::parse(pinyin) else {
+                continue;
+            };
+            for c in hanzi.chars() {
+                chars.entry(c).or_insert_with(|| reading.clone());
+            }
+        }
+
+        let mut overrides = HashMap::new();
+        let mut words = HashMap::new();
+        for fields in word_lists.iter().flat_map(|list| data_lines(list)) {
+            let [hanzi, pinyin, ..] = fields[..] else {
+                continue;
+            };
+            let hanzi: Vec<char> = hanzi.chars().collect();
+            let Some(readings) = pinyin
+                .split_whitespace()
+                .map(Reading::parse)
+                .collect::<Option<Vec<_>>>()
+                .filter(|readings| readings.len() == hanzi.len())
+            else {
+                continue;
+            };
+            match hanzi[..] {
+                [c] => {
+                    overrides.entry(c).or_insert_with(|| readings[0].clone());
+                }
+                _ => {
+                    words.entry(hanzi).or_insert(readings);
+                }
+            }
+        }
+        chars.extend(overrides);
+
+        let longest_word = words.keys().map(Vec::len).max().unwrap_or(1);
+        Self {
+            chars,
+            words,
+            longest_word,
+        }
+    }
+
+    pub fn convert(&self, text: &str) -> Conversion {
+        let chars: Vec<char> = text.chars().collect();
+        let mut tokens = Vec::new();
+        // Syllables since the last break, for sandhi: token index and input
+        let mut phrase: Vec<(usize, SandhiSyllable)> = Vec::new();
+
+        let mut i = 0;
+        while i < chars.len() {
+            if !is_hanzi(chars[i]) {
+                let len = chars[i..].iter().take_while(|&&c| !is_hanzi(c)).count();
+                apply_sandhi(&mut tokens, &mut phrase);
+                tokens.push(Token::Other {
+                    text: chars[i..i + len].iter().collect(),
+                });
+                i += len;
+                continue;
+            }
+
+            let run = chars[i..].iter().take_while(|&&c| is_hanzi(c)).count();
+            let word = (2..=self.longest_word.min(run))
+                .rev()
+                .find_map(|len| Some((len, self.words.get(&chars[i..i + len])?)));
+
+            match word {
+                Some((len, readings)) => {
+                    for (offset, reading) in readings.iter().enumerate() {
+                        push_syllable(
+                            &mut tokens,
+                            &mut phrase,
+                            chars[i + offset],
+                            reading,
+                            offset == len - 1,
+                        );
+                    }
+                    i += len;
+                }
+                None => {
+                    match self.chars.get(&chars[i]) {
+                        Some(reading) => {
+                            push_syllable(&mut tokens, &mut phrase, chars[i], reading, false)
+                        }
+                        None => {
+                            apply_sandhi(&mut tokens, &mut phrase);
+                            tokens.push(Token::Unknown {
+                                text: chars[i].to_string(),
+                            });
+                        }
+                    }
+                    i += 1;
+                }
+            }
+        }
+        apply_sandhi(&mut tokens, &mut phrase);
+
+        Conversion { tokens }
+    }
+}
+
+fn push_syllable(
+    tokens: &mut Vec<Token>,
+    phrase: &mut Vec<(usize, SandhiSyllable)>,
+    hanzi: char,
+    reading: &Reading,
+    word_final: bool,
+) {
+    phrase.push((
+        tokens.len(),
+        SandhiSyllable {
+            hanzi: Some(hanzi),
+            tone: reading.tone,
+            word_final,
+        },
+    ));
+    tokens.push(Token::Syllable(Syllable {
+        hanzi,
+        spelling: reading.spelling.clone(),
+        citation_tone: reading.tone,
+        sandhi_tone: reading.tone,
+    }));
+}
+
+/// Fill in the spoken tones of the phrase that just ended.
+fn apply_sandhi(tokens: &mut [Token], phrase: &mut Vec<(usize, SandhiSyllable)>) {
+    let inputs: Vec<SandhiSyllable> = phrase.iter().map(|(_, syllable)| *syllable).collect();
+    for ((index, _), tone) in phrase.drain(..).zip(sandhi::apply(&inputs)) {
+        if let Token::Syllable(syllable) = &mut tokens[index] {
+            syllable.sandhi_tone = tone;
+        }
+    }
+}
+
+pub fn is_hanzi(c: char) -> bool {
+    matches!(c,
+        '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FFFF}'
+    )
+}
+
+/// Write `tone` over the right vowel: a or e if present, the o of ou, else the last vowel.
+pub fn mark_tone(spelling: &str, tone: u8) -> String {
+    let spelling = spelling.replace('v', "ü");
+    if !(1..=4).contains(&tone) {
+        return spelling;
+    }
+
+    let position = ['a', 'e']
+        .iter()
+        .find_map(|vowel| spelling.find(*vowel))
+        .or_else(|| spelling.find("ou"))
+        .or_else(|| spelling.rfind(['i', 'o', 'u', 'ü']));
+    let Some(position) = position else {
+        return spelling;
+    };
+
+    let mut marked = String::with_capacity(spelling.len() + 1);
+    for (i, c) in spelling.char_indices() {
+        if i != position {
+            marked.push(c);
+            continue;
+        }
+        let variants = match c {
+            'a' => "āáǎà",
+            'e' => "ēéěè",
+            'i' => "īíǐì",
+            'o' => "ōóǒò",
+            'u' => "ūúǔù",
+            _ => "ǖǘǚǜ",
+        };
+        marked.extend(variants.chars().nth(tone as usize - 1));
+    }
+    marked
+}
+
+fn data_lines(tsv: &str) -> impl Iterator<Item = Vec<&str>> {
+    tsv.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.split('\t').map(str::trim).collect())
+}
@@ -0,0 +1,378 @@
+//! Reading pinyin the way people type it.
+//!
+//! Accepts numbered (`ni3 hao3`, `ni3hao3`), tone-marked (`nǐhǎo`) and toneless
+//! (`nihao`) pinyin in any case, with ü written as `ü`, `v` or `u:`, and
+//! syllables optionally separated by spaces, apostrophes (`xi'an`) or hyphens.
+//! Runs of letters are split against the full syllable inventory, preferring
+//! longer syllables, so `xian` is one syllable and `xi'an` two.
+
+use std::{
+    collections::HashSet,
+    sync::OnceLock,
+};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use super::mark_tone;
+
+/// Every Mandarin syllable, toneless, with ü written as `v` after l and n.
+const SYLLABLES: &str = "\
+    a o e ai ei ao ou an en ang eng er \
+    yi ya yo ye yao you yan yin yang ying yong yu yue yuan yun \
+    wu wa wo wai wei wan wen wang weng \
+    ba bo bai bei bao ban ben bang beng bi bie biao bian bin bing bu \
+    pa po pai pei pao pou pan pen pang peng pi pie piao pian pin ping pu \
+    ma mo me mai mei mao mou man men mang meng mi mie miao miu mian min ming mu \
+    fa fo fei fou fan fen fang feng fiao fu \
+    da de dai dei dao dou dan den dang deng dong di dia die diao diu dian ding \
+    du duo dui duan dun \
+    ta te tai tei tao tou tan tang teng tong ti tie tiao tian ting tu tuo tui tuan tun \
+    na ne nai nei nao nou nan nen nang neng nong ni nie niao niu nian nin niang ning \
+    nu nuo nuan nun nv nve \
+    la lo le lai lei lao lou lan lang leng long li lia lie liao liu lian lin liang ling \
+    lu luo luan lun lv lve \
+    ga ge gai gei gao gou gan gen gang geng gong gu gua guo guai gui guan gun guang \
+    ka ke kai kei kao kou kan ken kang keng kong ku kua kuo kuai kui kuan kun kuang \
+    ha he hai hei hao hou han hen hang heng hong hu hua huo huai hui huan hun huang \
+    ji jia jie jiao jiu jian jin jiang jing jiong ju jue juan jun \
+    qi qia qie qiao qiu qian qin qiang qing qiong qu que quan qun \
+    xi xia xie xiao xiu xian xin xiang xing xiong xu xue xuan xun \
+    zha zhe zhi zhai zhei zhao zhou zhan zhen zhang zheng zhong \
+    zhu zhua zhuo zhuai zhui zhuan zhun zhuang \
+    cha che chi chai chao chou chan chen chang cheng chong \
+    chu chua chuo chuai chui chuan chun chuang \
+    sha she shi shai shei shao shou shan shen shang sheng \
+    shu shua shuo shuai shui shuan shun shuang \
+    re ri rao rou ran ren rang reng rong ru rua ruo rui ruan run \
+    za ze zi zai zei zao zou zan zen zang zeng zong zu zuo zui zuan zun \
+    ca ce ci cai cao cou can cen cang ceng cong cu cuo cui cuan cun \
+    sa se si sai sao sou san sen sang seng song su suo sui suan sun";
+
+/// Mandarin initials, longest first so `zh`/`ch`/`sh` win over `z`/`c`/`s`.
+pub const INITIALS: [&str; 23] = [
+    "zh", "ch", "sh", "b", "p", "m", "f", "d", "t", "n", "l", "g", "k", "h", "j", "q", "x", "r",
+    "z", "c", "s", "y", "w",
+];
+
+/// Longest syllables, e.g. `zhuang`, in letters.
+const LONGEST_SYLLABLE: usize = 6;
+
+fn inventory() -> &'static HashSet<&'static str> {
+    static INVENTORY: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    INVENTORY.get_or_init(|| SYLLABLES.split_whitespace().collect())
+}
+
+/// Why a pinyin string was rejected. Positions count characters from 0.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PinyinError {
+    #[error("no pinyin given")]
+    Empty,
+
+    #[error("unexpected '{found}' at position {position}")]
+    UnexpectedCharacter { position: usize, found: char },
+
+    #[error("'{text}' at position {position} is not a Mandarin syllable")]
+    UnknownSyllable { position: usize, text: String },
+
+    #[error("tone {tone} at position {position} is not 1-5")]
+    InvalidTone { position: usize, tone: char },
+
+    #[error("second tone for one syllable at position {position}")]
+    ExtraTone { position: usize },
+
+    #[error("no tones given; write them as numbers (ni3 hao3) or marks (nǐ hǎo)")]
+    MissingTones,
+}
+
+impl PinyinError {
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            Self::UnexpectedCharacter { position, .. }
+            | Self::UnknownSyllable { position, .. }
+            | Self::InvalidTone { position, .. }
+            | Self::ExtraTone { position } => Some(*position),
+            Self::Empty | Self::MissingTones => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PinyinSyllable {
+    /// Lowercase and toneless, with ü written as `v` where it is written at all
+    /// (`lv`, but `ju`)
+    pub spelling: String,
+    /// `None` when written without a tone
+    pub tone: Option<u8>,
+    /// Where the syllable starts in the input
+    pub position: usize,
+}
+
+impl PinyinSyllable {
+    /// Numbered form, e.g. `lv4`; a syllable written without a tone is neutral.
+    pub fn numbered(&self) -> String {
+        format!("{}{}", self.spelling, self.tone.unwrap_or(5))
+    }
+
+    /// Tone-marked form, e.g. `lǜ`.
+    pub fn marked(&self) -> String {
+        mark_tone(&self.spelling, self.tone.unwrap_or(5))
+    }
+
+    /// The spelling split into its initial, if any, and final: `zh` + `ang`.
+    pub fn initial_and_final(&self) -> (Option<&str>, &str) {
+        let spelling = self.spelling.as_str();
+        INITIALS
+            .iter()
+            .find(|initial| spelling.len() > initial.len() && spelling.starts_with(*initial))
+            .map(|initial| (Some(&spelling[..initial.len()]), &spelling[initial.len()..]))
+            .unwrap_or((None, spelling))
+    }
+}
+
+/// Whether a sound label, such as a provider's phoneme (`zh`, `ao3`), is an initial.
+pub fn is_initial(sound: &str) -> bool {
+    INITIALS.contains(&sound.trim_end_matches(|c: char| c.is_ascii_digit()))
+}
+
+/// A letter of the input with any tone mark split off.
+struct Letter {
+    base: char,
+    tone: Option<u8>,
+    position: usize,
+}
+
+/// Split `input` into syllables.
+pub fn parse(input: &str) -> Result<Vec<PinyinSyllable>, PinyinError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut syllables = Vec::new();
+    let mut letters: Vec<Letter> = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some((base, tone)) = letter(c) {
+            // `u:` is a common ASCII spelling of ü
+            let (base, skip) = match (base, chars.get(i + 1)) {
+                ('u', Some(':')) => ('v', 2),
+                _ => (base, 1),
+            };
+            letters.push(Letter {
+                base,
+                tone,
+                position: i,
+            });
+            i += skip;
+            continue;
+        }
+        if let Some(mark) = combining_mark(c) {
+            // Decomposed input: the mark follows its vowel
+            let Some(last) = letters.last_mut() else {
+                return Err(PinyinError::UnexpectedCharacter {
+                    position: i,
+                    found: c,
+                });
+            };
+            match mark {
+                // A diaeresis turns the u before it into ü
+                None if last.base == 'u' => last.base = 'v',
+                None => {
+                    return Err(PinyinError::UnexpectedCharacter {
+                        position: i,
+                        found: c,
+                    })
+                }
+                Some(_) if last.tone.is_some() => {
+                    return Err(PinyinError::ExtraTone { position: i })
+                }
+                Some(_) => last.tone = mark,
+            }
+            i += 1;
+            continue;
+        }
+
+        // A digit belongs to the syllable just before it, with nothing between
+        let ended_run = !letters.is_empty() || (i > 0 && chars[i - 1].is_ascii_digit());
+        split_run(&letters, &mut syllables)?;
+        letters.clear();
+
+        match c {
+            '1'..='5' => {
+                let syllable = syllables.last_mut().filter(|_| ended_run).ok_or(
+                    PinyinError::UnexpectedCharacter {
+                        position: i,
+                        found: c,
+                    },
+                )?;
+                if syllable.tone.is_some() {
+                    return Err(PinyinError::ExtraTone { position: i });
+                }
+                syllable.tone = c.to_digit(10).map(|tone| tone as u8);
+            }
+            '0' | '6'..='9' => {
+                return Err(PinyinError::InvalidTone {
+                    position: i,
+                    tone: c,
+                })
+            }
+            '\'' | '’' | '-' => {}
+            c if c.is_whitespace() => {}
+            _ => {
+                return Err(PinyinError::UnexpectedCharacter {
+                    position: i,
+                    found: c,
+                })
+            }
+        }
+        i += 1;
+    }
+    split_run(&letters, &mut syllables)?;
+
+    if syllables.is_empty() {
+        return Err(PinyinError::Empty);
+    }
+    Ok(syllables)
+}
+
+/// Rewrite pinyin in any accepted style as space-separated numbered syllables.
+pub fn to_numbered(input: &str) -> Result<String, PinyinError> {
+    Ok(join(&parse(input)?, PinyinSyllable::numbered))
+}
+
+/// Rewrite pinyin in any accepted style as space-separated tone-marked syllables.
+pub fn to_marked(input: &str) -> Result<String, PinyinError> {
+    Ok(join(&parse(input)?, PinyinSyllable::marked))
+}
+
+/// Check reference pinyin for an evaluation and rewrite it in the numbered form
+/// providers expect. Pinyin with no tones at all cannot be scored for tone.
+pub fn normalize_reference(input: &str) -> Result<String, PinyinError> {
+    let syllables = parse(input)?;
+    if syllables.iter().all(|syllable| syllable.tone.is_none()) {
+        return Err(PinyinError::MissingTones);
+    }
+    Ok(join(&syllables, PinyinSyllable::numbered))
+}
+
+fn join(syllables: &[PinyinSyllable], write: fn(&PinyinSyllable) -> String) -> String {
+    syllables.iter().map(write).collect::<Vec<_>>().join(" ")
+}
+
+/// Split a run of letters into syllables, preferring longer ones.
+fn split_run(letters: &[Letter], syllables: &mut Vec<PinyinSyllable>) -> Result<(), PinyinError> {
+    let n = letters.len();
+    // parses[i]: whether letters[i..] splits into syllables
+    let mut parses = vec![false; n + 1];
+    parses[n] = true;
+    for start in (0..n).rev() {
+        parses[start] = (1..=LONGEST_SYLLABLE.min(n - start))
+            .any(|len| parses[start + len] && spelling(&letters[start..start + len]).is_some());
+    }
+
+    if !parses[0] {
+        // Report the first letter no split of the preceding ones gets past
+        let mut reachable = vec![false; n + 1];
+        reachable[0] = true;
+        let mut stuck = 0;
+        for start in 0..n {
+            if !reachable[start] {
+                continue;
+            }
+            stuck = start;
+            for len in 1..=LONGEST_SYLLABLE.min(n - start) {
+                if spelling(&letters[start..start + len]).is_some() {
+                    reachable[start + len] = true;
+                }
+            }
+        }
+        return Err(PinyinError::UnknownSyllable {
+            position: letters[stuck].position,
+            text: letters[stuck..].iter().map(|letter| letter.base).collect(),
+        });
+    }
+
+    let mut start = 0;
+    while start < n {
+        let (len, spelling) = (1..=LONGEST_SYLLABLE.min(n - start))
+            .rev()
+            .filter(|len| parses[start + len])
+            .find_map(|len| Some((len, spelling(&letters[start..start + len])?)))
+            .expect("a split exists");
+        let letters = &letters[start..start + len];
+
+        let mut marks = letters.iter().filter(|letter| letter.tone.is_some());
+        let tone = marks.next().and_then(|letter| letter.tone);
+        if let Some(extra) = marks.next() {
+            return Err(PinyinError::ExtraTone {
+                position: extra.position,
+            });
+        }
+
+        syllables.push(PinyinSyllable {
+            spelling,
+            tone,
+            position: letters[0].position,
+        });
+        start += len;
+    }
+    Ok(())
+}
+
+/// The inventory spelling of `letters`, if they form a syllable.
+fn spelling(letters: &[Letter]) -> Option<String> {
+    let mut spelling: String = letters.iter().map(|letter| letter.base).collect();
+    // ü is written u after j, q, x and y, but people type it anyway; and lüe
+    // is often typed without its dots
+    if spelling.starts_with(['j', 'q', 'x', 'y']) {
+        spelling = spelling.replace('v', "u");
+    } else if spelling == "lue" || spelling == "nue" {
+        spelling = spelling.replace('u', "v");
+    }
+    inventory().contains(spelling.as_str()).then_some(spelling)
+}
+
+fn letter(c: char) -> Option<(char, Option<u8>)> {
+    let c = c.to_lowercase().next()?;
+    let (base, tone) = match c {
+        'a'..='z' => (c, 0),
+        'ā' => ('a', 1),
+        'á' => ('a', 2),
+        'ǎ' => ('a', 3),
+        'à' => ('a', 4),
+        'ē' => ('e', 1),
+        'é' => ('e', 2),
+        'ě' => ('e', 3),
+        'è' => ('e', 4),
+        'ī' => ('i', 1),
+        'í' => ('i', 2),
+        'ǐ' => ('i', 3),
+        'ì' => ('i', 4),
+        'ō' => ('o', 1),
+        'ó' => ('o', 2),
+        'ǒ' => ('o', 3),
+        'ò' => ('o', 4),
+        'ū' => ('u', 1),
+        'ú' => ('u', 2),
+        'ǔ' => ('u', 3),
+        'ù' => ('u', 4),
+        'ü' => ('v', 0),
+        'ǖ' => ('v', 1),
+        'ǘ' => ('v', 2),
+        'ǚ' => ('v', 3),
+        'ǜ' => ('v', 4),
+        _ => return None,
+    };
+    Some((base, (tone != 0).then_some(tone)))
+}
+
+/// Combining tone marks: `Some(tone)`, or `Some(None)` for the diaeresis of ü.
+fn combining_mark(c: char) -> Option<Option<u8>> {
+    match c {
+        '\u{0304}' => Some(Some(1)),
+        '\u{0301}' => Some(Some(2)),
+        '\u{030C}' => Some(Some(3)),
+        '\u{0300}' => Some(Some(4)),
+        '\u{0308}' => Some(None),
+        _ => None,
+    }
+}
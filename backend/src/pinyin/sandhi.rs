@@ -0,0 +1,99 @@
+//! Tone changes in connected speech.
+//!
+//! Applied per phrase, i.e. a run of syllables with no punctuation between them:
+//!
+//! - 不 (bù) is said bú before a fourth tone.
+//! - 一 (yī) is said yí before a fourth tone and yì before the other tones, but
+//!   keeps its first tone at the end of a phrase or word (统一), as an ordinal
+//!   (第一) and when counting (十一, 一二三).
+//! - 一 and 不 are neutral between a repeated syllable (看一看, 是不是).
+//! - In a run of third tones, all but the last are said as second tones.
+
+/// One syllable of a phrase, as far as sandhi is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SandhiSyllable {
+    /// The character read, when known
+    pub hanzi: Option<char>,
+    /// Citation tone, 1-4 or 5 for neutral
+    pub tone: u8,
+    /// Last syllable of a word of two or more syllables
+    pub word_final: bool,
+}
+
+/// Digits that make a neighbouring 一 part of a number read out digit by digit.
+const DIGITS: &str = "零〇一二三四五六七八九十";
+
+/// The syllables of one word from its citation tones, with its characters when
+/// there is one per syllable.
+pub fn word(hanzi: &str, tones: &[u8]) -> Vec<SandhiSyllable> {
+    let hanzi: Vec<char> = hanzi.chars().collect();
+    let count = tones.len();
+    tones
+        .iter()
+        .enumerate()
+        .map(|(i, &tone)| SandhiSyllable {
+            hanzi: (hanzi.len() == count).then(|| hanzi[i]),
+            tone,
+            word_final: count > 1 && i + 1 == count,
+        })
+        .collect()
+}
+
+/// The spoken tone of each syllable in `phrase`.
+pub fn apply(phrase: &[SandhiSyllable]) -> Vec<u8> {
+    let mut tones: Vec<u8> = phrase.iter().map(|syllable| syllable.tone).collect();
+
+    for (i, syllable) in phrase.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|prev| &phrase[prev]);
+        let next = phrase.get(i + 1);
+        let between_repeats = matches!(
+            (prev, next),
+            (Some(prev), Some(next)) if prev.hanzi.is_some() && prev.hanzi == next.hanzi
+        );
+
+        match (syllable.hanzi, syllable.tone) {
+            (Some('不'), 4) if between_repeats => tones[i] = 5,
+            (Some('不'), 4) if next.is_some_and(|next| next.tone == 4) => tones[i] = 2,
+            (Some('一'), 1) if between_repeats => tones[i] = 5,
+            (Some('一'), 1) => tones[i] = yi_tone(syllable, prev, next),
+            _ => {}
+        }
+    }
+
+    // Runs are found on the tones above; neither rule produces a third tone
+    let mut start = 0;
+    while start < tones.len() {
+        let run = tones[start..].iter().take_while(|&&tone| tone == 3).count();
+        if run > 1 {
+            tones[start..start + run - 1].fill(2);
+        }
+        start += run.max(1);
+    }
+
+    tones
+}
+
+fn yi_tone(
+    yi: &SandhiSyllable,
+    prev: Option<&SandhiSyllable>,
+    next: Option<&SandhiSyllable>,
+) -> u8 {
+    let Some(next) = next else {
+        return 1;
+    };
+    let is_digit = |syllable: Option<&SandhiSyllable>| {
+        syllable
+            .and_then(|syllable| syllable.hanzi)
+            .is_some_and(|hanzi| DIGITS.contains(hanzi))
+    };
+    let ordinal = prev.is_some_and(|prev| matches!(prev.hanzi, Some('第' | '初')));
+    if yi.word_final || ordinal || is_digit(prev) || is_digit(Some(next)) {
+        return 1;
+    }
+
+    match next.tone {
+        // Measure words such as 个 keep the fourth tone's effect when unstressed
+        4 | 5 => 2,
+        _ => 4,
+    }
+}
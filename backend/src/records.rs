@@ -0,0 +1,41 @@
+//! Line-oriented data files such as CC-CEDICT and the HSK lists: one record
+//! per line, with `#` starting a comment line.
+
+use thiserror::Error;
+
+/// A line that could not be read as a record.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("line {line}: {reason}")]
+pub struct RecordError {
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+/// Parse every record of `text` with `parse_record`, skipping blank and
+/// comment lines. Records come with their line number, counting from 1.
+pub fn parse<T: 'static>(
+    text: &str,
+    parse_record: fn(&str) -> Result<T, &'static str>,
+) -> impl Iterator<Item = Result<(usize, T), RecordError>> + '_ {
+    text.lines().enumerate().filter_map(move |(index, line)| {
+        let line_number = index + 1;
+        parse_line(line, parse_record).map(|record| {
+            record
+                .map(|record| (line_number, record))
+                .map_err(|reason| RecordError {
+                    line: line_number,
+                    reason,
+                })
+        })
+    })
+}
+
+/// Parse one line with `parse_record`, trimmed; `None` for blank and comment
+/// lines.
+pub fn parse_line<T>(
+    line: &str,
+    parse_record: fn(&str) -> Result<T, &'static str>,
+) -> Option<Result<T, &'static str>> {
+    let line = line.trim();
+    (!line.is_empty() && !line.starts_with('#')).then(|| parse_record(line))
+}
@@ -1,28 +1,45 @@
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{
+        delete,
         get,
         post,
     },
     Extension,
     Router,
 };
+use tower::ServiceBuilder;
 
 use crate::{
     auth::{
+        audit::AuditService,
         jwt::JwtService,
         password::PasswordAuthService,
         session::SessionService,
     },
     config::Config,
+    curriculum::CurriculumService,
     db::Database,
+    dictionary::DictionaryService,
     handlers::{
         auth,
+        curriculum,
+        dictionary,
         health,
+        review,
         speech,
+        text,
+        vocabulary,
     },
+    middleware::rate_limit::RateLimiter,
     speech::{
-        iflytek::IFlytekConfig,
-        IFlytekService,
+        analytics::SpeechAnalyticsService,
+        create_evaluator,
+        history::SpeechHistoryService,
+    },
+    vocabulary::{
+        review::ReviewService,
+        VocabularyService,
     },
 };
 
@@ -31,10 +48,22 @@ pub fn create_routes(db: Database, config: Config) -> Router {
     let jwt_service = JwtService::new(&config);
     let session_service = SessionService::new(db.clone());
     let password_auth_service = PasswordAuthService::new(db.pool().clone());
+    let audit_service = AuditService::new(db.clone());
+
+    // Rate limits by route group
+    let rate_limiter = RateLimiter::new(db.clone(), config.trusted_proxies.clone());
+    let auth_rate_limit = rate_limiter.layer("auth", config.rate_limits.auth.clone());
+    let speech_rate_limit = rate_limiter.layer("speech", config.rate_limits.speech.clone());
 
-    // Initialize speech evaluation service
-    let iflytek_config = IFlytekConfig::default();
-    let iflytek_service = IFlytekService::new(iflytek_config);
+    // Initialize the configured speech evaluation provider
+    let speech_evaluator = create_evaluator(&config.speech);
+    tracing::info!("Speech evaluation provider: {}", speech_evaluator.name());
+    let speech_history_service = SpeechHistoryService::new(db.clone());
+    let speech_analytics_service = SpeechAnalyticsService::new(db.clone());
+    let dictionary_service = DictionaryService::new(db.clone());
+    let vocabulary_service = VocabularyService::new(db.clone());
+    let review_service = ReviewService::new(db.clone());
+    let curriculum_service = CurriculumService::new(db.clone());
 
     Router::new()
         // Health checks
@@ -42,21 +71,87 @@ pub fn create_routes(db: Database, config: Config) -> Router {
         .route("/ready", get(health::readiness_check))
 
         // Authentication routes
-        .route("/auth/register", post(auth::register))
-        .route("/auth/login", post(auth::login))
+        .route(
+            "/auth/register",
+            post(auth::register).layer(auth_rate_limit.clone()),
+        )
+        .route("/auth/login", post(auth::login).layer(auth_rate_limit))
         .route("/auth/me", get(auth::me))
         .route("/auth/refresh", post(auth::refresh_token))
         .route("/auth/logout", post(auth::logout))
         .route("/auth/logout-all", post(auth::logout_all))
+        .route("/auth/sessions", get(auth::list_sessions))
+        .route("/auth/sessions/:id", delete(auth::revoke_session))
 
         // Speech evaluation routes
-        .route("/speech/evaluate", post(speech::evaluate_speech))
+        .route(
+            "/speech/evaluate",
+            post(speech::evaluate_speech).layer(
+                ServiceBuilder::new()
+                    .layer(speech_rate_limit.clone())
+                    .layer(DefaultBodyLimit::max(config.speech.max_upload_bytes())),
+            ),
+        )
+        .route(
+            "/speech/stream",
+            get(speech::stream_speech).layer(speech_rate_limit),
+        )
         .route("/speech/health", get(speech::health_check))
+        .route("/speech/attempts", get(speech::list_attempts))
+        .route("/speech/attempts/:id", get(speech::get_attempt))
+        .route("/speech/trends/words", get(speech::word_trends))
+        .route("/speech/trends/words/:word", get(speech::word_trend))
+        .route("/speech/insights", get(speech::insights))
+        .route("/speech/drills", get(speech::drills))
+
+        // Text routes
+        .route("/text/segment", post(text::segment))
+
+        // Dictionary routes
+        .route("/dictionary/search", get(dictionary::search))
+
+        // Vocabulary routes
+        .route(
+            "/words",
+            get(vocabulary::list_words).post(vocabulary::add_word),
+        )
+        .route("/words/bulk", post(vocabulary::add_from_dictionary))
+        .route(
+            "/words/:id",
+            get(vocabulary::get_word)
+                .put(vocabulary::update_word)
+                .delete(vocabulary::delete_word),
+        )
+
+        // Review routes
+        .route("/reviews/due", get(review::due))
+        .route(
+            "/reviews/settings",
+            get(review::get_settings).put(review::update_settings),
+        )
+        .route("/reviews/:id/grade", post(review::grade))
+        .route("/reviews/:id/log", get(review::history))
+
+        // Curriculum routes
+        .route("/curriculum/hsk/coverage", get(curriculum::coverage))
+        .route("/curriculum/hsk/:level", get(curriculum::level_words))
+        .route(
+            "/curriculum/hsk/:level/characters",
+            get(curriculum::level_characters),
+        )
 
         // Add service extensions
         .layer(Extension(password_auth_service))
         .layer(Extension(jwt_service))
         .layer(Extension(session_service))
-        .layer(Extension(iflytek_service))
+        .layer(Extension(audit_service))
+        .layer(Extension(speech_evaluator))
+        .layer(Extension(speech_history_service))
+        .layer(Extension(speech_analytics_service))
+        .layer(Extension(dictionary_service))
+        .layer(Extension(vocabulary_service))
+        .layer(Extension(review_service))
+        .layer(Extension(curriculum_service))
+        .layer(Extension(db))
         .layer(Extension(config))
 }
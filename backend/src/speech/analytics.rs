@@ -0,0 +1,311 @@
+//! Turns a learner's stored pronunciation history into per-sound accuracy.
+//!
+//! Initials and finals are scored from the provider's phoneme breakdown. Tones
+//! compare the tone expected by the reference pinyin, after the word's own
+//! sandhi (你好 is expected as ni2 hao3), with the tone the provider heard (the
+//! word's `toneN` label), which also feeds the confusion matrix.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use super::iflytek::parse_tone_label;
+use crate::{
+    db::Database,
+    error::Result,
+    pinyin::{
+        parse::{
+            self,
+            is_initial,
+        },
+        sandhi,
+    },
+};
+
+/// A sound counts as accurate when it scores at least this (0–100 scale).
+pub const PASS_SCORE: f64 = 75.0;
+/// Sounds practised fewer times than this are left out of the weakest ranking.
+const MIN_SAMPLES_FOR_RANKING: usize = 3;
+const WEAKEST_SOUND_COUNT: usize = 10;
+/// Tones 1–4 plus the neutral tone, numbered 5.
+const TONES: [u8; 5] = [1, 2, 3, 4, 5];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundKind {
+    Initial,
+    Final,
+    Tone,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SoundAccuracy {
+    /// `zh`, `ang`, `tone3`, ...
+    pub sound: String,
+    pub kind: SoundKind,
+    pub samples: usize,
+    pub average_score: f64,
+    /// Percentage of samples scoring at least [`PASS_SCORE`]
+    pub accuracy: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToneConfusion {
+    pub expected: u8,
+    pub realised: u8,
+    pub count: usize,
+    /// Percentage of syllables with the expected tone that were read this way
+    pub rate: f64,
+}
+
+/// `counts[e][r]` is how often tone `TONES[e]` was read as tone `TONES[r]`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToneConfusionMatrix {
+    pub tones: [u8; 5],
+    pub counts: [[usize; 5]; 5],
+    /// Off-diagonal cells, most frequent first
+    pub confusions: Vec<ToneConfusion>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechInsights {
+    pub attempts: i64,
+    pub initials: Vec<SoundAccuracy>,
+    pub finals: Vec<SoundAccuracy>,
+    pub tones: Vec<SoundAccuracy>,
+    pub tone_confusion: ToneConfusionMatrix,
+    pub weakest_sounds: Vec<SoundAccuracy>,
+}
+
+/// A correctly placed word from a Chinese attempt.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ScoredWord {
+    pub word: String,
+    pub pinyin: Option<String>,
+    pub tone: Option<String>,
+    pub tone_score: Option<f64>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ScoredPhoneme {
+    pub phoneme: String,
+    pub pronunciation: f64,
+}
+
+#[derive(Default)]
+struct Tally {
+    samples: usize,
+    total: f64,
+    passes: usize,
+}
+
+impl Tally {
+    fn add(&mut self, score: f64) {
+        self.samples += 1;
+        self.total += score;
+        if score >= PASS_SCORE {
+            self.passes += 1;
+        }
+    }
+
+    fn accuracy(&self, sound: String, kind: SoundKind) -> SoundAccuracy {
+        SoundAccuracy {
+            sound,
+            kind,
+            samples: self.samples,
+            average_score: round1(self.total / self.samples as f64),
+            accuracy: round1(self.passes as f64 * 100.0 / self.samples as f64),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SpeechAnalyticsService {
+    db: Database,
+}
+
+impl SpeechAnalyticsService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Insights over the user's Chinese attempts, optionally only those since `since`.
+    pub async fn insights(
+        &self,
+        user_id: &str,
+        since: Option<NaiveDateTime>,
+    ) -> Result<SpeechInsights> {
+        let attempts = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) FROM speech_attempts
+            WHERE user_id = ?1 AND lang != 'en' AND (?2 IS NULL OR created_at >= ?2)
+            "#,
+            user_id,
+            since
+        )
+        .fetch_one(self.db.pool())
+        .await?;
+
+        // Only words read as written (read_type 0) say anything about a sound
+        let words = sqlx::query_as!(
+            ScoredWord,
+            r#"
+            SELECT w.word, w.pinyin, w.tone, w.tone_score
+            FROM speech_word_scores w
+            JOIN speech_attempts a ON a.id = w.attempt_id
+            WHERE a.user_id = ?1 AND a.lang != 'en' AND (?2 IS NULL OR a.created_at >= ?2)
+              AND w.read_type = 0
+            "#,
+            user_id,
+            since
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let phonemes = sqlx::query_as!(
+            ScoredPhoneme,
+            r#"
+            SELECT p.phoneme, p.pronunciation
+            FROM speech_phoneme_scores p
+            JOIN speech_word_scores w ON w.id = p.word_score_id
+            JOIN speech_attempts a ON a.id = w.attempt_id
+            WHERE a.user_id = ?1 AND a.lang != 'en' AND (?2 IS NULL OR a.created_at >= ?2)
+              AND w.read_type = 0
+            "#,
+            user_id,
+            since
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(build_insights(attempts as i64, &words, &phonemes))
+    }
+}
+
+pub fn build_insights(
+    attempts: i64,
+    words: &[ScoredWord],
+    phonemes: &[ScoredPhoneme],
+) -> SpeechInsights {
+    let mut initials: BTreeMap<String, Tally> = BTreeMap::new();
+    let mut finals: BTreeMap<String, Tally> = BTreeMap::new();
+    for phoneme in phonemes {
+        let sound = phoneme
+            .phoneme
+            .trim_end_matches(|c: char| c.is_ascii_digit())
+            .to_lowercase();
+        if sound.is_empty() {
+            continue;
+        }
+        let tallies = if is_initial(&sound) {
+            &mut initials
+        } else {
+            &mut finals
+        };
+        tallies.entry(sound).or_default().add(phoneme.pronunciation);
+    }
+
+    let mut tones: BTreeMap<u8, Tally> = BTreeMap::new();
+    let mut counts = [[0usize; 5]; 5];
+    for word in words {
+        let Some(pinyin) = &word.pinyin else {
+            continue;
+        };
+        let Ok(syllables) = parse::parse(pinyin) else {
+            continue;
+        };
+        let citation: Vec<u8> = syllables
+            .iter()
+            .map(|syllable| syllable.tone.unwrap_or(5))
+            .collect();
+        let expected = sandhi::apply(&sandhi::word(&word.word, &citation));
+
+        if let Some(score) = word.tone_score {
+            for tone in &expected {
+                tones.entry(*tone).or_default().add(score);
+            }
+        }
+
+        // Only line up realised tones when the label covers every syllable
+        let realised = word.tone.as_deref().map(parse_tone_label);
+        if let Some(realised) = realised.filter(|r| r.len() == expected.len()) {
+            for (expected, realised) in expected.iter().zip(realised) {
+                if let Some(realised) = realised {
+                    counts[tone_slot(*expected)][tone_slot(realised)] += 1;
+                }
+            }
+        }
+    }
+
+    let initials: Vec<SoundAccuracy> = initials
+        .into_iter()
+        .map(|(sound, tally)| tally.accuracy(sound, SoundKind::Initial))
+        .collect();
+    let finals: Vec<SoundAccuracy> = finals
+        .into_iter()
+        .map(|(sound, tally)| tally.accuracy(sound, SoundKind::Final))
+        .collect();
+    let tones: Vec<SoundAccuracy> = tones
+        .into_iter()
+        .map(|(tone, tally)| tally.accuracy(format!("tone{}", tone), SoundKind::Tone))
+        .collect();
+
+    let mut weakest_sounds: Vec<SoundAccuracy> = initials
+        .iter()
+        .chain(&finals)
+        .chain(&tones)
+        .filter(|sound| sound.samples >= MIN_SAMPLES_FOR_RANKING)
+        .cloned()
+        .collect();
+    weakest_sounds.sort_by(|a, b| {
+        a.average_score
+            .total_cmp(&b.average_score)
+            .then(b.samples.cmp(&a.samples))
+    });
+    weakest_sounds.truncate(WEAKEST_SOUND_COUNT);
+
+    SpeechInsights {
+        attempts,
+        initials,
+        finals,
+        tones,
+        tone_confusion: confusion_matrix(counts),
+        weakest_sounds,
+    }
+}
+
+fn confusion_matrix(counts: [[usize; 5]; 5]) -> ToneConfusionMatrix {
+    let mut confusions = Vec::new();
+    for (e, row) in counts.iter().enumerate() {
+        let total: usize = row.iter().sum();
+        for (r, count) in row.iter().enumerate() {
+            if e != r && *count > 0 {
+                confusions.push(ToneConfusion {
+                    expected: TONES[e],
+                    realised: TONES[r],
+                    count: *count,
+                    rate: round1(*count as f64 * 100.0 / total as f64),
+                });
+            }
+        }
+    }
+    confusions.sort_by(|a, b| b.count.cmp(&a.count).then(b.rate.total_cmp(&a.rate)));
+
+    ToneConfusionMatrix {
+        tones: TONES,
+        counts,
+        confusions,
+    }
+}
+
+fn tone_slot(tone: u8) -> usize {
+    match tone {
+        1..=4 => tone as usize - 1,
+        _ => 4,
+    }
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
@@ -0,0 +1,540 @@
+//! Audio inspection and normalisation for speech uploads.
+//!
+//! Browsers record in whatever container their `MediaRecorder` prefers (WebM/Opus
+//! in Chrome and Firefox, OGG in some builds), so uploads are sniffed from their
+//! leading bytes, decoded, downmixed and resampled to the 16 kHz mono 16-bit PCM
+//! the evaluation providers expect.
+
+use std::{
+    f64::consts::PI,
+    io::{
+        Cursor,
+        ErrorKind,
+    },
+    time::Duration,
+};
+
+use bytes::Bytes;
+use symphonia::{
+    core::{
+        audio::SampleBuffer,
+        codecs::{
+            CodecParameters,
+            Decoder,
+            DecoderOptions,
+            CODEC_TYPE_AAC,
+            CODEC_TYPE_FLAC,
+            CODEC_TYPE_NULL,
+            CODEC_TYPE_OPUS,
+        },
+        errors::Error as SymphoniaError,
+        formats::{
+            FormatOptions,
+            FormatReader,
+            Packet,
+        },
+        io::MediaSourceStream,
+    },
+    default::formats::{
+        MkvReader,
+        MpaReader,
+        OggReader,
+        WavReader,
+    },
+};
+use thiserror::Error;
+
+/// Sample rate of the PCM handed to speech providers.
+pub const TARGET_SAMPLE_RATE: u32 = 16_000;
+
+/// Zero crossings of the windowed-sinc kernel on either side of each output sample.
+const RESAMPLE_ZERO_CROSSINGS: f64 = 16.0;
+
+/// MPEG-1 Layer III bitrates in kbit/s, indexed by the header's bitrate field.
+const MPEG1_L3_BITRATES: [u32; 15] = [
+    0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+/// MPEG-2/2.5 Layer III bitrates in kbit/s.
+const MPEG2_L3_BITRATES: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+const MPEG1_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+
+/// Estimate how long a recording plays for, given the encoding the client declared.
+///
+/// Raw PCM is computed exactly from the byte count; MP3 (`lame`) is measured by
+/// walking its frame headers. Returns `None` when the duration cannot be determined.
+pub fn estimate_duration(
+    audio: &[u8],
+    encoding: &str,
+    sample_rate: u32,
+    channels: u8,
+    bit_depth: u8,
+) -> Option<Duration> {
+    match encoding {
+        "raw" => {
+            let bytes_per_second = sample_rate as u64 * channels as u64 * bit_depth as u64 / 8;
+            if bytes_per_second == 0 {
+                return None;
+            }
+            Some(Duration::from_secs_f64(
+                audio.len() as f64 / bytes_per_second as f64,
+            ))
+        }
+        "lame" => mp3_duration(audio),
+        _ => None,
+    }
+}
+
+struct Mp3Frame {
+    length: usize,
+    samples: u32,
+    sample_rate: u32,
+}
+
+fn mp3_duration(audio: &[u8]) -> Option<Duration> {
+    let mut offset = skip_id3v2(audio);
+    let mut seconds = 0.0f64;
+    let mut frames = 0usize;
+
+    while offset + 4 <= audio.len() {
+        match parse_mp3_header(&audio[offset..offset + 4]) {
+            Some(frame) if frame.length > 4 => {
+                seconds += frame.samples as f64 / frame.sample_rate as f64;
+                frames += 1;
+                offset += frame.length;
+            }
+            // Resynchronise on the next byte after garbage or a corrupt header
+            _ => offset += 1,
+        }
+    }
+
+    (frames > 0).then(|| Duration::from_secs_f64(seconds))
+}
+
+fn skip_id3v2(audio: &[u8]) -> usize {
+    if audio.len() < 10 || &audio[..3] != b"ID3" {
+        return 0;
+    }
+    // Tag size is a 28-bit "syncsafe" integer (7 bits per byte)
+    let size = audio[6..10]
+        .iter()
+        .fold(0usize, |size, byte| (size << 7) | (*byte as usize & 0x7f));
+    10 + size
+}
+
+fn parse_mp3_header(header: &[u8]) -> Option<Mp3Frame> {
+    if header[0] != 0xff || header[1] & 0xe0 != 0xe0 {
+        return None;
+    }
+
+    let version = (header[1] >> 3) & 0x03; // 0 = MPEG 2.5, 2 = MPEG 2, 3 = MPEG 1
+    let layer = (header[1] >> 1) & 0x03; // 1 = Layer III
+    if version == 1 || layer != 1 {
+        return None;
+    }
+
+    let bitrate_index = (header[2] >> 4) as usize;
+    let sample_rate_index = ((header[2] >> 2) & 0x03) as usize;
+    let padding = ((header[2] >> 1) & 0x01) as usize;
+    if bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 {
+        return None;
+    }
+
+    let is_mpeg1 = version == 3;
+    let bitrate = if is_mpeg1 {
+        MPEG1_L3_BITRATES[bitrate_index]
+    } else {
+        MPEG2_L3_BITRATES[bitrate_index]
+    } * 1000;
+    let sample_rate = match version {
+        3 => MPEG1_SAMPLE_RATES[sample_rate_index],
+        2 => MPEG1_SAMPLE_RATES[sample_rate_index] / 2,
+        _ => MPEG1_SAMPLE_RATES[sample_rate_index] / 4,
+    };
+    let (samples, coefficient) = if is_mpeg1 { (1152, 144) } else { (576, 72) };
+
+    Some(Mp3Frame {
+        length: (coefficient * bitrate / sample_rate) as usize + padding,
+        samples,
+        sample_rate,
+    })
+}
+
+#[derive(Debug, Error)]
+pub enum AudioError {
+    #[error("Unrecognised audio format; upload WAV, WebM, OGG or MP3")]
+    UnknownFormat,
+
+    #[error("Unsupported audio codec: {0}")]
+    UnsupportedCodec(String),
+
+    #[error("Audio could not be decoded: {0}")]
+    Malformed(String),
+
+    #[error("Audio contains no samples")]
+    Empty,
+
+    #[error("Audio is longer than the {} second limit", .0.as_secs())]
+    TooLong(Duration),
+}
+
+/// Container formats recognised from an upload's leading bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    Wav,
+    WebM,
+    Ogg,
+    Mp3,
+}
+
+impl Container {
+    pub fn sniff(audio: &[u8]) -> Option<Self> {
+        if audio.len() >= 12 && &audio[..4] == b"RIFF" && &audio[8..12] == b"WAVE" {
+            Some(Self::Wav)
+        } else if audio.starts_with(&[0x1a, 0x45, 0xdf, 0xa3]) {
+            // EBML header; Matroska and WebM share the same demuxer
+            Some(Self::WebM)
+        } else if audio.starts_with(b"OggS") {
+            Some(Self::Ogg)
+        } else if audio.starts_with(b"ID3")
+            || (audio.len() >= 4 && parse_mp3_header(&audio[..4]).is_some())
+        {
+            Some(Self::Mp3)
+        } else {
+            None
+        }
+    }
+}
+
+/// 16 kHz mono signed 16-bit little-endian PCM ready to send as `raw`.
+#[derive(Debug, Clone)]
+pub struct NormalizedAudio {
+    pub pcm: Bytes,
+    pub duration: Duration,
+}
+
+/// Decode a containerised upload into provider-ready PCM.
+///
+/// Decoding stops with [`AudioError::TooLong`] as soon as `max_duration` is
+/// exceeded, so a small, highly compressed upload cannot expand without bound.
+pub fn normalize(audio: Bytes, max_duration: Duration) -> Result<NormalizedAudio, AudioError> {
+    let container = Container::sniff(&audio).ok_or(AudioError::UnknownFormat)?;
+    let source = MediaSourceStream::new(Box::new(Cursor::new(audio)), Default::default());
+    let options = FormatOptions::default();
+
+    let mut reader: Box<dyn FormatReader> = match container {
+        Container::Wav => Box::new(WavReader::try_new(source, &options).map_err(malformed)?),
+        Container::WebM => Box::new(MkvReader::try_new(source, &options).map_err(malformed)?),
+        Container::Ogg => Box::new(OggReader::try_new(source, &options).map_err(malformed)?),
+        Container::Mp3 => Box::new(MpaReader::try_new(source, &options).map_err(malformed)?),
+    };
+
+    let track = reader
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| {
+            if reader.tracks().is_empty() {
+                AudioError::Empty
+            } else {
+                AudioError::UnsupportedCodec("unrecognised codec".to_string())
+            }
+        })?;
+    let track_id = track.id;
+    let mut params = track.codec_params.clone();
+    if container == Container::WebM && params.max_frames_per_packet.is_none() {
+        // Matroska does not declare a block size, which the PCM decoder needs
+        // (Chrome can record `audio/webm;codecs=pcm`); one second is ample
+        let frames = params.sample_rate.unwrap_or(48_000) as u64;
+        params.with_max_frames_per_packet(frames);
+    }
+    let mut decoder = open_decoder(&params)?;
+
+    let mut samples = Vec::new();
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(malformed(e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        decoder.decode(&packet, &mut samples)?;
+
+        if let Some(sample_rate) = decoder.sample_rate() {
+            check_duration(samples.len(), sample_rate, max_duration)?;
+        }
+    }
+
+    let sample_rate = decoder.sample_rate().ok_or(AudioError::Empty)?;
+    finish(&samples, sample_rate)
+}
+
+/// Normalise headerless PCM whose layout the client declared explicitly.
+pub fn normalize_raw(
+    audio: &[u8],
+    sample_rate: u32,
+    channels: u8,
+    bit_depth: u8,
+    max_duration: Duration,
+) -> Result<NormalizedAudio, AudioError> {
+    if bit_depth != 16 {
+        return Err(AudioError::UnsupportedCodec(format!(
+            "{}-bit raw PCM",
+            bit_depth
+        )));
+    }
+    if sample_rate == 0 || channels == 0 {
+        return Err(AudioError::Malformed(
+            "raw PCM needs a sample rate and channel count".to_string(),
+        ));
+    }
+
+    let channels = channels as usize;
+    let frames = audio.len() / 2 / channels;
+    check_duration(frames, sample_rate, max_duration)?;
+
+    let interleaved: Vec<f32> = audio
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+        .collect();
+    let mut samples = Vec::with_capacity(frames);
+    downmix(&interleaved, channels, &mut samples);
+
+    finish(&samples, sample_rate)
+}
+
+/// Decodes one demuxed packet, appending mono samples to `out`.
+trait MonoDecoder {
+    fn decode(&mut self, packet: &Packet, out: &mut Vec<f32>) -> Result<(), AudioError>;
+
+    /// Rate of the decoded samples, once known.
+    fn sample_rate(&self) -> Option<u32>;
+}
+
+fn open_decoder(params: &CodecParameters) -> Result<Box<dyn MonoDecoder>, AudioError> {
+    if params.codec == CODEC_TYPE_OPUS {
+        return open_opus_decoder(params);
+    }
+
+    let decoder = symphonia::default::get_codecs()
+        .make(params, &DecoderOptions::default())
+        .map_err(|_| AudioError::UnsupportedCodec(codec_name(params)))?;
+    Ok(Box::new(SymphoniaDecoder {
+        decoder,
+        buffer: None,
+        sample_rate: params.sample_rate,
+    }))
+}
+
+struct SymphoniaDecoder {
+    decoder: Box<dyn Decoder>,
+    buffer: Option<SampleBuffer<f32>>,
+    sample_rate: Option<u32>,
+}
+
+impl MonoDecoder for SymphoniaDecoder {
+    fn decode(&mut self, packet: &Packet, out: &mut Vec<f32>) -> Result<(), AudioError> {
+        let decoded = match self.decoder.decode(packet) {
+            Ok(decoded) => decoded,
+            // A single corrupt packet is skipped rather than failing the upload
+            Err(SymphoniaError::DecodeError(_)) => return Ok(()),
+            Err(e) => return Err(malformed(e)),
+        };
+
+        let spec = *decoded.spec();
+        let frames = decoded.capacity() as u64;
+        let buffer = match &mut self.buffer {
+            Some(buffer) if buffer.capacity() >= decoded.capacity() => buffer,
+            buffer => buffer.insert(SampleBuffer::new(frames, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+
+        self.sample_rate = Some(spec.rate);
+        downmix(buffer.samples(), spec.channels.count(), out);
+        Ok(())
+    }
+
+    fn sample_rate(&self) -> Option<u32> {
+        self.sample_rate
+    }
+}
+
+#[cfg(feature = "opus")]
+fn open_opus_decoder(params: &CodecParameters) -> Result<Box<dyn MonoDecoder>, AudioError> {
+    use audiopus::{
+        coder::Decoder as OpusDecoder,
+        Channels,
+        SampleRate,
+    };
+
+    let channels = match params.channels.map(|c| c.count()).unwrap_or(1) {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        n => return Err(AudioError::UnsupportedCodec(format!("{}-channel opus", n))),
+    };
+    let decoder = OpusDecoder::new(SampleRate::Hz48000, channels)
+        .map_err(|e| AudioError::Malformed(e.to_string()))?;
+
+    Ok(Box::new(opus::LibOpusDecoder {
+        decoder,
+        channels: channels as usize,
+        buffer: vec![0.0; opus::MAX_FRAME_SAMPLES * channels as usize],
+    }))
+}
+
+#[cfg(not(feature = "opus"))]
+fn open_opus_decoder(_params: &CodecParameters) -> Result<Box<dyn MonoDecoder>, AudioError> {
+    Err(AudioError::UnsupportedCodec(
+        "opus (server built without the `opus` feature)".to_string(),
+    ))
+}
+
+#[cfg(feature = "opus")]
+mod opus {
+    use audiopus::{
+        coder::Decoder,
+        packet::Packet as OpusPacket,
+        MutSignals,
+    };
+    use symphonia::core::formats::Packet;
+
+    use super::{
+        downmix,
+        AudioError,
+        MonoDecoder,
+    };
+
+    /// Opus always decodes at 48 kHz; a packet holds at most 120ms.
+    pub const MAX_FRAME_SAMPLES: usize = 5760;
+
+    pub struct LibOpusDecoder {
+        pub decoder: Decoder,
+        pub channels: usize,
+        pub buffer: Vec<f32>,
+    }
+
+    impl MonoDecoder for LibOpusDecoder {
+        fn decode(&mut self, packet: &Packet, out: &mut Vec<f32>) -> Result<(), AudioError> {
+            let input = OpusPacket::try_from(&packet.data[..])
+                .map_err(|e| AudioError::Malformed(e.to_string()))?;
+            let output = MutSignals::try_from(&mut self.buffer[..])
+                .map_err(|e| AudioError::Malformed(e.to_string()))?;
+            let frames = self
+                .decoder
+                .decode_float(Some(input), output, false)
+                .map_err(|e| AudioError::Malformed(e.to_string()))?;
+
+            downmix(&self.buffer[..frames * self.channels], self.channels, out);
+            Ok(())
+        }
+
+        fn sample_rate(&self) -> Option<u32> {
+            Some(48_000)
+        }
+    }
+}
+
+fn codec_name(params: &CodecParameters) -> String {
+    match params.codec {
+        CODEC_TYPE_AAC => "aac".to_string(),
+        CODEC_TYPE_FLAC => "flac".to_string(),
+        other => format!("codec {}", other),
+    }
+}
+
+fn malformed(error: SymphoniaError) -> AudioError {
+    match error {
+        SymphoniaError::Unsupported(feature) => AudioError::UnsupportedCodec(feature.to_string()),
+        other => AudioError::Malformed(other.to_string()),
+    }
+}
+
+fn check_duration(
+    frames: usize,
+    sample_rate: u32,
+    max_duration: Duration,
+) -> Result<(), AudioError> {
+    if frames as f64 / sample_rate as f64 > max_duration.as_secs_f64() {
+        return Err(AudioError::TooLong(max_duration));
+    }
+    Ok(())
+}
+
+/// Average interleaved channels into `out`.
+fn downmix(interleaved: &[f32], channels: usize, out: &mut Vec<f32>) {
+    if channels <= 1 {
+        out.extend_from_slice(interleaved);
+        return;
+    }
+    out.extend(
+        interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+    );
+}
+
+fn finish(samples: &[f32], sample_rate: u32) -> Result<NormalizedAudio, AudioError> {
+    if samples.is_empty() {
+        return Err(AudioError::Empty);
+    }
+
+    let resampled = resample(samples, sample_rate, TARGET_SAMPLE_RATE);
+    let mut pcm = Vec::with_capacity(resampled.len() * 2);
+    for sample in &resampled {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+        pcm.extend_from_slice(&value.to_le_bytes());
+    }
+
+    Ok(NormalizedAudio {
+        duration: Duration::from_secs_f64(resampled.len() as f64 / TARGET_SAMPLE_RATE as f64),
+        pcm: pcm.into(),
+    })
+}
+
+/// Band-limited resampling with a Hann-windowed sinc kernel.
+///
+/// When downsampling, the kernel's cutoff drops to the output Nyquist frequency so
+/// that content above 8 kHz is filtered out instead of aliasing into the speech band.
+fn resample(input: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to {
+        return input.to_vec();
+    }
+
+    let ratio = to as f64 / from as f64;
+    let cutoff = ratio.min(1.0);
+    let half_width = RESAMPLE_ZERO_CROSSINGS / cutoff;
+    let output_len = (input.len() as f64 * ratio).round() as usize;
+
+    (0..output_len)
+        .map(|n| {
+            let position = n as f64 / ratio;
+            let first = (position - half_width).ceil().max(0.0) as usize;
+            let last = ((position + half_width).floor() as usize).min(input.len() - 1);
+
+            let (mut sum, mut weight) = (0.0f64, 0.0f64);
+            for (offset, sample) in input[first..=last].iter().enumerate() {
+                let x = position - (first + offset) as f64;
+                let window = 0.5 * (1.0 + (PI * x / half_width).cos());
+                let w = cutoff * sinc(cutoff * x) * window;
+                sum += *sample as f64 * w;
+                weight += w;
+            }
+            // Normalising by the kernel sum keeps unity gain at the edges
+            if weight.abs() > f64::EPSILON {
+                (sum / weight) as f32
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
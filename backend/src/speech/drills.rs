@@ -0,0 +1,394 @@
+//! Practice material aimed at specific sounds.
+//!
+//! Drills draw from a small bundled lexicon of common words and a curated list
+//! of minimal pairs (`data/drills/`). A drill targets an initial, a final, a
+//! tone or a pair of adjacent tones (e.g. 3-3 for third-tone sandhi), and every
+//! item it returns can be posted to `/speech/evaluate` as-is.
+
+use std::{
+    fmt,
+    sync::OnceLock,
+};
+
+use bytes::Bytes;
+use rand::{
+    seq::SliceRandom,
+    Rng,
+};
+use serde::Serialize;
+
+use super::{
+    analytics::SoundKind,
+    audio::TARGET_SAMPLE_RATE,
+    SpeechEvaluationRequest,
+};
+use crate::pinyin::parse::{
+    self,
+    PinyinSyllable,
+    INITIALS,
+};
+
+const LEXICON: &str = include_str!("../../data/drills/lexicon.tsv");
+const MINIMAL_PAIRS: &str = include_str!("../../data/drills/minimal_pairs.tsv");
+
+/// What a drill practises, parsed from the sound labels used by speech insights.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DrillTarget {
+    Initial {
+        sound: String,
+    },
+    Final {
+        sound: String,
+    },
+    Tone {
+        tone: u8,
+    },
+    /// Two tones on adjacent syllables
+    TonePair {
+        first: u8,
+        second: u8,
+    },
+}
+
+impl DrillTarget {
+    /// Parse `zh`, `ang`, `ü`/`v`, `tone3`/`3`, or a tone pair like `tone3-tone3`/`3-3`.
+    pub fn parse(label: &str) -> Option<Self> {
+        let label = label.trim().to_lowercase().replace('ü', "v");
+        if label.is_empty() {
+            return None;
+        }
+
+        if let Some((first, second)) = label.split_once('-') {
+            return Some(Self::TonePair {
+                first: parse_tone(first)?,
+                second: parse_tone(second)?,
+            });
+        }
+        if let Some(tone) = parse_tone(&label) {
+            return Some(Self::Tone { tone });
+        }
+        if !label.chars().all(|c| c.is_ascii_lowercase()) {
+            return None;
+        }
+        if INITIALS.contains(&label.as_str()) {
+            Some(Self::Initial { sound: label })
+        } else {
+            Some(Self::Final { sound: label })
+        }
+    }
+
+    fn matches(&self, syllables: &[Syllable]) -> bool {
+        match self {
+            Self::Initial { sound } => syllables
+                .iter()
+                .any(|s| s.initial.as_deref() == Some(sound.as_str())),
+            Self::Final { sound } => syllables.iter().any(|s| s.final_ == *sound),
+            Self::Tone { tone } => syllables.iter().any(|s| s.tone == *tone),
+            Self::TonePair { first, second } => syllables
+                .windows(2)
+                .any(|pair| pair[0].tone == *first && pair[1].tone == *second),
+        }
+    }
+
+    /// The sound label a minimal pair has to contrast to practise this target.
+    fn contrast_label(&self) -> Option<(SoundKind, String)> {
+        match self {
+            Self::Initial { sound } => Some((SoundKind::Initial, sound.clone())),
+            Self::Final { sound } => Some((SoundKind::Final, sound.clone())),
+            Self::Tone { tone } => Some((SoundKind::Tone, tone_label(*tone))),
+            Self::TonePair { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for DrillTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Initial { sound } | Self::Final { sound } => f.write_str(sound),
+            Self::Tone { tone } => write!(f, "tone{}", tone),
+            Self::TonePair { first, second } => write!(f, "tone{}-tone{}", first, second),
+        }
+    }
+}
+
+/// A word to read aloud, in the shape `/speech/evaluate` expects for its `params`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DrillWord {
+    pub ref_text: String,
+    /// Numbered pinyin, one syllable per hanzi
+    pub ref_pinyin: String,
+    pub english: String,
+    pub lang: String,
+    pub core: String,
+}
+
+impl DrillWord {
+    fn new(ref_text: &str, ref_pinyin: &str, english: &str) -> Self {
+        Self {
+            ref_text: ref_text.to_string(),
+            ref_pinyin: ref_pinyin.to_string(),
+            english: english.to_string(),
+            lang: "cn".to_string(),
+            core: "word".to_string(),
+        }
+    }
+
+    /// Evaluation request for a recording of this word as 16 kHz mono 16-bit PCM.
+    pub fn to_request(&self, audio_data: Bytes) -> SpeechEvaluationRequest {
+        SpeechEvaluationRequest {
+            audio_data,
+            ref_text: self.ref_text.clone(),
+            lang: self.lang.clone(),
+            core: self.core.clone(),
+            ref_pinyin: Some(self.ref_pinyin.clone()),
+            phoneme_output: true,
+            audio_encoding: "raw".to_string(),
+            sample_rate: TARGET_SAMPLE_RATE,
+            channels: 1,
+            bit_depth: 16,
+        }
+    }
+}
+
+/// The single sound two words of a minimal pair differ in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Contrast {
+    pub kind: SoundKind,
+    /// Sound in `first`, e.g. `zh` or `tone3`
+    pub first: String,
+    /// Sound in `second`
+    pub second: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MinimalPair {
+    pub contrast: Contrast,
+    pub first: DrillWord,
+    pub second: DrillWord,
+}
+
+impl MinimalPair {
+    fn swapped(&self) -> Self {
+        Self {
+            contrast: Contrast {
+                kind: self.contrast.kind,
+                first: self.contrast.second.clone(),
+                second: self.contrast.first.clone(),
+            },
+            first: self.second.clone(),
+            second: self.first.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Drill {
+    /// The target's label, e.g. `zh` or `tone3-tone3`
+    pub sound: String,
+    pub target: DrillTarget,
+    pub words: Vec<DrillWord>,
+    /// Pairs contrasting the target sound with another, target sound first
+    pub minimal_pairs: Vec<MinimalPair>,
+}
+
+impl Drill {
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty() && self.minimal_pairs.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Syllable {
+    initial: Option<String>,
+    final_: String,
+    tone: u8,
+}
+
+impl Syllable {
+    fn new(syllable: &PinyinSyllable) -> Self {
+        let (initial, final_) = syllable.initial_and_final();
+        // Pinyin writes ü as u after j, q, x and y
+        let final_ = match (initial, final_.strip_prefix('u')) {
+            (Some("j" | "q" | "x" | "y"), Some(rest)) => format!("v{}", rest),
+            _ => final_.to_string(),
+        };
+
+        Self {
+            initial: initial.map(str::to_string),
+            final_,
+            tone: syllable.tone.unwrap_or(5),
+        }
+    }
+}
+
+struct LexiconEntry {
+    word: DrillWord,
+    syllables: Vec<Syllable>,
+}
+
+/// Picks drill material out of the bundled word lists.
+pub struct DrillGenerator {
+    lexicon: Vec<LexiconEntry>,
+    minimal_pairs: Vec<MinimalPair>,
+}
+
+impl DrillGenerator {
+    /// The generator over the word lists compiled into the binary.
+    pub fn bundled() -> &'static Self {
+        static GENERATOR: OnceLock<DrillGenerator> = OnceLock::new();
+        GENERATOR.get_or_init(|| Self::from_tsv(LEXICON, MINIMAL_PAIRS))
+    }
+
+    /// Build a generator from tab-separated word lists.
+    ///
+    /// Lexicon lines are `hanzi, pinyin, english`; minimal pair lines are two such
+    /// triples side by side. Lines starting with `#` are comments. Malformed lines
+    /// and pairs that do not differ in exactly one sound are skipped.
+    pub fn from_tsv(lexicon: &str, minimal_pairs: &str) -> Self {
+        let lexicon = data_lines(lexicon)
+            .filter_map(|fields| match fields[..] {
+                [hanzi, pinyin, english] => Some(LexiconEntry {
+                    word: DrillWord::new(hanzi, pinyin, english),
+                    syllables: parse_syllables(pinyin)?,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        let minimal_pairs = data_lines(minimal_pairs)
+            .filter_map(|fields| match fields[..] {
+                [hanzi_a, pinyin_a, english_a, hanzi_b, pinyin_b, english_b] => Some(MinimalPair {
+                    contrast: contrast(pinyin_a, pinyin_b)?,
+                    first: DrillWord::new(hanzi_a, pinyin_a, english_a),
+                    second: DrillWord::new(hanzi_b, pinyin_b, english_b),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            lexicon,
+            minimal_pairs,
+        }
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = &DrillWord> {
+        self.lexicon.iter().map(|entry| &entry.word)
+    }
+
+    pub fn minimal_pairs(&self) -> &[MinimalPair] {
+        &self.minimal_pairs
+    }
+
+    /// Up to `count` random words exercising `target`, plus up to `count` minimal pairs.
+    pub fn drill<R: Rng + ?Sized>(&self, target: &DrillTarget, count: usize, rng: &mut R) -> Drill {
+        let mut words: Vec<DrillWord> = self
+            .lexicon
+            .iter()
+            .filter(|entry| target.matches(&entry.syllables))
+            .map(|entry| entry.word.clone())
+            .collect();
+        words.shuffle(rng);
+        words.truncate(count);
+
+        let mut minimal_pairs: Vec<MinimalPair> = match target.contrast_label() {
+            Some((kind, label)) => self
+                .minimal_pairs
+                .iter()
+                .filter(|pair| pair.contrast.kind == kind)
+                .filter_map(|pair| {
+                    if pair.contrast.first == label {
+                        Some(pair.clone())
+                    } else if pair.contrast.second == label {
+                        Some(pair.swapped())
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        minimal_pairs.shuffle(rng);
+        minimal_pairs.truncate(count);
+
+        Drill {
+            sound: target.to_string(),
+            target: target.clone(),
+            words,
+            minimal_pairs,
+        }
+    }
+}
+
+fn data_lines(tsv: &str) -> impl Iterator<Item = Vec<&str>> {
+    tsv.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.split('\t').map(str::trim).collect())
+}
+
+fn parse_syllables(pinyin: &str) -> Option<Vec<Syllable>> {
+    let syllables = parse::parse(pinyin).ok()?;
+    Some(syllables.iter().map(Syllable::new).collect())
+}
+
+/// The one sound `a` and `b` differ in, if every differing syllable differs the same way.
+fn contrast(a: &str, b: &str) -> Option<Contrast> {
+    let a = parse_syllables(a)?;
+    let b = parse_syllables(b)?;
+    if a.len() != b.len() {
+        return None;
+    }
+
+    let mut found: Option<Contrast> = None;
+    for (a, b) in a.iter().zip(&b) {
+        if a == b {
+            continue;
+        }
+        let differs = (a.initial != b.initial) as u8
+            + (a.final_ != b.final_) as u8
+            + (a.tone != b.tone) as u8;
+        if differs != 1 {
+            return None;
+        }
+
+        let contrast = if a.initial != b.initial {
+            Contrast {
+                kind: SoundKind::Initial,
+                first: a.initial.clone()?,
+                second: b.initial.clone()?,
+            }
+        } else if a.final_ != b.final_ {
+            Contrast {
+                kind: SoundKind::Final,
+                first: a.final_.clone(),
+                second: b.final_.clone(),
+            }
+        } else {
+            Contrast {
+                kind: SoundKind::Tone,
+                first: tone_label(a.tone),
+                second: tone_label(b.tone),
+            }
+        };
+        if found.as_ref().is_some_and(|found| *found != contrast) {
+            return None;
+        }
+        found = Some(contrast);
+    }
+
+    found
+}
+
+fn parse_tone(label: &str) -> Option<u8> {
+    let digits = label.strip_prefix("tone").unwrap_or(label);
+    digits
+        .parse::<u8>()
+        .ok()
+        .filter(|tone| (1..=5).contains(tone))
+}
+
+fn tone_label(tone: u8) -> String {
+    format!("tone{}", tone)
+}
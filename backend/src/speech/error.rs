@@ -0,0 +1,100 @@
+//! Why a pronunciation evaluation failed.
+//!
+//! Providers report failures as numeric codes; [`SpeechError::from_provider_code`]
+//! sorts iFlytek's into the few cases callers handle differently. The HTTP
+//! status for each variant lives with the rest of the mapping in `crate::error`.
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SpeechError {
+    /// The provider rejected our credentials or app id
+    #[error("speech provider rejected our credentials: {0}")]
+    Auth(String),
+
+    /// Daily, per-second or concurrency allowance used up
+    #[error("speech provider quota exhausted: {0}")]
+    QuotaExceeded(String),
+
+    #[error("audio too short or silent to evaluate: {0}")]
+    AudioTooShort(String),
+
+    #[error("unsupported audio encoding: {0}")]
+    UnsupportedEncoding(String),
+
+    /// The provider refused the evaluation parameters (reference text, pinyin, ...)
+    #[error("invalid evaluation request: {0}")]
+    InvalidRequest(String),
+
+    #[error("speech provider timed out: {0}")]
+    Timeout(String),
+
+    #[error("malformed speech provider response: {0}")]
+    MalformedResponse(String),
+
+    /// The provider could not be reached or dropped the connection
+    #[error("speech service unavailable: {0}")]
+    Unavailable(String),
+
+    /// A provider error code with no more specific variant
+    #[error("speech provider error {code}: {message}")]
+    Provider { code: i64, message: String },
+
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl SpeechError {
+    /// Classify an iFlytek `header.code`.
+    ///
+    /// Codes are from the iFlytek WebSocket API and ISE error tables.
+    pub fn from_provider_code(code: i64, message: impl Into<String>) -> Self {
+        let message = message.into();
+        match code {
+            // Illegal access, invalid app id, feature not licensed
+            10105 | 10313 | 11200 => Self::Auth(message),
+            // Daily, per-second and concurrency limits
+            10110 | 11201 | 11202 | 11203 => Self::QuotaExceeded(message),
+            // No usable speech in the recording, no audio input, volume too low
+            48199 | 28689 | 28673 => Self::AudioTooShort(message),
+            // Audio decoding failed
+            10043 => Self::UnsupportedEncoding(message),
+            // Invalid parameters or request JSON
+            10106 | 10107 | 10160 | 10161 | 10163 => Self::InvalidRequest(message),
+            // Session or read timeout on the provider side
+            10114 | 10200 => Self::Timeout(message),
+            _ => Self::Provider { code, message },
+        }
+    }
+
+    /// Stable machine-readable code, used as the `code` of API error bodies.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Auth(_) => "SPEECH_PROVIDER_AUTH_FAILED",
+            Self::QuotaExceeded(_) => "SPEECH_QUOTA_EXCEEDED",
+            Self::AudioTooShort(_) => "AUDIO_TOO_SHORT",
+            Self::UnsupportedEncoding(_) => "UNSUPPORTED_AUDIO_FORMAT",
+            Self::InvalidRequest(_) => "INVALID_SPEECH_REQUEST",
+            Self::Timeout(_) => "SPEECH_TIMEOUT",
+            Self::MalformedResponse(_) => "SPEECH_MALFORMED_RESPONSE",
+            Self::Unavailable(_) => "SPEECH_UNAVAILABLE",
+            Self::Provider { .. } => "SPEECH_PROVIDER_ERROR",
+            Self::Internal(_) => "SPEECH_EVALUATION_FAILED",
+        }
+    }
+
+    /// Whether the provider itself failed, as opposed to rejecting this request.
+    ///
+    /// Only these count towards opening the circuit breaker.
+    pub fn is_provider_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::Auth(_)
+                | Self::QuotaExceeded(_)
+                | Self::Timeout(_)
+                | Self::MalformedResponse(_)
+                | Self::Unavailable(_)
+                | Self::Provider { .. }
+        )
+    }
+}
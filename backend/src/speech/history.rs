@@ -0,0 +1,496 @@
+//! Pronunciation history: every successful evaluation of a signed-in learner is
+//! stored with its word and phoneme breakdown so progress can be charted later.
+
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    time::Duration,
+};
+
+use chrono::{
+    NaiveDateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use uuid::Uuid;
+
+use super::{
+    iflytek::{
+        PhonemeScore,
+        TimeSpan,
+        WordScore,
+        WordScores,
+    },
+    SpeechEvaluationRequest,
+    SpeechEvaluationResponse,
+};
+use crate::{
+    db::Database,
+    error::Result,
+};
+
+/// Words the learner inserted that were not in the reference text.
+const READ_TYPE_INSERTION: i64 = 1;
+
+/// What was asked of the learner, captured before the request is handed to a provider.
+#[derive(Debug, Clone)]
+pub struct AttemptContext {
+    pub ref_text: String,
+    pub ref_pinyin: Option<String>,
+    pub lang: String,
+    pub core: String,
+    pub duration: Option<Duration>,
+}
+
+impl AttemptContext {
+    pub fn new(request: &SpeechEvaluationRequest, duration: Option<Duration>) -> Self {
+        Self {
+            ref_text: request.ref_text.clone(),
+            ref_pinyin: request.ref_pinyin.clone(),
+            lang: request.lang.clone(),
+            core: request.core.clone(),
+            duration,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SpeechAttempt {
+    pub id: String,
+    pub user_id: String,
+    pub created_at: NaiveDateTime,
+    pub provider: String,
+    pub ref_text: String,
+    pub ref_pinyin: Option<String>,
+    pub lang: String,
+    pub core: String,
+    pub duration_ms: Option<i64>,
+    pub overall_score: Option<f64>,
+    pub pronunciation_score: Option<f64>,
+    pub fluency_score: Option<f64>,
+    pub tone_score: Option<f64>,
+}
+
+/// A stored attempt with the same breakdown the evaluator originally returned.
+#[derive(Debug, Serialize)]
+pub struct SpeechAttemptDetail {
+    #[serde(flatten)]
+    pub attempt: SpeechAttempt,
+    pub overall_scores: HashMap<String, f32>,
+    pub words: Vec<WordScore>,
+}
+
+/// One practice of a word, for charting its score over time.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct WordTrendPoint {
+    pub attempt_id: String,
+    pub created_at: NaiveDateTime,
+    pub pinyin: Option<String>,
+    pub overall: f64,
+    pub pronunciation: f64,
+    pub tone_score: Option<f64>,
+}
+
+/// How a learner's scores for one word have moved across all their attempts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordTrendSummary {
+    pub word: String,
+    pub pinyin: Option<String>,
+    pub attempts: usize,
+    pub average_score: f64,
+    pub best_score: f64,
+    pub first_score: f64,
+    pub latest_score: f64,
+    /// `latest_score - first_score`; positive means the word is improving
+    pub change: f64,
+    pub last_practiced_at: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow)]
+struct WordScoreRow {
+    id: i64,
+    word: String,
+    pinyin: Option<String>,
+    tone: Option<String>,
+    overall: f64,
+    pronunciation: f64,
+    tone_score: Option<f64>,
+    prominence: Option<f64>,
+    read_type: i64,
+    span_start: Option<i64>,
+    span_end: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct PhonemeScoreRow {
+    word_score_id: i64,
+    phoneme: String,
+    phone: Option<String>,
+    pronunciation: f64,
+    tone_index: Option<i64>,
+    span_start: Option<i64>,
+    span_end: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct WordTrendRow {
+    word: String,
+    created_at: NaiveDateTime,
+    pinyin: Option<String>,
+    overall: f64,
+}
+
+#[derive(Clone)]
+pub struct SpeechHistoryService {
+    db: Database,
+}
+
+impl SpeechHistoryService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Store an evaluation and its word/phoneme breakdown in one transaction.
+    pub async fn record_attempt(
+        &self,
+        user_id: &str,
+        provider: &str,
+        context: &AttemptContext,
+        response: &SpeechEvaluationResponse,
+    ) -> Result<SpeechAttempt> {
+        let score = |key: &str| response.overall_scores.get(key).map(|s| *s as f64);
+        let attempt = SpeechAttempt {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            created_at: Utc::now().naive_utc(),
+            provider: provider.to_string(),
+            ref_text: context.ref_text.clone(),
+            ref_pinyin: context.ref_pinyin.clone(),
+            lang: context.lang.clone(),
+            core: context.core.clone(),
+            duration_ms: context.duration.map(|d| d.as_millis() as i64),
+            overall_score: score("overall"),
+            pronunciation_score: score("pronunciation"),
+            fluency_score: score("fluency"),
+            tone_score: score("tone"),
+        };
+
+        let mut tx = self.db.pool().begin().await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO speech_attempts (id, user_id, created_at, provider, ref_text, ref_pinyin, lang, core,
+                                         duration_ms, overall_score, pronunciation_score, fluency_score, tone_score)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+            "#,
+            attempt.id,
+            attempt.user_id,
+            attempt.created_at,
+            attempt.provider,
+            attempt.ref_text,
+            attempt.ref_pinyin,
+            attempt.lang,
+            attempt.core,
+            attempt.duration_ms,
+            attempt.overall_score,
+            attempt.pronunciation_score,
+            attempt.fluency_score,
+            attempt.tone_score
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for (position, word) in response.words.iter().enumerate() {
+            let position = position as i64;
+            let overall = word.scores.overall as f64;
+            let pronunciation = word.scores.pronunciation as f64;
+            let tone_score = word.scores.tone.map(|s| s as f64);
+            let prominence = word.scores.prominence.map(|s| s as f64);
+            let read_type = word.read_type as i64;
+            let span_start = word.span.as_ref().map(|s| s.start as i64);
+            let span_end = word.span.as_ref().map(|s| s.end as i64);
+
+            let word_score_id = sqlx::query!(
+                r#"
+                INSERT INTO speech_word_scores (attempt_id, position, word, pinyin, tone, overall, pronunciation,
+                                                tone_score, prominence, read_type, span_start, span_end)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                "#,
+                attempt.id,
+                position,
+                word.word,
+                word.pinyin,
+                word.tone,
+                overall,
+                pronunciation,
+                tone_score,
+                prominence,
+                read_type,
+                span_start,
+                span_end
+            )
+            .execute(&mut *tx)
+            .await?
+            .last_insert_rowid();
+
+            for (position, phoneme) in word.phonemes.iter().flatten().enumerate() {
+                let position = position as i64;
+                let pronunciation = phoneme.pronunciation as f64;
+                let tone_index = phoneme.tone_index.map(|t| t as i64);
+                let span_start = phoneme.span.as_ref().map(|s| s.start as i64);
+                let span_end = phoneme.span.as_ref().map(|s| s.end as i64);
+
+                sqlx::query!(
+                    r#"
+                    INSERT INTO speech_phoneme_scores (word_score_id, position, phoneme, phone, pronunciation,
+                                                       tone_index, span_start, span_end)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                    "#,
+                    word_score_id,
+                    position,
+                    phoneme.phoneme,
+                    phoneme.phone,
+                    pronunciation,
+                    tone_index,
+                    span_start,
+                    span_end
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(attempt)
+    }
+
+    /// A page of the user's attempts, newest first, plus the total count.
+    pub async fn list_attempts(
+        &self,
+        user_id: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<SpeechAttempt>, i64)> {
+        let attempts = sqlx::query_as!(
+            SpeechAttempt,
+            r#"
+            SELECT id as "id!", user_id, created_at, provider, ref_text, ref_pinyin, lang, core,
+                   duration_ms, overall_score, pronunciation_score, fluency_score, tone_score
+            FROM speech_attempts
+            WHERE user_id = ?1
+            ORDER BY created_at DESC
+            LIMIT ?2 OFFSET ?3
+            "#,
+            user_id,
+            limit,
+            offset
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let total = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM speech_attempts WHERE user_id = ?1",
+            user_id
+        )
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok((attempts, total as i64))
+    }
+
+    /// One of the user's attempts with its breakdown; `None` if it is not theirs.
+    pub async fn get_attempt(
+        &self,
+        user_id: &str,
+        attempt_id: &str,
+    ) -> Result<Option<SpeechAttemptDetail>> {
+        let attempt = sqlx::query_as!(
+            SpeechAttempt,
+            r#"
+            SELECT id as "id!", user_id, created_at, provider, ref_text, ref_pinyin, lang, core,
+                   duration_ms, overall_score, pronunciation_score, fluency_score, tone_score
+            FROM speech_attempts
+            WHERE id = ?1 AND user_id = ?2
+            "#,
+            attempt_id,
+            user_id
+        )
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        let Some(attempt) = attempt else {
+            return Ok(None);
+        };
+
+        let word_rows = sqlx::query_as!(
+            WordScoreRow,
+            r#"
+            SELECT id as "id!", word, pinyin, tone, overall, pronunciation, tone_score, prominence,
+                   read_type, span_start, span_end
+            FROM speech_word_scores
+            WHERE attempt_id = ?1
+            ORDER BY position
+            "#,
+            attempt.id
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let phoneme_rows = sqlx::query_as!(
+            PhonemeScoreRow,
+            r#"
+            SELECT p.word_score_id, p.phoneme, p.phone, p.pronunciation, p.tone_index, p.span_start, p.span_end
+            FROM speech_phoneme_scores p
+            JOIN speech_word_scores w ON w.id = p.word_score_id
+            WHERE w.attempt_id = ?1
+            ORDER BY p.word_score_id, p.position
+            "#,
+            attempt.id
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut phonemes: HashMap<i64, Vec<PhonemeScore>> = HashMap::new();
+        for row in phoneme_rows {
+            phonemes
+                .entry(row.word_score_id)
+                .or_default()
+                .push(PhonemeScore {
+                    phoneme: row.phoneme,
+                    pronunciation: row.pronunciation as f32,
+                    span: time_span(row.span_start, row.span_end),
+                    tone_index: row.tone_index.map(|t| t as u8),
+                    phone: row.phone,
+                });
+        }
+
+        let words = word_rows
+            .into_iter()
+            .map(|row| WordScore {
+                word: row.word,
+                pinyin: row.pinyin,
+                tone: row.tone,
+                scores: WordScores {
+                    overall: row.overall as f32,
+                    pronunciation: row.pronunciation as f32,
+                    tone: row.tone_score.map(|s| s as f32),
+                    prominence: row.prominence.map(|s| s as f32),
+                },
+                read_type: row.read_type as u8,
+                span: time_span(row.span_start, row.span_end),
+                phonemes: phonemes.remove(&row.id),
+            })
+            .collect();
+
+        let overall_scores = [
+            ("overall", attempt.overall_score),
+            ("pronunciation", attempt.pronunciation_score),
+            ("fluency", attempt.fluency_score),
+            ("tone", attempt.tone_score),
+        ]
+        .into_iter()
+        .filter_map(|(key, score)| score.map(|s| (key.to_string(), s as f32)))
+        .collect();
+
+        Ok(Some(SpeechAttemptDetail {
+            attempt,
+            overall_scores,
+            words,
+        }))
+    }
+
+    /// Every scored reading of `word` by the user, oldest first.
+    pub async fn word_trend(&self, user_id: &str, word: &str) -> Result<Vec<WordTrendPoint>> {
+        let points = sqlx::query_as!(
+            WordTrendPoint,
+            r#"
+            SELECT a.id as "attempt_id!", a.created_at, w.pinyin, w.overall, w.pronunciation, w.tone_score
+            FROM speech_word_scores w
+            JOIN speech_attempts a ON a.id = w.attempt_id
+            WHERE a.user_id = ?1 AND w.word = ?2 AND w.read_type != ?3
+            ORDER BY a.created_at, w.position
+            "#,
+            user_id,
+            word,
+            READ_TYPE_INSERTION
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(points)
+    }
+
+    /// Per-word progress across all of the user's attempts, most recently practised first.
+    pub async fn word_trends(&self, user_id: &str) -> Result<Vec<WordTrendSummary>> {
+        let rows = sqlx::query_as!(
+            WordTrendRow,
+            r#"
+            SELECT w.word, a.created_at, w.pinyin, w.overall
+            FROM speech_word_scores w
+            JOIN speech_attempts a ON a.id = w.attempt_id
+            WHERE a.user_id = ?1 AND w.read_type != ?2
+            ORDER BY a.created_at, w.position
+            "#,
+            user_id,
+            READ_TYPE_INSERTION
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut summaries: Vec<WordTrendSummary> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for row in rows {
+            match index.get(&row.word) {
+                Some(&i) => {
+                    let summary = &mut summaries[i];
+                    summary.attempts += 1;
+                    summary.average_score += row.overall;
+                    summary.best_score = summary.best_score.max(row.overall);
+                    summary.latest_score = row.overall;
+                    summary.last_practiced_at = row.created_at;
+                    if row.pinyin.is_some() {
+                        summary.pinyin = row.pinyin;
+                    }
+                }
+                None => {
+                    index.insert(row.word.clone(), summaries.len());
+                    summaries.push(WordTrendSummary {
+                        word: row.word,
+                        pinyin: row.pinyin,
+                        attempts: 1,
+                        average_score: row.overall,
+                        best_score: row.overall,
+                        first_score: row.overall,
+                        latest_score: row.overall,
+                        change: 0.0,
+                        last_practiced_at: row.created_at,
+                    });
+                }
+            }
+        }
+
+        for summary in &mut summaries {
+            // `average_score` accumulated the sum above
+            summary.average_score = round1(summary.average_score / summary.attempts as f64);
+            summary.change = round1(summary.latest_score - summary.first_score);
+        }
+        summaries.sort_by_key(|s| Reverse(s.last_practiced_at));
+
+        Ok(summaries)
+    }
+}
+
+fn time_span(start: Option<i64>, end: Option<i64>) -> Option<TimeSpan> {
+    Some(TimeSpan {
+        start: start? as u32,
+        end: end? as u32,
+    })
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt,
+    time::Duration,
+};
 
 use anyhow::{
     Context,
@@ -14,6 +18,7 @@ use chrono::{
     Utc,
 };
 use futures_util::{
+    future::BoxFuture,
     SinkExt,
     StreamExt,
 };
@@ -27,9 +32,24 @@ use serde::{
 };
 use serde_json::json;
 use sha2::Sha256;
+use tokio::{
+    net::TcpStream,
+    sync::mpsc,
+    time::{
+        sleep,
+        sleep_until,
+        timeout,
+        Instant,
+    },
+};
 use tokio_tungstenite::{
     connect_async,
-    tungstenite::Message,
+    tungstenite::{
+        self,
+        Message,
+    },
+    MaybeTlsStream,
+    WebSocketStream,
 };
 use tracing::{
     debug,
@@ -38,7 +58,27 @@ use tracing::{
 };
 use url::Url;
 
+use super::{
+    resilience::{
+        ProviderTimeouts,
+        RetryPolicy,
+    },
+    ProviderHealth,
+    SpeechError,
+    SpeechEvaluator,
+    SpeechStream,
+    SpeechStreamEvent,
+    STREAM_CHANNEL_CAPACITY,
+};
+use crate::pinyin::parse;
+
 type HmacSha256 = Hmac<Sha256>;
+type IFlytekSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Delay between audio frames recommended by iFlytek; also the PCM duration of one frame.
+const FRAME_INTERVAL: Duration = Duration::from_millis(40);
+/// Per-frame payload for encodings whose byte rate we cannot derive (`lame`, `speex`).
+const COMPRESSED_FRAME_BYTES: usize = 1280;
 
 #[derive(Debug, Clone)]
 pub struct IFlytekConfig {
@@ -56,7 +96,7 @@ pub struct SpeechEvaluationRequest {
     pub core: String, // "word", "sent", "para"
     pub ref_pinyin: Option<String>,
     pub phoneme_output: bool,
-    pub audio_encoding: String, // "lame", "speex", "speex-wb"
+    pub audio_encoding: String, // "raw", "lame", "speex", "speex-wb"
     pub sample_rate: u32,
     pub channels: u8,
     pub bit_depth: u8,
@@ -73,6 +113,21 @@ pub struct WordScore {
     pub phonemes: Option<Vec<PhonemeScore>>,
 }
 
+/// Parse a word's `tone` label (`tone3`, or `tone2_tone4` for two syllables).
+pub fn parse_tone_label(label: &str) -> Vec<Option<u8>> {
+    label
+        .split('_')
+        .map(|tone| {
+            tone.trim()
+                .strip_prefix("tone")
+                .and_then(|t| t.parse::<u8>().ok())
+                .filter(|t| (0..=5).contains(t))
+                // Providers use 0 for the neutral tone
+                .map(|t| if t == 0 { 5 } else { t })
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WordScores {
     pub overall: f32,
@@ -100,17 +155,32 @@ pub struct TimeSpan {
 pub struct SpeechEvaluationResponse {
     pub overall_scores: HashMap<String, f32>,
     pub words: Vec<WordScore>,
-    pub error: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct IFlytekService {
     config: IFlytekConfig,
+    timeouts: ProviderTimeouts,
+    retry: RetryPolicy,
 }
 
 impl IFlytekService {
     pub fn new(config: IFlytekConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            timeouts: ProviderTimeouts::default(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_timeouts(mut self, timeouts: ProviderTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
     }
 
     pub fn config(&self) -> &IFlytekConfig {
@@ -120,42 +190,128 @@ impl IFlytekService {
     pub async fn evaluate_speech(
         &self,
         request: SpeechEvaluationRequest,
-    ) -> Result<SpeechEvaluationResponse> {
+    ) -> Result<SpeechEvaluationResponse, SpeechError> {
         info!("Starting speech evaluation for text: {}", request.ref_text);
 
         // Generate authentication parameters
         let auth_url = self.generate_auth_url()?;
+        // Built first so a malformed request fails without opening a connection
+        let start_frame = self.create_start_frame(&request)?;
         debug!("Connecting to iFlytek WebSocket: {}", auth_url);
 
-        // Connect to WebSocket
-        let (ws_stream, _) = connect_async(&auth_url)
-            .await
-            .context("Failed to connect to iFlytek WebSocket")?;
+        let ws_stream = self.connect(&auth_url).await?;
 
         let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
+        // Receive concurrently with sending so that an early provider error (bad
+        // parameters, invalid app id) is reported instead of a broken-pipe send failure
+        let sending = self.send_audio(&mut ws_sender, start_frame, &request);
+        let receiving = Self::receive_results(&mut ws_receiver);
+        tokio::pin!(sending, receiving);
+
+        let (response_data, received_final) = tokio::select! {
+            received = &mut receiving => received,
+            sent = &mut sending => {
+                // Once the audio is out, the provider has the read timeout to answer
+                let received = timeout(self.timeouts.read, receiving)
+                    .await
+                    .map_err(|_| self.read_timed_out())?;
+                if let Err(e) = sent {
+                    if !received.1 {
+                        return Err(e);
+                    }
+                }
+                received
+            }
+        };
+
+        if !received_final {
+            return Err(closed_early());
+        }
+
+        // Parse the accumulated response data
+        self.parse_response(response_data).await
+    }
+
+    /// Connect, retrying transient failures with jittered backoff.
+    async fn connect(&self, auth_url: &str) -> Result<IFlytekSocket, SpeechError> {
+        let mut retry = 0;
+        loop {
+            let error = match timeout(self.timeouts.connect, open_socket(auth_url)).await {
+                Ok(Ok(ws_stream)) => return Ok(ws_stream),
+                // A rejected signature will not be accepted on a second try
+                Ok(Err(e @ SpeechError::Auth(_))) => return Err(e),
+                Ok(Err(e)) => e,
+                Err(_) => SpeechError::Timeout(format!(
+                    "no connection to iFlytek within {:?}",
+                    self.timeouts.connect
+                )),
+            };
+            if retry >= self.retry.max_retries {
+                return Err(error);
+            }
+
+            let delay = self.retry.backoff(retry);
+            warn!("{}; retrying in {:?}", error, delay);
+            sleep(delay).await;
+            retry += 1;
+        }
+    }
+
+    fn read_timed_out(&self) -> SpeechError {
+        SpeechError::Timeout(format!(
+            "no result from iFlytek within {:?} of the end of the audio",
+            self.timeouts.read
+        ))
+    }
+
+    async fn send_audio<S>(
+        &self,
+        ws_sender: &mut S,
+        start_frame: String,
+        request: &SpeechEvaluationRequest,
+    ) -> Result<(), SpeechError>
+    where
+        S: SinkExt<Message> + Unpin,
+        S::Error: fmt::Display,
+    {
         // Send start frame
-        let start_frame = self.create_start_frame(&request)?;
         ws_sender
             .send(Message::Text(start_frame))
             .await
-            .context("Failed to send start frame")?;
-
-        // Send audio data frame
-        let audio_frame = self.create_audio_frame(&request)?;
-        ws_sender
-            .send(Message::Text(audio_frame))
-            .await
-            .context("Failed to send audio frame")?;
+            .map_err(|e| send_failed("start frame", e))?;
+
+        // Stream the audio in protocol-sized frames, paced like a live microphone
+        let frame_size = Self::frame_size(request);
+        let mut seq = 0;
+        for (index, chunk) in request.audio_data.chunks(frame_size).enumerate() {
+            if index > 0 {
+                sleep(FRAME_INTERVAL).await;
+            }
+            seq += 1;
+            let audio_frame = self.create_audio_frame(request, chunk, seq)?;
+            ws_sender
+                .send(Message::Text(audio_frame))
+                .await
+                .map_err(|e| send_failed(format!("audio frame {}", seq), e))?;
+        }
+        debug!("Sent {} audio frames of up to {} bytes", seq, frame_size);
 
         // Send end frame
-        let end_frame = self.create_end_frame(&request)?;
+        let end_frame = self.create_end_frame(request, seq + 1)?;
         ws_sender
             .send(Message::Text(end_frame))
             .await
-            .context("Failed to send end frame")?;
+            .map_err(|e| send_failed("end frame", e))?;
+
+        Ok(())
+    }
 
-        // Receive and process response
+    /// Collect response frames until the final one; the flag reports whether it arrived.
+    async fn receive_results<S>(ws_receiver: &mut S) -> (Vec<String>, bool)
+    where
+        S: StreamExt<Item = tungstenite::Result<Message>> + Unpin,
+    {
         let mut response_data = Vec::new();
 
         while let Some(msg_result) = ws_receiver.next().await {
@@ -163,26 +319,11 @@ impl IFlytekService {
                 Ok(Message::Text(text)) => {
                     debug!("Received response: {}", text);
 
-                    // Check if this is the final message
-                    let is_final =
-                        if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(&text) {
-                            if let Some(header) = json_value.get("header") {
-                                if let Some(status) = header.get("status") {
-                                    status == 2 // Final frame
-                                } else {
-                                    false
-                                }
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
-                        };
-
+                    let is_final = is_final_frame(&text);
                     response_data.push(text);
 
                     if is_final {
-                        break;
+                        return (response_data, true);
                     }
                 }
                 Ok(Message::Close(_)) => {
@@ -197,8 +338,131 @@ impl IFlytekService {
             }
         }
 
-        // Parse the accumulated response data
-        self.parse_response(response_data).await
+        (response_data, false)
+    }
+
+    /// Open a live session: audio written to the returned stream is relayed to
+    /// iFlytek as it arrives, and each result frame is pushed back as an event.
+    pub async fn open_stream(
+        &self,
+        request: SpeechEvaluationRequest,
+    ) -> Result<SpeechStream, SpeechError> {
+        info!(
+            "Opening streaming evaluation for text: {}",
+            request.ref_text
+        );
+
+        let auth_url = self.generate_auth_url()?;
+        let start_frame = self.create_start_frame(&request)?;
+        let ws_stream = self.connect(&auth_url).await?;
+        let (mut ws_sender, ws_receiver) = ws_stream.split();
+
+        ws_sender
+            .send(Message::Text(start_frame))
+            .await
+            .map_err(|e| send_failed("start frame", e))?;
+
+        let (audio_tx, audio_rx) = mpsc::channel::<Bytes>(STREAM_CHANNEL_CAPACITY);
+        let (event_tx, event_rx) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            let event = match service
+                .relay_stream(request, ws_sender, ws_receiver, audio_rx, &event_tx)
+                .await
+            {
+                Ok(response) => SpeechStreamEvent::Final(response),
+                Err(e) => {
+                    warn!("Streaming evaluation failed: {}", e);
+                    SpeechStreamEvent::Failed(e)
+                }
+            };
+            let _ = event_tx.send(event).await;
+        });
+
+        Ok(SpeechStream {
+            audio: audio_tx,
+            events: event_rx,
+        })
+    }
+
+    /// Pump caller audio to iFlytek and result frames back until the final frame.
+    async fn relay_stream<S, R>(
+        &self,
+        request: SpeechEvaluationRequest,
+        mut ws_sender: S,
+        mut ws_receiver: R,
+        mut audio_rx: mpsc::Receiver<Bytes>,
+        event_tx: &mpsc::Sender<SpeechStreamEvent>,
+    ) -> Result<SpeechEvaluationResponse, SpeechError>
+    where
+        S: SinkExt<Message> + Unpin,
+        S::Error: fmt::Display,
+        R: StreamExt<Item = tungstenite::Result<Message>> + Unpin,
+    {
+        let frame_size = Self::frame_size(&request);
+        let mut pending = request.audio_data.to_vec();
+        let mut seq = 0;
+        let mut audio_open = true;
+        // When the provider's answer is due, once the caller has finished sending audio
+        let mut read_deadline: Option<Instant> = None;
+        let mut response_data = Vec::new();
+
+        loop {
+            tokio::select! {
+                chunk = audio_rx.recv(), if audio_open => {
+                    match chunk {
+                        Some(chunk) => pending.extend_from_slice(&chunk),
+                        None => audio_open = false,
+                    }
+
+                    // Hold back a partial frame until more audio arrives or the caller finishes
+                    while pending.len() >= frame_size || (!audio_open && !pending.is_empty()) {
+                        let len = pending.len().min(frame_size);
+                        let chunk: Vec<u8> = pending.drain(..len).collect();
+                        seq += 1;
+                        let audio_frame = self.create_audio_frame(&request, &chunk, seq)?;
+                        ws_sender
+                            .send(Message::Text(audio_frame))
+                            .await
+                            .map_err(|e| send_failed(format!("audio frame {}", seq), e))?;
+                    }
+
+                    if !audio_open {
+                        debug!("Caller finished streaming after {} audio frames", seq);
+                        read_deadline = Some(Instant::now() + self.timeouts.read);
+                        let end_frame = self.create_end_frame(&request, seq + 1)?;
+                        ws_sender
+                            .send(Message::Text(end_frame))
+                            .await
+                            .map_err(|e| send_failed("end frame", e))?;
+                    }
+                }
+                message = ws_receiver.next() => match message {
+                    Some(Ok(Message::Text(text))) => {
+                        debug!("Received streaming response: {}", text);
+                        if is_final_frame(&text) {
+                            response_data.push(text);
+                            return self.parse_response(response_data).await;
+                        }
+
+                        let partial = self.parse_response(vec![text.clone()]).await?;
+                        if !partial.words.is_empty() {
+                            let _ = event_tx.send(SpeechStreamEvent::Partial(partial)).await;
+                        }
+                        response_data.push(text);
+                    }
+                    Some(Ok(Message::Close(_))) | None => return Err(closed_early()),
+                    Some(Err(e)) => {
+                        return Err(SpeechError::Unavailable(format!("iFlytek WebSocket error: {}", e)));
+                    }
+                    Some(Ok(_)) => {}
+                },
+                _ = sleep_until(read_deadline.unwrap_or_else(Instant::now)), if read_deadline.is_some() => {
+                    return Err(self.read_timed_out());
+                }
+            }
+        }
     }
 
     fn generate_auth_url(&self) -> Result<String> {
@@ -238,7 +502,7 @@ impl IFlytekService {
         Ok(url.to_string())
     }
 
-    fn create_start_frame(&self, request: &SpeechEvaluationRequest) -> Result<String> {
+    fn create_start_frame(&self, request: &SpeechEvaluationRequest) -> Result<String, SpeechError> {
         let mut parameter = json!({
             "st": {
                 "lang": request.lang,
@@ -253,8 +517,10 @@ impl IFlytekService {
             }
         });
 
-        // Add optional refPinyin if provided
+        // Add optional refPinyin if provided, in the numbered form iFlytek reads
         if let Some(ref_pinyin) = &request.ref_pinyin {
+            let ref_pinyin = parse::normalize_reference(ref_pinyin)
+                .map_err(|e| SpeechError::InvalidRequest(format!("Invalid ref_pinyin: {}", e)))?;
             parameter["st"]["refPinyin"] = json!(ref_pinyin);
         }
 
@@ -282,11 +548,31 @@ impl IFlytekService {
             }
         });
 
-        Ok(serde_json::to_string(&frame)?)
+        serde_json::to_string(&frame).map_err(|e| SpeechError::Internal(e.into()))
+    }
+
+    /// Bytes of audio per frame: 40ms of PCM for `raw`, a fixed budget for compressed encodings.
+    fn frame_size(request: &SpeechEvaluationRequest) -> usize {
+        if request.audio_encoding == "raw" {
+            let bytes_per_second = request.sample_rate as usize
+                * request.channels as usize
+                * request.bit_depth as usize
+                / 8;
+            let size = bytes_per_second * FRAME_INTERVAL.as_millis() as usize / 1000;
+            if size > 0 {
+                return size;
+            }
+        }
+        COMPRESSED_FRAME_BYTES
     }
 
-    fn create_audio_frame(&self, request: &SpeechEvaluationRequest) -> Result<String> {
-        let audio_b64 = STANDARD.encode(&request.audio_data);
+    fn create_audio_frame(
+        &self,
+        request: &SpeechEvaluationRequest,
+        chunk: &[u8],
+        seq: u32,
+    ) -> Result<String> {
+        let audio_b64 = STANDARD.encode(chunk);
 
         let frame = json!({
             "header": {
@@ -300,7 +586,7 @@ impl IFlytekService {
                     "channels": request.channels,
                     "bit_depth": request.bit_depth,
                     "status": 1,
-                    "seq": 1,
+                    "seq": seq,
                     "audio": audio_b64
                 }
             }
@@ -309,7 +595,7 @@ impl IFlytekService {
         Ok(serde_json::to_string(&frame)?)
     }
 
-    fn create_end_frame(&self, request: &SpeechEvaluationRequest) -> Result<String> {
+    fn create_end_frame(&self, request: &SpeechEvaluationRequest, seq: u32) -> Result<String> {
         let frame = json!({
             "header": {
                 "app_id": self.config.app_id,
@@ -322,7 +608,7 @@ impl IFlytekService {
                     "channels": request.channels,
                     "bit_depth": request.bit_depth,
                     "status": 2,
-                    "seq": 2,
+                    "seq": seq,
                     "audio": ""
                 }
             }
@@ -331,93 +617,68 @@ impl IFlytekService {
         Ok(serde_json::to_string(&frame)?)
     }
 
-    async fn parse_response(&self, response_data: Vec<String>) -> Result<SpeechEvaluationResponse> {
+    async fn parse_response(
+        &self,
+        response_data: Vec<String>,
+    ) -> Result<SpeechEvaluationResponse, SpeechError> {
         let mut overall_scores = HashMap::new();
         let mut words = Vec::new();
-        let mut error_msg = None;
+        let mut errors: Vec<(i64, String)> = Vec::new();
 
         for response_text in response_data {
             let json_value: serde_json::Value =
-                serde_json::from_str(&response_text).context("Failed to parse response JSON")?;
-
-            // Check for errors
-            if let Some(header) = json_value.get("header") {
-                if let Some(code) = header.get("code") {
-                    if code.as_i64() != Some(0) {
-                        if let Some(message) = header.get("message") {
-                            error_msg =
-                                Some(message.as_str().unwrap_or("Unknown error").to_string());
-                            continue;
-                        }
-                    }
+                serde_json::from_str(&response_text).map_err(|e| {
+                    SpeechError::MalformedResponse(format!("invalid frame JSON: {}", e))
+                })?;
+
+            // Check for errors, keeping every frame's message rather than the last
+            if let Some(code) = json_value.pointer("/header/code") {
+                if code.as_i64() != Some(0) {
+                    let message = json_value
+                        .pointer("/header/message")
+                        .and_then(|m| m.as_str())
+                        .unwrap_or("Unknown error");
+                    errors.push((code.as_i64().unwrap_or(-1), message.to_string()));
+                    continue;
                 }
             }
 
-            // Parse payload data
-            if let Some(payload) = json_value.get("payload") {
-                if let Some(result_data) = payload.get("result") {
-                    if let Some(data) = result_data.get("data") {
-                        if let Some(data_str) = data.as_str() {
-                            // Decode base64 data
-                            if let Ok(decoded_bytes) = STANDARD.decode(data_str) {
-                                if let Ok(decoded_str) = String::from_utf8(decoded_bytes) {
-                                    debug!("Decoded result data: {}", decoded_str);
-
-                                    // Parse the result JSON
-                                    if let Ok(result_json) =
-                                        serde_json::from_str::<serde_json::Value>(&decoded_str)
-                                    {
-                                        // Extract overall scores
-                                        if let Some(scores) = result_json.get("overall") {
-                                            overall_scores.insert(
-                                                "overall".to_string(),
-                                                scores.as_f64().unwrap_or(0.0) as f32,
-                                            );
-                                        }
-                                        if let Some(scores) = result_json.get("pronunciation") {
-                                            overall_scores.insert(
-                                                "pronunciation".to_string(),
-                                                scores.as_f64().unwrap_or(0.0) as f32,
-                                            );
-                                        }
-                                        if let Some(scores) = result_json.get("fluency") {
-                                            overall_scores.insert(
-                                                "fluency".to_string(),
-                                                scores.as_f64().unwrap_or(0.0) as f32,
-                                            );
-                                        }
-                                        if let Some(scores) = result_json.get("tone") {
-                                            overall_scores.insert(
-                                                "tone".to_string(),
-                                                scores.as_f64().unwrap_or(0.0) as f32,
-                                            );
-                                        }
-
-                                        // Extract word-level scores
-                                        if let Some(words_array) = result_json.get("words") {
-                                            if let Some(words_vec) = words_array.as_array() {
-                                                for word_data in words_vec {
-                                                    if let Ok(word_score) =
-                                                        self.parse_word_score(word_data)
-                                                    {
-                                                        words.push(word_score);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+            let Some(data) = json_value.pointer("/payload/result/data") else {
+                continue;
+            };
+            let result_json = decode_result(data)?;
+
+            // Extract overall scores
+            for key in ["overall", "pronunciation", "fluency", "tone"] {
+                if let Some(score) = result_json.get(key) {
+                    overall_scores.insert(key.to_string(), score.as_f64().unwrap_or(0.0) as f32);
+                }
+            }
+
+            // Extract word-level scores
+            if let Some(words_vec) = result_json.get("words").and_then(|w| w.as_array()) {
+                for word_data in words_vec {
+                    if let Ok(word_score) = self.parse_word_score(word_data) {
+                        words.push(word_score);
                     }
                 }
             }
         }
 
+        // The first code decides the kind of failure; the message keeps them all
+        if let Some((code, _)) = errors.first() {
+            let mut messages: Vec<&str> = Vec::new();
+            for (_, message) in &errors {
+                if !messages.contains(&message.as_str()) {
+                    messages.push(message);
+                }
+            }
+            return Err(SpeechError::from_provider_code(*code, messages.join("; ")));
+        }
+
         Ok(SpeechEvaluationResponse {
             overall_scores,
             words,
-            error: error_msg,
         })
     }
 
@@ -473,14 +734,10 @@ impl IFlytekService {
         };
 
         // Parse time span
-        let span = if let Some(span_obj) = word_data.get("span") {
-            Some(TimeSpan {
-                start: span_obj.get("start").and_then(|s| s.as_u64()).unwrap_or(0) as u32,
-                end: span_obj.get("end").and_then(|e| e.as_u64()).unwrap_or(0) as u32,
-            })
-        } else {
-            None
-        };
+        let span = word_data.get("span").map(|span_obj| TimeSpan {
+            start: span_obj.get("start").and_then(|s| s.as_u64()).unwrap_or(0) as u32,
+            end: span_obj.get("end").and_then(|e| e.as_u64()).unwrap_or(0) as u32,
+        });
 
         // Parse phonemes
         let phonemes = if let Some(phonemes_array) = word_data.get("phonemes") {
@@ -526,14 +783,10 @@ impl IFlytekService {
             .and_then(|p| p.as_f64())
             .unwrap_or(0.0) as f32;
 
-        let span = if let Some(span_obj) = phoneme_data.get("span") {
-            Some(TimeSpan {
-                start: span_obj.get("start").and_then(|s| s.as_u64()).unwrap_or(0) as u32,
-                end: span_obj.get("end").and_then(|e| e.as_u64()).unwrap_or(0) as u32,
-            })
-        } else {
-            None
-        };
+        let span = phoneme_data.get("span").map(|span_obj| TimeSpan {
+            start: span_obj.get("start").and_then(|s| s.as_u64()).unwrap_or(0) as u32,
+            end: span_obj.get("end").and_then(|e| e.as_u64()).unwrap_or(0) as u32,
+        });
 
         let tone_index = phoneme_data
             .get("tone_index")
@@ -555,6 +808,97 @@ impl IFlytekService {
     }
 }
 
+/// Open the provider socket, telling a rejected signature apart from an unreachable host.
+async fn open_socket(auth_url: &str) -> Result<IFlytekSocket, SpeechError> {
+    match connect_async(auth_url).await {
+        Ok((ws_stream, _)) => Ok(ws_stream),
+        Err(tungstenite::Error::Http(response))
+            if matches!(response.status().as_u16(), 401 | 403) =>
+        {
+            Err(SpeechError::Auth(format!(
+                "handshake rejected with {}",
+                response.status()
+            )))
+        }
+        Err(e) => Err(SpeechError::Unavailable(format!(
+            "failed to connect to iFlytek: {}",
+            e
+        ))),
+    }
+}
+
+fn send_failed(what: impl fmt::Display, error: impl fmt::Display) -> SpeechError {
+    SpeechError::Unavailable(format!("failed to send {} to iFlytek: {}", what, error))
+}
+
+fn closed_early() -> SpeechError {
+    SpeechError::Unavailable(
+        "iFlytek connection closed before the final result was received".to_string(),
+    )
+}
+
+/// Decode a frame's `payload.result.data`: base64-encoded UTF-8 JSON.
+fn decode_result(data: &serde_json::Value) -> Result<serde_json::Value, SpeechError> {
+    let malformed = |what: &str, e: &dyn fmt::Display| {
+        SpeechError::MalformedResponse(format!("result data {}: {}", what, e))
+    };
+
+    let data = data
+        .as_str()
+        .ok_or_else(|| malformed("is not a string", &data))?;
+    let bytes = STANDARD
+        .decode(data)
+        .map_err(|e| malformed("is not base64", &e))?;
+    let text = String::from_utf8(bytes).map_err(|e| malformed("is not UTF-8", &e))?;
+    debug!("Decoded result data: {}", text);
+
+    serde_json::from_str(&text).map_err(|e| malformed("is not JSON", &e))
+}
+
+/// The final frame has status 2; a non-zero error code also ends the session.
+fn is_final_frame(text: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(text)
+        .ok()
+        .and_then(|json_value| {
+            let header = json_value.get("header")?;
+            let failed = header
+                .get("code")
+                .and_then(|c| c.as_i64())
+                .is_some_and(|c| c != 0);
+            Some(failed || header.get("status")? == 2)
+        })
+        .unwrap_or(false)
+}
+
+impl SpeechEvaluator for IFlytekService {
+    fn name(&self) -> &'static str {
+        "iflytek"
+    }
+
+    fn evaluate(
+        &self,
+        request: SpeechEvaluationRequest,
+    ) -> BoxFuture<'_, Result<SpeechEvaluationResponse, SpeechError>> {
+        Box::pin(self.evaluate_speech(request))
+    }
+
+    fn open_stream(
+        &self,
+        request: SpeechEvaluationRequest,
+    ) -> BoxFuture<'_, Result<SpeechStream, SpeechError>> {
+        Box::pin(IFlytekService::open_stream(self, request))
+    }
+
+    fn health(&self) -> ProviderHealth {
+        ProviderHealth {
+            credentials_configured: !self.config.app_id.is_empty()
+                && !self.config.api_key.is_empty()
+                && !self.config.api_secret.is_empty(),
+            ..ProviderHealth::new(self.name())
+        }
+    }
+}
+
 impl Default for IFlytekConfig {
     fn default() -> Self {
         Self {
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use tokio::sync::mpsc;
+
+use super::{
+    iflytek::{
+        PhonemeScore,
+        TimeSpan,
+        WordScore,
+        WordScores,
+    },
+    SpeechError,
+    SpeechEvaluationRequest,
+    SpeechEvaluationResponse,
+    SpeechEvaluator,
+    SpeechStream,
+    SpeechStreamEvent,
+    STREAM_CHANNEL_CAPACITY,
+};
+use crate::pinyin::parse::{
+    self,
+    PinyinSyllable,
+};
+
+/// Leading silence before the first word, in 10ms units.
+const LEADING_SILENCE: u32 = 20;
+/// Duration assigned to every mock word, in 10ms units.
+const WORD_DURATION: u32 = 36;
+
+/// Offline evaluator that fabricates plausible scores from the reference text.
+///
+/// The same `ref_text`/`ref_pinyin` always yields the same scores, which keeps
+/// tests and local development reproducible without iFlytek credentials.
+#[derive(Clone, Default)]
+pub struct MockSpeechEvaluator;
+
+impl MockSpeechEvaluator {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn evaluate_speech(
+        &self,
+        request: &SpeechEvaluationRequest,
+    ) -> Result<SpeechEvaluationResponse, SpeechError> {
+        let is_chinese = request.lang != "en";
+        let units = if is_chinese {
+            request
+                .ref_text
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+        } else {
+            request
+                .ref_text
+                .split_whitespace()
+                .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_string())
+                .filter(|w| !w.is_empty())
+                .collect::<Vec<_>>()
+        };
+
+        if units.is_empty() {
+            return Err(SpeechError::InvalidRequest(
+                "Reference text contains no words to evaluate".to_string(),
+            ));
+        }
+
+        // Only trust the supplied pinyin when it lines up one syllable per character
+        let syllables: Option<Vec<PinyinSyllable>> = request
+            .ref_pinyin
+            .as_deref()
+            .and_then(|p| parse::parse(p).ok())
+            .filter(|s| is_chinese && s.len() == units.len());
+
+        let words: Vec<WordScore> = units
+            .iter()
+            .enumerate()
+            .map(|(index, unit)| {
+                let syllable = syllables.as_ref().map(|s| &s[index]);
+                self.score_word(index, unit, syllable, is_chinese, request.phoneme_output)
+            })
+            .collect();
+
+        let count = words.len() as f32;
+        let mut overall_scores = HashMap::new();
+        overall_scores.insert(
+            "overall".to_string(),
+            round1(words.iter().map(|w| w.scores.overall).sum::<f32>() / count),
+        );
+        overall_scores.insert(
+            "pronunciation".to_string(),
+            round1(words.iter().map(|w| w.scores.pronunciation).sum::<f32>() / count),
+        );
+        overall_scores.insert(
+            "fluency".to_string(),
+            score_from_seed(&format!("fluency:{}", request.ref_text)),
+        );
+        if is_chinese {
+            let tones: Vec<f32> = words.iter().filter_map(|w| w.scores.tone).collect();
+            if !tones.is_empty() {
+                overall_scores.insert(
+                    "tone".to_string(),
+                    round1(tones.iter().sum::<f32>() / tones.len() as f32),
+                );
+            }
+        }
+
+        Ok(SpeechEvaluationResponse {
+            overall_scores,
+            words,
+        })
+    }
+
+    fn score_word(
+        &self,
+        index: usize,
+        unit: &str,
+        syllable: Option<&PinyinSyllable>,
+        is_chinese: bool,
+        phoneme_output: bool,
+    ) -> WordScore {
+        let seed = format!("{}:{}", index, unit);
+        let pronunciation = score_from_seed(&format!("pron:{}", seed));
+        let tone_score = is_chinese.then(|| score_from_seed(&format!("tone:{}", seed)));
+        let overall = match tone_score {
+            Some(tone) => round1((pronunciation * 2.0 + tone) / 3.0),
+            None => pronunciation,
+        };
+
+        let start = LEADING_SILENCE + index as u32 * WORD_DURATION;
+        let span = TimeSpan {
+            start,
+            end: start + WORD_DURATION,
+        };
+
+        let tone_number = syllable.map(|s| s.tone.unwrap_or(5));
+        let phonemes = syllable
+            .filter(|_| phoneme_output)
+            .map(|s| self.score_phonemes(&seed, s, &span));
+
+        WordScore {
+            word: unit.to_string(),
+            pinyin: syllable.map(PinyinSyllable::numbered),
+            tone: tone_number.map(|t| format!("tone{}", t)),
+            scores: WordScores {
+                overall,
+                pronunciation,
+                tone: tone_score,
+                prominence: None,
+            },
+            read_type: 0,
+            span: Some(span),
+            phonemes,
+        }
+    }
+
+    fn score_phonemes(
+        &self,
+        seed: &str,
+        syllable: &PinyinSyllable,
+        span: &TimeSpan,
+    ) -> Vec<PhonemeScore> {
+        let tone = Some(syllable.tone.unwrap_or(5));
+        let (initial, final_) = syllable.initial_and_final();
+        let parts: Vec<(&str, Option<u8>)> = match initial {
+            Some(initial) => vec![(initial, None), (final_, tone)],
+            None => vec![(final_, tone)],
+        };
+
+        let step = (span.end - span.start) / parts.len() as u32;
+        parts
+            .into_iter()
+            .enumerate()
+            .map(|(i, (phoneme, tone_index))| {
+                let start = span.start + i as u32 * step;
+                PhonemeScore {
+                    phoneme: phoneme.to_string(),
+                    pronunciation: score_from_seed(&format!("phone:{}:{}", seed, phoneme)),
+                    span: Some(TimeSpan {
+                        start,
+                        end: start + step,
+                    }),
+                    tone_index,
+                    phone: Some(phoneme.to_string()),
+                }
+            })
+            .collect()
+    }
+}
+
+impl SpeechEvaluator for MockSpeechEvaluator {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn evaluate(
+        &self,
+        request: SpeechEvaluationRequest,
+    ) -> BoxFuture<'_, Result<SpeechEvaluationResponse, SpeechError>> {
+        Box::pin(async move { self.evaluate_speech(&request) })
+    }
+
+    fn open_stream(
+        &self,
+        request: SpeechEvaluationRequest,
+    ) -> BoxFuture<'_, Result<SpeechStream, SpeechError>> {
+        let evaluator = self.clone();
+        Box::pin(async move {
+            let (audio_tx, mut audio_rx) = mpsc::channel::<Bytes>(STREAM_CHANNEL_CAPACITY);
+            let (event_tx, event_rx) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+
+            // Buffer the whole recording and score it once the caller hangs up
+            tokio::spawn(async move {
+                let mut request = request;
+                let mut audio = request.audio_data.to_vec();
+                while let Some(chunk) = audio_rx.recv().await {
+                    audio.extend_from_slice(&chunk);
+                }
+                request.audio_data = audio.into();
+
+                let event = match evaluator.evaluate_speech(&request) {
+                    Ok(response) => SpeechStreamEvent::Final(response),
+                    Err(error) => SpeechStreamEvent::Failed(error),
+                };
+                let _ = event_tx.send(event).await;
+            });
+
+            Ok(SpeechStream {
+                audio: audio_tx,
+                events: event_rx,
+            })
+        })
+    }
+}
+
+/// Map a seed string to a stable score in the 60.0–99.9 range.
+fn score_from_seed(seed: &str) -> f32 {
+    // FNV-1a, so scores do not change between Rust releases
+    let hash = seed.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    60.0 + (hash % 400) as f32 / 10.0
+}
+
+fn round1(value: f32) -> f32 {
+    (value * 10.0).round() / 10.0
+}
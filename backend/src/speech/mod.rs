@@ -1,10 +1,149 @@
+pub mod analytics;
+pub mod audio;
+pub mod drills;
+pub mod error;
+pub mod history;
 pub mod iflytek;
+pub mod mock;
+pub mod resilience;
+pub mod tone;
 
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::{
+    DateTime,
+    Utc,
+};
+pub use error::SpeechError;
+use futures_util::future::BoxFuture;
 pub use iflytek::{
     IFlytekService,
     SpeechEvaluationRequest,
     SpeechEvaluationResponse,
 };
+pub use mock::MockSpeechEvaluator;
+use resilience::{
+    BreakerState,
+    BreakerStatus,
+    GuardedEvaluator,
+    ProviderTimeouts,
+    RetryPolicy,
+};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::config::{
+    SpeechConfig,
+    SpeechProvider,
+};
+
+/// A backend that scores a learner's recording against a reference text.
+///
+/// Handlers depend on this trait rather than a concrete provider so that the
+/// iFlytek integration can be swapped for the offline mock in development and CI.
+pub trait SpeechEvaluator: Send + Sync {
+    /// Short identifier used in logs and health output.
+    fn name(&self) -> &'static str;
+
+    fn evaluate(
+        &self,
+        request: SpeechEvaluationRequest,
+    ) -> BoxFuture<'_, Result<SpeechEvaluationResponse, SpeechError>>;
+
+    /// Start a live evaluation fed chunk by chunk.
+    ///
+    /// `request.audio_data` is sent ahead of anything written to the returned
+    /// stream's `audio` channel and is usually empty.
+    fn open_stream(
+        &self,
+        request: SpeechEvaluationRequest,
+    ) -> BoxFuture<'_, Result<SpeechStream, SpeechError>>;
+
+    /// What the evaluator knows about its provider without calling it.
+    fn health(&self) -> ProviderHealth {
+        ProviderHealth::new(self.name())
+    }
+}
+
+/// Provider status reported by `/ready` and `/speech/health`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderHealth {
+    pub provider: &'static str,
+    /// Whether the credentials the provider needs are set (always true for the mock)
+    pub credentials_configured: bool,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// How long the most recent provider call took
+    pub last_latency_ms: Option<u64>,
+    pub circuit_breaker: Option<BreakerStatus>,
+}
+
+impl ProviderHealth {
+    pub fn new(provider: &'static str) -> Self {
+        Self {
+            provider,
+            credentials_configured: true,
+            last_success_at: None,
+            last_failure_at: None,
+            last_error: None,
+            last_latency_ms: None,
+            circuit_breaker: None,
+        }
+    }
+
+    /// Whether evaluations can be expected to go through right now.
+    pub fn is_healthy(&self) -> bool {
+        self.credentials_configured
+            && self
+                .circuit_breaker
+                .as_ref()
+                .is_none_or(|breaker| breaker.state == BreakerState::Closed)
+    }
+}
+
+pub type SharedSpeechEvaluator = Arc<dyn SpeechEvaluator>;
+
+/// Buffer size of the audio and event channels behind a [`SpeechStream`].
+const STREAM_CHANNEL_CAPACITY: usize = 32;
+
+/// Channels connecting a caller to a live evaluation session.
+///
+/// Audio chunks are written to `audio`; dropping the sender marks the end of the
+/// recording. The session then emits zero or more `Partial` events followed by
+/// exactly one `Final` or `Failed`.
+pub struct SpeechStream {
+    pub audio: mpsc::Sender<Bytes>,
+    pub events: mpsc::Receiver<SpeechStreamEvent>,
+}
+
+#[derive(Debug)]
+pub enum SpeechStreamEvent {
+    /// Scores for the words recognised so far
+    Partial(SpeechEvaluationResponse),
+    Final(SpeechEvaluationResponse),
+    Failed(SpeechError),
+}
+
+/// Build the evaluator selected by `config.provider`, behind a deadline and circuit breaker.
+pub fn create_evaluator(config: &SpeechConfig) -> SharedSpeechEvaluator {
+    let provider: SharedSpeechEvaluator = match config.provider {
+        SpeechProvider::Iflytek => Arc::new(
+            IFlytekService::new(iflytek::IFlytekConfig::default())
+                .with_timeouts(ProviderTimeouts {
+                    connect: config.connect_timeout,
+                    read: config.read_timeout,
+                })
+                .with_retry(RetryPolicy {
+                    max_retries: config.max_retries,
+                    ..Default::default()
+                }),
+        ),
+        SpeechProvider::Mock => Arc::new(MockSpeechEvaluator::new()),
+    };
+    Arc::new(GuardedEvaluator::new(provider, config))
+}
 
 #[cfg(test)]
 mod tests {
@@ -108,7 +247,6 @@ mod tests {
         let response = SpeechEvaluationResponse {
             overall_scores,
             words: vec![word_score.clone()],
-            error: None,
         };
 
         assert_eq!(response.overall_scores.len(), 2);
@@ -116,7 +254,6 @@ mod tests {
         assert_eq!(response.words.len(), 1);
         assert_eq!(response.words[0].word, "你好");
         assert_eq!(response.words[0].scores.pronunciation, 88.0);
-        assert!(response.error.is_none());
     }
 
     #[test]
@@ -0,0 +1,434 @@
+//! Keeps a slow or flapping speech provider from taking requests down with it.
+//!
+//! Provider clients bound each network step with [`ProviderTimeouts`] and retry
+//! failed connections per [`RetryPolicy`]. [`GuardedEvaluator`] wraps whichever
+//! provider is configured with an overall deadline and a [`CircuitBreaker`], so
+//! that once the provider keeps failing, requests fail fast instead of queueing.
+//! A streaming session counts by how it ends rather than by whether it opened.
+
+use std::{
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+        PoisonError,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use futures_util::future::BoxFuture;
+use rand::Rng;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use super::{
+    audio,
+    ProviderHealth,
+    SharedSpeechEvaluator,
+    SpeechError,
+    SpeechEvaluationRequest,
+    SpeechEvaluationResponse,
+    SpeechEvaluator,
+    SpeechStream,
+    SpeechStreamEvent,
+    STREAM_CHANNEL_CAPACITY,
+};
+use crate::config::SpeechConfig;
+
+/// Per-step deadlines for a provider connection.
+#[derive(Debug, Clone, Copy)]
+pub struct ProviderTimeouts {
+    /// TCP + TLS + WebSocket handshake
+    pub connect: Duration,
+    /// Longest wait for the provider's result once all audio has been sent
+    pub read: Duration,
+}
+
+impl Default for ProviderTimeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(5),
+            read: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Exponential backoff with full jitter for reconnecting after transient failures.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Attempts after the first; 0 disables retrying
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (0-based): uniform in `[0, min(max, base·2^retry)]`.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        ceiling.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Requests flow normally
+    Closed,
+    /// Requests are rejected until the cooldown ends
+    Open,
+    /// The cooldown has ended; the next request probes the provider
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub failure_threshold: u32,
+    /// Seconds until an open breaker lets a probe through
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerInner {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// When the half-open probe was let through, if one is in flight
+    probe_started_at: Option<Instant>,
+}
+
+/// Opens after `failure_threshold` consecutive provider failures and lets a single
+/// probe through every `cooldown` until one succeeds.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            inner: Mutex::new(BreakerInner::default()),
+        }
+    }
+
+    /// The breaker's state, even if a panic poisoned the lock: every update
+    /// leaves it consistent, and a poisoned breaker must not fail all requests.
+    fn inner(&self) -> MutexGuard<'_, BreakerInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Admit a request, or fail fast while the breaker is open.
+    pub fn acquire(&self) -> Result<(), SpeechError> {
+        let mut inner = self.inner();
+        let Some(opened_at) = inner.opened_at else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let remaining = self.cooldown.saturating_sub(now - opened_at);
+        if !remaining.is_zero() {
+            return Err(SpeechError::Unavailable(format!(
+                "circuit breaker open after {} consecutive failures; retry in {}s",
+                inner.consecutive_failures,
+                remaining.as_secs().max(1)
+            )));
+        }
+
+        // A probe that never reported back (e.g. the caller went away) expires too
+        if inner
+            .probe_started_at
+            .is_some_and(|started| now - started < self.cooldown)
+        {
+            return Err(SpeechError::Unavailable(
+                "circuit breaker half-open; waiting on a probe request".to_string(),
+            ));
+        }
+        inner.probe_started_at = Some(now);
+        Ok(())
+    }
+
+    pub fn record_success(&self) {
+        *self.inner() = BreakerInner::default();
+    }
+
+    pub fn record_failure(&self) {
+        let mut inner = self.inner();
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        let probe_failed = inner.probe_started_at.take().is_some();
+        if probe_failed || inner.consecutive_failures >= self.failure_threshold {
+            if inner.opened_at.is_none() || probe_failed {
+                warn!(
+                    "Speech provider circuit breaker opened after {} consecutive failures",
+                    inner.consecutive_failures
+                );
+            }
+            inner.opened_at = Some(Instant::now());
+        }
+    }
+
+    /// Record how a provider call ended. Only the provider's own failures count
+    /// against it; other errors, such as a request it rejected, leave the
+    /// breaker as it was.
+    pub fn record<T>(&self, result: &Result<T, SpeechError>) {
+        self.record_outcome(result.as_ref().err());
+    }
+
+    fn record_outcome(&self, error: Option<&SpeechError>) {
+        match error {
+            None => self.record_success(),
+            Some(e) if e.is_provider_failure() => self.record_failure(),
+            Some(_) => self.release_probe(),
+        }
+    }
+
+    /// End a half-open probe that said nothing about the provider, so that the
+    /// next request probes instead.
+    fn release_probe(&self) {
+        self.inner().probe_started_at = None;
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let inner = self.inner();
+        let remaining = inner
+            .opened_at
+            .map(|opened_at| self.cooldown.saturating_sub(opened_at.elapsed()));
+        let state = match remaining {
+            None => BreakerState::Closed,
+            Some(remaining) if !remaining.is_zero() => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        };
+
+        BreakerStatus {
+            state,
+            consecutive_failures: inner.consecutive_failures,
+            failure_threshold: self.failure_threshold,
+            retry_after_secs: remaining
+                .filter(|remaining| !remaining.is_zero())
+                .map(|remaining| remaining.as_secs().max(1)),
+        }
+    }
+}
+
+/// How recent provider calls went, for health reporting.
+#[derive(Debug, Default)]
+struct Activity {
+    last_success_at: Option<DateTime<Utc>>,
+    last_failure_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    last_latency: Option<Duration>,
+}
+
+/// The breaker and the health report, shared with the streams being watched.
+#[derive(Debug)]
+struct Monitor {
+    breaker: CircuitBreaker,
+    activity: Mutex<Activity>,
+}
+
+impl Monitor {
+    /// Feed a finished provider call to the breaker and the health report.
+    fn record(&self, error: Option<&SpeechError>, latency: Duration) {
+        self.breaker.record_outcome(error);
+
+        let mut activity = self.activity.lock().unwrap_or_else(PoisonError::into_inner);
+        activity.last_latency = Some(latency);
+        match error {
+            None => activity.last_success_at = Some(Utc::now()),
+            Some(e) if e.is_provider_failure() => {
+                activity.last_failure_at = Some(Utc::now());
+                activity.last_error = Some(e.to_string());
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Adds an overall deadline and a circuit breaker to any provider.
+pub struct GuardedEvaluator {
+    inner: SharedSpeechEvaluator,
+    monitor: Arc<Monitor>,
+    request_timeout: Duration,
+}
+
+impl GuardedEvaluator {
+    pub fn new(inner: SharedSpeechEvaluator, config: &SpeechConfig) -> Self {
+        Self {
+            inner,
+            monitor: Arc::new(Monitor {
+                breaker: CircuitBreaker::new(
+                    config.breaker_failure_threshold,
+                    config.breaker_cooldown,
+                ),
+                activity: Mutex::new(Activity::default()),
+            }),
+            request_timeout: config.request_timeout,
+        }
+    }
+
+    /// The deadline for one evaluation: providers stream audio in real time, so
+    /// the recording's length is added to the configured allowance.
+    fn deadline(&self, request: &SpeechEvaluationRequest) -> Duration {
+        let recording = audio::estimate_duration(
+            &request.audio_data,
+            &request.audio_encoding,
+            request.sample_rate,
+            request.channels,
+            request.bit_depth,
+        );
+        self.request_timeout + recording.unwrap_or_default()
+    }
+
+    /// Pass a stream's events through, recording its `Final` or `Failed` event
+    /// once it arrives. `latency` is how long the stream took to open.
+    fn watch_stream(&self, stream: SpeechStream, latency: Duration) -> SpeechStream {
+        let SpeechStream {
+            audio,
+            events: mut provider_events,
+        } = stream;
+        let (sender, events) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+        let monitor = Arc::clone(&self.monitor);
+
+        tokio::spawn(async move {
+            let mut ended = false;
+            while let Some(event) = provider_events.recv().await {
+                match &event {
+                    SpeechStreamEvent::Partial(_) => {}
+                    SpeechStreamEvent::Final(_) => monitor.record(None, latency),
+                    SpeechStreamEvent::Failed(e) => monitor.record(Some(e), latency),
+                }
+                ended |= !matches!(event, SpeechStreamEvent::Partial(_));
+                if sender.send(event).await.is_err() {
+                    break;
+                }
+            }
+            // The caller went away first, which says nothing about the provider
+            if !ended {
+                monitor.breaker.release_probe();
+            }
+        });
+
+        SpeechStream { audio, events }
+    }
+}
+
+impl SpeechEvaluator for GuardedEvaluator {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn evaluate(
+        &self,
+        request: SpeechEvaluationRequest,
+    ) -> BoxFuture<'_, Result<SpeechEvaluationResponse, SpeechError>> {
+        Box::pin(async move {
+            self.monitor.breaker.acquire()?;
+
+            let deadline = self.deadline(&request);
+            let started = Instant::now();
+            let result = tokio::time::timeout(deadline, self.inner.evaluate(request))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(SpeechError::Timeout(format!(
+                        "no result within {:?}",
+                        deadline
+                    )))
+                });
+            self.monitor
+                .record(result.as_ref().err(), started.elapsed());
+            result
+        })
+    }
+
+    fn open_stream(
+        &self,
+        request: SpeechEvaluationRequest,
+    ) -> BoxFuture<'_, Result<SpeechStream, SpeechError>> {
+        Box::pin(async move {
+            self.monitor.breaker.acquire()?;
+
+            let started = Instant::now();
+            let result =
+                tokio::time::timeout(self.request_timeout, self.inner.open_stream(request))
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(SpeechError::Timeout(
+                            "timed out opening a streaming session".to_string(),
+                        ))
+                    });
+            match result {
+                Ok(stream) => Ok(self.watch_stream(stream, started.elapsed())),
+                Err(e) => {
+                    self.monitor.record(Some(&e), started.elapsed());
+                    Err(e)
+                }
+            }
+        })
+    }
+
+    fn health(&self) -> ProviderHealth {
+        let activity = self
+            .monitor
+            .activity
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        ProviderHealth {
+            last_success_at: activity.last_success_at,
+            last_failure_at: activity.last_failure_at,
+            last_error: activity.last_error.clone(),
+            last_latency_ms: activity
+                .last_latency
+                .map(|latency| latency.as_millis() as u64),
+            circuit_breaker: Some(self.monitor.breaker.status()),
+            ..self.inner.health()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_survives_a_poisoned_lock() {
+        let breaker = Arc::new(CircuitBreaker::new(1, Duration::from_secs(30)));
+        let poisoner = Arc::clone(&breaker);
+        std::thread::spawn(move || {
+            let _inner = poisoner.inner.lock().unwrap();
+            panic!("poison the breaker");
+        })
+        .join()
+        .unwrap_err();
+        assert!(breaker.inner.is_poisoned());
+
+        breaker.acquire().unwrap();
+        breaker.record_failure();
+        assert!(breaker.acquire().is_err());
+    }
+}
@@ -0,0 +1,449 @@
+//! Tone scoring from the learner's own pitch, independent of the provider.
+//!
+//! [`track_pitch`] estimates F0 every 10 ms with YIN (de Cheveigné & Kawahara,
+//! 2002), the same resolution as provider [`TimeSpan`]s. [`analyze`] cuts that
+//! contour into syllables using an evaluation's word and phoneme spans, maps each
+//! syllable onto the speaker's own pitch range in Chao tone numerals (1 = low,
+//! 5 = high), and compares its shape with the four tones and the neutral tone.
+
+use serde::Serialize;
+
+use super::iflytek::{
+    TimeSpan,
+    WordScore,
+};
+use crate::pinyin::{
+    parse::{
+        self,
+        is_initial,
+    },
+    sandhi::{
+        self,
+        SandhiSyllable,
+    },
+};
+
+/// Spacing of contour frames; matches the 10 ms units of [`TimeSpan`].
+pub const FRAME_MS: u32 = 10;
+
+const MIN_F0: f32 = 60.0;
+const MAX_F0: f32 = 500.0;
+/// Audio is decimated towards this rate before tracking; ample for F0 under 500 Hz
+const ANALYSIS_RATE: u32 = 8_000;
+/// YIN's aperiodicity threshold; frames whose best dip stays above it are unvoiced
+const YIN_THRESHOLD: f32 = 0.15;
+/// Frames quieter than this RMS (full scale = 1.0) are silence
+const SILENCE_RMS: f32 = 0.01;
+/// Points each syllable's contour is resampled to
+const CONTOUR_POINTS: usize = 5;
+/// Fewest voiced frames a syllable needs before its tone is judged
+const MIN_VOICED_FRAMES: usize = 3;
+/// Narrowest pitch range, in semitones, a recording is normalised to, so a
+/// monotone speaker is not stretched across the whole scale
+const MIN_RANGE_SEMITONES: f32 = 6.0;
+/// Spread of the match likelihood, in Chao units
+const MATCH_SIGMA: f32 = 0.7;
+
+/// Tone shapes on the Chao scale at [`CONTOUR_POINTS`] evenly spaced points.
+const TONE1: [f32; CONTOUR_POINTS] = [5.0, 5.0, 5.0, 5.0, 5.0];
+const TONE2: [f32; CONTOUR_POINTS] = [3.0, 3.2, 3.7, 4.3, 5.0];
+/// The full 214 dip, heard in isolation and at the end of a phrase
+const TONE3_FULL: [f32; CONTOUR_POINTS] = [2.0, 1.4, 1.0, 1.4, 2.4];
+/// The low "half third" (21) used before most other syllables
+const TONE3_HALF: [f32; CONTOUR_POINTS] = [2.0, 1.6, 1.2, 1.0, 1.0];
+const TONE4: [f32; CONTOUR_POINTS] = [5.0, 4.3, 3.3, 2.2, 1.0];
+const NEUTRAL: [f32; CONTOUR_POINTS] = [3.0, 2.8, 2.6, 2.4, 2.2];
+
+/// F0 estimates at [`FRAME_MS`] intervals.
+#[derive(Debug, Clone, Serialize)]
+pub struct PitchContour {
+    pub frame_ms: u32,
+    /// F0 in Hz; `None` where the frame is silent or unvoiced
+    pub f0: Vec<Option<f32>>,
+}
+
+impl PitchContour {
+    fn voiced_in(&self, span: &TimeSpan) -> Vec<f32> {
+        let end = (span.end as usize).min(self.f0.len());
+        let start = (span.start as usize).min(end);
+        self.f0[start..end].iter().flatten().copied().collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToneAnalysis {
+    pub contour: PitchContour,
+    /// Median F0 of the recording, the centre of the speaker's range
+    pub reference_hz: Option<f32>,
+    pub syllables: Vec<SyllableTone>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyllableTone {
+    /// Index into the evaluation's `words`
+    pub word_index: usize,
+    pub pinyin: String,
+    pub span: TimeSpan,
+    /// Tone written in the reference pinyin (5 for neutral)
+    pub expected_tone: u8,
+    /// The tone to produce once 不/一 and third-tone sandhi are applied
+    pub target_tone: u8,
+    /// Closest tone shape, if the syllable had enough voiced frames
+    pub detected_tone: Option<u8>,
+    /// 0-100 likelihood that `target_tone` was produced
+    pub score: Option<f32>,
+    /// The learner's pitch across the syllable in Chao numerals
+    pub contour: Vec<f32>,
+    /// Shape of `target_tone` at the same points
+    pub expected_contour: Vec<f32>,
+}
+
+/// Track F0 through mono samples in `[-1, 1]`.
+pub fn track_pitch(samples: &[f32], sample_rate: u32) -> PitchContour {
+    // Box-filter decimation; the pitch range sits far below the new Nyquist
+    let factor = (sample_rate / ANALYSIS_RATE).max(1) as usize;
+    let rate = sample_rate as f32 / factor as f32;
+    let signal: Vec<f32> = samples
+        .chunks(factor)
+        .map(|chunk| chunk.iter().sum::<f32>() / chunk.len() as f32)
+        .collect();
+
+    let hop = (rate * FRAME_MS as f32 / 1000.0).round() as usize;
+    let tau_min = (rate / MAX_F0).floor() as usize;
+    let tau_max = (rate / MIN_F0).ceil() as usize;
+    let window = 2 * tau_max;
+    let frames = signal.len().div_ceil(hop.max(1));
+
+    let mut f0: Vec<Option<f32>> = (0..frames)
+        .map(|i| {
+            // Centre the analysis window on the frame
+            let start = (i * hop + hop / 2).saturating_sub((window + tau_max) / 2);
+            let frame = signal.get(start..start + window + tau_max)?;
+            yin(frame, window, tau_min, tau_max).map(|tau| rate / tau)
+        })
+        .collect();
+
+    // A voiced frame with no voiced neighbour is almost always noise
+    let isolated: Vec<usize> = (0..f0.len())
+        .filter(|&i| {
+            f0[i].is_some()
+                && (i == 0 || f0[i - 1].is_none())
+                && f0.get(i + 1).is_none_or(|next| next.is_none())
+        })
+        .collect();
+    for i in isolated {
+        f0[i] = None;
+    }
+
+    PitchContour {
+        frame_ms: FRAME_MS,
+        f0,
+    }
+}
+
+/// The period of `frame` in samples, or `None` if it is silent or aperiodic.
+fn yin(frame: &[f32], window: usize, tau_min: usize, tau_max: usize) -> Option<f32> {
+    let energy = frame[..window].iter().map(|x| x * x).sum::<f32>() / window as f32;
+    if energy.sqrt() < SILENCE_RMS {
+        return None;
+    }
+
+    // Cumulative mean normalised difference
+    let mut cmnd = vec![1.0f32; tau_max + 1];
+    let mut running = 0.0f32;
+    for tau in 1..=tau_max {
+        let difference: f32 = (0..window)
+            .map(|j| {
+                let delta = frame[j] - frame[j + tau];
+                delta * delta
+            })
+            .sum();
+        running += difference;
+        cmnd[tau] = if running > 0.0 {
+            difference * tau as f32 / running
+        } else {
+            1.0
+        };
+    }
+
+    let mut tau = (tau_min.max(2)..tau_max).find(|&tau| cmnd[tau] < YIN_THRESHOLD)?;
+    while tau + 1 < tau_max && cmnd[tau + 1] < cmnd[tau] {
+        tau += 1;
+    }
+
+    // Parabolic interpolation between lags
+    let (a, b, c) = (cmnd[tau - 1], cmnd[tau], cmnd[tau + 1]);
+    let curvature = a - 2.0 * b + c;
+    let shift = if curvature.abs() > f32::EPSILON {
+        (0.5 * (a - c) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    Some(tau as f32 + shift)
+}
+
+/// Score the tones of `words` against 16-bit little-endian mono PCM.
+pub fn analyze(pcm: &[u8], sample_rate: u32, words: &[WordScore]) -> ToneAnalysis {
+    let samples: Vec<f32> = pcm
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+        .collect();
+    let contour = track_pitch(&samples, sample_rate);
+
+    let segments = segment(words);
+    let voiced: Vec<f32> = segments
+        .iter()
+        .flat_map(|segment| contour.voiced_in(&segment.span))
+        .collect();
+    let Some(range) = SpeakerRange::from_f0(&voiced) else {
+        return ToneAnalysis {
+            contour,
+            reference_hz: None,
+            syllables: Vec::new(),
+        };
+    };
+
+    let mut lengths: Vec<u32> = segments
+        .iter()
+        .map(|segment| segment.span.end.saturating_sub(segment.span.start))
+        .collect();
+    lengths.sort_unstable();
+    let typical_length = lengths[lengths.len() / 2].max(1) as f32;
+
+    let phrase: Vec<SandhiSyllable> = segments.iter().map(|segment| segment.sandhi).collect();
+    let target_tones = sandhi::apply(&phrase);
+
+    let syllables = segments
+        .iter()
+        .zip(target_tones)
+        .enumerate()
+        .map(|(i, (segment, target_tone))| {
+            let phrase_final = i + 1 == segments.len();
+            let length = segment.span.end.saturating_sub(segment.span.start) as f32;
+
+            let chao = range.to_chao(&contour.voiced_in(&segment.span));
+            let points = (chao.len() >= MIN_VOICED_FRAMES).then(|| resample(&chao));
+            let matches = points
+                .as_ref()
+                .map(|points| match_tones(points, length / typical_length));
+
+            SyllableTone {
+                word_index: segment.word_index,
+                pinyin: segment.pinyin.clone(),
+                span: segment.span.clone(),
+                expected_tone: segment.sandhi.tone,
+                target_tone,
+                detected_tone: matches.as_ref().map(|m| m.best),
+                score: matches
+                    .as_ref()
+                    .map(|m| (m.likelihood[target_tone as usize - 1] * 1000.0).round() / 10.0),
+                contour: points.map(|points| points.to_vec()).unwrap_or_default(),
+                expected_contour: template(target_tone, phrase_final).to_vec(),
+            }
+        })
+        .collect();
+
+    ToneAnalysis {
+        contour,
+        reference_hz: Some(range.reference_hz),
+        syllables,
+    }
+}
+
+/// One reference syllable and the part of the recording it was read in.
+struct Segment {
+    word_index: usize,
+    pinyin: String,
+    sandhi: SandhiSyllable,
+    span: TimeSpan,
+}
+
+/// Syllable spans for the words the learner actually read.
+///
+/// A syllable's tone rides on its final, so finals' phoneme spans are used when
+/// there is one per syllable; otherwise the word's span is split evenly.
+fn segment(words: &[WordScore]) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for (word_index, word) in words.iter().enumerate() {
+        // Insertions have no reference tone, omissions no audio
+        if word.read_type != 0 {
+            continue;
+        }
+        let Some(pinyin) = &word.pinyin else {
+            continue;
+        };
+        let Ok(parts) = parse::parse(pinyin) else {
+            continue;
+        };
+
+        let finals: Vec<&TimeSpan> = word
+            .phonemes
+            .iter()
+            .flatten()
+            .filter(|phoneme| !is_initial(&phoneme.phoneme))
+            .filter_map(|phoneme| phoneme.span.as_ref())
+            .collect();
+        let spans: Vec<TimeSpan> = if finals.len() == parts.len() {
+            finals.into_iter().cloned().collect()
+        } else if let Some(span) = &word.span {
+            let step = span.end.saturating_sub(span.start) / parts.len() as u32;
+            (0..parts.len() as u32)
+                .map(|i| TimeSpan {
+                    start: span.start + i * step,
+                    end: span.start + (i + 1) * step,
+                })
+                .collect()
+        } else {
+            continue;
+        };
+
+        // 一 and 不 change tone, so sandhi needs the characters where they line up
+        let tones: Vec<u8> = parts
+            .iter()
+            .map(|syllable| syllable.tone.unwrap_or(5))
+            .collect();
+        let word_sandhi = sandhi::word(&word.word, &tones);
+        for ((syllable, sandhi), span) in parts.into_iter().zip(word_sandhi).zip(spans) {
+            segments.push(Segment {
+                word_index,
+                pinyin: syllable.numbered(),
+                sandhi,
+                span,
+            });
+        }
+    }
+    segments
+}
+
+/// Maps Hz onto the speaker's range in Chao numerals.
+struct SpeakerRange {
+    reference_hz: f32,
+    /// Semitones from the reference at Chao 1 and Chao 5
+    low: f32,
+    high: f32,
+}
+
+impl SpeakerRange {
+    fn from_f0(voiced: &[f32]) -> Option<Self> {
+        if voiced.is_empty() {
+            return None;
+        }
+        let mut sorted = voiced.to_vec();
+        sorted.sort_by(f32::total_cmp);
+        let reference_hz = sorted[sorted.len() / 2];
+
+        let percentile = |p: f32| {
+            let hz = sorted[((sorted.len() - 1) as f32 * p).round() as usize];
+            semitones(hz, reference_hz)
+        };
+        let (mut low, mut high) = (percentile(0.05), percentile(0.95));
+        let shortfall = MIN_RANGE_SEMITONES - (high - low);
+        if shortfall > 0.0 {
+            low -= shortfall / 2.0;
+            high += shortfall / 2.0;
+        }
+
+        Some(Self {
+            reference_hz,
+            low,
+            high,
+        })
+    }
+
+    fn to_chao(&self, voiced: &[f32]) -> Vec<f32> {
+        voiced
+            .iter()
+            .map(|&hz| semitones(hz, self.reference_hz))
+            // Octave jumps are tracking errors, not intonation
+            .filter(|st| st.abs() <= 12.0)
+            .map(|st| (1.0 + 4.0 * (st - self.low) / (self.high - self.low)).clamp(0.5, 5.5))
+            .collect()
+    }
+}
+
+fn semitones(hz: f32, reference_hz: f32) -> f32 {
+    12.0 * (hz / reference_hz).log2()
+}
+
+/// Evenly spaced points along a contour, linearly interpolated.
+fn resample(values: &[f32]) -> [f32; CONTOUR_POINTS] {
+    let mut points = [0.0; CONTOUR_POINTS];
+    if values.is_empty() {
+        return points;
+    }
+    for (i, point) in points.iter_mut().enumerate() {
+        let position = i as f32 * (values.len() - 1) as f32 / (CONTOUR_POINTS - 1) as f32;
+        let lower = position.floor() as usize;
+        let upper = (lower + 1).min(values.len() - 1);
+        let weight = position - lower as f32;
+        *point = values[lower] * (1.0 - weight) + values[upper] * weight;
+    }
+    points
+}
+
+fn template(tone: u8, phrase_final: bool) -> [f32; CONTOUR_POINTS] {
+    match tone {
+        1 => TONE1,
+        2 => TONE2,
+        3 if phrase_final => TONE3_FULL,
+        3 => TONE3_HALF,
+        4 => TONE4,
+        _ => NEUTRAL,
+    }
+}
+
+struct ToneMatch {
+    best: u8,
+    /// Likelihood of tones 1-5, summing to 1
+    likelihood: [f32; 5],
+}
+
+/// Compare a syllable's contour with every tone shape.
+///
+/// Shape counts more than absolute height, which depends on how well the
+/// speaker's range could be estimated. `relative_length` is the syllable's
+/// length over the recording's typical syllable; the neutral tone is short.
+fn match_tones(contour: &[f32; CONTOUR_POINTS], relative_length: f32) -> ToneMatch {
+    let mut distances = [0.0f32; 5];
+    for (i, distance) in distances.iter_mut().enumerate() {
+        let tone = i as u8 + 1;
+        let candidates: &[[f32; CONTOUR_POINTS]] = match tone {
+            3 => &[TONE3_FULL, TONE3_HALF],
+            _ => &[template(tone, false)],
+        };
+        *distance = candidates
+            .iter()
+            .map(|template| shape_distance(contour, template))
+            .fold(f32::INFINITY, f32::min);
+        if tone == 5 {
+            *distance += (relative_length - 0.7).max(0.0) * 2.0;
+        }
+    }
+
+    let weights = distances.map(|d| (-d * d / (2.0 * MATCH_SIGMA * MATCH_SIGMA)).exp());
+    let total: f32 = weights.iter().sum();
+    let likelihood = if total > 0.0 {
+        weights.map(|w| w / total)
+    } else {
+        [0.2; 5]
+    };
+    let best = (0..5)
+        .min_by(|&a, &b| distances[a].total_cmp(&distances[b]))
+        .unwrap_or(0) as u8
+        + 1;
+
+    ToneMatch { best, likelihood }
+}
+
+/// RMS difference in shape plus half the difference in mean height.
+fn shape_distance(contour: &[f32; CONTOUR_POINTS], template: &[f32; CONTOUR_POINTS]) -> f32 {
+    let mean = |values: &[f32; CONTOUR_POINTS]| values.iter().sum::<f32>() / CONTOUR_POINTS as f32;
+    let (contour_mean, template_mean) = (mean(contour), mean(template));
+    let shape = contour
+        .iter()
+        .zip(template)
+        .map(|(c, t)| ((c - contour_mean) - (t - template_mean)).powi(2))
+        .sum::<f32>()
+        / CONTOUR_POINTS as f32;
+    shape.sqrt() + 0.5 * (contour_mean - template_mean).abs()
+}
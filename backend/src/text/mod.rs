@@ -0,0 +1,284 @@
+//! Splitting Chinese text into words.
+//!
+//! Each run of hanzi is segmented against a bundled frequency lexicon
+//! (`data/text/lexicon.tsv`), topped up with the words of the pinyin word
+//! list: every dictionary word in the run becomes an edge of a DAG, and
+//! the path whose words have the highest joint frequency wins, so 研究生命
+//! splits as 研究/生命 rather than 研究生/命 when 生命 is the likelier word.
+//! Characters in no word stand alone. Everything else — spaces, punctuation,
+//! latin letters and digits — is passed through as its own segments.
+
+use std::{
+    collections::HashMap,
+    sync::OnceLock,
+};
+
+use serde::Serialize;
+
+use crate::pinyin::{
+    is_hanzi,
+    PinyinConverter,
+    Tones,
+};
+
+const LEXICON: &str = include_str!("../../data/text/lexicon.tsv");
+const PINYIN_WORDS: &str = include_str!("../../data/pinyin/words.tsv");
+
+/// Frequency of words known only from the word lists, per million words.
+const DEFAULT_FREQUENCY: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentKind {
+    /// A word of hanzi, or a single character found in no word
+    Word,
+    Punctuation,
+    Space,
+    /// Latin letters and digits
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Segment {
+    pub text: String,
+    pub kind: SegmentKind,
+    /// Where the segment starts in the input, in characters
+    pub start: usize,
+    /// Numbered citation-tone pinyin of a word, e.g. `yin2 hang2`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinyin: Option<String>,
+    /// Tone-marked pinyin of a word, e.g. `yín háng`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinyin_marked: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub english: Option<String>,
+    /// Whether a word is in the lexicon, rather than a character left over
+    pub in_lexicon: bool,
+}
+
+impl Segment {
+    fn passthrough(text: String, kind: SegmentKind, start: usize) -> Self {
+        Self {
+            text,
+            kind,
+            start,
+            pinyin: None,
+            pinyin_marked: None,
+            english: None,
+            in_lexicon: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    frequency: u32,
+    english: Option<String>,
+}
+
+/// Segments hanzi text into words using a frequency lexicon.
+pub struct Segmenter {
+    words: HashMap<Vec<char>, Entry>,
+    longest_word: usize,
+    /// Natural log of the summed frequencies
+    log_total: f64,
+}
+
+impl Segmenter {
+    /// The segmenter over the word lists compiled into the binary.
+    pub fn bundled() -> &'static Self {
+        static SEGMENTER: OnceLock<Segmenter> = OnceLock::new();
+        SEGMENTER.get_or_init(|| Self::from_tsv(LEXICON, &[PINYIN_WORDS]))
+    }
+
+    /// Build a segmenter from tab-separated word lists.
+    ///
+    /// `lexicon` lines are `word, frequency, english`, the gloss being optional.
+    /// Further lists are `hanzi, pinyin` with an optional English gloss; their
+    /// words are added at a low default frequency, and their glosses fill in
+    /// lexicon words that have none. Where lines repeat a word the first wins.
+    /// Lines starting with `#` are comments; malformed lines are skipped.
+    pub fn from_tsv(lexicon: &str, word_lists: &[&str]) -> Self {
+        let mut words: HashMap<Vec<char>, Entry> = HashMap::new();
+        for fields in data_lines(lexicon) {
+            let (word, frequency, english) = match fields[..] {
+                [word, frequency] => (word, frequency, None),
+                [word, frequency, english, ..] => (word, frequency, Some(english)),
+                _ => continue,
+            };
+            let Ok(frequency) = frequency.parse::<u32>() else {
+                continue;
+            };
+            add_word(&mut words, word, frequency.max(1), english);
+        }
+        for fields in word_lists.iter().flat_map(|list| data_lines(list)) {
+            let (word, english) = match fields[..] {
+                [word, _] => (word, None),
+                [word, _, english, ..] => (word, Some(english)),
+                _ => continue,
+            };
+            add_word(&mut words, word, DEFAULT_FREQUENCY, english);
+        }
+
+        let total: u64 = words.values().map(|entry| u64::from(entry.frequency)).sum();
+        let longest_word = words.keys().map(Vec::len).max().unwrap_or(1);
+        Self {
+            words,
+            longest_word,
+            log_total: (total.max(1) as f64).ln(),
+        }
+    }
+
+    /// Whether `word` is in the lexicon.
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains_key(&word.chars().collect::<Vec<_>>())
+    }
+
+    /// How often `word` occurs per million words, when it is in the lexicon.
+    pub fn frequency(&self, word: &str) -> Option<u32> {
+        Some(self.words.get(&word.chars().collect::<Vec<_>>())?.frequency)
+    }
+
+    /// English gloss of `word`, when the lexicon has one.
+    pub fn english(&self, word: &str) -> Option<&str> {
+        self.words
+            .get(&word.chars().collect::<Vec<_>>())?
+            .english
+            .as_deref()
+    }
+
+    /// Split `text` into words and the text between them.
+    pub fn segment(&self, text: &str) -> Vec<Segment> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut segments = Vec::new();
+
+        let mut i = 0;
+        while i < chars.len() {
+            let kind = char_kind(chars[i]);
+            let len = chars[i..]
+                .iter()
+                .take_while(|&&c| char_kind(c) == kind)
+                .count();
+            let run = &chars[i..i + len];
+            match kind {
+                SegmentKind::Word => {
+                    let mut start = i;
+                    for len in self.split(run) {
+                        segments.push(self.word(&chars[start..start + len], start));
+                        start += len;
+                    }
+                }
+                _ => segments.push(Segment::passthrough(run.iter().collect(), kind, i)),
+            }
+            i += len;
+        }
+
+        segments
+    }
+
+    /// Just the words of `text`.
+    pub fn words(&self, text: &str) -> Vec<String> {
+        self.segment(text)
+            .into_iter()
+            .filter(|segment| segment.kind == SegmentKind::Word)
+            .map(|segment| segment.text)
+            .collect()
+    }
+
+    /// Word lengths along the likeliest path through a run of hanzi.
+    fn split(&self, run: &[char]) -> Vec<usize> {
+        let n = run.len();
+        // best[i]: log probability of the likeliest split of run[i..], and the
+        // length of its first word
+        let mut best = vec![(0.0, 0); n + 1];
+        for start in (0..n).rev() {
+            best[start] = (1..=self.longest_word.min(n - start))
+                .filter_map(|len| {
+                    let frequency = match self.words.get(&run[start..start + len]) {
+                        Some(entry) => entry.frequency,
+                        // Any character may stand alone, however unlikely
+                        None if len == 1 => 1,
+                        None => return None,
+                    };
+                    let score = f64::from(frequency).ln() - self.log_total + best[start + len].0;
+                    Some((score, len))
+                })
+                .max_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)))
+                .expect("a single character always splits");
+        }
+
+        let mut lengths = Vec::new();
+        let mut start = 0;
+        while start < n {
+            let len = best[start].1;
+            lengths.push(len);
+            start += len;
+        }
+        lengths
+    }
+
+    fn word(&self, chars: &[char], start: usize) -> Segment {
+        let text: String = chars.iter().collect();
+        let entry = self.words.get(chars);
+        let conversion = PinyinConverter::bundled().convert(&text);
+        let (pinyin, pinyin_marked) = if conversion.is_complete() {
+            (
+                Some(conversion.numbered(Tones::Citation)),
+                Some(conversion.marked(Tones::Citation)),
+            )
+        } else {
+            (None, None)
+        };
+
+        Segment {
+            text,
+            kind: SegmentKind::Word,
+            start,
+            pinyin,
+            pinyin_marked,
+            english: entry.and_then(|entry| entry.english.clone()),
+            in_lexicon: entry.is_some(),
+        }
+    }
+}
+
+fn add_word(
+    words: &mut HashMap<Vec<char>, Entry>,
+    word: &str,
+    frequency: u32,
+    english: Option<&str>,
+) {
+    let chars: Vec<char> = word.chars().collect();
+    if chars.is_empty() || !chars.iter().all(|&c| is_hanzi(c)) {
+        return;
+    }
+    let english = english
+        .filter(|english| !english.is_empty())
+        .map(str::to_string);
+    let entry = words.entry(chars).or_insert(Entry {
+        frequency,
+        english: None,
+    });
+    if entry.english.is_none() {
+        entry.english = english;
+    }
+}
+
+fn char_kind(c: char) -> SegmentKind {
+    if is_hanzi(c) {
+        SegmentKind::Word
+    } else if c.is_whitespace() {
+        SegmentKind::Space
+    } else if c.is_alphanumeric() {
+        SegmentKind::Other
+    } else {
+        SegmentKind::Punctuation
+    }
+}
+
+fn data_lines(tsv: &str) -> impl Iterator<Item = Vec<&str>> {
+    tsv.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.split('\t').map(str::trim).collect())
+}
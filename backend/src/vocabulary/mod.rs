@@ -0,0 +1,508 @@
+//! A learner's own vocabulary, kept server-side so it follows them across
+//! devices.
+//!
+//! Words mirror the app's `Word` model field for field, camelCase included, so
+//! the vocabulary store can load and save them as they are. Each word belongs to
+//! one user and is unique by its hanzi within their list, and is also a
+//! spaced-repetition card scheduled by [`review`].
+
+pub mod review;
+pub mod scheduler;
+pub mod speech;
+
+use chrono::{
+    DateTime,
+    NaiveDateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Deserializer,
+    Serialize,
+};
+use sqlx::SqliteConnection;
+use uuid::Uuid;
+
+use crate::{
+    db::Database,
+    dictionary::DictionaryEntry,
+    error::{
+        AppError,
+        Result,
+    },
+    pinyin::parse,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Difficulty {
+    #[default]
+    Beginner,
+    Intermediate,
+    Advanced,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Word {
+    pub id: String,
+    pub chinese: String,
+    /// Tone-marked, e.g. `nǐ hǎo`
+    pub pinyin: Option<String>,
+    pub definition: String,
+    pub difficulty: Difficulty,
+    pub added_date: DateTime<Utc>,
+    pub last_reviewed: Option<DateTime<Utc>>,
+    pub next_review: Option<DateTime<Utc>>,
+    /// 0 to 100
+    pub strength: i64,
+    pub correct_attempts: i64,
+    pub total_attempts: i64,
+    pub tasks: WordTasks,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WordTasks {
+    pub speaking: ScoredTask,
+    pub reading: ReadingTask,
+    pub writing: ScoredTask,
+}
+
+/// Progress on a task that is scored each time, like speaking or writing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ScoredTask {
+    pub completed: bool,
+    pub last_score: Option<f64>,
+    pub attempts: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ReadingTask {
+    pub completed: bool,
+    pub times_encountered: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewWord {
+    pub chinese: String,
+    /// Pinyin in any style; stored tone-marked
+    pub pinyin: Option<String>,
+    pub definition: String,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+}
+
+/// Changes to a word; fields left out keep their value, and review times set
+/// to `null` are cleared.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WordUpdate {
+    pub pinyin: Option<String>,
+    pub definition: Option<String>,
+    pub difficulty: Option<Difficulty>,
+    pub strength: Option<i64>,
+    pub correct_attempts: Option<i64>,
+    pub total_attempts: Option<i64>,
+    #[serde(default, deserialize_with = "present")]
+    pub last_reviewed: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "present")]
+    pub next_review: Option<Option<DateTime<Utc>>>,
+    /// Replaces all task progress; tasks left out are reset
+    pub tasks: Option<WordTasks>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WordFilter {
+    pub difficulty: Option<Difficulty>,
+    /// Only words due for review by this time: those never scheduled, or
+    /// scheduled at or before it
+    pub due_by: Option<DateTime<Utc>>,
+}
+
+/// The outcome of adding dictionary entries to a vocabulary.
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkAddition {
+    pub added: Vec<Word>,
+    /// Words already in the vocabulary, which are left as they were
+    pub skipped: Vec<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct WordRow {
+    id: String,
+    chinese: String,
+    pinyin: Option<String>,
+    definition: String,
+    difficulty: Difficulty,
+    added_at: NaiveDateTime,
+    last_reviewed_at: Option<NaiveDateTime>,
+    next_review_at: Option<NaiveDateTime>,
+    strength: i64,
+    correct_attempts: i64,
+    total_attempts: i64,
+    speaking_completed: bool,
+    speaking_last_score: Option<f64>,
+    speaking_attempts: i64,
+    reading_completed: bool,
+    reading_times_encountered: i64,
+    writing_completed: bool,
+    writing_last_score: Option<f64>,
+    writing_attempts: i64,
+}
+
+impl From<WordRow> for Word {
+    fn from(row: WordRow) -> Self {
+        Self {
+            id: row.id,
+            chinese: row.chinese,
+            pinyin: row.pinyin,
+            definition: row.definition,
+            difficulty: row.difficulty,
+            added_date: row.added_at.and_utc(),
+            last_reviewed: row.last_reviewed_at.map(|at| at.and_utc()),
+            next_review: row.next_review_at.map(|at| at.and_utc()),
+            strength: row.strength,
+            correct_attempts: row.correct_attempts,
+            total_attempts: row.total_attempts,
+            tasks: WordTasks {
+                speaking: ScoredTask {
+                    completed: row.speaking_completed,
+                    last_score: row.speaking_last_score,
+                    attempts: row.speaking_attempts,
+                },
+                reading: ReadingTask {
+                    completed: row.reading_completed,
+                    times_encountered: row.reading_times_encountered,
+                },
+                writing: ScoredTask {
+                    completed: row.writing_completed,
+                    last_score: row.writing_last_score,
+                    attempts: row.writing_attempts,
+                },
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct VocabularyService {
+    db: Database,
+}
+
+impl VocabularyService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// A page of the user's words, newest first, with the number matching the filter.
+    pub async fn list_words(
+        &self,
+        user_id: &str,
+        filter: &WordFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<Word>, i64)> {
+        let due_by = filter.due_by.map(|at| at.naive_utc());
+        let rows = sqlx::query_as!(
+            WordRow,
+            r#"
+            SELECT id as "id!", chinese, pinyin, definition,
+                   difficulty as "difficulty: Difficulty", added_at, last_reviewed_at,
+                   next_review_at, strength, correct_attempts, total_attempts,
+                   speaking_completed, speaking_last_score, speaking_attempts,
+                   reading_completed, reading_times_encountered,
+                   writing_completed, writing_last_score, writing_attempts
+            FROM user_words
+            WHERE user_id = ?1
+              AND (?2 IS NULL OR difficulty = ?2)
+              AND (?3 IS NULL OR next_review_at IS NULL OR next_review_at <= ?3)
+            ORDER BY added_at DESC, id
+            LIMIT ?4 OFFSET ?5
+            "#,
+            user_id,
+            filter.difficulty,
+            due_by,
+            limit,
+            offset
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*)
+            FROM user_words
+            WHERE user_id = ?1
+              AND (?2 IS NULL OR difficulty = ?2)
+              AND (?3 IS NULL OR next_review_at IS NULL OR next_review_at <= ?3)
+            "#,
+            user_id,
+            filter.difficulty,
+            due_by
+        )
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok((rows.into_iter().map(Word::from).collect(), total as i64))
+    }
+
+    /// One of the user's words; `None` if it is not theirs.
+    pub async fn get_word(&self, user_id: &str, word_id: &str) -> Result<Option<Word>> {
+        let row = sqlx::query_as!(
+            WordRow,
+            r#"
+            SELECT id as "id!", chinese, pinyin, definition,
+                   difficulty as "difficulty: Difficulty", added_at, last_reviewed_at,
+                   next_review_at, strength, correct_attempts, total_attempts,
+                   speaking_completed, speaking_last_score, speaking_attempts,
+                   reading_completed, reading_times_encountered,
+                   writing_completed, writing_last_score, writing_attempts
+            FROM user_words
+            WHERE id = ?1 AND user_id = ?2
+            "#,
+            word_id,
+            user_id
+        )
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(row.map(Word::from))
+    }
+
+    /// Add a word, or fail with a conflict if the user already has it.
+    pub async fn add_word(&self, user_id: &str, word: NewWord) -> Result<Word> {
+        let chinese = word.chinese.trim();
+        if chinese.is_empty() {
+            return Err(AppError::BadRequest("Word is empty".to_string()));
+        }
+        let definition = word.definition.trim();
+        if definition.is_empty() {
+            return Err(AppError::BadRequest("Definition is empty".to_string()));
+        }
+        let pinyin = word.pinyin.as_deref().map(marked_pinyin).transpose()?;
+
+        let inserted = insert(
+            &mut *self.db.pool().acquire().await?,
+            user_id,
+            chinese,
+            pinyin.as_deref(),
+            definition,
+            word.difficulty,
+        )
+        .await?;
+        match inserted {
+            Some(id) => self.expect_word(user_id, &id).await,
+            None => Err(AppError::Conflict(format!(
+                "'{}' is already in your vocabulary",
+                chinese
+            ))),
+        }
+    }
+
+    /// Add dictionary entries as words, by their simplified form, passing over
+    /// any the user already has. Either every new word is added or none is.
+    pub async fn add_entries(
+        &self,
+        user_id: &str,
+        entries: &[DictionaryEntry],
+        difficulty: Difficulty,
+    ) -> Result<BulkAddition> {
+        let mut ids = Vec::new();
+        let mut skipped = Vec::new();
+        let mut tx = self.db.pool().begin().await?;
+        for entry in entries {
+            let definition = entry
+                .definitions
+                .iter()
+                .map(|definition| definition.meaning.as_str())
+                .collect::<Vec<_>>()
+                .join("; ");
+            let inserted = insert(
+                &mut tx,
+                user_id,
+                &entry.simplified,
+                Some(&entry.pinyin_marked),
+                &definition,
+                difficulty,
+            )
+            .await?;
+            match inserted {
+                Some(id) => ids.push(id),
+                None => skipped.push(entry.simplified.clone()),
+            }
+        }
+        tx.commit().await?;
+
+        let mut added = Vec::with_capacity(ids.len());
+        for id in ids {
+            added.push(self.expect_word(user_id, &id).await?);
+        }
+        Ok(BulkAddition { added, skipped })
+    }
+
+    /// Apply an update to one of the user's words; `None` if it is not theirs.
+    pub async fn update_word(
+        &self,
+        user_id: &str,
+        word_id: &str,
+        update: WordUpdate,
+    ) -> Result<Option<Word>> {
+        let Some(current) = self.get_word(user_id, word_id).await? else {
+            return Ok(None);
+        };
+
+        let pinyin = match update.pinyin.as_deref() {
+            Some(pinyin) => Some(marked_pinyin(pinyin)?),
+            None => current.pinyin,
+        };
+        let definition = match update.definition {
+            Some(definition) if definition.trim().is_empty() => {
+                return Err(AppError::BadRequest("Definition is empty".to_string()))
+            }
+            Some(definition) => definition.trim().to_string(),
+            None => current.definition,
+        };
+        let difficulty = update.difficulty.unwrap_or(current.difficulty);
+        let strength = update.strength.unwrap_or(current.strength);
+        if !(0..=100).contains(&strength) {
+            return Err(AppError::BadRequest(
+                "Strength must be between 0 and 100".to_string(),
+            ));
+        }
+        let correct_attempts = update.correct_attempts.unwrap_or(current.correct_attempts);
+        let total_attempts = update.total_attempts.unwrap_or(current.total_attempts);
+        if correct_attempts < 0 || correct_attempts > total_attempts {
+            return Err(AppError::BadRequest(
+                "Correct attempts must be between 0 and total attempts".to_string(),
+            ));
+        }
+        let last_reviewed_at = update
+            .last_reviewed
+            .unwrap_or(current.last_reviewed)
+            .map(|at| at.naive_utc());
+        let next_review_at = update
+            .next_review
+            .unwrap_or(current.next_review)
+            .map(|at| at.naive_utc());
+        let tasks = update.tasks.unwrap_or(current.tasks);
+        if [
+            tasks.speaking.attempts,
+            tasks.reading.times_encountered,
+            tasks.writing.attempts,
+        ]
+        .iter()
+        .any(|&count| count < 0)
+        {
+            return Err(AppError::BadRequest(
+                "Task counts cannot be negative".to_string(),
+            ));
+        }
+        let now = Utc::now().naive_utc();
+
+        sqlx::query!(
+            r#"
+            UPDATE user_words
+            SET pinyin = ?3, definition = ?4, difficulty = ?5, strength = ?6,
+                correct_attempts = ?7, total_attempts = ?8, last_reviewed_at = ?9,
+                next_review_at = ?10, speaking_completed = ?11, speaking_last_score = ?12,
+                speaking_attempts = ?13, reading_completed = ?14, reading_times_encountered = ?15,
+                writing_completed = ?16, writing_last_score = ?17, writing_attempts = ?18,
+                updated_at = ?19
+            WHERE id = ?1 AND user_id = ?2
+            "#,
+            word_id,
+            user_id,
+            pinyin,
+            definition,
+            difficulty,
+            strength,
+            correct_attempts,
+            total_attempts,
+            last_reviewed_at,
+            next_review_at,
+            tasks.speaking.completed,
+            tasks.speaking.last_score,
+            tasks.speaking.attempts,
+            tasks.reading.completed,
+            tasks.reading.times_encountered,
+            tasks.writing.completed,
+            tasks.writing.last_score,
+            tasks.writing.attempts,
+            now
+        )
+        .execute(self.db.pool())
+        .await?;
+
+        self.get_word(user_id, word_id).await
+    }
+
+    /// Remove one of the user's words; `false` if it is not theirs.
+    pub async fn delete_word(&self, user_id: &str, word_id: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM user_words WHERE id = ?1 AND user_id = ?2",
+            word_id,
+            user_id
+        )
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn expect_word(&self, user_id: &str, word_id: &str) -> Result<Word> {
+        self.get_word(user_id, word_id)
+            .await?
+            .ok_or_else(|| AppError::InternalServerError("Stored word went missing".to_string()))
+    }
+}
+
+/// Insert a fresh word, returning its id, or `None` if the user already has it.
+async fn insert(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    chinese: &str,
+    pinyin: Option<&str>,
+    definition: &str,
+    difficulty: Difficulty,
+) -> Result<Option<String>> {
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().naive_utc();
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO user_words (id, user_id, chinese, pinyin, definition, difficulty,
+                                added_at, updated_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+        ON CONFLICT (user_id, chinese) DO NOTHING
+        "#,
+        id,
+        user_id,
+        chinese,
+        pinyin,
+        definition,
+        difficulty,
+        now
+    )
+    .execute(conn)
+    .await?;
+
+    Ok((result.rows_affected() > 0).then_some(id))
+}
+
+/// For optional fields that can also be cleared: a field that is present,
+/// even as `null`, is `Some`, while a missing one is left to `#[serde(default)]`.
+fn present<'de, T, D>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Validate pinyin in any style and bring it to the tone-marked form the app shows.
+fn marked_pinyin(pinyin: &str) -> Result<String> {
+    parse::to_marked(pinyin).map_err(|e| AppError::BadRequest(format!("Invalid pinyin: {}", e)))
+}
@@ -0,0 +1,619 @@
+//! The review queue, grading, and each learner's scheduling settings.
+//!
+//! A word is a card. Grading one runs it through the learner's chosen
+//! [`Algorithm`], stores its new memory and due date on the word, updates the
+//! word's `strength` and attempt counts, and appends to `review_logs`. Reviews
+//! come from the learner grading themselves or from speaking the word in a
+//! speech evaluation, which only reviews words that are due and new words the
+//! day's allowance has room for. The daily new-card limit counts first reviews
+//! since midnight UTC.
+
+use chrono::{
+    DateTime,
+    Duration,
+    NaiveDateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    scheduler::{
+        self,
+        Algorithm,
+        CardState,
+        Grade,
+        Memory,
+        SchedulerSettings,
+    },
+    speech,
+    Word,
+    WordRow,
+};
+use crate::{
+    db::Database,
+    error::{
+        AppError,
+        Result,
+    },
+    speech::iflytek::WordScore,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReviewSettings {
+    pub algorithm: Algorithm,
+    /// Chance of recall to schedule reviews for, 0.7 to 0.99 (FSRS only)
+    pub desired_retention: f64,
+    /// Unseen words introduced per day
+    pub new_cards_per_day: i64,
+    pub maximum_interval_days: i64,
+}
+
+impl Default for ReviewSettings {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::Fsrs,
+            desired_retention: 0.9,
+            new_cards_per_day: 20,
+            maximum_interval_days: 36_500,
+        }
+    }
+}
+
+impl ReviewSettings {
+    fn scheduler(&self) -> SchedulerSettings {
+        SchedulerSettings {
+            algorithm: self.algorithm,
+            desired_retention: self.desired_retention,
+            maximum_interval_days: self.maximum_interval_days,
+        }
+    }
+}
+
+/// Changes to review settings; fields left out keep their value.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReviewSettingsUpdate {
+    pub algorithm: Option<Algorithm>,
+    pub desired_retention: Option<f64>,
+    pub new_cards_per_day: Option<i64>,
+    pub maximum_interval_days: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewQueue {
+    /// Words due for review, most overdue first, then new words oldest first
+    pub words: Vec<Word>,
+    /// All reviews due, including any beyond the queue's limit
+    pub due_count: i64,
+    /// New words in this queue
+    pub new_count: i64,
+    /// New words that may still be introduced today
+    pub new_remaining_today: i64,
+}
+
+/// Where a review's grade came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum ReviewSource {
+    /// Graded by the learner
+    Manual,
+    /// Graded from a speech evaluation's scores
+    Speech,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ReviewLog {
+    pub id: i64,
+    pub word_id: String,
+    pub reviewed_at: NaiveDateTime,
+    pub grade: Grade,
+    /// State before the review
+    pub state: CardState,
+    pub algorithm: Algorithm,
+    pub elapsed_days: Option<f64>,
+    pub interval_days: i64,
+    pub stability: Option<f64>,
+    pub difficulty: Option<f64>,
+    pub source: ReviewSource,
+    /// The evaluation a speech review was graded from, while it is kept
+    pub speech_attempt_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GradedReview {
+    pub word: Word,
+    pub log: ReviewLog,
+}
+
+/// A word reviewed by speaking it, as reported back with the evaluation.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechReview {
+    pub word_id: String,
+    pub chinese: String,
+    pub grade: Grade,
+    /// Mean overall score of the word's characters; `None` if it was not read
+    pub score: Option<f64>,
+    pub omitted: bool,
+    pub next_review: DateTime<Utc>,
+    pub strength: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct MemoryRow {
+    review_state: CardState,
+    stability: Option<f64>,
+    review_difficulty: Option<f64>,
+    ease_factor: f64,
+    repetitions: i64,
+    lapses: i64,
+    interval_days: i64,
+    last_reviewed_at: Option<NaiveDateTime>,
+}
+
+impl From<MemoryRow> for Memory {
+    fn from(row: MemoryRow) -> Self {
+        Self {
+            state: row.review_state,
+            stability: row.stability,
+            difficulty: row.review_difficulty,
+            ease_factor: row.ease_factor,
+            repetitions: row.repetitions,
+            lapses: row.lapses,
+            interval_days: row.interval_days,
+            last_review: row.last_reviewed_at.map(|at| at.and_utc()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ReviewService {
+    db: Database,
+}
+
+impl ReviewService {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// The user's settings, or the defaults if they never changed them.
+    pub async fn settings(&self, user_id: &str) -> Result<ReviewSettings> {
+        let settings = sqlx::query_as!(
+            ReviewSettings,
+            r#"
+            SELECT algorithm as "algorithm: Algorithm", desired_retention,
+                   new_cards_per_day, maximum_interval_days
+            FROM review_settings
+            WHERE user_id = ?1
+            "#,
+            user_id
+        )
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(settings.unwrap_or_default())
+    }
+
+    pub async fn update_settings(
+        &self,
+        user_id: &str,
+        update: ReviewSettingsUpdate,
+    ) -> Result<ReviewSettings> {
+        let current = self.settings(user_id).await?;
+        let settings = ReviewSettings {
+            algorithm: update.algorithm.unwrap_or(current.algorithm),
+            desired_retention: update
+                .desired_retention
+                .unwrap_or(current.desired_retention),
+            new_cards_per_day: update
+                .new_cards_per_day
+                .unwrap_or(current.new_cards_per_day),
+            maximum_interval_days: update
+                .maximum_interval_days
+                .unwrap_or(current.maximum_interval_days),
+        };
+        if !(0.7..=0.99).contains(&settings.desired_retention) {
+            return Err(AppError::BadRequest(
+                "Desired retention must be between 0.7 and 0.99".to_string(),
+            ));
+        }
+        if settings.new_cards_per_day < 0 {
+            return Err(AppError::BadRequest(
+                "New cards per day cannot be negative".to_string(),
+            ));
+        }
+        if settings.maximum_interval_days < 1 {
+            return Err(AppError::BadRequest(
+                "Maximum interval must be at least one day".to_string(),
+            ));
+        }
+
+        let now = Utc::now().naive_utc();
+        sqlx::query!(
+            r#"
+            INSERT INTO review_settings (user_id, algorithm, desired_retention, new_cards_per_day,
+                                         maximum_interval_days, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT (user_id) DO UPDATE SET
+                algorithm = excluded.algorithm,
+                desired_retention = excluded.desired_retention,
+                new_cards_per_day = excluded.new_cards_per_day,
+                maximum_interval_days = excluded.maximum_interval_days,
+                updated_at = excluded.updated_at
+            "#,
+            user_id,
+            settings.algorithm,
+            settings.desired_retention,
+            settings.new_cards_per_day,
+            settings.maximum_interval_days,
+            now
+        )
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(settings)
+    }
+
+    /// Up to `limit` words to review at `now`: those due, then as many new
+    /// words as today's allowance leaves room for.
+    pub async fn queue(
+        &self,
+        user_id: &str,
+        now: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<ReviewQueue> {
+        let settings = self.settings(user_id).await?;
+        let now_naive = now.naive_utc();
+
+        let due = sqlx::query_as!(
+            WordRow,
+            r#"
+            SELECT id as "id!", chinese, pinyin, definition,
+                   difficulty as "difficulty: _", added_at, last_reviewed_at,
+                   next_review_at, strength, correct_attempts, total_attempts,
+                   speaking_completed, speaking_last_score, speaking_attempts,
+                   reading_completed, reading_times_encountered,
+                   writing_completed, writing_last_score, writing_attempts
+            FROM user_words
+            WHERE user_id = ?1 AND review_state != 'new' AND next_review_at <= ?2
+            ORDER BY next_review_at, id
+            LIMIT ?3
+            "#,
+            user_id,
+            now_naive,
+            limit
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let due_count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*)
+            FROM user_words
+            WHERE user_id = ?1 AND review_state != 'new' AND next_review_at <= ?2
+            "#,
+            user_id,
+            now_naive
+        )
+        .fetch_one(self.db.pool())
+        .await? as i64;
+
+        let new_remaining_today = self.new_remaining_today(user_id, now, &settings).await?;
+
+        let new_limit = new_remaining_today.min(limit - due.len() as i64);
+        let new = sqlx::query_as!(
+            WordRow,
+            r#"
+            SELECT id as "id!", chinese, pinyin, definition,
+                   difficulty as "difficulty: _", added_at, last_reviewed_at,
+                   next_review_at, strength, correct_attempts, total_attempts,
+                   speaking_completed, speaking_last_score, speaking_attempts,
+                   reading_completed, reading_times_encountered,
+                   writing_completed, writing_last_score, writing_attempts
+            FROM user_words
+            WHERE user_id = ?1 AND review_state = 'new'
+            ORDER BY added_at, id
+            LIMIT ?2
+            "#,
+            user_id,
+            new_limit
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(ReviewQueue {
+            new_count: new.len() as i64,
+            words: due.into_iter().chain(new).map(Word::from).collect(),
+            due_count,
+            new_remaining_today,
+        })
+    }
+
+    /// New words that may still be introduced on `now`'s day.
+    async fn new_remaining_today(
+        &self,
+        user_id: &str,
+        now: DateTime<Utc>,
+        settings: &ReviewSettings,
+    ) -> Result<i64> {
+        let midnight = now
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or(now.naive_utc());
+        let introduced_today = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*)
+            FROM review_logs
+            WHERE user_id = ?1 AND state = 'new' AND reviewed_at >= ?2
+            "#,
+            user_id,
+            midnight
+        )
+        .fetch_one(self.db.pool())
+        .await? as i64;
+        Ok((settings.new_cards_per_day - introduced_today).max(0))
+    }
+
+    /// Grade a review of one of the user's words at `now` and schedule its
+    /// next one; `None` if the word is not theirs.
+    pub async fn grade(
+        &self,
+        user_id: &str,
+        word_id: &str,
+        grade: Grade,
+        now: DateTime<Utc>,
+    ) -> Result<Option<GradedReview>> {
+        let settings = self.settings(user_id).await?;
+        self.record(user_id, word_id, grade, now, &settings, None)
+            .await
+    }
+
+    /// Review the words of the user's vocabulary read in a speech evaluation,
+    /// grading each from its scores; see [`speech`]. Like the review queue,
+    /// only words due by `now` are reviewed, and new words only while today's
+    /// allowance lasts, so rereading a sentence does not keep pushing its words
+    /// out. Each reviewed word's speaking task is updated with its score as well.
+    pub async fn grade_speech(
+        &self,
+        user_id: &str,
+        speech_attempt_id: Option<&str>,
+        ref_text: &str,
+        words: &[WordScore],
+        now: DateTime<Utc>,
+    ) -> Result<Vec<SpeechReview>> {
+        let cards = sqlx::query!(
+            r#"
+            SELECT id as "id!", chinese, review_state as "review_state: CardState",
+                   next_review_at
+            FROM user_words
+            WHERE user_id = ?1
+            "#,
+            user_id
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+        // Words that are not due still take part in matching, so that a longer
+        // word read keeps covering the shorter ones inside it
+        let vocabulary: Vec<(String, String)> = cards
+            .iter()
+            .map(|card| (card.id.clone(), card.chinese.clone()))
+            .collect();
+        let spoken_words = speech::grade_spoken_words(ref_text, words, &vocabulary);
+        if spoken_words.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let settings = self.settings(user_id).await?;
+        let mut new_remaining = self.new_remaining_today(user_id, now, &settings).await?;
+        let now_naive = now.naive_utc();
+        let mut reviews = Vec::new();
+        for spoken in spoken_words {
+            let Some(card) = cards.iter().find(|card| card.id == spoken.word_id) else {
+                continue;
+            };
+            if card.review_state == CardState::New {
+                if new_remaining == 0 {
+                    continue;
+                }
+                new_remaining -= 1;
+            } else if !card.next_review_at.is_some_and(|at| at <= now_naive) {
+                continue;
+            }
+
+            let Some(graded) = self
+                .record(
+                    user_id,
+                    &spoken.word_id,
+                    spoken.grade,
+                    now,
+                    &settings,
+                    Some(speech_attempt_id),
+                )
+                .await?
+            else {
+                continue;
+            };
+
+            let passed = spoken.grade.is_pass();
+            sqlx::query!(
+                r#"
+                UPDATE user_words
+                SET speaking_attempts = speaking_attempts + 1,
+                    speaking_last_score = COALESCE(?3, speaking_last_score),
+                    speaking_completed = speaking_completed OR ?4
+                WHERE id = ?1 AND user_id = ?2
+                "#,
+                spoken.word_id,
+                user_id,
+                spoken.score,
+                passed
+            )
+            .execute(self.db.pool())
+            .await?;
+
+            reviews.push(SpeechReview {
+                word_id: spoken.word_id,
+                chinese: spoken.chinese,
+                grade: spoken.grade,
+                score: spoken.score,
+                omitted: spoken.omitted,
+                next_review: graded.word.next_review.unwrap_or(now),
+                strength: graded.word.strength,
+            });
+        }
+
+        Ok(reviews)
+    }
+
+    /// Store a graded review; `speech` is set, with the evaluation's attempt
+    /// if it was saved, for reviews graded from speech.
+    async fn record(
+        &self,
+        user_id: &str,
+        word_id: &str,
+        grade: Grade,
+        now: DateTime<Utc>,
+        settings: &ReviewSettings,
+        speech: Option<Option<&str>>,
+    ) -> Result<Option<GradedReview>> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let Some(memory) = sqlx::query_as!(
+            MemoryRow,
+            r#"
+            SELECT review_state as "review_state: CardState", stability, review_difficulty,
+                   ease_factor, repetitions, lapses, interval_days, last_reviewed_at
+            FROM user_words
+            WHERE id = ?1 AND user_id = ?2
+            "#,
+            word_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .map(Memory::from) else {
+            return Ok(None);
+        };
+
+        let next = memory.review(grade, now, &settings.scheduler());
+        let now_naive = now.naive_utc();
+        let next_review_at = (now + Duration::days(next.interval_days)).naive_utc();
+        let strength = scheduler::strength(next.interval_days);
+        let correct = i64::from(grade.is_pass());
+        sqlx::query!(
+            r#"
+            UPDATE user_words
+            SET review_state = ?3, stability = ?4, review_difficulty = ?5, ease_factor = ?6,
+                repetitions = ?7, lapses = ?8, interval_days = ?9, last_reviewed_at = ?10,
+                next_review_at = ?11, strength = ?12, correct_attempts = correct_attempts + ?13,
+                total_attempts = total_attempts + 1, updated_at = ?10
+            WHERE id = ?1 AND user_id = ?2
+            "#,
+            word_id,
+            user_id,
+            next.state,
+            next.stability,
+            next.difficulty,
+            next.ease_factor,
+            next.repetitions,
+            next.lapses,
+            next.interval_days,
+            now_naive,
+            next_review_at,
+            strength,
+            correct
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let elapsed_days = memory.elapsed_days(now);
+        let source = match speech {
+            Some(_) => ReviewSource::Speech,
+            None => ReviewSource::Manual,
+        };
+        let speech_attempt_id = speech.flatten().map(str::to_string);
+        let log_id = sqlx::query!(
+            r#"
+            INSERT INTO review_logs (user_id, word_id, reviewed_at, grade, state, algorithm,
+                                     elapsed_days, interval_days, stability, difficulty, source,
+                                     speech_attempt_id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            "#,
+            user_id,
+            word_id,
+            now_naive,
+            grade,
+            memory.state,
+            settings.algorithm,
+            elapsed_days,
+            next.interval_days,
+            next.stability,
+            next.difficulty,
+            source,
+            speech_attempt_id
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        let word = sqlx::query_as!(
+            WordRow,
+            r#"
+            SELECT id as "id!", chinese, pinyin, definition,
+                   difficulty as "difficulty: _", added_at, last_reviewed_at,
+                   next_review_at, strength, correct_attempts, total_attempts,
+                   speaking_completed, speaking_last_score, speaking_attempts,
+                   reading_completed, reading_times_encountered,
+                   writing_completed, writing_last_score, writing_attempts
+            FROM user_words
+            WHERE id = ?1
+            "#,
+            word_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(Some(GradedReview {
+            word: word.into(),
+            log: ReviewLog {
+                id: log_id,
+                word_id: word_id.to_string(),
+                reviewed_at: now_naive,
+                grade,
+                state: memory.state,
+                algorithm: settings.algorithm,
+                elapsed_days,
+                interval_days: next.interval_days,
+                stability: next.stability,
+                difficulty: next.difficulty,
+                source,
+                speech_attempt_id,
+            },
+        }))
+    }
+
+    /// The user's reviews of one word, oldest first.
+    pub async fn history(&self, user_id: &str, word_id: &str) -> Result<Vec<ReviewLog>> {
+        let logs = sqlx::query_as!(
+            ReviewLog,
+            r#"
+            SELECT id as "id!", word_id, reviewed_at, grade as "grade: Grade",
+                   state as "state: CardState", algorithm as "algorithm: Algorithm",
+                   elapsed_days, interval_days, stability, difficulty,
+                   source as "source: ReviewSource", speech_attempt_id
+            FROM review_logs
+            WHERE user_id = ?1 AND word_id = ?2
+            ORDER BY reviewed_at, id
+            "#,
+            user_id,
+            word_id
+        )
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(logs)
+    }
+}
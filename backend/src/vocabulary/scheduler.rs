@@ -0,0 +1,250 @@
+//! Spaced-repetition scheduling.
+//!
+//! FSRS (version 4.5, with its published default weights) tracks each card's
+//! memory as a stability — the days until the chance of recall falls to 90% —
+//! and a difficulty from 1 to 10, and schedules the next review for when
+//! recall is predicted to fall to the learner's desired retention. SM-2 is kept
+//! as a simpler fallback; it grows intervals by a per-card ease factor and has
+//! no notion of desired retention.
+//!
+//! Both algorithms work in whole days and keep the shared fields of a card's
+//! [`Memory`] up to date, so a learner can switch between them mid-course.
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// FSRS-4.5 default weights.
+const W: [f64; 17] = [
+    0.4872, 1.4003, 3.7145, 13.8206, 5.1618, 1.2298, 0.8975, 0.031, 1.6474, 0.1367, 1.0461, 2.1072,
+    0.0793, 0.3246, 1.587, 0.2272, 2.8755,
+];
+/// Shape of the FSRS forgetting curve; `FACTOR` makes recall 90% after one stability.
+const DECAY: f64 = -0.5;
+const FACTOR: f64 = 19.0 / 81.0;
+
+const INITIAL_EASE: f64 = 2.5;
+const MINIMUM_EASE: f64 = 1.3;
+
+/// Interval, in days, at which a card counts as fully known.
+const STRONG_INTERVAL_DAYS: f64 = 365.0;
+
+/// How well a card was recalled; stored as its number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[repr(i64)]
+pub enum Grade {
+    /// Forgotten
+    Again = 1,
+    /// Recalled with serious difficulty
+    Hard = 2,
+    Good = 3,
+    /// Recalled effortlessly
+    Easy = 4,
+}
+
+impl Grade {
+    pub fn value(self) -> i64 {
+        self as i64
+    }
+
+    /// Whether the card was recalled at all.
+    pub fn is_pass(self) -> bool {
+        self != Self::Again
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum CardState {
+    /// Never reviewed
+    #[default]
+    New,
+    Review,
+    /// Forgotten at its last review
+    Relearning,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Algorithm {
+    #[default]
+    Fsrs,
+    Sm2,
+}
+
+/// What a scheduler knows about one card.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Memory {
+    pub state: CardState,
+    /// FSRS stability in days
+    pub stability: Option<f64>,
+    /// FSRS difficulty, 1 to 10
+    pub difficulty: Option<f64>,
+    /// SM-2 ease factor
+    pub ease_factor: f64,
+    /// Successful reviews in a row
+    pub repetitions: i64,
+    /// Times the card was forgotten after being learned
+    pub lapses: i64,
+    pub interval_days: i64,
+    pub last_review: Option<DateTime<Utc>>,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self {
+            state: CardState::New,
+            stability: None,
+            difficulty: None,
+            ease_factor: INITIAL_EASE,
+            repetitions: 0,
+            lapses: 0,
+            interval_days: 0,
+            last_review: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SchedulerSettings {
+    pub algorithm: Algorithm,
+    /// Chance of recall to schedule for, used by FSRS
+    pub desired_retention: f64,
+    pub maximum_interval_days: i64,
+}
+
+impl Memory {
+    /// Days since the last review, if there was one.
+    pub fn elapsed_days(&self, now: DateTime<Utc>) -> Option<f64> {
+        self.last_review
+            .map(|last| ((now - last).num_seconds() as f64 / 86_400.0).max(0.0))
+    }
+
+    /// The memory after a review graded `grade` at `now`.
+    pub fn review(&self, grade: Grade, now: DateTime<Utc>, settings: &SchedulerSettings) -> Self {
+        let mut next = match settings.algorithm {
+            Algorithm::Fsrs => self.review_fsrs(grade, now, settings.desired_retention),
+            Algorithm::Sm2 => self.review_sm2(grade),
+        };
+        next.interval_days = next
+            .interval_days
+            .clamp(1, settings.maximum_interval_days.max(1));
+        next.last_review = Some(now);
+        if grade.is_pass() {
+            next.state = CardState::Review;
+            next.repetitions = self.repetitions + 1;
+        } else {
+            if self.state != CardState::New {
+                next.lapses = self.lapses + 1;
+            }
+            next.state = CardState::Relearning;
+            next.repetitions = 0;
+        }
+        next
+    }
+
+    fn review_fsrs(&self, grade: Grade, now: DateTime<Utc>, desired_retention: f64) -> Self {
+        let g = grade.value() as f64;
+        let (stability, difficulty) = match (self.stability, self.difficulty) {
+            (Some(stability), Some(difficulty)) => {
+                let elapsed = self.elapsed_days(now).unwrap_or(0.0);
+                let recall = retrievability(elapsed, stability);
+                let stability = if grade.is_pass() {
+                    recall_stability(difficulty, stability, recall, grade)
+                } else {
+                    forget_stability(difficulty, stability, recall)
+                };
+                (stability, next_difficulty(difficulty, g))
+            }
+            // First review, or the card was scheduled by SM-2 until now
+            _ => (W[grade.value() as usize - 1], initial_difficulty(g)),
+        };
+
+        let interval = stability / FACTOR * (desired_retention.powf(1.0 / DECAY) - 1.0);
+        Self {
+            stability: Some(stability),
+            difficulty: Some(difficulty),
+            interval_days: interval.round() as i64,
+            ..self.clone()
+        }
+    }
+
+    fn review_sm2(&self, grade: Grade) -> Self {
+        // SM-2 answer quality, 0 to 5
+        let quality = match grade {
+            Grade::Again => 1.0,
+            Grade::Hard => 3.0,
+            Grade::Good => 4.0,
+            Grade::Easy => 5.0,
+        };
+        let ease_factor = (self.ease_factor
+            + (0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02)))
+            .max(MINIMUM_EASE);
+        let interval_days = if !grade.is_pass() {
+            1
+        } else {
+            match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval_days.max(1) as f64 * ease_factor).round() as i64,
+            }
+        };
+        Self {
+            ease_factor,
+            interval_days,
+            ..self.clone()
+        }
+    }
+}
+
+/// FSRS's chance of recalling a card `elapsed_days` after its last review.
+pub fn retrievability(elapsed_days: f64, stability: f64) -> f64 {
+    (1.0 + FACTOR * elapsed_days / stability).powf(DECAY)
+}
+
+/// How well a card is known, 0 to 100, from its interval: a card reviewed a
+/// year apart is fully known.
+pub fn strength(interval_days: i64) -> i64 {
+    let strength = (interval_days.max(0) as f64).ln_1p() / STRONG_INTERVAL_DAYS.ln_1p();
+    (strength * 100.0).round().min(100.0) as i64
+}
+
+fn initial_difficulty(g: f64) -> f64 {
+    (W[4] - (g - 3.0) * W[5]).clamp(1.0, 10.0)
+}
+
+fn next_difficulty(difficulty: f64, g: f64) -> f64 {
+    let difficulty = difficulty - W[6] * (g - 3.0);
+    // Mean reversion towards the difficulty of a card first graded Good
+    (W[7] * initial_difficulty(3.0) + (1.0 - W[7]) * difficulty).clamp(1.0, 10.0)
+}
+
+fn recall_stability(difficulty: f64, stability: f64, recall: f64, grade: Grade) -> f64 {
+    let hard_penalty = if grade == Grade::Hard { W[15] } else { 1.0 };
+    let easy_bonus = if grade == Grade::Easy { W[16] } else { 1.0 };
+    stability
+        * (1.0
+            + W[8].exp()
+                * (11.0 - difficulty)
+                * stability.powf(-W[9])
+                * ((W[10] * (1.0 - recall)).exp() - 1.0)
+                * hard_penalty
+                * easy_bonus)
+}
+
+fn forget_stability(difficulty: f64, stability: f64, recall: f64) -> f64 {
+    let stability_after = W[11]
+        * difficulty.powf(-W[12])
+        * ((stability + 1.0).powf(W[13]) - 1.0)
+        * (W[14] * (1.0 - recall)).exp();
+    // Forgetting never leaves a card more stable than before
+    stability_after.min(stability)
+}
@@ -0,0 +1,152 @@
+//! Review grades read off speech evaluations.
+//!
+//! Every word of the learner's vocabulary found in the reference text counts as
+//! a review of that word. The text is split into words with the bundled
+//! [`Segmenter`], and a vocabulary word is only found where it spans whole
+//! words, so a card for 生 is not reviewed by reading 学生. The provider's
+//! overall scores for its characters are averaged and mapped to a grade; a word
+//! with any character left unread (`read_type` 2) is graded [`Grade::Again`].
+//! Inserted words (`read_type` 1) were never in the reference text and are
+//! ignored. Where vocabulary words overlap the longest wins, and a word read
+//! several times is graded by its worst reading. Which of the words found are
+//! due for a review is left to the caller.
+
+use std::collections::HashSet;
+
+use super::scheduler::Grade;
+use crate::{
+    pinyin::is_hanzi,
+    speech::iflytek::WordScore,
+    text::Segmenter,
+};
+
+/// Scores below this are forgotten words.
+const AGAIN_BELOW: f64 = 60.0;
+const HARD_BELOW: f64 = 75.0;
+/// Scores from this up are effortless.
+const EASY_FROM: f64 = 90.0;
+
+/// A vocabulary word heard in a recording, and the grade it earned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpokenWord {
+    pub word_id: String,
+    pub chinese: String,
+    /// Mean overall score of the characters read; `None` if none were
+    pub score: Option<f64>,
+    /// Whether any character of the word was left out
+    pub omitted: bool,
+    pub grade: Grade,
+}
+
+/// The review grade for a word's overall speech score, 0 to 100.
+pub fn grade_for_score(score: f64) -> Grade {
+    if score < AGAIN_BELOW {
+        Grade::Again
+    } else if score < HARD_BELOW {
+        Grade::Hard
+    } else if score < EASY_FROM {
+        Grade::Good
+    } else {
+        Grade::Easy
+    }
+}
+
+/// Grade the `(id, hanzi)` vocabulary words found in an evaluation's word
+/// scores, in the order they were first read.
+pub fn grade_spoken_words(
+    ref_text: &str,
+    scores: &[WordScore],
+    vocabulary: &[(String, String)],
+) -> Vec<SpokenWord> {
+    // Each character of the reference text with its word's score and whether it was omitted
+    let spoken: Vec<(char, f32, bool)> = scores
+        .iter()
+        .filter(|score| score.read_type != 1)
+        .flat_map(|score| {
+            score
+                .word
+                .chars()
+                .filter(|&c| is_hanzi(c))
+                .map(move |c| (c, score.scores.overall, score.read_type == 2))
+        })
+        .collect();
+    let boundaries = word_boundaries(ref_text, &spoken);
+
+    let mut vocabulary: Vec<(&str, Vec<char>)> = vocabulary
+        .iter()
+        .map(|(id, chinese)| (id.as_str(), chinese.chars().collect()))
+        .filter(|(_, chars): &(&str, Vec<char>)| !chars.is_empty())
+        .collect();
+    vocabulary.sort_by_key(|(_, chars)| std::cmp::Reverse(chars.len()));
+
+    let mut graded: Vec<SpokenWord> = Vec::new();
+    let mut i = 0;
+    while i < spoken.len() {
+        if !boundaries.contains(&i) {
+            i += 1;
+            continue;
+        }
+        let found = vocabulary.iter().find(|(_, chars)| {
+            spoken[i..].len() >= chars.len()
+                && boundaries.contains(&(i + chars.len()))
+                && spoken[i..i + chars.len()]
+                    .iter()
+                    .zip(chars)
+                    .all(|(&(c, _, _), &word_char)| c == word_char)
+        });
+        let Some((word_id, chars)) = found else {
+            i += 1;
+            continue;
+        };
+
+        let reading = &spoken[i..i + chars.len()];
+        let omitted = reading.iter().any(|&(_, _, omitted)| omitted);
+        let read: Vec<f64> = reading
+            .iter()
+            .filter(|&&(_, _, omitted)| !omitted)
+            .map(|&(_, score, _)| f64::from(score))
+            .collect();
+        let score = (!read.is_empty()).then(|| read.iter().sum::<f64>() / read.len() as f64);
+        let grade = match score {
+            Some(score) if !omitted => grade_for_score(score),
+            _ => Grade::Again,
+        };
+        let word = SpokenWord {
+            word_id: word_id.to_string(),
+            chinese: chars.iter().collect(),
+            score,
+            omitted,
+            grade,
+        };
+
+        match graded.iter_mut().find(|seen| seen.word_id == word.word_id) {
+            Some(seen) if word.grade.value() < seen.grade.value() => *seen = word,
+            Some(_) => {}
+            None => graded.push(word),
+        }
+        i += chars.len();
+    }
+
+    graded
+}
+
+/// Offsets into the spoken characters where a word starts or ends. Words come
+/// from the reference text, where punctuation helps split them, unless the
+/// provider's words spell out other characters.
+fn word_boundaries(ref_text: &str, spoken: &[(char, f32, bool)]) -> HashSet<usize> {
+    let spoken: String = spoken.iter().map(|&(c, _, _)| c).collect();
+    let ref_hanzi: String = ref_text.chars().filter(|&c| is_hanzi(c)).collect();
+    let text = if ref_hanzi == spoken {
+        ref_text
+    } else {
+        spoken.as_str()
+    };
+
+    let mut boundaries = HashSet::from([0]);
+    let mut end = 0;
+    for word in Segmenter::bundled().words(text) {
+        end += word.chars().count();
+        boundaries.insert(end);
+    }
+    boundaries
+}
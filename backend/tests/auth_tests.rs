@@ -0,0 +1,411 @@
+mod support;
+
+use axum::http::{
+    HeaderName,
+    HeaderValue,
+    StatusCode,
+};
+use axum_test::TestServer;
+use mandarinpath_backend::*;
+use serde_json::{
+    json,
+    Value,
+};
+use support::{
+    bearer,
+    connected_test_server_with,
+    register,
+    test_server,
+    PASSWORD,
+};
+
+async fn login(server: &TestServer, email: &str) -> Value {
+    let response = server
+        .post("/auth/login")
+        .json(&json!({"email": email, "password": PASSWORD}))
+        .await;
+    response.assert_status_ok();
+    response.json()
+}
+
+async fn me_status(server: &TestServer, auth: &Value) -> StatusCode {
+    let (name, value) = bearer(auth);
+    server
+        .get("/auth/me")
+        .add_header(name, value)
+        .await
+        .status_code()
+}
+
+#[tokio::test]
+async fn test_me_requires_live_session() {
+    let (server, _db, _temp_dir) = test_server().await;
+    let auth = register(&server, "me@example.com").await;
+
+    server
+        .get("/auth/me")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    server
+        .get("/auth/me")
+        .add_header(
+            HeaderName::from_static("authorization"),
+            HeaderValue::from_static("Bearer not-a-token"),
+        )
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let (name, value) = bearer(&auth);
+    let response = server
+        .get("/auth/me")
+        .add_header(name.clone(), value.clone())
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["email"], "me@example.com");
+
+    // A signed token is not enough once its session has ended
+    server
+        .post("/auth/logout")
+        .add_header(name, value)
+        .await
+        .assert_status_ok();
+    assert_eq!(me_status(&server, &auth).await, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_logout_only_ends_own_sessions() {
+    let (server, _db, _temp_dir) = test_server().await;
+    let alice = register(&server, "alice@example.com").await;
+    let alice_phone = login(&server, "alice@example.com").await;
+    let mallory = register(&server, "mallory@example.com").await;
+
+    // Ids in the body are ignored: the caller's own session is the one ended
+    let (name, value) = bearer(&mallory);
+    server
+        .post("/auth/logout")
+        .json(&json!({"session_id": "current"}))
+        .add_header(name, value)
+        .await
+        .assert_status_ok();
+    assert_eq!(me_status(&server, &mallory).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(me_status(&server, &alice).await, StatusCode::OK);
+
+    let mallory = login(&server, "mallory@example.com").await;
+    let (name, value) = bearer(&mallory);
+    server
+        .post("/auth/logout-all")
+        .json(&json!({"user_id": alice["user"]["id"]}))
+        .add_header(name, value)
+        .await
+        .assert_status_ok();
+    assert_eq!(me_status(&server, &mallory).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(me_status(&server, &alice).await, StatusCode::OK);
+    assert_eq!(me_status(&server, &alice_phone).await, StatusCode::OK);
+
+    // Without a token nothing is ended
+    server
+        .post("/auth/logout-all")
+        .json(&json!({"user_id": alice["user"]["id"]}))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let (name, value) = bearer(&alice);
+    server
+        .post("/auth/logout-all")
+        .add_header(name, value)
+        .await
+        .assert_status_ok();
+    assert_eq!(me_status(&server, &alice).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        me_status(&server, &alice_phone).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+async fn refresh(server: &TestServer, refresh_token: &Value) -> axum_test::TestResponse {
+    server
+        .post("/auth/refresh")
+        .json(&json!({"refresh_token": refresh_token}))
+        .await
+}
+
+#[tokio::test]
+async fn test_refresh_token_rotation() {
+    let (server, db, _temp_dir) = test_server().await;
+    let first = register(&server, "rotate@example.com").await;
+    let other = login(&server, "rotate@example.com").await;
+
+    // Tokens are only accepted as their own kind
+    refresh(&server, &first["access_token"])
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    let as_access = json!({"access_token": first["refresh_token"]});
+    assert_eq!(
+        me_status(&server, &as_access).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Each refresh spends the token and hands out the next one
+    let response = refresh(&server, &first["refresh_token"]).await;
+    response.assert_status_ok();
+    let second: Value = response.json();
+    assert_ne!(second["refresh_token"], first["refresh_token"]);
+    assert_eq!(me_status(&server, &second).await, StatusCode::OK);
+    let response = refresh(&server, &second["refresh_token"]).await;
+    response.assert_status_ok();
+    let third: Value = response.json();
+
+    // Replaying a spent token revokes the whole session and is audited
+    refresh(&server, &first["refresh_token"])
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(me_status(&server, &third).await, StatusCode::UNAUTHORIZED);
+    refresh(&server, &third["refresh_token"])
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let events: Vec<(String, Option<String>)> =
+        sqlx::query_as("SELECT event, user_id FROM audit_events")
+            .fetch_all(db.pool())
+            .await
+            .unwrap();
+    assert_eq!(
+        events,
+        vec![(
+            "refresh_token_reuse".to_string(),
+            first["user"]["id"].as_str().map(str::to_string)
+        )]
+    );
+
+    // The user's other sessions are untouched
+    assert_eq!(me_status(&server, &other).await, StatusCode::OK);
+    refresh(&server, &other["refresh_token"])
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_refresh_from_two_tabs_at_once() {
+    let (server, db, _temp_dir) = test_server().await;
+    let first = register(&server, "tabs@example.com").await;
+
+    // Both tabs refresh with the same token; the second is not taken for theft
+    let response = refresh(&server, &first["refresh_token"]).await;
+    response.assert_status_ok();
+    let tab_a: Value = response.json();
+    let response = refresh(&server, &first["refresh_token"]).await;
+    response.assert_status_ok();
+    let tab_b: Value = response.json();
+    assert_eq!(me_status(&server, &tab_a).await, StatusCode::OK);
+    assert_eq!(me_status(&server, &tab_b).await, StatusCode::OK);
+
+    // Either tab's token carries on the family
+    let response = refresh(&server, &tab_b["refresh_token"]).await;
+    response.assert_status_ok();
+    let next: Value = response.json();
+
+    // Once the grace window has passed, the spent token is a replay again
+    sqlx::query("UPDATE sessions SET refresh_rotated_at = ?")
+        .bind((chrono::Utc::now() - chrono::Duration::minutes(1)).naive_utc())
+        .execute(db.pool())
+        .await
+        .unwrap();
+    refresh(&server, &tab_a["refresh_token"])
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(me_status(&server, &next).await, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_access_tokens_die_with_their_session() {
+    let (server, db, _temp_dir) = test_server().await;
+    let auth = register(&server, "bound@example.com").await;
+    let (name, value) = bearer(&auth);
+    let session_id: String = sqlx::query_scalar("SELECT id FROM sessions")
+        .fetch_one(db.pool())
+        .await
+        .unwrap();
+    let last_used = || async {
+        sqlx::query_scalar::<_, chrono::NaiveDateTime>(
+            "SELECT last_used_at FROM sessions WHERE id = ?",
+        )
+        .bind(&session_id)
+        .fetch_one(db.pool())
+        .await
+        .unwrap()
+    };
+    let an_hour_ago = (chrono::Utc::now() - chrono::Duration::hours(1)).naive_utc();
+    let set_last_used = |at: chrono::NaiveDateTime| {
+        sqlx::query("UPDATE sessions SET last_used_at = ? WHERE id = ?")
+            .bind(at)
+            .bind(&session_id)
+            .execute(db.pool())
+    };
+
+    // A stale last_used_at is brought up to date, then left alone for a while
+    set_last_used(an_hour_ago).await.unwrap();
+    server
+        .get("/words")
+        .add_header(name.clone(), value.clone())
+        .await
+        .assert_status_ok();
+    assert!(last_used().await > an_hour_ago);
+    set_last_used(an_hour_ago).await.unwrap();
+    server
+        .get("/words")
+        .add_header(name.clone(), value.clone())
+        .await
+        .assert_status_ok();
+    assert_eq!(last_used().await, an_hour_ago);
+
+    // Every endpoint refuses the token as soon as its session is revoked
+    server
+        .post("/auth/logout-all")
+        .add_header(name.clone(), value.clone())
+        .await
+        .assert_status_ok();
+    server
+        .get("/words")
+        .add_header(name.clone(), value.clone())
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    server
+        .get("/curriculum/hsk/1")
+        .add_header(name, value)
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+const FIREFOX_ON_WINDOWS: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:131.0) Gecko/20100101 Firefox/131.0";
+
+#[tokio::test]
+async fn test_list_and_revoke_sessions() {
+    let (server, _db, _temp_dir) = test_server().await;
+    let laptop = register(&server, "devices@example.com").await;
+    let response = server
+        .post("/auth/login")
+        .json(&json!({"email": "devices@example.com", "password": PASSWORD}))
+        .add_header(
+            HeaderName::from_static("user-agent"),
+            HeaderValue::from_static(FIREFOX_ON_WINDOWS),
+        )
+        .await;
+    response.assert_status_ok();
+    let desktop: Value = response.json();
+    let mallory = register(&server, "mallory@example.com").await;
+
+    server
+        .get("/auth/sessions")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let (name, value) = bearer(&laptop);
+    let response = server
+        .get("/auth/sessions")
+        .add_header(name.clone(), value.clone())
+        .await;
+    response.assert_status_ok();
+    let sessions = response.json::<Value>()["sessions"].clone();
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    let current: Vec<&Value> = sessions.iter().filter(|s| s["current"] == true).collect();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0]["device"]["name"], "Unknown device");
+    let other = sessions.iter().find(|s| s["current"] == false).unwrap();
+    assert_eq!(other["device"]["name"], "Firefox on Windows");
+    assert_eq!(other["device"]["browser"], "Firefox");
+    let other_id = other["id"].as_str().unwrap();
+
+    // Another user's session is indistinguishable from a missing one
+    let (mallory_name, mallory_value) = bearer(&mallory);
+    server
+        .delete(&format!("/auth/sessions/{}", other_id))
+        .add_header(mallory_name, mallory_value)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    assert_eq!(me_status(&server, &desktop).await, StatusCode::OK);
+
+    server
+        .delete(&format!("/auth/sessions/{}", other_id))
+        .add_header(name.clone(), value.clone())
+        .await
+        .assert_status_ok();
+    assert_eq!(me_status(&server, &desktop).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(me_status(&server, &laptop).await, StatusCode::OK);
+    server
+        .delete(&format!("/auth/sessions/{}", other_id))
+        .add_header(name, value)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_session_ip_trusts_proxy_headers_only_from_proxies() {
+    for trust_loopback in [false, true] {
+        let (server, _db, _temp_dir) = connected_test_server_with(|config| {
+            if trust_loopback {
+                config.trusted_proxies = vec![std::net::Ipv4Addr::LOCALHOST.into()];
+            }
+        })
+        .await;
+        let response = server
+            .post("/auth/register")
+            .json(&json!({"email": "proxied@example.com", "password": PASSWORD}))
+            .add_header(
+                HeaderName::from_static("x-forwarded-for"),
+                HeaderValue::from_static("203.0.113.7"),
+            )
+            .await;
+        response.assert_status_ok();
+
+        let (name, value) = bearer(&response.json());
+        let sessions: Value = server
+            .get("/auth/sessions")
+            .add_header(name, value)
+            .await
+            .json();
+        let expected = if trust_loopback {
+            "203.0.113.7"
+        } else {
+            "127.0.0.1"
+        };
+        assert_eq!(sessions["sessions"][0]["ip_address"], expected);
+    }
+}
+
+#[test]
+fn test_describe_device() {
+    use auth::device::describe;
+
+    assert_eq!(
+        describe(Some(FIREFOX_ON_WINDOWS)).name,
+        "Firefox on Windows"
+    );
+    assert_eq!(
+        describe(Some(
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 \
+             (KHTML, like Gecko) Chrome/129.0.0.0 Safari/537.36 Edg/129.0.0.0"
+        ))
+        .name,
+        "Edge on macOS"
+    );
+    assert_eq!(
+        describe(Some(
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 17_6 like Mac OS X) AppleWebKit/605.1.15 \
+             (KHTML, like Gecko) Version/17.6 Mobile/15E148 Safari/604.1"
+        ))
+        .name,
+        "Safari on iOS"
+    );
+    assert_eq!(
+        describe(Some(
+            "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 \
+             (KHTML, like Gecko) Chrome/129.0.0.0 Mobile Safari/537.36"
+        ))
+        .name,
+        "Chrome on Android"
+    );
+    assert_eq!(describe(Some("curl/8.9.1")).name, "Unknown device");
+    assert_eq!(describe(None).name, "Unknown device");
+}
@@ -0,0 +1,386 @@
+mod support;
+
+use axum::http::StatusCode;
+use mandarinpath_backend::{
+    curriculum::{
+        hsk,
+        CurriculumService,
+        Standard,
+    },
+    vocabulary::{
+        review::ReviewService,
+        scheduler::Grade,
+    },
+};
+use serde_json::{
+    json,
+    Value,
+};
+use support::{
+    bearer,
+    register,
+    test_server,
+};
+
+const SAMPLE: &str = "\
+# level\tword\tpinyin\tenglish
+1\t你好\tnǐ hǎo\thello
+1\t中国\tZhōngguó\tChina
+2\t中文\tZhōngwén\tChinese (language)
+1\t你好\tnǐ hǎo\thello again
+8\t好感\thǎogǎn\tfavourable impression
+10\t太\ttài\ttoo
+not a level\t是\tshì\tto be
+";
+
+#[tokio::test]
+async fn test_import_hsk_list() {
+    let (_server, db, _temp_dir) = test_server().await;
+    let curriculum = CurriculumService::new(db);
+
+    let summary = curriculum.import(Standard::Hsk3, SAMPLE).await.unwrap();
+    assert_eq!(summary.words, 4);
+    let mut skipped: Vec<(usize, &str)> = summary
+        .skipped
+        .iter()
+        .map(|error| (error.line, error.reason))
+        .collect();
+    skipped.sort();
+    assert_eq!(
+        skipped,
+        vec![
+            (5, "word listed twice"),
+            (7, "level out of range"),
+            (8, "invalid level"),
+        ]
+    );
+
+    // 中 is first used at level 1, so 中文 only brings 文 to level 2
+    let characters: Vec<(String, i64)> = curriculum
+        .level_characters(Standard::Hsk3, 2)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|character| (character.character, character.level))
+        .collect();
+    assert_eq!(characters, vec![("文".to_string(), 2)]);
+    assert_eq!(summary.characters, 6);
+
+    // Level 8 belongs to the 7-9 band
+    let (words, total) = curriculum
+        .level_words(Standard::Hsk3, 7, None, 20, 0)
+        .await
+        .unwrap();
+    assert_eq!(total, 1);
+    assert_eq!(words[0].word, "好感");
+
+    // Importing one standard leaves the other alone
+    curriculum.import_bundled().await.unwrap();
+    let summary = curriculum.import(Standard::Hsk2, SAMPLE).await.unwrap();
+    assert_eq!(summary.words, 3);
+    let (_, total) = curriculum
+        .level_words(Standard::Hsk3, 1, None, 20, 0)
+        .await
+        .unwrap();
+    assert!(total > 400);
+}
+
+#[tokio::test]
+async fn test_import_character_list() {
+    let (_server, db, _temp_dir) = test_server().await;
+    let curriculum = CurriculumService::new(db);
+
+    // The published list can place a character apart from the words using it
+    let characters = "\
+# level\tcharacter
+1\t你
+1\t好
+2\t中
+3\t国
+2\t中
+1\t中国
+8\t感
+";
+    let summary = curriculum
+        .import_lists(Standard::Hsk3, SAMPLE, Some(characters))
+        .await
+        .unwrap();
+    assert_eq!(summary.words, 4);
+    assert_eq!(summary.characters, 5);
+    let mut skipped: Vec<(usize, &str)> = summary
+        .skipped_characters
+        .iter()
+        .map(|error| (error.line, error.reason))
+        .collect();
+    skipped.sort();
+    assert_eq!(
+        skipped,
+        vec![(6, "character listed twice"), (7, "not a single character")]
+    );
+
+    let level = |level| {
+        let curriculum = curriculum.clone();
+        async move {
+            curriculum
+                .level_characters(Standard::Hsk3, level)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|character| character.character)
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(level(1).await, vec!["你", "好"]);
+    assert_eq!(level(2).await, vec!["中"]);
+    assert_eq!(level(3).await, vec!["国"]);
+    assert_eq!(level(7).await, vec!["感"]);
+}
+
+#[tokio::test]
+async fn test_bundled_lists_load_into_empty_database() {
+    let (_server, db, _temp_dir) = test_server().await;
+    let curriculum = CurriculumService::new(db);
+
+    let imported = curriculum.import_bundled_if_empty().await.unwrap();
+    let standards: Vec<Standard> = imported.iter().map(|(standard, _)| *standard).collect();
+    assert_eq!(standards, vec![Standard::Hsk2, Standard::Hsk3]);
+    assert!(imported.iter().all(|(_, summary)| summary.words > 400));
+
+    // A list imported since is not replaced on the next start
+    curriculum.import(Standard::Hsk2, SAMPLE).await.unwrap();
+    assert!(curriculum
+        .import_bundled_if_empty()
+        .await
+        .unwrap()
+        .is_empty());
+    let (_, total) = curriculum
+        .level_words(Standard::Hsk2, 1, None, 20, 0)
+        .await
+        .unwrap();
+    assert_eq!(total, 2);
+}
+
+#[test]
+fn test_bundled_lists_parse() {
+    for list in [hsk::BUNDLED_2_0, hsk::BUNDLED_3_0] {
+        assert!(hsk::parse(list).all(|entry| entry.is_ok()));
+    }
+    let hsk1 = hsk::parse(hsk::BUNDLED_2_0)
+        .filter_map(Result::ok)
+        .filter(|(_, entry)| entry.level == 1)
+        .count();
+    assert_eq!(hsk1, 150);
+}
+
+#[tokio::test]
+async fn test_level_words() {
+    let (server, db, _temp_dir) = test_server().await;
+    CurriculumService::new(db).import_bundled().await.unwrap();
+    let (name, value) = bearer(&register(&server, "levels@example.com").await);
+
+    let response = server
+        .post("/words")
+        .add_header(name.clone(), value.clone())
+        .json(&json!({"chinese": "爱", "definition": "love"}))
+        .await;
+    response.assert_status_ok();
+
+    let body: Value = server
+        .get("/curriculum/hsk/1")
+        .add_query_param("limit", 2)
+        .await
+        .json();
+    assert_eq!(body["standard"], "2.0");
+    assert_eq!(body["level"], 1);
+    assert_eq!(body["total"], 150);
+    assert_eq!(
+        body["list"],
+        json!({"imported": false, "characterList": false, "levels": [1, 2, 3], "complete": false})
+    );
+    assert_eq!(
+        body["words"][0],
+        json!({"id": body["words"][0]["id"], "word": "爱", "pinyin": "ài", "english": "to love", "level": 1})
+    );
+
+    // Signed in, each word says whether it is in the learner's vocabulary
+    let body: Value = server
+        .get("/curriculum/hsk/1")
+        .add_query_param("limit", 2)
+        .add_header(name, value)
+        .await
+        .json();
+    assert_eq!(body["words"][0]["inVocabulary"], true);
+    assert_eq!(body["words"][1]["inVocabulary"], false);
+
+    let body: Value = server
+        .get("/curriculum/hsk/1")
+        .add_query_param("standard", "3.0")
+        .add_query_param("offset", 10)
+        .await
+        .json();
+    assert_eq!(body["standard"], "3.0");
+    assert!(body["total"].as_i64().unwrap() > 400);
+    assert_eq!(body["offset"], 10);
+
+    // Levels the bundle leaves out are empty rather than missing
+    let body: Value = server
+        .get("/curriculum/hsk/9")
+        .add_query_param("standard", "3.0")
+        .await
+        .json();
+    assert_eq!(body["level"], 9);
+    assert_eq!(body["total"], 0);
+    assert_eq!(body["list"]["levels"], json!([1]));
+    assert_eq!(body["list"]["complete"], false);
+
+    let body: Value = server.get("/curriculum/hsk/2/characters").await.json();
+    assert_eq!(body["list"]["complete"], false);
+    let characters = body["characters"].as_array().unwrap();
+    assert!(characters.iter().all(|character| character["level"] == 2));
+    assert!(characters
+        .iter()
+        .any(|character| character["character"] == "吧"));
+    // 爱 is a level 1 character
+    assert!(!characters
+        .iter()
+        .any(|character| character["character"] == "爱"));
+
+    server
+        .get("/curriculum/hsk/7")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    server
+        .get("/curriculum/hsk/0")
+        .add_query_param("standard", "3.0")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    server
+        .get("/curriculum/hsk/1")
+        .add_query_param("standard", "4.0")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_coverage() {
+    let (server, db, _temp_dir) = test_server().await;
+    CurriculumService::new(db.clone())
+        .import(Standard::Hsk2, SAMPLE)
+        .await
+        .unwrap();
+
+    server
+        .get("/curriculum/hsk/coverage")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let auth = register(&server, "coverage@example.com").await;
+    let user_id = auth["user"]["id"].as_str().unwrap();
+    let (name, value) = bearer(&auth);
+    let mut ids = Vec::new();
+    for chinese in ["你好", "中文", "好"] {
+        let response = server
+            .post("/words")
+            .add_header(name.clone(), value.clone())
+            .json(&json!({"chinese": chinese, "definition": "-"}))
+            .await;
+        response.assert_status_ok();
+        ids.push(response.json::<Value>()["id"].as_str().unwrap().to_string());
+    }
+    // 你好 passes its review and counts as known
+    ReviewService::new(db)
+        .grade(user_id, &ids[0], Grade::Good, chrono::Utc::now())
+        .await
+        .unwrap();
+
+    let response = server
+        .get("/curriculum/hsk/coverage")
+        .add_header(name, value)
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["standard"], "2.0");
+    let levels = body["levels"].as_array().unwrap();
+    assert_eq!(levels.len(), 6);
+    assert_eq!(
+        levels[0],
+        json!({
+            "level": 1,
+            "words": 2,
+            "wordsInVocabulary": 1,
+            "wordsKnown": 1,
+            "characters": 4,
+            "charactersInVocabulary": 3,
+            "charactersKnown": 2,
+        })
+    );
+    assert_eq!(levels[1]["words"], 1);
+    assert_eq!(levels[1]["wordsInVocabulary"], 1);
+    assert_eq!(levels[1]["wordsKnown"], 0);
+    assert_eq!(levels[1]["charactersInVocabulary"], 1);
+    assert_eq!(levels[5]["words"], 0);
+    // Imported without a character list and missing levels, so not complete
+    assert_eq!(
+        body["list"],
+        json!({"imported": true, "characterList": false, "levels": [1, 2], "complete": false})
+    );
+}
+
+#[tokio::test]
+async fn test_list_status() {
+    let (_server, db, _temp_dir) = test_server().await;
+    let curriculum = CurriculumService::new(db);
+
+    let status = curriculum.list_status(Standard::Hsk2).await.unwrap();
+    assert!(!status.imported);
+    assert!(status.levels.is_empty());
+    assert!(!status.complete);
+
+    // The bundle is a part of each standard
+    curriculum.import_bundled().await.unwrap();
+    let status = curriculum.list_status(Standard::Hsk2).await.unwrap();
+    assert_eq!(status.levels, vec![1, 2, 3]);
+    assert!(!status.imported);
+    assert!(!status.complete);
+    let status = curriculum.list_status(Standard::Hsk3).await.unwrap();
+    assert_eq!(status.levels, vec![1]);
+    assert!(!status.complete);
+
+    // Every level, with the character list
+    let words = "\
+1\t一\tyī\tone
+2\t二\tèr\ttwo
+3\t三\tsān\tthree
+4\t四\tsì\tfour
+5\t五\twǔ\tfive
+6\t六\tliù\tsix
+";
+    let characters = "1\t一\n2\t二\n3\t三\n4\t四\n5\t五\n6\t六\n";
+    curriculum
+        .import_lists(Standard::Hsk2, words, None)
+        .await
+        .unwrap();
+    let status = curriculum.list_status(Standard::Hsk2).await.unwrap();
+    assert!(status.imported);
+    assert!(!status.character_list);
+    assert!(!status.complete);
+
+    curriculum
+        .import_lists(Standard::Hsk2, words, Some(characters))
+        .await
+        .unwrap();
+    let status = curriculum.list_status(Standard::Hsk2).await.unwrap();
+    assert_eq!(status.levels, vec![1, 2, 3, 4, 5, 6]);
+    assert!(status.character_list);
+    assert!(status.complete);
+
+    // Going back to the bundle is partial again
+    curriculum.import_bundled().await.unwrap();
+    assert!(
+        !curriculum
+            .list_status(Standard::Hsk2)
+            .await
+            .unwrap()
+            .complete
+    );
+}
@@ -0,0 +1,279 @@
+mod support;
+
+use mandarinpath_backend::{
+    dictionary::{
+        cedict,
+        Classifier,
+        DictionaryEntry,
+        DictionaryService,
+    },
+    pinyin::parse,
+    *,
+};
+use serde_json::{
+    json,
+    Value,
+};
+use tempfile::TempDir;
+
+const SAMPLE: &str = "\
+# CC-CEDICT
+# A few entries for tests
+你好 你好 [ni3 hao3] /hello/hi/
+行 行 [hang2] /row/line/line of business/profession/
+行 行 [xing2] /to walk/to go/capable/OK/CL:個|个[ge4]/
+銀行 银行 [yin2 hang2] /bank/CL:家[jia1],個|个[ge4]/
+西安 西安 [Xi1 an1] /Xi'an, capital of Shaanxi/
+先 先 [xian1] /early/prior/first/
+吃 吃 [chi1] /to eat/to consume/
+吃飯 吃饭 [chi1 fan4] /to have a meal/to eat/
+女兒 女儿 [nu:3 er2] /daughter/CL:個|个[ge4]/
+綠色 绿色 [lu:4 se4] /green/
+哪兒 哪儿 [na3 r5] /where?/wherever/
+卡拉OK 卡拉OK [ka3 la1 O K] /karaoke (loanword)/
+張 张 [Zhang1] /surname Zhang/
+張 张 [zhang1] /to open up/classifier for flat objects, sheet/
+銀 银 [yin2 /silver/
+";
+
+async fn setup() -> (TempDir, db::Database) {
+    let temp_dir = TempDir::new().unwrap();
+    let database_url = format!("sqlite:{}", temp_dir.path().join("dictionary.db").display());
+    let db = db::Database::new(&database_url)
+        .await
+        .expect("Failed to connect to database");
+    (temp_dir, db)
+}
+
+fn words(entries: &[DictionaryEntry]) -> Vec<String> {
+    entries
+        .iter()
+        .map(|entry| format!("{} {}", entry.simplified, entry.pinyin))
+        .collect()
+}
+
+#[test]
+fn test_parse_cedict_lines() {
+    let entry = cedict::parse_line("銀行 银行 [yin2 hang2] /bank/CL:家[jia1],個|个[ge4]/")
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.traditional, "銀行");
+    assert_eq!(entry.simplified, "银行");
+    assert_eq!(entry.pinyin, "yin2 hang2");
+    assert_eq!(entry.senses, ["bank"]);
+    assert_eq!(entry.classifiers.as_deref(), Some("家[jia1],個|个[ge4]"));
+
+    assert_eq!(cedict::parse_line("# comment"), None);
+    assert_eq!(cedict::parse_line("   "), None);
+    assert_eq!(
+        cedict::parse_line("銀 银 [yin2 /silver/"),
+        Some(Err("unterminated pinyin"))
+    );
+    assert_eq!(
+        cedict::parse_line("銀 银 [yin2]"),
+        Some(Err("missing senses"))
+    );
+
+    assert_eq!(
+        cedict::search_keys("nu:3 er2"),
+        ("nv3 er2".to_string(), "nver".to_string())
+    );
+    assert_eq!(
+        cedict::search_keys("Xi1 an1"),
+        ("xi1 an1".to_string(), "xian".to_string())
+    );
+    assert_eq!(
+        cedict::search_keys("ka3 la1 O K"),
+        ("ka3 la1 o k".to_string(), "kalaok".to_string())
+    );
+    assert_eq!(cedict::marked("Bei3 jing1"), "Běi jīng");
+    assert_eq!(cedict::marked("lu:4 se4"), "lǜ sè");
+}
+
+#[tokio::test]
+async fn test_import_and_lookup() {
+    let (_temp_dir, db) = setup().await;
+    let dictionary = DictionaryService::new(db);
+
+    let summary = dictionary.import_cedict(SAMPLE).await.unwrap();
+    assert_eq!(summary.entries, 14);
+    assert_eq!(summary.senses, 27);
+    assert_eq!(summary.skipped.len(), 1);
+    assert_eq!(summary.skipped[0].line, 17);
+    assert_eq!(dictionary.entry_count().await.unwrap(), 14);
+
+    // Simplified and traditional forms find the same entry
+    let bank = dictionary.lookup("银行", 10).await.unwrap();
+    assert_eq!(words(&bank), ["银行 yin2 hang2"]);
+    assert_eq!(
+        words(&dictionary.lookup("銀行", 10).await.unwrap()),
+        ["银行 yin2 hang2"]
+    );
+    assert_eq!(bank[0].traditional, "銀行");
+    assert_eq!(bank[0].pinyin_marked, "yín háng");
+    assert_eq!(bank[0].definitions[0].meaning, "bank");
+    assert_eq!(
+        bank[0].classifiers,
+        [
+            Classifier {
+                traditional: "家".to_string(),
+                simplified: "家".to_string(),
+                pinyin: "jia1".to_string(),
+            },
+            Classifier {
+                traditional: "個".to_string(),
+                simplified: "个".to_string(),
+                pinyin: "ge4".to_string(),
+            },
+        ]
+    );
+
+    // Each reading is its own entry; proper nouns come after common words
+    assert_eq!(
+        words(&dictionary.lookup("行", 10).await.unwrap()),
+        ["行 hang2", "行 xing2"]
+    );
+    let zhang = dictionary.lookup("张", 10).await.unwrap();
+    assert_eq!(words(&zhang), ["张 zhang1", "张 Zhang1"]);
+    assert_eq!(
+        zhang[0].definitions[0].part_of_speech.as_deref(),
+        Some("verb")
+    );
+    assert_eq!(
+        zhang[0].definitions[1].part_of_speech.as_deref(),
+        Some("classifier")
+    );
+    assert!(dictionary.lookup("没有", 10).await.unwrap().is_empty());
+
+    // Re-importing replaces the dictionary, full-text index included
+    let summary = dictionary
+        .import_cedict("吃 吃 [chi1] /to eat/\n")
+        .await
+        .unwrap();
+    assert_eq!(summary.entries, 1);
+    assert_eq!(dictionary.entry_count().await.unwrap(), 1);
+    assert!(dictionary
+        .search_english("bank", 10)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_search_by_pinyin_english_and_prefix() {
+    let (_temp_dir, db) = setup().await;
+    let dictionary = DictionaryService::new(db);
+    dictionary.import_cedict(SAMPLE).await.unwrap();
+
+    let pinyin = |query: &str| {
+        let dictionary = dictionary.clone();
+        let syllables = parse::parse(query).unwrap();
+        async move { words(&dictionary.search_pinyin(&syllables, 10).await.unwrap()) }
+    };
+    assert_eq!(pinyin("ni3 hao3").await, ["你好 ni3 hao3"]);
+    assert_eq!(pinyin("nihao").await, ["你好 ni3 hao3"]);
+    assert_eq!(pinyin("nǐ hǎo").await, ["你好 ni3 hao3"]);
+    // Without tones, syllable boundaries do not matter either
+    assert_eq!(pinyin("xian").await, ["先 xian1", "西安 Xi1 an1"]);
+    assert_eq!(pinyin("xian1").await, ["先 xian1"]);
+    assert_eq!(pinyin("xi'an1").await, ["西安 Xi1 an1"]);
+    assert_eq!(pinyin("hang").await, ["行 hang2"]);
+    assert_eq!(pinyin("nü3 er2").await, ["女儿 nu:3 er2"]);
+    assert_eq!(pinyin("lvse").await, ["绿色 lu:4 se4"]);
+    assert!(pinyin("ni3 hao4").await.is_empty());
+
+    let english = |query: &str| {
+        let dictionary = dictionary.clone();
+        let query = query.to_string();
+        async move { words(&dictionary.search_english(&query, 10).await.unwrap()) }
+    };
+    assert_eq!(english("bank").await, ["银行 yin2 hang2"]);
+    assert_eq!(english("Walk").await, ["行 xing2"]);
+    // A sense that is the query itself comes before one that merely contains it
+    assert_eq!(english("eat").await, ["吃 chi1", "吃饭 chi1 fan4"]);
+    assert_eq!(english("have a meal").await, ["吃饭 chi1 fan4"]);
+    assert!(english("\"bank\" OR").await.is_empty());
+
+    let prefix = |query: &str| {
+        let dictionary = dictionary.clone();
+        let query = query.to_string();
+        async move { words(&dictionary.search_prefix(&query, 10).await.unwrap()) }
+    };
+    assert_eq!(prefix("吃").await, ["吃 chi1", "吃饭 chi1 fan4"]);
+    assert_eq!(prefix("銀").await, ["银行 yin2 hang2"]);
+    assert_eq!(prefix("卡拉").await, ["卡拉OK ka3 la1 O K"]);
+    assert!(prefix("*").await.is_empty());
+    // Readings of one word tie on frequency, the proper noun coming last
+    assert_eq!(prefix("张").await, ["张 zhang1", "张 Zhang1"]);
+    assert_eq!(
+        words(&dictionary.search_prefix("吃", 1).await.unwrap()),
+        ["吃 chi1"]
+    );
+}
+
+#[tokio::test]
+async fn test_dictionary_endpoint() {
+    let (server, db, _temp_dir) = support::test_server().await;
+    DictionaryService::new(db)
+        .import_cedict(SAMPLE)
+        .await
+        .unwrap();
+
+    let response = server
+        .get("/dictionary/search")
+        .add_query_param("hanzi", "吃")
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(
+        body["entries"][0],
+        json!({
+            "id": body["entries"][0]["id"],
+            "traditional": "吃",
+            "simplified": "吃",
+            "pinyin": "chi1",
+            "pinyin_marked": "chī",
+            "definitions": [
+                {"partOfSpeech": "verb", "meaning": "to eat"},
+                {"partOfSpeech": "verb", "meaning": "to consume"}
+            ],
+            "classifiers": []
+        })
+    );
+
+    let response = server
+        .get("/dictionary/search")
+        .add_query_param("pinyin", "yin2hang2")
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["entries"][0]["simplified"], "银行");
+    assert_eq!(
+        body["entries"][0]["definitions"],
+        json!([{"meaning": "bank"}])
+    );
+
+    let response = server
+        .get("/dictionary/search")
+        .add_query_param("english", "capital")
+        .add_query_param("limit", "1")
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["entries"].as_array().unwrap().len(), 1);
+    assert_eq!(body["entries"][0]["pinyin_marked"], "Xī ān");
+
+    // Exactly one kind of query, and pinyin that parses
+    for query in [
+        vec![],
+        vec![("hanzi", "吃"), ("prefix", "吃")],
+        vec![("pinyin", "  ")],
+        vec![("pinyin", "chr1")],
+    ] {
+        let mut request = server.get("/dictionary/search");
+        for (name, value) in query {
+            request = request.add_query_param(name, value);
+        }
+        request.await.assert_status_bad_request();
+    }
+}
@@ -0,0 +1,285 @@
+mod support;
+
+use bytes::Bytes;
+use mandarinpath_backend::speech::{
+    IFlytekService,
+    SpeechError,
+    SpeechStreamEvent,
+};
+use serde_json::json;
+use support::{
+    iflytek_server::{
+        recorded_frames,
+        IFlytekStandIn,
+        Script,
+        APP_ID,
+    },
+    nihao_request,
+};
+
+#[tokio::test]
+async fn test_recorded_multi_frame_result() {
+    let server = IFlytekStandIn::start(Script::Replay(recorded_frames("sent_nihao.jsonl"))).await;
+    let service = IFlytekService::new(server.config());
+
+    let response = service.evaluate_speech(nihao_request()).await.unwrap();
+
+    assert_eq!(response.overall_scores.get("overall"), Some(&83.9));
+    assert_eq!(response.overall_scores.get("fluency"), Some(&80.5));
+
+    // Words arrive split across an intermediate and a final frame
+    let words: Vec<&str> = response.words.iter().map(|w| w.word.as_str()).collect();
+    assert_eq!(words, ["你", "好"]);
+    let hao = &response.words[1];
+    assert_eq!(hao.pinyin.as_deref(), Some("hao3"));
+    assert_eq!(hao.scores.tone, Some(74.0));
+    let phonemes = hao.phonemes.as_ref().unwrap();
+    assert_eq!(phonemes.len(), 2);
+    assert_eq!(phonemes[1].phoneme, "ao");
+    assert_eq!(phonemes[1].tone_index, Some(3));
+
+    // The client opened with a parameterised status 0 frame and closed with status 2
+    let frames = server.frames();
+    assert_eq!(server.connections(), 1);
+    assert_eq!(frames.first().unwrap()["header"]["status"], 0);
+    assert_eq!(frames.first().unwrap()["header"]["app_id"], APP_ID);
+    assert_eq!(
+        frames.first().unwrap()["parameter"]["st"]["refText"],
+        "你好"
+    );
+    assert_eq!(
+        frames.first().unwrap()["parameter"]["st"]["refPinyin"],
+        "ni3 hao3"
+    );
+    assert_eq!(frames.last().unwrap()["header"]["status"], 2);
+}
+
+#[tokio::test]
+async fn test_bad_signature_is_rejected_at_handshake() {
+    let server = IFlytekStandIn::start(Script::Replay(recorded_frames("sent_nihao.jsonl"))).await;
+    let mut config = server.config();
+    config.api_secret = "wrong_secret".to_string();
+    let service = IFlytekService::new(config);
+
+    let result = service.evaluate_speech(nihao_request()).await;
+
+    assert!(matches!(result, Err(SpeechError::Auth(_))));
+    assert_eq!(server.auth_failures(), 1);
+    assert_eq!(server.connections(), 0);
+    assert!(server.frames().is_empty());
+}
+
+#[tokio::test]
+async fn test_unknown_api_key_is_rejected_at_handshake() {
+    let server = IFlytekStandIn::start(Script::Replay(recorded_frames("sent_nihao.jsonl"))).await;
+    let mut config = server.config();
+    config.api_key = "someone_elses_key".to_string();
+    let service = IFlytekService::new(config);
+
+    assert!(service.evaluate_speech(nihao_request()).await.is_err());
+    assert_eq!(server.auth_failures(), 1);
+}
+
+#[tokio::test]
+async fn test_provider_error_code_is_reported() {
+    let server = IFlytekStandIn::start(Script::Error {
+        code: 10163,
+        message: "param validate error".to_string(),
+    })
+    .await;
+    let service = IFlytekService::new(server.config());
+
+    let error = service.evaluate_speech(nihao_request()).await.unwrap_err();
+
+    match error {
+        SpeechError::InvalidRequest(message) => assert_eq!(message, "param validate error"),
+        other => panic!("Expected an invalid request, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_error_codes_map_to_speech_errors() {
+    for (code, message, expected) in [
+        (11201, "licc limit", "SPEECH_QUOTA_EXCEEDED"),
+        (48199, "no valid speech", "AUDIO_TOO_SHORT"),
+        (10043, "audio decode failed", "UNSUPPORTED_AUDIO_FORMAT"),
+        (10114, "session timeout", "SPEECH_TIMEOUT"),
+        (10700, "engine error", "SPEECH_PROVIDER_ERROR"),
+    ] {
+        let server = IFlytekStandIn::start(Script::Error {
+            code,
+            message: message.to_string(),
+        })
+        .await;
+        let service = IFlytekService::new(server.config());
+
+        let error = service.evaluate_speech(nihao_request()).await.unwrap_err();
+
+        assert_eq!(error.code(), expected, "iFlytek code {}", code);
+        assert!(error.to_string().contains(message));
+    }
+}
+
+#[test]
+fn test_unknown_provider_codes_keep_the_code() {
+    match SpeechError::from_provider_code(10999, "something new") {
+        SpeechError::Provider { code, message } => {
+            assert_eq!(code, 10999);
+            assert_eq!(message, "something new");
+        }
+        other => panic!("Expected a generic provider error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_undecodable_result_is_a_malformed_response() {
+    let frame = json!({
+        "header": { "code": 0, "message": "success", "status": 2 },
+        "payload": { "result": { "data": "not base64!", "status": 2 } }
+    });
+    let server = IFlytekStandIn::start(Script::Replay(vec![frame.to_string()])).await;
+    let service = IFlytekService::new(server.config());
+
+    let error = service.evaluate_speech(nihao_request()).await.unwrap_err();
+
+    assert!(matches!(error, SpeechError::MalformedResponse(_)));
+}
+
+#[tokio::test]
+async fn test_invalid_app_id_is_reported() {
+    let server = IFlytekStandIn::start(Script::Replay(recorded_frames("sent_nihao.jsonl"))).await;
+    let mut config = server.config();
+    config.app_id = "not_my_app".to_string();
+    let service = IFlytekService::new(config);
+
+    let error = service.evaluate_speech(nihao_request()).await.unwrap_err();
+
+    match error {
+        SpeechError::Auth(message) => assert_eq!(message, "invalid appid"),
+        other => panic!("Expected an auth failure, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_dropped_connection_is_an_error() {
+    let server = IFlytekStandIn::start(Script::DropAfterFirstFrame).await;
+    let service = IFlytekService::new(server.config());
+
+    let result = service.evaluate_speech(nihao_request()).await;
+
+    assert!(matches!(result, Err(SpeechError::Unavailable(_))));
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn test_audio_is_streamed_in_sequenced_frames() {
+    use base64::{
+        engine::general_purpose::STANDARD,
+        Engine as _,
+    };
+
+    let server = IFlytekStandIn::start(Script::Replay(recorded_frames("sent_nihao.jsonl"))).await;
+    let service = IFlytekService::new(server.config());
+
+    // One second of 16 kHz mono 16-bit PCM is 25 frames of 40ms (1280 bytes)
+    let audio: Vec<u8> = (0..32_000u32).map(|i| (i % 251) as u8).collect();
+    let mut request = nihao_request();
+    request.audio_data = Bytes::from(audio.clone());
+
+    service.evaluate_speech(request).await.unwrap();
+
+    let frames = server.frames();
+    assert_eq!(frames.len(), 27);
+
+    let mut reassembled = Vec::new();
+    for (seq, frame) in frames.iter().enumerate() {
+        let data = &frame["payload"]["data"];
+        assert_eq!(data["seq"], seq as u64);
+
+        let expected_status = match seq {
+            0 => 0,
+            26 => 2,
+            _ => 1,
+        };
+        assert_eq!(frame["header"]["status"], expected_status);
+        assert_eq!(data["status"], expected_status);
+
+        let chunk = STANDARD.decode(data["audio"].as_str().unwrap()).unwrap();
+        if expected_status == 1 {
+            assert_eq!(chunk.len(), 1280);
+        } else {
+            assert!(chunk.is_empty());
+        }
+        reassembled.extend(chunk);
+    }
+    assert_eq!(reassembled, audio);
+}
+
+#[tokio::test]
+async fn test_stream_relays_chunks_and_pushes_partial_results() {
+    let server = IFlytekStandIn::start(Script::Replay(recorded_frames("sent_nihao.jsonl"))).await;
+    let service = IFlytekService::new(server.config());
+
+    let mut request = nihao_request();
+    request.audio_data = Bytes::new();
+    let mut stream = service.open_stream(request).await.unwrap();
+
+    // Microphone chunks do not line up with 1280-byte protocol frames
+    for _ in 0..3 {
+        stream
+            .audio
+            .send(Bytes::from(vec![7u8; 1000]))
+            .await
+            .unwrap();
+    }
+    drop(stream.audio);
+
+    let mut events = Vec::new();
+    while let Some(event) = stream.events.recv().await {
+        events.push(event);
+    }
+
+    assert_eq!(events.len(), 2);
+    match &events[0] {
+        SpeechStreamEvent::Partial(partial) => {
+            assert_eq!(partial.words.len(), 1);
+            assert_eq!(partial.words[0].word, "你");
+        }
+        other => panic!("Expected a partial result, got {:?}", other),
+    }
+    match &events[1] {
+        SpeechStreamEvent::Final(response) => {
+            assert_eq!(response.words.len(), 2);
+            assert_eq!(response.overall_scores.get("overall"), Some(&83.9));
+        }
+        other => panic!("Expected the final result, got {:?}", other),
+    }
+
+    let frames = server.frames();
+    let seqs: Vec<u64> = frames
+        .iter()
+        .map(|f| f["payload"]["data"]["seq"].as_u64().unwrap())
+        .collect();
+    assert_eq!(seqs, [0, 1, 2, 3, 4]);
+    assert_eq!(frames.last().unwrap()["header"]["status"], 2);
+}
+
+#[tokio::test]
+async fn test_stream_reports_provider_errors() {
+    let server = IFlytekStandIn::start(Script::Error {
+        code: 11201,
+        message: "licc limit".to_string(),
+    })
+    .await;
+    let service = IFlytekService::new(server.config());
+
+    let mut stream = service.open_stream(nihao_request()).await.unwrap();
+    drop(stream.audio);
+
+    match stream.events.recv().await {
+        Some(SpeechStreamEvent::Failed(SpeechError::QuotaExceeded(message))) => {
+            assert_eq!(message, "licc limit")
+        }
+        other => panic!("Expected a failure, got {:?}", other),
+    }
+}
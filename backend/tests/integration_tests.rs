@@ -1,3 +1,5 @@
+mod support;
+
 use axum::{
     http::{
         HeaderName,
@@ -50,13 +52,10 @@ fn create_test_app(db: db::Database, config: config::Config) -> Router {
 async fn test_health_endpoints() {
     // Create a test config with defaults
     let config = config::Config {
-        database_url: std::env::var("DATABASE_URL")
-            .unwrap_or_else(|_| "sqlite::memory:".to_string()),
-        frontend_url: "http://localhost:5173".to_string(),
-        port: 3000,
-        debug_mode: true,
-        verbosity: 0,
-        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: Default::default(),
+        ..support::test_config(
+            std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+        )
     };
     let db = db::Database::new(&config.database_url)
         .await
@@ -80,19 +79,80 @@ async fn test_health_endpoints() {
     let body: serde_json::Value = response.json();
     assert_eq!(body["status"], "ready");
     assert!(body["timestamp"].is_string());
+    assert_eq!(body["checks"]["database"]["status"], "ok");
+    assert!(body["checks"]["database"]["latency_ms"].is_number());
+    assert_eq!(body["checks"]["migrations"]["status"], "ok");
+    assert_eq!(
+        body["checks"]["migrations"]["details"]["pending"],
+        json!([])
+    );
+    // Missing provider credentials only degrade speech features
+    assert_eq!(body["checks"]["speech_provider"]["status"], "degraded");
+    assert_eq!(
+        body["checks"]["speech_provider"]["details"]["credentials_configured"],
+        false
+    );
+}
+
+#[tokio::test]
+async fn test_readiness_fails_on_database_problems() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config = support::test_config(format!(
+        "sqlite:{}",
+        temp_dir.path().join("ready.db").display()
+    ));
+    let db = db::Database::new(&config.database_url)
+        .await
+        .expect("Failed to connect to database");
+    let server = TestServer::new(create_test_app(db.clone(), config)).unwrap();
+
+    let response = server.get("/api/ready").await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["checks"]["speech_provider"]["status"], "ok");
+    assert_eq!(
+        body["checks"]["speech_provider"]["details"]["provider"],
+        "mock"
+    );
+
+    // A schema behind the binary is a hard failure
+    sqlx::query(
+        "DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)",
+    )
+    .execute(db.pool())
+    .await
+    .unwrap();
+    let response = server.get("/api/ready").await;
+    response.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"], "not_ready");
+    assert_eq!(body["checks"]["database"]["status"], "ok");
+    assert_eq!(body["checks"]["migrations"]["status"], "failed");
+    assert_eq!(
+        body["checks"]["migrations"]["details"]["pending"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+
+    // So is an unreachable database
+    db.pool().close().await;
+    let response = server.get("/api/ready").await;
+    response.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["checks"]["database"]["status"], "failed");
+    assert!(body["checks"]["database"]["error"].is_string());
 }
 
 #[tokio::test]
 async fn test_cors_configuration() {
     // Create a test config with defaults
     let config = config::Config {
-        database_url: std::env::var("DATABASE_URL")
-            .unwrap_or_else(|_| "sqlite::memory:".to_string()),
-        frontend_url: "http://localhost:5173".to_string(),
-        port: 3000,
-        debug_mode: true,
-        verbosity: 0,
-        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: Default::default(),
+        ..support::test_config(
+            std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+        )
     };
     let db = db::Database::new(&config.database_url)
         .await
@@ -142,13 +202,10 @@ async fn test_cors_configuration() {
 async fn test_cors_on_error_responses() {
     // Create a test config with defaults
     let config = config::Config {
-        database_url: std::env::var("DATABASE_URL")
-            .unwrap_or_else(|_| "sqlite::memory:".to_string()),
-        frontend_url: "http://localhost:5173".to_string(),
-        port: 3000,
-        debug_mode: true,
-        verbosity: 0,
-        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: Default::default(),
+        ..support::test_config(
+            std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+        )
     };
     let db = db::Database::new(&config.database_url)
         .await
@@ -194,13 +251,10 @@ async fn test_cors_on_error_responses() {
 async fn test_jwt_service() {
     // Create a test config with defaults
     let config = config::Config {
-        database_url: std::env::var("DATABASE_URL")
-            .unwrap_or_else(|_| "sqlite::memory:".to_string()),
-        frontend_url: "http://localhost:5173".to_string(),
-        port: 3000,
-        debug_mode: true,
-        verbosity: 0,
-        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: Default::default(),
+        ..support::test_config(
+            std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+        )
     };
     let jwt_service = auth::jwt::JwtService::new(&config);
 
@@ -217,21 +271,28 @@ async fn test_jwt_service() {
     assert_eq!(claims.session_id, session_id);
 
     // Test refresh token creation and verification
+    let refresh = models::RefreshClaims {
+        family: "test-family".to_string(),
+        generation: 2,
+    };
     let refresh_token = jwt_service
-        .create_refresh_token(user_id, session_id)
+        .create_refresh_token(user_id, session_id, &refresh)
         .unwrap();
     let refresh_claims = jwt_service.verify_token(&refresh_token).unwrap();
 
     assert_eq!(refresh_claims.sub, user_id);
     assert_eq!(refresh_claims.session_id, session_id);
 
-    // Test token refresh
-    let (new_access_token, new_claims) = jwt_service.refresh_access_token(&refresh_token).unwrap();
-    assert_eq!(new_claims.sub, user_id);
-    assert_eq!(new_claims.session_id, session_id);
+    // Each kind of token is only accepted as itself
+    let (claims, presented) = jwt_service.verify_refresh_token(&refresh_token).unwrap();
+    assert_eq!(claims.sub, user_id);
+    assert_eq!(claims.session_id, session_id);
+    assert_eq!(presented, refresh);
+    assert!(jwt_service.verify_access_token(&refresh_token).is_err());
+    assert!(jwt_service.verify_refresh_token(&access_token).is_err());
 
-    // Verify the new token is valid and contains expected claims
-    let verified_claims = jwt_service.verify_token(&new_access_token).unwrap();
+    // Verify the access token is valid and contains expected claims
+    let verified_claims = jwt_service.verify_access_token(&access_token).unwrap();
     assert_eq!(verified_claims.sub, user_id);
     assert_eq!(verified_claims.session_id, session_id);
 }
@@ -240,12 +301,11 @@ async fn test_jwt_service() {
 async fn test_session_service() {
     // Create a test config with unique in-memory database
     let config = config::Config {
-        database_url: format!("sqlite::memory:session_test_{}", std::process::id()),
-        frontend_url: "http://localhost:5173".to_string(),
-        port: 3000,
-        debug_mode: true,
-        verbosity: 0,
-        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: Default::default(),
+        ..support::test_config(format!(
+            "sqlite::memory:session_test_{}",
+            std::process::id()
+        ))
     };
     let db = db::Database::new(&config.database_url)
         .await
@@ -311,13 +371,10 @@ async fn test_session_service() {
 async fn test_speech_health_endpoint() {
     // Create a test config with defaults
     let config = config::Config {
-        database_url: std::env::var("DATABASE_URL")
-            .unwrap_or_else(|_| "sqlite::memory:".to_string()),
-        frontend_url: "http://localhost:5173".to_string(),
-        port: 3000,
-        debug_mode: true,
-        verbosity: 0,
-        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: Default::default(),
+        ..support::test_config(
+            std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+        )
     };
     let db = db::Database::new(&config.database_url)
         .await
@@ -330,8 +387,19 @@ async fn test_speech_health_endpoint() {
     let response = server.get("/api/speech/health").await;
     response.assert_status_ok();
     response.assert_json(&json!({
-        "status": "ok",
-        "service": "speech_evaluation"
+        "status": "degraded",
+        "service": "speech_evaluation",
+        "provider": "iflytek",
+        "credentials_configured": false,
+        "last_success_at": null,
+        "last_failure_at": null,
+        "last_error": null,
+        "last_latency_ms": null,
+        "circuit_breaker": {
+            "state": "closed",
+            "consecutive_failures": 0,
+            "failure_threshold": 5
+        }
     }));
 }
 
@@ -339,13 +407,10 @@ async fn test_speech_health_endpoint() {
 async fn test_speech_evaluate_endpoint_missing_data() {
     // Create a test config with defaults
     let config = config::Config {
-        database_url: std::env::var("DATABASE_URL")
-            .unwrap_or_else(|_| "sqlite::memory:".to_string()),
-        frontend_url: "http://localhost:5173".to_string(),
-        port: 3000,
-        debug_mode: true,
-        verbosity: 0,
-        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: Default::default(),
+        ..support::test_config(
+            std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+        )
     };
     let db = db::Database::new(&config.database_url)
         .await
@@ -365,13 +430,10 @@ async fn test_speech_evaluate_endpoint_missing_data() {
 async fn test_speech_evaluate_endpoint_invalid_multipart() {
     // Create a test config with defaults
     let config = config::Config {
-        database_url: std::env::var("DATABASE_URL")
-            .unwrap_or_else(|_| "sqlite::memory:".to_string()),
-        frontend_url: "http://localhost:5173".to_string(),
-        port: 3000,
-        debug_mode: true,
-        verbosity: 0,
-        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: Default::default(),
+        ..support::test_config(
+            std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+        )
     };
     let db = db::Database::new(&config.database_url)
         .await
@@ -420,13 +482,10 @@ async fn test_speech_evaluate_endpoint_structure() {
 async fn test_cors_headers_on_speech_endpoints() {
     // Create a test config with defaults
     let config = config::Config {
-        database_url: std::env::var("DATABASE_URL")
-            .unwrap_or_else(|_| "sqlite::memory:".to_string()),
-        frontend_url: "http://localhost:5173".to_string(),
-        port: 3000,
-        debug_mode: true,
-        verbosity: 0,
-        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: Default::default(),
+        ..support::test_config(
+            std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+        )
     };
     let db = db::Database::new(&config.database_url)
         .await
@@ -454,3 +513,408 @@ async fn test_cors_headers_on_speech_endpoints() {
         &config.frontend_url
     );
 }
+
+#[tokio::test]
+async fn test_speech_evaluate_with_mock_provider() {
+    let config = support::test_config(
+        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+    );
+    let db = db::Database::new(&config.database_url)
+        .await
+        .expect("Failed to connect to database");
+
+    // Skip the CSRF layer so the multipart POST reaches the handler
+    let server = TestServer::new(routes::create_routes(db, config)).unwrap();
+
+    // A quarter second of silence as a 48 kHz stereo WAV; the handler resamples it
+    let boundary = "----mock-provider-boundary";
+    let mut body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"params\"\r\n\r\n{params}\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"audio\"; filename=\"a.wav\"\r\n\
+         Content-Type: audio/wav\r\n\r\n",
+        b = boundary,
+        params = json!({"ref_text": "你好", "ref_pinyin": "ni3 hao3"}),
+    )
+    .into_bytes();
+    body.extend(silent_wav(48_000, 2, 12_000));
+    body.extend(format!("\r\n--{}--\r\n", boundary).into_bytes());
+
+    let response = server
+        .post("/speech/evaluate")
+        .bytes(body.into())
+        .content_type(&format!("multipart/form-data; boundary={}", boundary))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["success"], true);
+    assert_eq!(body["data"]["words"].as_array().unwrap().len(), 2);
+    assert_eq!(body["data"]["words"][1]["pinyin"], "hao3");
+}
+
+#[cfg(feature = "opus")]
+#[tokio::test]
+async fn test_speech_evaluate_webm_opus() {
+    let config = support::test_config(
+        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+    );
+    let db = db::Database::new(&config.database_url)
+        .await
+        .expect("Failed to connect to database");
+    let server = TestServer::new(routes::create_routes(db, config)).unwrap();
+
+    // Half a second of a 220 Hz tone, recorded the way Chrome's MediaRecorder does
+    let samples: Vec<i16> = (0..24_000)
+        .map(|i| {
+            let t = i as f32 / 48_000.0;
+            ((2.0 * std::f32::consts::PI * 220.0 * t).sin() * 8_000.0) as i16
+        })
+        .collect();
+    let boundary = "----webm-opus-boundary";
+    let mut body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"params\"\r\n\r\n{params}\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"audio\"; filename=\"a.webm\"\r\n\
+         Content-Type: audio/webm;codecs=opus\r\n\r\n",
+        b = boundary,
+        params = json!({"ref_text": "你好", "ref_pinyin": "ni3 hao3"}),
+    )
+    .into_bytes();
+    body.extend(opus_webm(&samples));
+    body.extend(format!("\r\n--{}--\r\n", boundary).into_bytes());
+
+    let response = server
+        .post("/speech/evaluate")
+        .bytes(body.into())
+        .content_type(&format!("multipart/form-data; boundary={}", boundary))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["success"], true);
+    assert_eq!(body["data"]["words"].as_array().unwrap().len(), 2);
+    // The pitch is measured from the decoded audio
+    let reference_hz = body["tone_analysis"]["reference_hz"].as_f64().unwrap();
+    assert!((200.0..240.0).contains(&reference_hz), "{}", reference_hz);
+}
+
+#[tokio::test]
+async fn test_speech_evaluate_rejects_audio_over_max_duration() {
+    let mut config = support::test_config(
+        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+    );
+    config.speech.max_audio_duration = std::time::Duration::from_secs(1);
+    let db = db::Database::new(&config.database_url)
+        .await
+        .expect("Failed to connect to database");
+    let server = TestServer::new(routes::create_routes(db, config)).unwrap();
+
+    // Two seconds of 16 kHz mono 16-bit PCM
+    let boundary = "----max-duration-boundary";
+    let mut body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"params\"\r\n\r\n{params}\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"encoding\"\r\n\r\nraw\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"audio\"; filename=\"a.pcm\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n",
+        b = boundary,
+        params = json!({"ref_text": "你好"}),
+    )
+    .into_bytes();
+    body.extend(vec![0u8; 64_000]);
+    body.extend(format!("\r\n--{}--\r\n", boundary).into_bytes());
+
+    let response = server
+        .post("/speech/evaluate")
+        .bytes(body.into())
+        .content_type(&format!("multipart/form-data; boundary={}", boundary))
+        .await;
+
+    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "BAD_REQUEST");
+}
+
+#[tokio::test]
+async fn test_speech_evaluate_rejects_unsupported_audio() {
+    let config = support::test_config(
+        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+    );
+    let db = db::Database::new(&config.database_url)
+        .await
+        .expect("Failed to connect to database");
+    let server = TestServer::new(routes::create_routes(db, config)).unwrap();
+
+    // The client's declared encoding is ignored when the bytes are not raw PCM
+    let boundary = "----unsupported-audio-boundary";
+    let body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"params\"\r\n\r\n{params}\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"encoding\"\r\n\r\nlame\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"audio\"; filename=\"a.mp3\"\r\n\
+         Content-Type: audio/mpeg\r\n\r\nfake audio\r\n--{b}--\r\n",
+        b = boundary,
+        params = json!({"ref_text": "你好"}),
+    );
+
+    let response = server
+        .post("/speech/evaluate")
+        .bytes(body.into())
+        .content_type(&format!("multipart/form-data; boundary={}", boundary))
+        .await;
+
+    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "UNSUPPORTED_AUDIO_FORMAT");
+}
+
+#[tokio::test]
+async fn test_speech_evaluate_reports_typed_errors() {
+    let config = support::test_config(
+        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+    );
+    let db = db::Database::new(&config.database_url)
+        .await
+        .expect("Failed to connect to database");
+    let server = TestServer::new(routes::create_routes(db, config)).unwrap();
+
+    let boundary = "----typed-error-boundary";
+    let post = |ref_text: &str, audio: Vec<u8>| {
+        let mut body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"params\"\r\n\r\n{params}\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"audio\"; filename=\"a.wav\"\r\n\
+             Content-Type: audio/wav\r\n\r\n",
+            b = boundary,
+            params = json!({ "ref_text": ref_text }),
+        )
+        .into_bytes();
+        body.extend(audio);
+        body.extend(format!("\r\n--{}--\r\n", boundary).into_bytes());
+        server
+            .post("/speech/evaluate")
+            .bytes(body.into())
+            .content_type(&format!("multipart/form-data; boundary={}", boundary))
+    };
+
+    // An empty upload can never be scored
+    let response = post("你好", Vec::new()).await;
+    response.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response.json::<serde_json::Value>()["code"],
+        "AUDIO_TOO_SHORT"
+    );
+
+    // The provider refuses a reference text with nothing to read
+    let response = post("，。", silent_wav(16_000, 1, 4_000)).await;
+    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "INVALID_SPEECH_REQUEST");
+    assert!(body["details"].as_str().unwrap().contains("no words"));
+}
+
+/// Serve the routes on a real socket, which WebSocket upgrades need rather than
+/// the in-memory test transport, and open a speech stream.
+async fn open_speech_stream(
+    config: config::Config,
+) -> tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>> {
+    let db = db::Database::new(&config.database_url)
+        .await
+        .expect("Failed to connect to database");
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, routes::create_routes(db, config))
+            .await
+            .unwrap();
+    });
+
+    let (socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/speech/stream", addr))
+        .await
+        .expect("Failed to open speech stream");
+    socket
+}
+
+fn stream_json(
+    message: Option<
+        Result<tokio_tungstenite::tungstenite::Message, tokio_tungstenite::tungstenite::Error>,
+    >,
+) -> serde_json::Value {
+    match message {
+        Some(Ok(tokio_tungstenite::tungstenite::Message::Text(text))) => {
+            serde_json::from_str(&text).unwrap()
+        }
+        other => panic!("Expected a text message, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_speech_stream_with_mock_provider() {
+    use futures_util::{
+        SinkExt,
+        StreamExt,
+    };
+    use tokio_tungstenite::tungstenite::Message;
+
+    let mut socket = open_speech_stream(support::test_config(
+        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+    ))
+    .await;
+
+    let start = json!({"type": "start", "ref_text": "你好", "ref_pinyin": "ni3 hao3"});
+    socket.send(Message::Text(start.to_string())).await.unwrap();
+    assert_eq!(stream_json(socket.next().await)["type"], "ready");
+
+    for _ in 0..4 {
+        socket.send(Message::Binary(vec![0u8; 3200])).await.unwrap();
+    }
+    socket
+        .send(Message::Text(json!({"type": "end"}).to_string()))
+        .await
+        .unwrap();
+
+    let result = stream_json(socket.next().await);
+    assert_eq!(result["type"], "final");
+    assert_eq!(result["data"]["words"].as_array().unwrap().len(), 2);
+    assert_eq!(result["data"]["words"][0]["word"], "你");
+}
+
+#[tokio::test]
+async fn test_speech_stream_takes_only_16khz_pcm() {
+    use futures_util::{
+        SinkExt,
+        StreamExt,
+    };
+    use tokio_tungstenite::tungstenite::Message;
+
+    let url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string());
+    for format in [
+        json!({"encoding": "lame"}),
+        json!({"sample_rate": 48_000}),
+        json!({"channels": 2}),
+    ] {
+        let mut socket = open_speech_stream(support::test_config(url.clone())).await;
+        let mut start = json!({"type": "start", "ref_text": "你好", "ref_pinyin": "ni3 hao3"});
+        start
+            .as_object_mut()
+            .unwrap()
+            .extend(format.as_object().unwrap().clone());
+        socket.send(Message::Text(start.to_string())).await.unwrap();
+
+        let message = stream_json(socket.next().await);
+        assert_eq!(message["type"], "error", "{}", format);
+        assert_eq!(message["code"], "INVALID_SPEECH_REQUEST");
+    }
+}
+
+#[tokio::test]
+async fn test_speech_stream_stops_at_max_duration() {
+    use futures_util::{
+        SinkExt,
+        StreamExt,
+    };
+    use tokio_tungstenite::tungstenite::Message;
+
+    let mut config = support::test_config(
+        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string()),
+    );
+    config.speech.max_audio_duration = std::time::Duration::from_secs(1);
+    let mut socket = open_speech_stream(config).await;
+
+    let start = json!({"type": "start", "ref_text": "你好", "ref_pinyin": "ni3 hao3"});
+    socket.send(Message::Text(start.to_string())).await.unwrap();
+    assert_eq!(stream_json(socket.next().await)["type"], "ready");
+
+    // Three quarters of a second at a time; the second chunk goes over the limit
+    for _ in 0..2 {
+        socket
+            .send(Message::Binary(vec![0u8; 24_000]))
+            .await
+            .unwrap();
+    }
+    let message = stream_json(socket.next().await);
+    assert_eq!(message["type"], "error");
+    assert!(message["error"].as_str().unwrap().contains("1 second"));
+}
+
+fn silent_wav(sample_rate: u32, channels: u16, frames: u32) -> Vec<u8> {
+    let data_len = frames * channels as u32 * 2;
+    let mut wav = Vec::new();
+    wav.extend(b"RIFF");
+    wav.extend((36 + data_len).to_le_bytes());
+    wav.extend(b"WAVEfmt ");
+    wav.extend(16u32.to_le_bytes());
+    wav.extend(1u16.to_le_bytes());
+    wav.extend(channels.to_le_bytes());
+    wav.extend(sample_rate.to_le_bytes());
+    wav.extend((sample_rate * channels as u32 * 2).to_le_bytes());
+    wav.extend((channels * 2).to_le_bytes());
+    wav.extend(16u16.to_le_bytes());
+    wav.extend(b"data");
+    wav.extend(data_len.to_le_bytes());
+    wav.resize(wav.len() + data_len as usize, 0);
+    wav
+}
+
+/// 48 kHz mono samples as WebM with an Opus track in 20ms blocks.
+#[cfg(feature = "opus")]
+fn opus_webm(samples: &[i16]) -> Vec<u8> {
+    use audiopus::{
+        coder::Encoder,
+        Application,
+        Channels,
+        SampleRate,
+    };
+
+    fn element(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.push(0x01);
+        out.extend(&(body.len() as u64).to_be_bytes()[1..]);
+        out.extend(body);
+        out
+    }
+    const UNKNOWN_SIZE: [u8; 8] = [0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+
+    let mut opus_head = b"OpusHead".to_vec();
+    opus_head.extend([1, 1]);
+    opus_head.extend(312u16.to_le_bytes());
+    opus_head.extend(48_000u32.to_le_bytes());
+    opus_head.extend([0, 0, 0]);
+    let audio = [
+        element(&[0xb5], &48_000f64.to_be_bytes()),
+        element(&[0x9f], &[1]),
+    ]
+    .concat();
+    let track = [
+        element(&[0xd7], &[1]),
+        element(&[0x73, 0xc5], &[1]),
+        element(&[0x83], &[2]),
+        element(&[0x86], b"A_OPUS"),
+        element(&[0x63, 0xa2], &opus_head),
+        element(&[0xe1], &audio),
+    ]
+    .concat();
+
+    let mut webm = element(&[0x1a, 0x45, 0xdf, 0xa3], &element(&[0x42, 0x82], b"webm"));
+    webm.extend([0x18, 0x53, 0x80, 0x67]);
+    webm.extend(UNKNOWN_SIZE);
+    webm.extend(element(
+        &[0x15, 0x49, 0xa9, 0x66],
+        &element(&[0x2a, 0xd7, 0xb1], &1_000_000u32.to_be_bytes()),
+    ));
+    webm.extend(element(
+        &[0x16, 0x54, 0xae, 0x6b],
+        &element(&[0xae], &track),
+    ));
+    webm.extend([0x1f, 0x43, 0xb6, 0x75]);
+    webm.extend(UNKNOWN_SIZE);
+    webm.extend(element(&[0xe7], &[0]));
+
+    let encoder = Encoder::new(SampleRate::Hz48000, Channels::Mono, Application::Voip).unwrap();
+    let mut packet = [0u8; 4_000];
+    for (i, frame) in samples.chunks_exact(960).enumerate() {
+        let len = encoder.encode(frame, &mut packet).unwrap();
+        let mut block = vec![0x81];
+        block.extend(((i * 20) as i16).to_be_bytes());
+        block.push(0x80);
+        block.extend(&packet[..len]);
+        webm.extend(element(&[0xa3], &block));
+    }
+    webm
+}
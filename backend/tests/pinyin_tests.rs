@@ -0,0 +1,323 @@
+mod support;
+
+use mandarinpath_backend::pinyin::{
+    mark_tone,
+    parse::{
+        self,
+        PinyinError,
+    },
+    sandhi::{
+        self,
+        SandhiSyllable,
+    },
+    PinyinConverter,
+    Token,
+    Tones,
+};
+use serde_json::{
+    json,
+    Value,
+};
+
+fn numbered(text: &str, tones: Tones) -> String {
+    PinyinConverter::bundled().convert(text).numbered(tones)
+}
+
+#[test]
+fn test_polyphones_follow_the_word() {
+    assert_eq!(numbered("银行", Tones::Citation), "yin2 hang2");
+    assert_eq!(numbered("行走", Tones::Citation), "xing2 zou3");
+    assert_eq!(numbered("他长大了", Tones::Citation), "ta1 zhang3 da4 le5");
+    assert_eq!(
+        numbered("长城很长", Tones::Citation),
+        "chang2 cheng2 hen3 chang2"
+    );
+    assert_eq!(numbered("我了解", Tones::Citation), "wo3 liao3 jie3");
+    assert_eq!(numbered("我去睡觉", Tones::Citation), "wo3 qu4 shui4 jiao4");
+    assert_eq!(numbered("你觉得呢", Tones::Citation), "ni3 jue2 de5 ne5");
+    assert_eq!(
+        numbered("音乐很快乐", Tones::Citation),
+        "yin1 yue4 hen3 kuai4 le4"
+    );
+    // Neutral tones come from the word list too
+    assert_eq!(numbered("什么东西", Tones::Citation), "shen2 me5 dong1 xi5");
+}
+
+#[test]
+fn test_sandhi_tones() {
+    let cases = [
+        ("你好", "ni2 hao3"),
+        ("我很好", "wo2 hen2 hao3"),
+        ("不是", "bu2 shi4"),
+        ("不好", "bu4 hao3"),
+        ("我不", "wo3 bu4"),
+        ("是不是", "shi4 bu5 shi4"),
+        ("一个", "yi2 ge4"),
+        ("一天", "yi4 tian1"),
+        ("一起", "yi4 qi3"),
+        ("看一看", "kan4 yi5 kan4"),
+        ("第一天", "di4 yi1 tian1"),
+        ("十一", "shi2 yi1"),
+        ("统一了", "tong3 yi1 le5"),
+        ("一", "yi1"),
+    ];
+    for (text, expected) in cases {
+        assert_eq!(numbered(text, Tones::Sandhi), expected, "{}", text);
+    }
+
+    // Citation tones are untouched
+    assert_eq!(numbered("不是一个", Tones::Citation), "bu4 shi4 yi1 ge4");
+}
+
+#[test]
+fn test_punctuation_breaks_sandhi_phrases() {
+    let conversion = PinyinConverter::bundled().convert("我，你好！OK");
+    assert_eq!(conversion.numbered(Tones::Sandhi), "wo3 ni2 hao3");
+    assert_eq!(conversion.marked(Tones::Citation), "wǒ nǐ hǎo");
+    assert!(conversion.is_complete());
+    assert_eq!(
+        conversion.tokens.last(),
+        Some(&Token::Other {
+            text: "！OK".to_string()
+        })
+    );
+
+    let json = serde_json::to_value(&conversion.tokens[0]).unwrap();
+    assert_eq!(
+        json,
+        json!({"kind": "syllable", "hanzi": "我", "spelling": "wo", "citation_tone": 3, "sandhi_tone": 3})
+    );
+}
+
+#[test]
+fn test_tone_marks() {
+    assert_eq!(mark_tone("lv", 4), "lǜ");
+    assert_eq!(mark_tone("nv", 3), "nǚ");
+    assert_eq!(mark_tone("hao", 3), "hǎo");
+    assert_eq!(mark_tone("xue", 2), "xué");
+    assert_eq!(mark_tone("dou", 1), "dōu");
+    assert_eq!(mark_tone("gui", 4), "guì");
+    assert_eq!(mark_tone("liu", 2), "liú");
+    assert_eq!(mark_tone("ma", 5), "ma");
+    assert_eq!(numbered("绿", Tones::Citation), "lv4");
+}
+
+#[test]
+fn test_custom_dictionary_and_unknown_characters() {
+    let converter = PinyinConverter::from_tsv(
+        "# pinyin\tcharacters\nhang2\t行\nyin2\t银\nbad\t坏\n",
+        &["行\txing2\n", "银行\tyin2 hang2\tbank\n银\tyin2 hang2\n"],
+    );
+
+    assert_eq!(converter.convert("行").numbered(Tones::Citation), "xing2");
+    assert_eq!(
+        converter.convert("银行").numbered(Tones::Citation),
+        "yin2 hang2"
+    );
+
+    let conversion = converter.convert("坏");
+    assert!(!conversion.is_complete());
+    assert_eq!(
+        conversion.tokens,
+        [Token::Unknown {
+            text: "坏".to_string()
+        }]
+    );
+}
+
+#[test]
+fn test_sandhi_on_provider_words() {
+    // 一 at the end of a word keeps its tone even before a fourth tone
+    let syllable = |hanzi: char, tone: u8, word_final: bool| SandhiSyllable {
+        hanzi: Some(hanzi),
+        tone,
+        word_final,
+    };
+    let phrase = [
+        syllable('唯', 2, false),
+        syllable('一', 1, true),
+        syllable('次', 4, false),
+    ];
+    assert_eq!(sandhi::apply(&phrase), [2, 1, 4]);
+
+    let unknown = SandhiSyllable {
+        hanzi: None,
+        tone: 3,
+        word_final: false,
+    };
+    assert_eq!(sandhi::apply(&[unknown, unknown, unknown]), [2, 2, 3]);
+}
+
+#[test]
+fn test_parse_pinyin_styles() {
+    let numbered = |input: &str| parse::to_numbered(input).unwrap();
+    assert_eq!(numbered("ni3 hao3"), "ni3 hao3");
+    assert_eq!(numbered("ni3hao3"), "ni3 hao3");
+    assert_eq!(numbered("Nǐ hǎo"), "ni3 hao3");
+    assert_eq!(numbered("nǐhǎo"), "ni3 hao3");
+    assert_eq!(numbered("ni\u{30C}ha\u{30C}o"), "ni3 hao3");
+    // Toneless syllables read as neutral
+    assert_eq!(numbered("nihao"), "ni5 hao5");
+    assert_eq!(numbered("ma5"), "ma5");
+
+    // Longer syllables win unless an apostrophe splits them
+    assert_eq!(numbered("xian1"), "xian1");
+    assert_eq!(numbered("Xi1'an1"), "xi1 an1");
+    assert_eq!(numbered("Xī’ān"), "xi1 an1");
+    assert_eq!(numbered("pi2ao3"), "pi2 ao3");
+    assert_eq!(numbered("zhuang4-tai4"), "zhuang4 tai4");
+
+    // ü in every spelling
+    for input in ["nv3", "nü3", "nǚ", "nu:3", "NU:3", "nu\u{308}3"] {
+        assert_eq!(numbered(input), "nv3", "{}", input);
+    }
+    assert_eq!(numbered("lüe4"), "lve4");
+    assert_eq!(numbered("lue4"), "lve4");
+    assert_eq!(numbered("qv4 xue2"), "qu4 xue2");
+
+    assert_eq!(parse::to_marked("lv4 nv3 hao3").unwrap(), "lǜ nǚ hǎo");
+    assert_eq!(parse::to_marked("shen2me5").unwrap(), "shén me");
+
+    let syllables = parse::parse("zhong1 guo2").unwrap();
+    assert_eq!(syllables[1].spelling, "guo");
+    assert_eq!(syllables[1].tone, Some(2));
+    assert_eq!(syllables[1].position, 7);
+}
+
+#[test]
+fn test_pinyin_errors_report_positions() {
+    let error = |input: &str| parse::parse(input).unwrap_err();
+
+    assert_eq!(
+        error("ni3 hoa3"),
+        PinyinError::UnknownSyllable {
+            position: 4,
+            text: "hoa".to_string()
+        }
+    );
+    assert_eq!(
+        error("ni7"),
+        PinyinError::InvalidTone {
+            position: 2,
+            tone: '7'
+        }
+    );
+    assert_eq!(error("ni33"), PinyinError::ExtraTone { position: 3 });
+    assert_eq!(error("nǐ3"), PinyinError::ExtraTone { position: 2 });
+    assert_eq!(error("nǐhǎǒ"), PinyinError::ExtraTone { position: 4 });
+    assert_eq!(
+        error("ni3, hao3"),
+        PinyinError::UnexpectedCharacter {
+            position: 3,
+            found: ','
+        }
+    );
+    assert_eq!(
+        error("3ni"),
+        PinyinError::UnexpectedCharacter {
+            position: 0,
+            found: '3'
+        }
+    );
+    assert_eq!(error("  "), PinyinError::Empty);
+    assert_eq!(error("ni3 hoa3").position(), Some(4));
+
+    assert_eq!(
+        parse::normalize_reference("ni hao"),
+        Err(PinyinError::MissingTones)
+    );
+    assert_eq!(parse::normalize_reference("nǐ hao").unwrap(), "ni3 hao5");
+}
+
+#[test]
+fn test_bundled_pinyin_is_valid() {
+    let data = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("data");
+    // File and the columns holding pinyin
+    let files = [
+        ("pinyin/readings.tsv", &[0][..]),
+        ("pinyin/words.tsv", &[1]),
+        ("drills/lexicon.tsv", &[1]),
+        ("drills/minimal_pairs.tsv", &[1, 4]),
+    ];
+    for (file, columns) in files {
+        let tsv = std::fs::read_to_string(data.join(file)).unwrap();
+        let lines = tsv
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'));
+        for line in lines {
+            let fields: Vec<&str> = line.split('\t').collect();
+            for &column in columns {
+                let pinyin = fields[column];
+                let syllables =
+                    parse::parse(pinyin).unwrap_or_else(|e| panic!("{}: {:?}: {}", file, line, e));
+                assert!(
+                    syllables.iter().all(|syllable| syllable.tone.is_some()),
+                    "{}: {:?} is missing a tone",
+                    file,
+                    line
+                );
+                assert_eq!(parse::to_numbered(pinyin).unwrap(), pinyin, "{}", file);
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_evaluate_fills_in_missing_ref_pinyin() {
+    let (server, _db, _temp_dir) = support::test_server().await;
+
+    let evaluate = |params: Value| {
+        let boundary = "----pinyin-boundary";
+        let data_len = 8_000u32;
+        let mut body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"params\"\r\n\r\n{params}\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"audio\"; filename=\"a.wav\"\r\n\
+             Content-Type: audio/wav\r\n\r\n",
+            b = boundary,
+        )
+        .into_bytes();
+        body.extend(b"RIFF");
+        body.extend((36 + data_len).to_le_bytes());
+        body.extend(b"WAVEfmt ");
+        body.extend(16u32.to_le_bytes());
+        body.extend(1u16.to_le_bytes());
+        body.extend(1u16.to_le_bytes());
+        body.extend(16_000u32.to_le_bytes());
+        body.extend(32_000u32.to_le_bytes());
+        body.extend(2u16.to_le_bytes());
+        body.extend(16u16.to_le_bytes());
+        body.extend(b"data");
+        body.extend(data_len.to_le_bytes());
+        body.extend(vec![0u8; data_len as usize]);
+        body.extend(format!("\r\n--{}--\r\n", boundary).into_bytes());
+
+        server
+            .post("/speech/evaluate")
+            .bytes(body.into())
+            .content_type(&format!("multipart/form-data; boundary={}", boundary))
+    };
+
+    let response = evaluate(json!({"ref_text": "去银行"})).await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    let pinyin: Vec<&str> = body["data"]["words"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|word| word["pinyin"].as_str().unwrap())
+        .collect();
+    assert_eq!(pinyin, ["qu4", "yin2", "hang2"]);
+
+    // Pinyin the client supplied is kept, rewritten as numbered syllables
+    let response = evaluate(json!({"ref_text": "去银行", "ref_pinyin": "qù yín xíng"})).await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["data"]["words"][2]["pinyin"], "xing2");
+
+    for ref_pinyin in ["qu4 yin2 hoang2", "qu4 yin2", "qu yin hang"] {
+        let response = evaluate(json!({"ref_text": "去银行", "ref_pinyin": ref_pinyin})).await;
+        response.assert_status_bad_request();
+        let body: Value = response.json();
+        assert_eq!(body["code"], "INVALID_SPEECH_REQUEST", "{}", ref_pinyin);
+    }
+}
@@ -0,0 +1,262 @@
+mod support;
+
+use std::{
+    net::{
+        IpAddr,
+        Ipv4Addr,
+        SocketAddr,
+    },
+    time::Duration,
+};
+
+use axum::{
+    http::{
+        header,
+        HeaderName,
+        HeaderValue,
+        StatusCode,
+    },
+    routing::get,
+    Extension,
+    Router,
+};
+use axum_test::{
+    TestResponse,
+    TestServer,
+};
+use mandarinpath_backend::{
+    config::{
+        RateLimit,
+        RateLimitKey,
+    },
+    middleware::rate_limit::RateLimiter,
+    *,
+};
+use serde_json::json;
+use support::{
+    connected_test_server_with,
+    database_url,
+    test_config,
+};
+use tempfile::TempDir;
+
+fn limit(key: RateLimitKey, max_requests: u32) -> RateLimit {
+    RateLimit {
+        key,
+        max_requests,
+        window: Duration::from_secs(600),
+    }
+}
+
+/// Auth limited per IP and per email, behind a proxy on the loopback address.
+async fn limited_server() -> (TestServer, db::Database, TempDir) {
+    limited_server_trusting(vec![Ipv4Addr::LOCALHOST.into()]).await
+}
+
+async fn limited_server_trusting(
+    trusted_proxies: Vec<IpAddr>,
+) -> (TestServer, db::Database, TempDir) {
+    connected_test_server_with(|config| {
+        config.rate_limits = config::RateLimitConfig {
+            auth: vec![limit(RateLimitKey::Ip, 5), limit(RateLimitKey::Email, 3)],
+            speech: vec![],
+        };
+        config.trusted_proxies = trusted_proxies;
+    })
+    .await
+}
+
+/// Sign in with a wrong password, with `ip` as the `X-Forwarded-For` header.
+async fn login(server: &TestServer, ip: &str, email: &str) -> TestResponse {
+    server
+        .post("/auth/login")
+        .json(&json!({"email": email, "password": "wrong-password"}))
+        .add_header(
+            HeaderName::from_static("x-forwarded-for"),
+            HeaderValue::from_str(ip).unwrap(),
+        )
+        .await
+}
+
+fn retry_after(response: &TestResponse) -> u64 {
+    response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .expect("Retry-After header")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_login_limited_per_email() {
+    let (server, db, _temp_dir) = limited_server().await;
+
+    // Spreading attempts over addresses does not reset the count for an account
+    for ip in ["10.0.0.1", "10.0.0.2", "10.0.0.3"] {
+        login(&server, ip, "victim@example.com")
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+    let response = login(&server, "10.0.0.4", " Victim@Example.com").await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.json::<serde_json::Value>()["code"], "RATE_LIMITED");
+    let wait = retry_after(&response);
+    assert!(wait > 590 && wait <= 600, "Retry-After {}", wait);
+
+    // Other accounts are unaffected
+    login(&server, "10.0.0.4", "someone@example.com")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // Once the window has run out the account can be tried again
+    sqlx::query("UPDATE rate_limits SET expires_at = ?")
+        .bind((chrono::Utc::now() - chrono::Duration::seconds(1)).naive_utc())
+        .execute(db.pool())
+        .await
+        .unwrap();
+    login(&server, "10.0.0.5", "victim@example.com")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_auth_limited_per_ip() {
+    let (server, _db, _temp_dir) = limited_server().await;
+
+    for n in 0..5 {
+        login(&server, "10.0.0.1", &format!("user{}@example.com", n))
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+    // Registration is in the same group
+    let response = server
+        .post("/auth/register")
+        .json(&json!({"email": "new@example.com", "password": "correct-horse-battery"}))
+        .add_header(
+            HeaderName::from_static("x-forwarded-for"),
+            HeaderValue::from_static("10.0.0.1"),
+        )
+        .await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    assert!(retry_after(&response) > 0);
+
+    login(&server, "10.0.0.2", "user0@example.com")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_spoofed_forwarded_for_is_still_limited() {
+    let (server, _db, _temp_dir) = limited_server().await;
+
+    // Only the entry the proxy appended counts; whatever the client put before
+    // it does not make a new key
+    for n in 0..5 {
+        let forwarded = format!("198.51.100.{}, 10.0.0.1", n);
+        login(&server, &forwarded, &format!("user{}@example.com", n))
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+    let response = login(&server, "198.51.100.99, 10.0.0.1", "user9@example.com").await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+    // Entries added by further trusted proxies are passed over
+    login(&server, "10.0.0.2, 127.0.0.1", "user0@example.com")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_forwarded_for_ignored_from_untrusted_peers() {
+    let (server, _db, _temp_dir) = limited_server_trusting(Vec::new()).await;
+
+    // Every request is counted against the peer, whatever it claims
+    for n in 0..5 {
+        login(
+            &server,
+            &format!("10.0.0.{}", n),
+            &format!("user{}@example.com", n),
+        )
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    }
+    login(&server, "10.0.0.9", "user9@example.com")
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_oversized_body_is_refused() {
+    let (server, _db, _temp_dir) = limited_server().await;
+
+    // The email has to be read from the body, which is only buffered up to a point
+    server
+        .post("/auth/login")
+        .json(&json!({
+            "email": "big@example.com",
+            "password": "x".repeat(128 * 1024),
+        }))
+        .await
+        .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn test_user_limits_fall_back_to_ip() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = test_config(database_url(&temp_dir));
+    let db = db::Database::new(&config.database_url)
+        .await
+        .expect("Failed to connect to database");
+    let jwt_service = auth::jwt::JwtService::new(&config);
+    let limiter = RateLimiter::new(db, vec![Ipv4Addr::LOCALHOST.into()]);
+    let app = Router::new()
+        .route(
+            "/ping",
+            get(|| async { "pong" })
+                .layer(limiter.layer("test", vec![limit(RateLimitKey::User, 2)])),
+        )
+        .layer(Extension(jwt_service.clone()));
+    let server = TestServer::new(app.into_make_service_with_connect_info::<SocketAddr>()).unwrap();
+
+    let ping = |token: Option<String>, ip: &'static str| {
+        let mut request = server.get("/ping").add_header(
+            HeaderName::from_static("x-forwarded-for"),
+            HeaderValue::from_static(ip),
+        );
+        if let Some(token) = token {
+            request = request.add_header(
+                HeaderName::from_static("authorization"),
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            );
+        }
+        request
+    };
+    let alice = jwt_service
+        .create_access_token("alice", "session-a")
+        .unwrap();
+    let bob = jwt_service.create_access_token("bob", "session-b").unwrap();
+
+    // Signed-in callers are counted by user, wherever they connect from
+    ping(Some(alice.clone()), "10.0.0.1")
+        .await
+        .assert_status_ok();
+    ping(Some(alice.clone()), "10.0.0.2")
+        .await
+        .assert_status_ok();
+    ping(Some(alice), "10.0.0.3")
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+    ping(Some(bob), "10.0.0.1").await.assert_status_ok();
+
+    // Anonymous callers, and ones with a bad token, by address
+    ping(None, "10.0.0.1").await.assert_status_ok();
+    ping(Some("not-a-token".to_string()), "10.0.0.1")
+        .await
+        .assert_status_ok();
+    ping(None, "10.0.0.1")
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+    ping(None, "10.0.0.2").await.assert_status_ok();
+}
@@ -1,3 +1,5 @@
+mod support;
+
 use axum::http::HeaderValue;
 use axum_test::TestServer;
 use mandarinpath_backend::*;
@@ -9,12 +11,8 @@ use mandarinpath_backend::*;
 async fn test_full_speech_evaluation_flow() {
     // Create real configuration
     let config = config::Config {
-        database_url: format!("sqlite::memory:speech_flow_{}", std::process::id()),
-        frontend_url: "http://localhost:5173".to_string(),
-        port: 3000,
-        debug_mode: true,
-        verbosity: 0,
-        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: Default::default(),
+        ..support::test_config(format!("sqlite::memory:speech_flow_{}", std::process::id()))
     };
 
     let db = db::Database::new(&config.database_url)
@@ -43,8 +41,14 @@ async fn test_full_speech_evaluation_flow() {
     health_response.assert_status_ok();
 
     let health_body: serde_json::Value = health_response.json();
-    assert_eq!(health_body["status"], "ok");
     assert_eq!(health_body["service"], "speech_evaluation");
+    // Without IFLYTEK_* credentials in the environment the provider is degraded
+    let expected_status = if health_body["credentials_configured"] == true {
+        "ok"
+    } else {
+        "degraded"
+    };
+    assert_eq!(health_body["status"], expected_status);
 
     // Test 2: Speech evaluation endpoint accepts multipart data
     // (Note: This will fail to connect to iFlytek in tests, but tests the integration)
@@ -80,12 +84,8 @@ async fn test_full_speech_evaluation_flow() {
 async fn test_authentication_and_session_flow() {
     // Create unique test database
     let config = config::Config {
-        database_url: format!("sqlite::memory:auth_flow_{}", std::process::id()),
-        frontend_url: "http://localhost:5173".to_string(),
-        port: 3000,
-        debug_mode: true,
-        verbosity: 0,
-        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: Default::default(),
+        ..support::test_config(format!("sqlite::memory:auth_flow_{}", std::process::id()))
     };
 
     let db = db::Database::new(&config.database_url)
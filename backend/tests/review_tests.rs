@@ -0,0 +1,408 @@
+mod support;
+
+use axum::http::{
+    HeaderName,
+    HeaderValue,
+    StatusCode,
+};
+use axum_test::TestServer;
+use chrono::{
+    Duration,
+    Utc,
+};
+use mandarinpath_backend::{
+    speech::iflytek::{
+        WordScore,
+        WordScores,
+    },
+    vocabulary::{
+        review::ReviewService,
+        scheduler::{
+            self,
+            Algorithm,
+            CardState,
+            Grade,
+            Memory,
+            SchedulerSettings,
+        },
+        speech::grade_spoken_words,
+    },
+};
+use serde_json::{
+    json,
+    Value,
+};
+use support::{
+    bearer,
+    evaluation_body,
+    register,
+    test_server,
+    BOUNDARY,
+};
+
+const FSRS: SchedulerSettings = SchedulerSettings {
+    algorithm: Algorithm::Fsrs,
+    desired_retention: 0.9,
+    maximum_interval_days: 36_500,
+};
+
+async fn add_words(server: &TestServer, header: &(HeaderName, HeaderValue), words: &[&str]) {
+    for word in words {
+        server
+            .post("/words")
+            .add_header(header.0.clone(), header.1.clone())
+            .json(&json!({"chinese": word, "definition": "-"}))
+            .await
+            .assert_status_ok();
+    }
+}
+
+fn word_score(word: &str, overall: f32, read_type: u8) -> WordScore {
+    WordScore {
+        word: word.to_string(),
+        pinyin: None,
+        tone: None,
+        scores: WordScores {
+            overall,
+            pronunciation: overall,
+            tone: None,
+            prominence: None,
+        },
+        read_type,
+        span: None,
+        phonemes: None,
+    }
+}
+
+#[test]
+fn test_fsrs_schedule() {
+    let now = Utc::now();
+    let first = |grade| Memory::default().review(grade, now, &FSRS);
+
+    // First reviews take the initial stabilities, which at 90% retention are the intervals
+    assert_eq!(first(Grade::Again).interval_days, 1);
+    assert_eq!(first(Grade::Good).interval_days, 4);
+    assert_eq!(first(Grade::Easy).interval_days, 14);
+    assert_eq!(first(Grade::Good).state, CardState::Review);
+    assert_eq!(first(Grade::Again).state, CardState::Relearning);
+    assert_eq!(first(Grade::Again).lapses, 0);
+    assert!(first(Grade::Easy).difficulty < first(Grade::Hard).difficulty);
+
+    // Recalled on time, the interval grows; forgotten, it collapses
+    let good = first(Grade::Good);
+    let later = now + Duration::days(4);
+    let again = good.review(Grade::Good, later, &FSRS);
+    assert!(again.interval_days > 10, "{:?}", again);
+    assert_eq!(again.repetitions, 2);
+    let lapsed = good.review(Grade::Again, later, &FSRS);
+    assert_eq!(lapsed.lapses, 1);
+    assert_eq!(lapsed.repetitions, 0);
+    assert!(lapsed.stability < good.stability);
+
+    // Asking for less retention spaces reviews further apart
+    let relaxed = SchedulerSettings {
+        desired_retention: 0.8,
+        ..FSRS
+    };
+    assert!(good.review(Grade::Good, later, &relaxed).interval_days > again.interval_days);
+    let capped = SchedulerSettings {
+        maximum_interval_days: 7,
+        ..FSRS
+    };
+    assert_eq!(good.review(Grade::Easy, later, &capped).interval_days, 7);
+
+    let recall = scheduler::retrievability(4.0, 4.0);
+    assert!((recall - 0.9).abs() < 1e-9);
+    assert_eq!(scheduler::strength(0), 0);
+    assert_eq!(scheduler::strength(365), 100);
+    assert!(scheduler::strength(4) < scheduler::strength(30));
+}
+
+#[test]
+fn test_sm2_schedule() {
+    let sm2 = SchedulerSettings {
+        algorithm: Algorithm::Sm2,
+        ..FSRS
+    };
+    let now = Utc::now();
+
+    let mut memory = Memory::default();
+    let mut intervals = Vec::new();
+    for _ in 0..3 {
+        memory = memory.review(Grade::Good, now, &sm2);
+        intervals.push(memory.interval_days);
+    }
+    assert_eq!(intervals, [1, 6, 15]);
+    assert_eq!(memory.ease_factor, 2.5);
+
+    let lapsed = memory.review(Grade::Again, now, &sm2);
+    assert_eq!(lapsed.interval_days, 1);
+    assert_eq!(lapsed.lapses, 1);
+    assert!((lapsed.ease_factor - 1.96).abs() < 1e-9);
+
+    // Switching to FSRS starts its memory from the next grade
+    let switched = memory.review(Grade::Good, now, &FSRS);
+    assert_eq!(switched.interval_days, 4);
+    assert_eq!(switched.repetitions, 4);
+}
+
+#[tokio::test]
+async fn test_review_queue_and_grading() {
+    let (server, db, _temp_dir) = test_server().await;
+    let auth = register(&server, "reviews@example.com").await;
+    let user_id = auth["user"]["id"].as_str().unwrap();
+    let header = bearer(&auth);
+    add_words(&server, &header, &["你好", "谢谢", "银行"]).await;
+
+    server
+        .put("/reviews/settings")
+        .add_header(header.0.clone(), header.1.clone())
+        .json(&json!({"new_cards_per_day": 2}))
+        .await
+        .assert_status_ok();
+    server
+        .put("/reviews/settings")
+        .add_header(header.0.clone(), header.1.clone())
+        .json(&json!({"desired_retention": 0.5}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Only two of the three new words fit today's allowance, oldest first
+    let queue: Value = server
+        .get("/reviews/due")
+        .add_header(header.0.clone(), header.1.clone())
+        .await
+        .json();
+    let words = queue["words"].as_array().unwrap();
+    assert_eq!(words.len(), 2);
+    assert_eq!(words[0]["chinese"], "你好");
+    assert_eq!(queue["new_count"], 2);
+    assert_eq!(queue["due_count"], 0);
+
+    let id = words[0]["id"].as_str().unwrap().to_string();
+    let response = server
+        .post(&format!("/reviews/{}/grade", id))
+        .add_header(header.0.clone(), header.1.clone())
+        .json(&json!({"grade": "good"}))
+        .await;
+    response.assert_status_ok();
+    let graded: Value = response.json();
+    assert_eq!(graded["word"]["totalAttempts"], 1);
+    assert_eq!(graded["word"]["correctAttempts"], 1);
+    assert!(graded["word"]["nextReview"].is_string());
+    assert!(graded["word"]["strength"].as_i64().unwrap() > 0);
+    assert_eq!(graded["log"]["grade"], "good");
+    assert_eq!(graded["log"]["state"], "new");
+    assert_eq!(graded["log"]["interval_days"], 4);
+
+    // The graded word is no longer new, and used up one of today's new cards
+    let queue: Value = server
+        .get("/reviews/due")
+        .add_header(header.0.clone(), header.1.clone())
+        .await
+        .json();
+    assert_eq!(queue["new_remaining_today"], 1);
+    assert_eq!(queue["words"].as_array().unwrap().len(), 1);
+    assert_eq!(queue["words"][0]["chinese"], "谢谢");
+
+    // Four days on, it is due ahead of new words
+    let reviews = ReviewService::new(db);
+    let later = Utc::now() + Duration::days(4) + Duration::hours(1);
+    let queue = reviews.queue(user_id, later, 20).await.unwrap();
+    assert_eq!(queue.due_count, 1);
+    assert_eq!(queue.words[0].chinese, "你好");
+    let graded = reviews
+        .grade(user_id, &id, Grade::Again, later)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(graded.log.state, CardState::Review);
+    assert_eq!(graded.word.total_attempts, 2);
+    assert_eq!(graded.word.correct_attempts, 1);
+
+    let log: Value = server
+        .get(&format!("/reviews/{}/log", id))
+        .add_header(header.0.clone(), header.1.clone())
+        .await
+        .json();
+    let grades: Vec<&str> = log["reviews"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|review| review["grade"].as_str().unwrap())
+        .collect();
+    assert_eq!(grades, ["good", "again"]);
+
+    // Other users cannot grade the word
+    let other = bearer(&register(&server, "other-reviews@example.com").await);
+    server
+        .post(&format!("/reviews/{}/grade", id))
+        .add_header(other.0, other.1)
+        .json(&json!({"grade": "easy"}))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_grade_spoken_words() {
+    let vocabulary: Vec<(String, String)> =
+        [("1", "你好"), ("2", "好"), ("3", "银行"), ("4", "谢谢")]
+            .iter()
+            .map(|(id, chinese)| (id.to_string(), chinese.to_string()))
+            .collect();
+    let scores = [
+        // Characters scored one by one still make up a word
+        word_score("你", 92.0, 0),
+        word_score("好", 96.0, 0),
+        // Inserted words were never in the reference text
+        word_score("谢谢", 95.0, 1),
+        word_score("去银行", 70.0, 0),
+        word_score("谢", 80.0, 0),
+        word_score("谢", 0.0, 2),
+    ];
+
+    let graded: Vec<(String, Grade, bool)> =
+        grade_spoken_words("你好，去银行。谢谢", &scores, &vocabulary)
+            .into_iter()
+            .map(|word| (word.chinese, word.grade, word.omitted))
+            .collect();
+    assert_eq!(
+        graded,
+        [
+            ("你好".to_string(), Grade::Easy, false),
+            ("银行".to_string(), Grade::Hard, false),
+            ("谢谢".to_string(), Grade::Again, true),
+        ]
+    );
+
+    // A word read twice is graded by its worst reading
+    let scores = [
+        word_score("好", 95.0, 0),
+        word_score("好", 50.0, 0),
+        word_score("好", 80.0, 0),
+    ];
+    let graded = grade_spoken_words("好，好，好", &scores, &vocabulary);
+    assert_eq!(graded.len(), 1);
+    assert_eq!(graded[0].grade, Grade::Again);
+    assert_eq!(graded[0].score, Some(50.0));
+
+    // A word is only read where it is a whole word of the text, not a part of one
+    let vocabulary: Vec<(String, String)> = [("1", "生"), ("2", "学")]
+        .iter()
+        .map(|(id, chinese)| (id.to_string(), chinese.to_string()))
+        .collect();
+    let scores = [
+        word_score("我", 90.0, 0),
+        word_score("是", 90.0, 0),
+        word_score("学生", 90.0, 0),
+    ];
+    assert!(grade_spoken_words("我是学生", &scores, &vocabulary).is_empty());
+}
+
+#[tokio::test]
+async fn test_speech_reviews_keep_to_the_new_card_limit() {
+    let (server, _db, _temp_dir) = test_server().await;
+    let header = bearer(&register(&server, "limited@example.com").await);
+    add_words(&server, &header, &["你好", "银行"]).await;
+    server
+        .put("/reviews/settings")
+        .add_header(header.0.clone(), header.1.clone())
+        .json(&json!({"new_cards_per_day": 1}))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post("/speech/evaluate")
+        .add_header(header.0.clone(), header.1.clone())
+        .bytes(evaluation_body(&json!({"ref_text": "你好，银行"})).into())
+        .content_type(&format!("multipart/form-data; boundary={}", BOUNDARY))
+        .await;
+    response.assert_status_ok();
+    let reviews = response.json::<Value>()["reviews"].clone();
+    assert_eq!(reviews.as_array().unwrap().len(), 1);
+    assert_eq!(reviews[0]["chinese"], "你好");
+
+    // The day's allowance is spent, for the queue as well
+    let queue: Value = server
+        .get("/reviews/due")
+        .add_header(header.0, header.1)
+        .await
+        .json();
+    assert_eq!(queue["new_remaining_today"], 0);
+}
+
+#[tokio::test]
+async fn test_speech_evaluation_reviews_vocabulary() {
+    let (server, _db, _temp_dir) = test_server().await;
+    let header = bearer(&register(&server, "speaking@example.com").await);
+    add_words(&server, &header, &["你好", "银行"]).await;
+
+    let response = server
+        .post("/speech/evaluate")
+        .add_header(header.0.clone(), header.1.clone())
+        .bytes(evaluation_body(&json!({"ref_text": "你好，谢谢"})).into())
+        .content_type(&format!("multipart/form-data; boundary={}", BOUNDARY))
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    let reviews = body["reviews"].as_array().unwrap();
+    assert_eq!(reviews.len(), 1);
+    assert_eq!(reviews[0]["chinese"], "你好");
+    let id = reviews[0]["word_id"].as_str().unwrap();
+
+    let word: Value = server
+        .get(&format!("/words/{}", id))
+        .add_header(header.0.clone(), header.1.clone())
+        .await
+        .json();
+    assert_eq!(word["totalAttempts"], 1);
+    assert_eq!(word["tasks"]["speaking"]["attempts"], 1);
+    assert_eq!(word["tasks"]["speaking"]["lastScore"], reviews[0]["score"]);
+    assert_eq!(word["nextReview"], reviews[0]["next_review"]);
+
+    let log: Value = server
+        .get(&format!("/reviews/{}/log", id))
+        .add_header(header.0.clone(), header.1.clone())
+        .await
+        .json();
+    assert_eq!(log["reviews"][0]["source"], "speech");
+    assert_eq!(log["reviews"][0]["speech_attempt_id"], body["attempt_id"]);
+    assert_eq!(log["reviews"][0]["grade"], reviews[0]["grade"]);
+
+    // A card for a character inside a longer word is not reviewed by reading the word
+    add_words(&server, &header, &["生", "学生"]).await;
+    let response = server
+        .post("/speech/evaluate")
+        .add_header(header.0.clone(), header.1.clone())
+        .bytes(evaluation_body(&json!({"ref_text": "我是学生"})).into())
+        .content_type(&format!("multipart/form-data; boundary={}", BOUNDARY))
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    let reviewed: Vec<&str> = body["reviews"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|review| review["chinese"].as_str().unwrap())
+        .collect();
+    assert_eq!(reviewed, ["学生"]);
+
+    // Words are not reviewed again before they are due
+    let response = server
+        .post("/speech/evaluate")
+        .add_header(header.0.clone(), header.1.clone())
+        .bytes(evaluation_body(&json!({"ref_text": "我是学生"})).into())
+        .content_type(&format!("multipart/form-data; boundary={}", BOUNDARY))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<Value>()["reviews"], json!([]));
+
+    // Anonymous evaluations review nothing
+    let response = server
+        .post("/speech/evaluate")
+        .bytes(evaluation_body(&json!({"ref_text": "你好"})).into())
+        .content_type(&format!("multipart/form-data; boundary={}", BOUNDARY))
+        .await;
+    response.assert_status_ok();
+    assert!(response.json::<Value>().get("reviews").is_none());
+}
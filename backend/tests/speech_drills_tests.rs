@@ -0,0 +1,312 @@
+mod support;
+
+use std::collections::HashMap;
+
+use axum::http::{
+    HeaderName,
+    HeaderValue,
+    StatusCode,
+};
+use axum_test::TestServer;
+use bytes::Bytes;
+use mandarinpath_backend::{
+    pinyin::parse::parse,
+    speech::{
+        analytics::SoundKind,
+        drills::{
+            DrillGenerator,
+            DrillTarget,
+        },
+        history::{
+            AttemptContext,
+            SpeechHistoryService,
+        },
+        iflytek::{
+            PhonemeScore,
+            WordScore,
+            WordScores,
+        },
+        SpeechEvaluationResponse,
+    },
+    *,
+};
+use rand::{
+    rngs::StdRng,
+    SeedableRng,
+};
+use serde_json::Value;
+use tempfile::TempDir;
+
+fn data_lines(tsv: &str) -> usize {
+    tsv.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .count()
+}
+
+#[test]
+fn test_bundled_word_lists_are_valid() {
+    let generator = DrillGenerator::bundled();
+
+    assert_eq!(
+        generator.words().count(),
+        data_lines(include_str!("../data/drills/lexicon.tsv"))
+    );
+    // Every curated pair differs in exactly one sound
+    assert_eq!(
+        generator.minimal_pairs().len(),
+        data_lines(include_str!("../data/drills/minimal_pairs.tsv"))
+    );
+    for word in generator.words() {
+        assert_eq!(
+            parse(&word.ref_pinyin).unwrap().len(),
+            word.ref_text.chars().count(),
+            "{} has one syllable per character",
+            word.ref_text
+        );
+    }
+}
+
+#[test]
+fn test_drill_target_parsing() {
+    assert_eq!(
+        DrillTarget::parse("zh"),
+        Some(DrillTarget::Initial {
+            sound: "zh".to_string()
+        })
+    );
+    assert_eq!(
+        DrillTarget::parse("ü"),
+        Some(DrillTarget::Final {
+            sound: "v".to_string()
+        })
+    );
+    assert_eq!(
+        DrillTarget::parse("tone3"),
+        Some(DrillTarget::Tone { tone: 3 })
+    );
+    assert_eq!(
+        DrillTarget::parse(" 3-tone3 "),
+        Some(DrillTarget::TonePair {
+            first: 3,
+            second: 3
+        })
+    );
+    assert_eq!(DrillTarget::parse("tone6"), None);
+    assert_eq!(DrillTarget::parse("z h"), None);
+    assert_eq!(DrillTarget::parse(""), None);
+}
+
+#[test]
+fn test_initial_drill_words_and_minimal_pairs() {
+    let generator = DrillGenerator::bundled();
+    let target = DrillTarget::parse("zh").unwrap();
+    let drill = generator.drill(&target, 5, &mut StdRng::seed_from_u64(7));
+
+    assert_eq!(drill.sound, "zh");
+    assert_eq!(drill.words.len(), 5);
+    for word in &drill.words {
+        assert!(
+            parse(&word.ref_pinyin)
+                .unwrap()
+                .iter()
+                .any(|s| s.spelling.starts_with("zh")),
+            "{} exercises zh",
+            word.ref_pinyin
+        );
+    }
+
+    // zh/z pairs, with the zh word first
+    assert!(!drill.minimal_pairs.is_empty());
+    for pair in &drill.minimal_pairs {
+        assert_eq!(pair.contrast.kind, SoundKind::Initial);
+        assert_eq!(pair.contrast.first, "zh");
+        assert!(pair.first.ref_pinyin.starts_with("zh"));
+    }
+    assert!(drill
+        .minimal_pairs
+        .iter()
+        .any(|pair| pair.first.ref_text == "纸" && pair.second.ref_text == "紫"));
+
+    // Minimal pairs listed the other way round are flipped for a z drill
+    let z = generator.drill(
+        &DrillTarget::parse("z").unwrap(),
+        50,
+        &mut StdRng::seed_from_u64(7),
+    );
+    assert!(z
+        .minimal_pairs
+        .iter()
+        .any(|pair| pair.first.ref_text == "紫" && pair.contrast.second == "zh"));
+}
+
+#[test]
+fn test_tone_drills() {
+    let generator = DrillGenerator::bundled();
+    let mut rng = StdRng::seed_from_u64(3);
+
+    // Third-tone sandhi needs two third tones side by side
+    let sandhi = generator.drill(&DrillTarget::parse("3-3").unwrap(), 50, &mut rng);
+    assert_eq!(sandhi.sound, "tone3-tone3");
+    assert!(sandhi.words.iter().any(|w| w.ref_text == "你好"));
+    assert!(sandhi.minimal_pairs.is_empty());
+    for word in &sandhi.words {
+        assert!(
+            word.ref_pinyin.contains("3 ") && parse(&word.ref_pinyin).unwrap().len() >= 2,
+            "{} has adjacent third tones",
+            word.ref_pinyin
+        );
+    }
+
+    // Multi-syllable pairs may differ the same way in every syllable
+    let tone4 = generator.drill(&DrillTarget::Tone { tone: 4 }, 50, &mut rng);
+    let dumplings = tone4
+        .minimal_pairs
+        .iter()
+        .find(|pair| pair.second.ref_text == "水饺")
+        .unwrap();
+    assert_eq!(dumplings.first.ref_text, "睡觉");
+    assert_eq!(dumplings.contrast.first, "tone4");
+    assert_eq!(dumplings.contrast.second, "tone3");
+
+    // Drill words are ready to evaluate
+    let request = sandhi.words[0].to_request(Bytes::from_static(&[0, 0]));
+    assert_eq!(request.ref_text, sandhi.words[0].ref_text);
+    assert_eq!(
+        request.ref_pinyin.as_deref(),
+        Some(sandhi.words[0].ref_pinyin.as_str())
+    );
+    assert_eq!(request.lang, "cn");
+    assert_eq!(request.audio_encoding, "raw");
+    assert_eq!(request.sample_rate, 16000);
+}
+
+#[test]
+fn test_minimal_pairs_must_differ_in_one_sound() {
+    let generator = DrillGenerator::from_tsv(
+        "",
+        "# a comment\n\
+         山\tshan1\tmountain\t三\tsan1\tthree\n\
+         山\tshan1\tmountain\t伞\tsan3\tumbrella\n\
+         你好\tni3 hao3\thello\t你\tni3\tyou\n\
+         小\txiao3\tsmall\n",
+    );
+
+    assert_eq!(generator.minimal_pairs().len(), 1);
+    let contrast = &generator.minimal_pairs()[0].contrast;
+    assert_eq!(contrast.kind, SoundKind::Initial);
+    assert_eq!(
+        (contrast.first.as_str(), contrast.second.as_str()),
+        ("sh", "s")
+    );
+}
+
+#[tokio::test]
+async fn test_drills_endpoint() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = support::test_config(support::database_url(&temp_dir));
+    let db = db::Database::new(&config.database_url)
+        .await
+        .expect("Failed to connect to database");
+
+    let user_id = "drills-user";
+    sqlx::query("INSERT INTO users (id, email, password_hash) VALUES (?, ?, ?)")
+        .bind(user_id)
+        .bind("drills@example.com")
+        .bind("dummy-hash")
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+    // The learner keeps missing zh
+    let history = SpeechHistoryService::new(db.clone());
+    let context = AttemptContext {
+        ref_text: "中".to_string(),
+        ref_pinyin: Some("zhong1".to_string()),
+        lang: "cn".to_string(),
+        core: "word".to_string(),
+        duration: None,
+    };
+    for _ in 0..3 {
+        let phonemes = [("zh", 40.0), ("ong", 90.0)]
+            .iter()
+            .map(|(phoneme, score)| PhonemeScore {
+                phoneme: phoneme.to_string(),
+                pronunciation: *score,
+                span: None,
+                tone_index: None,
+                phone: None,
+            })
+            .collect();
+        let response = SpeechEvaluationResponse {
+            overall_scores: HashMap::new(),
+            words: vec![WordScore {
+                word: "中".to_string(),
+                pinyin: Some("zhong1".to_string()),
+                tone: Some("tone1".to_string()),
+                scores: WordScores {
+                    overall: 70.0,
+                    pronunciation: 65.0,
+                    tone: Some(95.0),
+                    prominence: None,
+                },
+                read_type: 0,
+                span: None,
+                phonemes: Some(phonemes),
+            }],
+        };
+        history
+            .record_attempt(user_id, "mock", &context, &response)
+            .await
+            .unwrap();
+    }
+
+    let session = auth::session::SessionService::new(db.clone())
+        .create_session(user_id, None, None)
+        .await
+        .unwrap();
+    let jwt_service = auth::jwt::JwtService::new(&config);
+    let token = jwt_service
+        .create_access_token(user_id, &session.id)
+        .unwrap();
+    let server = TestServer::new(routes::create_routes(db, config)).unwrap();
+
+    // Explicit sounds need no account
+    let response = server
+        .get("/speech/drills")
+        .add_query_param("sounds", "sh,tone3-tone3")
+        .add_query_param("count", 4)
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["drills"][0]["sound"], "sh");
+    assert_eq!(body["drills"][0]["target"]["kind"], "initial");
+    assert_eq!(body["drills"][0]["words"].as_array().unwrap().len(), 4);
+    assert_eq!(body["drills"][1]["target"]["kind"], "tone_pair");
+    assert!(body["drills"][1]["words"][0]["ref_pinyin"].is_string());
+
+    server
+        .get("/speech/drills")
+        .add_query_param("sounds", "zh,qx")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    server
+        .get("/speech/drills")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // Without sounds, drills follow the learner's weakest sounds
+    let response = server
+        .get("/speech/drills")
+        .add_header(
+            HeaderName::from_static("authorization"),
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["drills"][0]["sound"], "zh");
+    assert_eq!(
+        body["drills"][0]["minimal_pairs"][0]["contrast"]["first"],
+        "zh"
+    );
+}
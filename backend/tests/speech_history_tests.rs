@@ -0,0 +1,133 @@
+mod support;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use serde_json::{
+    json,
+    Value,
+};
+use support::{
+    bearer,
+    evaluation_body,
+    register,
+    test_server,
+    BOUNDARY,
+};
+
+async fn evaluate(
+    server: &TestServer,
+    auth: Option<&Value>,
+    ref_text: &str,
+    pinyin: &str,
+) -> Value {
+    let mut request = server
+        .post("/speech/evaluate")
+        .bytes(evaluation_body(&json!({"ref_text": ref_text, "ref_pinyin": pinyin})).into())
+        .content_type(&format!("multipart/form-data; boundary={}", BOUNDARY));
+    if let Some(auth) = auth {
+        let (name, value) = bearer(auth);
+        request = request.add_header(name, value);
+    }
+    let response = request.await;
+    response.assert_status_ok();
+    response.json()
+}
+
+#[tokio::test]
+async fn test_evaluations_are_recorded_for_signed_in_users() {
+    let (server, _db, _temp_dir) = test_server().await;
+    let auth = register(&server, "learner@example.com").await;
+
+    let first = evaluate(&server, Some(&auth), "你好", "ni3 hao3").await;
+    let second = evaluate(&server, Some(&auth), "好的", "hao3 de5").await;
+    assert_eq!(first["success"], true);
+    let attempt_id = first["attempt_id"].as_str().unwrap();
+    assert!(second["attempt_id"].is_string());
+
+    // Anonymous evaluations still work but are not stored
+    let anonymous = evaluate(&server, None, "你好", "ni3 hao3").await;
+    assert_eq!(anonymous["success"], true);
+    assert!(anonymous.get("attempt_id").is_none());
+
+    let (name, value) = bearer(&auth);
+    let list: Value = server
+        .get("/speech/attempts")
+        .add_header(name.clone(), value.clone())
+        .await
+        .json();
+    assert_eq!(list["total"], 2);
+    assert_eq!(list["attempts"][0]["ref_text"], "好的");
+    assert_eq!(list["attempts"][1]["id"], attempt_id);
+    assert_eq!(list["attempts"][1]["provider"], "mock");
+    assert_eq!(list["attempts"][1]["duration_ms"], 250);
+
+    // The stored breakdown matches what the evaluator returned
+    let detail: Value = server
+        .get(&format!("/speech/attempts/{}", attempt_id))
+        .add_header(name, value)
+        .await
+        .json();
+    assert_eq!(detail["ref_pinyin"], "ni3 hao3");
+    assert_eq!(detail["overall_scores"], first["data"]["overall_scores"]);
+    assert_eq!(detail["words"], first["data"]["words"]);
+    assert_eq!(detail["words"][1]["phonemes"][1]["phoneme"], "ao");
+}
+
+#[tokio::test]
+async fn test_word_trends_follow_repeated_practice() {
+    let (server, _db, _temp_dir) = test_server().await;
+    let auth = register(&server, "trends@example.com").await;
+
+    evaluate(&server, Some(&auth), "你好", "ni3 hao3").await;
+    evaluate(&server, Some(&auth), "好的", "hao3 de5").await;
+
+    let (name, value) = bearer(&auth);
+    let trends: Value = server
+        .get("/speech/trends/words")
+        .add_header(name.clone(), value.clone())
+        .await
+        .json();
+    let words = trends["words"].as_array().unwrap();
+    assert_eq!(words.len(), 3);
+    let hao = words.iter().find(|w| w["word"] == "好").unwrap();
+    assert_eq!(hao["attempts"], 2);
+    assert_eq!(hao["pinyin"], "hao3");
+
+    let trend: Value = server
+        .get("/speech/trends/words/%E5%A5%BD")
+        .add_header(name, value)
+        .await
+        .json();
+    assert_eq!(trend["word"], "好");
+    let points = trend["points"].as_array().unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[1]["overall"], hao["latest_score"]);
+}
+
+#[tokio::test]
+async fn test_history_is_private_to_each_user() {
+    let (server, _db, _temp_dir) = test_server().await;
+    let owner = register(&server, "owner@example.com").await;
+    let other = register(&server, "other@example.com").await;
+
+    let result = evaluate(&server, Some(&owner), "你好", "ni3 hao3").await;
+    let attempt_id = result["attempt_id"].as_str().unwrap();
+
+    let (name, value) = bearer(&other);
+    server
+        .get(&format!("/speech/attempts/{}", attempt_id))
+        .add_header(name.clone(), value.clone())
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    let list: Value = server
+        .get("/speech/attempts")
+        .add_header(name, value)
+        .await
+        .json();
+    assert_eq!(list["total"], 0);
+
+    server
+        .get("/speech/attempts")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
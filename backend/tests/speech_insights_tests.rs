@@ -0,0 +1,240 @@
+mod support;
+
+use std::collections::HashMap;
+
+use axum::http::{
+    HeaderName,
+    HeaderValue,
+    StatusCode,
+};
+use axum_test::TestServer;
+use mandarinpath_backend::{
+    speech::{
+        analytics::{
+            build_insights,
+            ScoredPhoneme,
+            ScoredWord,
+            SoundKind,
+        },
+        history::{
+            AttemptContext,
+            SpeechHistoryService,
+        },
+        iflytek::{
+            PhonemeScore,
+            WordScore,
+            WordScores,
+        },
+        SpeechEvaluationResponse,
+    },
+    *,
+};
+use serde_json::Value;
+use tempfile::TempDir;
+
+fn word(
+    word: &str,
+    pinyin: &str,
+    tone: &str,
+    tone_score: f32,
+    phonemes: &[(&str, f32)],
+) -> WordScore {
+    WordScore {
+        word: word.to_string(),
+        pinyin: Some(pinyin.to_string()),
+        tone: Some(tone.to_string()),
+        scores: WordScores {
+            overall: 80.0,
+            pronunciation: 80.0,
+            tone: Some(tone_score),
+            prominence: None,
+        },
+        read_type: 0,
+        span: None,
+        phonemes: Some(
+            phonemes
+                .iter()
+                .map(|(phoneme, score)| PhonemeScore {
+                    phoneme: phoneme.to_string(),
+                    pronunciation: *score,
+                    span: None,
+                    tone_index: None,
+                    phone: None,
+                })
+                .collect(),
+        ),
+    }
+}
+
+fn scored_word(word: &str, pinyin: &str, tone: &str, tone_score: f64) -> ScoredWord {
+    ScoredWord {
+        word: word.to_string(),
+        pinyin: Some(pinyin.to_string()),
+        tone: Some(tone.to_string()),
+        tone_score: Some(tone_score),
+    }
+}
+
+fn scored_phoneme(phoneme: &str, pronunciation: f64) -> ScoredPhoneme {
+    ScoredPhoneme {
+        phoneme: phoneme.to_string(),
+        pronunciation,
+    }
+}
+
+#[test]
+fn test_build_insights_groups_initials_finals_and_tones() {
+    let words = vec![
+        scored_word("中", "zhong1", "tone1", 90.0),
+        scored_word("人", "ren2", "tone3", 55.0),
+        scored_word("来", "lai2", "tone3", 60.0),
+        scored_word("没有", "mei2 you3", "tone2_tone3", 85.0),
+    ];
+    let phonemes = vec![
+        scored_phoneme("zh", 50.0),
+        scored_phoneme("zh", 60.0),
+        scored_phoneme("zh", 70.0),
+        scored_phoneme("z", 95.0),
+        scored_phoneme("z", 85.0),
+        scored_phoneme("z", 90.0),
+        scored_phoneme("ong1", 80.0),
+        scored_phoneme("en", 72.0),
+    ];
+
+    let insights = build_insights(4, &words, &phonemes);
+
+    let zh = insights.initials.iter().find(|s| s.sound == "zh").unwrap();
+    assert_eq!(zh.samples, 3);
+    assert_eq!(zh.average_score, 60.0);
+    assert_eq!(zh.accuracy, 0.0);
+    // Tone digits on phone labels are ignored when grouping finals
+    let finals: Vec<&str> = insights.finals.iter().map(|s| s.sound.as_str()).collect();
+    assert_eq!(finals, ["en", "ong"]);
+
+    let tone2 = insights.tones.iter().find(|s| s.sound == "tone2").unwrap();
+    assert_eq!(tone2.samples, 3);
+    assert_eq!(tone2.average_score, 66.7);
+
+    // Two second-tone syllables were heard as third tone
+    let matrix = &insights.tone_confusion;
+    assert_eq!(matrix.counts[1][2], 2);
+    assert_eq!(matrix.counts[1][1], 1);
+    assert_eq!(matrix.counts[0][0], 1);
+    assert_eq!(matrix.confusions.len(), 1);
+    assert_eq!(matrix.confusions[0].expected, 2);
+    assert_eq!(matrix.confusions[0].realised, 3);
+    assert_eq!(matrix.confusions[0].rate, 66.7);
+
+    // Only sounds with enough samples are ranked, weakest first
+    let weakest: Vec<(&str, SoundKind)> = insights
+        .weakest_sounds
+        .iter()
+        .map(|s| (s.sound.as_str(), s.kind))
+        .collect();
+    assert_eq!(
+        weakest,
+        [
+            ("zh", SoundKind::Initial),
+            ("tone2", SoundKind::Tone),
+            ("z", SoundKind::Initial)
+        ]
+    );
+}
+
+#[test]
+fn test_tone_insights_expect_sandhi() {
+    // 你好 said correctly is ni2 hao3, which is no 3→2 confusion
+    let words = vec![scored_word("你好", "ni3 hao3", "tone2_tone3", 90.0)];
+
+    let insights = build_insights(1, &words, &[]);
+
+    let matrix = &insights.tone_confusion;
+    assert_eq!(matrix.counts[2][1], 0);
+    assert_eq!(matrix.counts[1][1], 1);
+    assert_eq!(matrix.counts[2][2], 1);
+    assert!(matrix.confusions.is_empty());
+    let tones: Vec<(&str, usize)> = insights
+        .tones
+        .iter()
+        .map(|s| (s.sound.as_str(), s.samples))
+        .collect();
+    assert_eq!(tones, [("tone2", 1), ("tone3", 1)]);
+}
+
+#[tokio::test]
+async fn test_insights_endpoint_uses_stored_history() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = support::test_config(support::database_url(&temp_dir));
+    let db = db::Database::new(&config.database_url)
+        .await
+        .expect("Failed to connect to database");
+
+    let user_id = "insights-user";
+    sqlx::query("INSERT INTO users (id, email, password_hash) VALUES (?, ?, ?)")
+        .bind(user_id)
+        .bind("insights@example.com")
+        .bind("dummy-hash")
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+    let history = SpeechHistoryService::new(db.clone());
+    let context = AttemptContext {
+        ref_text: "中文".to_string(),
+        ref_pinyin: Some("zhong1 wen2".to_string()),
+        lang: "cn".to_string(),
+        core: "sent".to_string(),
+        duration: None,
+    };
+    for zh_score in [40.0, 50.0, 60.0] {
+        let response = SpeechEvaluationResponse {
+            overall_scores: HashMap::from([("overall".to_string(), 70.0)]),
+            words: vec![
+                word(
+                    "中",
+                    "zhong1",
+                    "tone1",
+                    88.0,
+                    &[("zh", zh_score), ("ong", 90.0)],
+                ),
+                word("文", "wen2", "tone3", 52.0, &[("w", 85.0), ("en", 80.0)]),
+            ],
+        };
+        history
+            .record_attempt(user_id, "mock", &context, &response)
+            .await
+            .unwrap();
+    }
+
+    let session = auth::session::SessionService::new(db.clone())
+        .create_session(user_id, None, None)
+        .await
+        .unwrap();
+    let jwt_service = auth::jwt::JwtService::new(&config);
+    let token = jwt_service
+        .create_access_token(user_id, &session.id)
+        .unwrap();
+    let server = TestServer::new(routes::create_routes(db, config)).unwrap();
+
+    server
+        .get("/speech/insights")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let response = server
+        .get("/speech/insights")
+        .add_header(
+            HeaderName::from_static("authorization"),
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        )
+        .await;
+    response.assert_status_ok();
+    let insights: Value = response.json();
+
+    assert_eq!(insights["attempts"], 3);
+    assert_eq!(insights["weakest_sounds"][0]["sound"], "zh");
+    assert_eq!(insights["weakest_sounds"][0]["average_score"], 50.0);
+    assert_eq!(insights["weakest_sounds"][1]["sound"], "tone2");
+    assert_eq!(insights["tone_confusion"]["counts"][1][2], 3);
+    assert_eq!(insights["tone_confusion"]["confusions"][0]["rate"], 100.0);
+}
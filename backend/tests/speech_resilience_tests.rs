@@ -0,0 +1,246 @@
+mod support;
+
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    routing::get,
+    Extension,
+    Router,
+};
+use axum_test::TestServer;
+use mandarinpath_backend::{
+    config::SpeechConfig,
+    handlers,
+    speech::{
+        resilience::{
+            BreakerState,
+            CircuitBreaker,
+            GuardedEvaluator,
+            ProviderTimeouts,
+            RetryPolicy,
+        },
+        IFlytekService,
+        SharedSpeechEvaluator,
+        SpeechError,
+        SpeechEvaluator,
+        SpeechStreamEvent,
+    },
+};
+use serde_json::Value;
+use support::{
+    iflytek_server::{
+        recorded_frames,
+        IFlytekStandIn,
+        Script,
+    },
+    nihao_request,
+};
+
+fn fast_retry(max_retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_retries,
+        base_delay: Duration::from_millis(5),
+        max_delay: Duration::from_millis(20),
+    }
+}
+
+#[test]
+fn test_backoff_is_jittered_within_bounds() {
+    let policy = RetryPolicy {
+        max_retries: 5,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(300),
+    };
+
+    for _ in 0..100 {
+        assert!(policy.backoff(0) <= Duration::from_millis(100));
+        assert!(policy.backoff(1) <= Duration::from_millis(200));
+        // Capped from the third retry on
+        assert!(policy.backoff(2) <= Duration::from_millis(300));
+        assert!(policy.backoff(40) <= Duration::from_millis(300));
+    }
+}
+
+#[tokio::test]
+async fn test_refused_handshakes_are_retried() {
+    let server = IFlytekStandIn::start(Script::Replay(recorded_frames("sent_nihao.jsonl"))).await;
+    server.refuse_handshakes(2);
+    let service = IFlytekService::new(server.config()).with_retry(fast_retry(2));
+
+    let response = service.evaluate_speech(nihao_request()).await.unwrap();
+    assert_eq!(response.overall_scores.get("overall"), Some(&83.9));
+    assert_eq!(server.connections(), 1);
+
+    // Retries are bounded
+    server.refuse_handshakes(3);
+    let error = service.evaluate_speech(nihao_request()).await.unwrap_err();
+    assert!(matches!(error, SpeechError::Unavailable(_)), "{:?}", error);
+    assert_eq!(server.connections(), 1);
+}
+
+#[tokio::test]
+async fn test_rejected_credentials_are_not_retried() {
+    let server = IFlytekStandIn::start(Script::Replay(Vec::new())).await;
+    let mut config = server.config();
+    config.api_secret = "wrong_secret".to_string();
+    let service = IFlytekService::new(config).with_retry(fast_retry(3));
+
+    let error = service.evaluate_speech(nihao_request()).await.unwrap_err();
+    assert!(matches!(error, SpeechError::Auth(_)), "{:?}", error);
+    assert_eq!(server.auth_failures(), 1);
+}
+
+#[tokio::test]
+async fn test_silent_provider_times_out() {
+    let server = IFlytekStandIn::start(Script::Silent).await;
+    let service = IFlytekService::new(server.config()).with_timeouts(ProviderTimeouts {
+        connect: Duration::from_secs(5),
+        read: Duration::from_millis(200),
+    });
+
+    let error = service.evaluate_speech(nihao_request()).await.unwrap_err();
+    assert!(matches!(error, SpeechError::Timeout(_)), "{:?}", error);
+}
+
+#[test]
+fn test_breaker_opens_and_probes_after_cooldown() {
+    let breaker = CircuitBreaker::new(2, Duration::from_millis(50));
+    let failure: Result<(), SpeechError> = Err(SpeechError::Unavailable("down".to_string()));
+    let rejected: Result<(), SpeechError> = Err(SpeechError::AudioTooShort("silence".to_string()));
+
+    // The caller's own mistakes neither count nor break a run of failures
+    breaker.acquire().unwrap();
+    breaker.record(&failure);
+    breaker.record(&rejected);
+    assert_eq!(breaker.status().state, BreakerState::Closed);
+    assert_eq!(breaker.status().consecutive_failures, 1);
+
+    breaker.record(&failure);
+    let status = breaker.status();
+    assert_eq!(status.state, BreakerState::Open);
+    assert_eq!(status.consecutive_failures, 2);
+    assert!(matches!(
+        breaker.acquire(),
+        Err(SpeechError::Unavailable(_))
+    ));
+
+    // One probe at a time once the cooldown is over; a failed probe reopens
+    std::thread::sleep(Duration::from_millis(60));
+    assert_eq!(breaker.status().state, BreakerState::HalfOpen);
+    breaker.acquire().unwrap();
+    assert!(breaker.acquire().is_err());
+    breaker.record(&failure);
+    assert_eq!(breaker.status().state, BreakerState::Open);
+
+    // A probe the provider rejected as a bad request lets the next one through
+    std::thread::sleep(Duration::from_millis(60));
+    breaker.acquire().unwrap();
+    breaker.record(&rejected);
+    assert_eq!(breaker.status().state, BreakerState::HalfOpen);
+    breaker.acquire().unwrap();
+    breaker.record(&Ok(()));
+    assert_eq!(breaker.status().state, BreakerState::Closed);
+    assert_eq!(breaker.status().consecutive_failures, 0);
+}
+
+#[tokio::test]
+async fn test_open_breaker_fails_fast_and_shows_in_health() {
+    let server = IFlytekStandIn::start(Script::Replay(recorded_frames("sent_nihao.jsonl"))).await;
+    server.refuse_handshakes(usize::MAX);
+    let provider = IFlytekService::new(server.config()).with_retry(fast_retry(0));
+    let config = SpeechConfig {
+        breaker_failure_threshold: 2,
+        breaker_cooldown: Duration::from_secs(60),
+        ..Default::default()
+    };
+    let evaluator: SharedSpeechEvaluator =
+        Arc::new(GuardedEvaluator::new(Arc::new(provider), &config));
+
+    for _ in 0..2 {
+        let error = evaluator.evaluate(nihao_request()).await.unwrap_err();
+        assert!(matches!(error, SpeechError::Unavailable(_)), "{:?}", error);
+    }
+    server.refuse_handshakes(0);
+
+    // Rejected without touching the provider
+    let error = evaluator.evaluate(nihao_request()).await.unwrap_err();
+    assert_eq!(error.code(), "SPEECH_UNAVAILABLE");
+    assert!(
+        error.to_string().contains("circuit breaker open"),
+        "{}",
+        error
+    );
+    assert_eq!(server.connections(), 0);
+
+    let app = Router::new()
+        .route("/speech/health", get(handlers::speech::health_check))
+        .layer(Extension(evaluator));
+    let server = TestServer::new(app).unwrap();
+    let response = server.get("/speech/health").await;
+    response.assert_status_ok();
+    let health: Value = response.json();
+    assert_eq!(health["status"], "degraded");
+    assert_eq!(health["provider"], "iflytek");
+    assert_eq!(health["circuit_breaker"]["state"], "open");
+    assert_eq!(health["circuit_breaker"]["consecutive_failures"], 2);
+    assert_eq!(health["credentials_configured"], true);
+    assert!(health["last_failure_at"].is_string());
+    assert!(health["last_success_at"].is_null());
+    assert!(health["last_error"]
+        .as_str()
+        .unwrap()
+        .contains("speech service unavailable"));
+    assert!(
+        health["circuit_breaker"]["retry_after_secs"]
+            .as_u64()
+            .unwrap()
+            > 0
+    );
+}
+
+#[tokio::test]
+async fn test_stream_outcome_feeds_the_breaker() {
+    let config = SpeechConfig {
+        breaker_failure_threshold: 1,
+        breaker_cooldown: Duration::from_secs(60),
+        ..Default::default()
+    };
+    let stream_error = |code: i64, message: &str| {
+        let script = Script::Error {
+            code,
+            message: message.to_string(),
+        };
+        let config = config.clone();
+        async move {
+            let server = IFlytekStandIn::start(script).await;
+            let provider = IFlytekService::new(server.config());
+            let evaluator = GuardedEvaluator::new(Arc::new(provider), &config);
+            let mut stream = evaluator.open_stream(nihao_request()).await.unwrap();
+            drop(stream.audio);
+            let event = stream.events.recv().await;
+            assert!(
+                matches!(event, Some(SpeechStreamEvent::Failed(_))),
+                "{:?}",
+                event
+            );
+            evaluator.health()
+        }
+    };
+
+    // A request the provider turns down says nothing about its health
+    let health = stream_error(10163, "param validate error").await;
+    let breaker = health.circuit_breaker.unwrap();
+    assert_eq!(breaker.state, BreakerState::Closed);
+    assert_eq!(breaker.consecutive_failures, 0);
+    assert!(health.last_failure_at.is_none());
+
+    // A session that opened but then failed on the provider's side counts
+    let health = stream_error(11201, "licc limit").await;
+    let breaker = health.circuit_breaker.unwrap();
+    assert_eq!(breaker.state, BreakerState::Open);
+    assert_eq!(breaker.consecutive_failures, 1);
+    assert!(health.last_error.unwrap().contains("licc limit"));
+}
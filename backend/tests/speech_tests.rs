@@ -1,10 +1,16 @@
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use bytes::Bytes;
     use mandarinpath_backend::speech::{
+        audio,
         iflytek::IFlytekConfig,
         IFlytekService,
+        MockSpeechEvaluator,
+        SpeechError,
         SpeechEvaluationRequest,
+        SpeechEvaluator,
     };
 
     #[tokio::test]
@@ -179,7 +185,289 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_mock_evaluator_scores_each_character() {
+        let evaluator = MockSpeechEvaluator::new();
+        let response = evaluator
+            .evaluate(create_chinese_evaluation_request())
+            .await
+            .unwrap();
+
+        assert_eq!(evaluator.name(), "mock");
+
+        let words: Vec<&str> = response.words.iter().map(|w| w.word.as_str()).collect();
+        assert_eq!(words, ["你", "好", "世", "界"]);
+        for key in ["overall", "pronunciation", "fluency", "tone"] {
+            let score = response.overall_scores[key];
+            assert!(
+                (60.0..100.0).contains(&score),
+                "{} out of range: {}",
+                key,
+                score
+            );
+        }
+
+        // Pinyin lines up with the characters, so phonemes are split into initial + final;
+        // like iFlytek, the mock reports numbered pinyin
+        let hao = &response.words[1];
+        assert_eq!(hao.pinyin.as_deref(), Some("hao3"));
+        assert_eq!(hao.tone.as_deref(), Some("tone3"));
+        let phonemes = hao.phonemes.as_ref().unwrap();
+        assert_eq!(phonemes[0].phoneme, "h");
+        assert_eq!(phonemes[1].phoneme, "ao");
+        assert_eq!(phonemes[1].tone_index, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_mock_evaluator_is_deterministic() {
+        let evaluator = MockSpeechEvaluator::new();
+        let first = evaluator
+            .evaluate(create_chinese_evaluation_request())
+            .await
+            .unwrap();
+        let second = evaluator
+            .evaluate(create_chinese_evaluation_request())
+            .await
+            .unwrap();
+
+        assert_eq!(first.overall_scores, second.overall_scores);
+        let first_scores: Vec<f32> = first.words.iter().map(|w| w.scores.overall).collect();
+        let second_scores: Vec<f32> = second.words.iter().map(|w| w.scores.overall).collect();
+        assert_eq!(first_scores, second_scores);
+    }
+
+    #[tokio::test]
+    async fn test_mock_evaluator_english_and_empty_text() {
+        let evaluator = MockSpeechEvaluator::new();
+
+        let response = evaluator
+            .evaluate(create_english_evaluation_request())
+            .await
+            .unwrap();
+        let words: Vec<&str> = response.words.iter().map(|w| w.word.as_str()).collect();
+        assert_eq!(words, ["Hello", "world"]);
+        assert!(response.words.iter().all(|w| w.scores.tone.is_none()));
+        assert!(!response.overall_scores.contains_key("tone"));
+
+        let mut request = create_test_request();
+        request.ref_text = "，。".to_string();
+        let error = evaluator.evaluate(request).await.unwrap_err();
+        assert!(matches!(error, SpeechError::InvalidRequest(_)));
+    }
+
+    #[test]
+    fn test_estimate_duration_raw_pcm() {
+        let two_seconds = vec![0u8; 64_000];
+        let duration = audio::estimate_duration(&two_seconds, "raw", 16000, 1, 16).unwrap();
+        assert_eq!(duration, Duration::from_secs(2));
+
+        assert!(audio::estimate_duration(&two_seconds, "raw", 0, 1, 16).is_none());
+        assert!(audio::estimate_duration(&two_seconds, "speex", 16000, 1, 16).is_none());
+    }
+
+    #[test]
+    fn test_estimate_duration_mp3_frames() {
+        // MPEG-1 Layer III, 128 kbit/s, 44.1 kHz, no padding: 417-byte frames of 1152 samples
+        let mut frame = vec![0u8; 417];
+        frame[..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+
+        // A 20-byte ID3v2 tag in front must be skipped
+        let mut mp3 = vec![b'I', b'D', b'3', 4, 0, 0, 0, 0, 0, 10];
+        mp3.extend([0u8; 10]);
+        for _ in 0..100 {
+            mp3.extend(&frame);
+        }
+
+        let duration = audio::estimate_duration(&mp3, "lame", 16000, 1, 16).unwrap();
+        let expected = 100.0 * 1152.0 / 44100.0;
+        assert!((duration.as_secs_f64() - expected).abs() < 1e-6);
+
+        assert!(audio::estimate_duration(b"not an mp3", "lame", 16000, 1, 16).is_none());
+    }
+
+    #[test]
+    fn test_sniff_container() {
+        assert_eq!(
+            audio::Container::sniff(&wav_bytes(16000, 1, &[0; 16])),
+            Some(audio::Container::Wav)
+        );
+        assert_eq!(
+            audio::Container::sniff(&webm_bytes("A_OPUS", 48000, 1, &[])),
+            Some(audio::Container::WebM)
+        );
+        assert_eq!(
+            audio::Container::sniff(b"OggS\0\x02"),
+            Some(audio::Container::Ogg)
+        );
+        assert_eq!(
+            audio::Container::sniff(&[0xff, 0xfb, 0x90, 0x64]),
+            Some(audio::Container::Mp3)
+        );
+        assert_eq!(audio::Container::sniff(b"fake audio"), None);
+    }
+
+    #[test]
+    fn test_normalize_wav_to_16khz_mono() {
+        // Half a second of a 440 Hz tone, 44.1 kHz stereo with the right channel silent
+        let samples: Vec<i16> = (0..22_050)
+            .flat_map(|i| {
+                let t = i as f64 / 44_100.0;
+                let left = ((2.0 * std::f64::consts::PI * 440.0 * t).sin() * 16_000.0) as i16;
+                [left, 0]
+            })
+            .collect();
+
+        let normalized =
+            audio::normalize(wav_bytes(44_100, 2, &samples), Duration::from_secs(10)).unwrap();
+
+        assert_eq!(normalized.pcm.len(), 8_000 * 2);
+        assert_eq!(normalized.duration, Duration::from_millis(500));
+
+        // Downmixing halves the amplitude; resampling must preserve the rest
+        let peak = pcm_samples(&normalized.pcm)
+            .iter()
+            .map(|s| s.unsigned_abs())
+            .max()
+            .unwrap();
+        assert!((7_600..=8_400).contains(&peak), "peak was {}", peak);
+    }
+
+    #[test]
+    fn test_normalize_webm_pcm_track() {
+        let samples: Vec<i16> = (0..9_600).map(|i| ((i % 100) * 100) as i16).collect();
+        let normalized = audio::normalize(
+            webm_bytes("A_PCM/INT/LIT", 48_000, 1, &samples),
+            Duration::from_secs(10),
+        )
+        .unwrap();
+
+        assert_eq!(normalized.duration, Duration::from_millis(200));
+        assert_eq!(normalized.pcm.len(), 3_200 * 2);
+    }
+
+    #[cfg(not(feature = "opus"))]
+    #[test]
+    fn test_normalize_opus_without_feature_is_unsupported() {
+        let result = audio::normalize(
+            webm_bytes("A_OPUS", 48_000, 1, &[]),
+            Duration::from_secs(10),
+        );
+        assert!(matches!(
+            result,
+            Err(audio::AudioError::UnsupportedCodec(codec)) if codec.starts_with("opus")
+        ));
+    }
+
+    #[test]
+    fn test_normalize_rejects_unknown_and_overlong_audio() {
+        assert!(matches!(
+            audio::normalize(Bytes::from_static(b"fake audio"), Duration::from_secs(10)),
+            Err(audio::AudioError::UnknownFormat)
+        ));
+
+        let three_seconds = wav_bytes(16_000, 1, &vec![0; 48_000]);
+        assert!(matches!(
+            audio::normalize(three_seconds, Duration::from_secs(2)),
+            Err(audio::AudioError::TooLong(_))
+        ));
+    }
+
+    #[test]
+    fn test_normalize_raw_pcm() {
+        let one_second = vec![0u8; 8_000 * 2 * 2];
+        let normalized =
+            audio::normalize_raw(&one_second, 8_000, 2, 16, Duration::from_secs(10)).unwrap();
+        assert_eq!(normalized.pcm.len(), 16_000 * 2);
+
+        assert!(matches!(
+            audio::normalize_raw(&one_second, 8_000, 1, 8, Duration::from_secs(10)),
+            Err(audio::AudioError::UnsupportedCodec(_))
+        ));
+    }
+
     // Helper functions
+    fn wav_bytes(sample_rate: u32, channels: u16, samples: &[i16]) -> Bytes {
+        let data_len = samples.len() as u32 * 2;
+        let mut wav = Vec::new();
+        wav.extend(b"RIFF");
+        wav.extend((36 + data_len).to_le_bytes());
+        wav.extend(b"WAVEfmt ");
+        wav.extend(16u32.to_le_bytes());
+        wav.extend(1u16.to_le_bytes()); // PCM
+        wav.extend(channels.to_le_bytes());
+        wav.extend(sample_rate.to_le_bytes());
+        wav.extend((sample_rate * channels as u32 * 2).to_le_bytes());
+        wav.extend((channels * 2).to_le_bytes());
+        wav.extend(16u16.to_le_bytes());
+        wav.extend(b"data");
+        wav.extend(data_len.to_le_bytes());
+        for sample in samples {
+            wav.extend(sample.to_le_bytes());
+        }
+        wav.into()
+    }
+
+    /// A minimal live-style WebM: unknown-size segment and cluster, one audio track.
+    fn webm_bytes(codec_id: &str, sample_rate: u32, channels: u8, samples: &[i16]) -> Bytes {
+        fn element(id: &[u8], body: &[u8]) -> Vec<u8> {
+            let mut out = id.to_vec();
+            out.push(0x01);
+            out.extend(&(body.len() as u64).to_be_bytes()[1..]);
+            out.extend(body);
+            out
+        }
+        const UNKNOWN_SIZE: [u8; 8] = [0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+
+        let audio = [
+            element(&[0xb5], &(sample_rate as f64).to_be_bytes()),
+            element(&[0x9f], &[channels]),
+            element(&[0x62, 0x64], &[16]),
+        ]
+        .concat();
+        let track = [
+            element(&[0xd7], &[1]),
+            element(&[0x73, 0xc5], &[1]),
+            element(&[0x83], &[2]),
+            element(&[0x86], codec_id.as_bytes()),
+            element(&[0xe1], &audio),
+        ]
+        .concat();
+
+        let mut webm = element(&[0x1a, 0x45, 0xdf, 0xa3], &element(&[0x42, 0x82], b"webm"));
+        webm.extend([0x18, 0x53, 0x80, 0x67]);
+        webm.extend(UNKNOWN_SIZE);
+        webm.extend(element(
+            &[0x15, 0x49, 0xa9, 0x66],
+            &element(&[0x2a, 0xd7, 0xb1], &1_000_000u32.to_be_bytes()),
+        ));
+        webm.extend(element(
+            &[0x16, 0x54, 0xae, 0x6b],
+            &element(&[0xae], &track),
+        ));
+        webm.extend([0x1f, 0x43, 0xb6, 0x75]);
+        webm.extend(UNKNOWN_SIZE);
+        webm.extend(element(&[0xe7], &[0]));
+
+        // 20ms SimpleBlocks, as MediaRecorder would emit them
+        let block_len = sample_rate as usize / 50 * channels as usize;
+        for (i, chunk) in samples.chunks(block_len).enumerate() {
+            let mut block = vec![0x81];
+            block.extend(((i * 20) as i16).to_be_bytes());
+            block.push(0x80);
+            for sample in chunk {
+                block.extend(sample.to_le_bytes());
+            }
+            webm.extend(element(&[0xa3], &block));
+        }
+        webm.into()
+    }
+
+    fn pcm_samples(pcm: &[u8]) -> Vec<i16> {
+        pcm.chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect()
+    }
+
     fn create_test_request() -> SpeechEvaluationRequest {
         SpeechEvaluationRequest {
             audio_data: Bytes::from("test audio data"),
@@ -0,0 +1,294 @@
+mod support;
+
+use mandarinpath_backend::speech::{
+    iflytek::{
+        PhonemeScore,
+        TimeSpan,
+        WordScore,
+        WordScores,
+    },
+    tone::{
+        analyze,
+        track_pitch,
+    },
+};
+use serde_json::{
+    json,
+    Value,
+};
+
+const RATE: u32 = 16_000;
+const SPEAKER_HZ: f32 = 200.0;
+
+/// A stretch of audio: pitch in semitones from the speaker's centre, sampled
+/// evenly over its length and linearly interpolated; no points for silence.
+struct Syllable {
+    ms: u32,
+    semitones: &'static [f32],
+}
+
+/// Harmonic-rich "speech" following each syllable's pitch, with leading and
+/// trailing silence of `silence_ms`.
+fn synthesize(syllables: &[Syllable], silence_ms: u32) -> Vec<f32> {
+    let silence = vec![0.0; (RATE * silence_ms / 1000) as usize];
+    let mut samples = silence.clone();
+    let mut phase = 0.0f32;
+    for syllable in syllables {
+        let len = (RATE * syllable.ms / 1000) as usize;
+        if syllable.semitones.is_empty() {
+            samples.resize(samples.len() + len, 0.0);
+            continue;
+        }
+        for n in 0..len {
+            let position = n as f32 / len as f32 * (syllable.semitones.len() - 1) as f32;
+            let lower = position.floor() as usize;
+            let upper = (lower + 1).min(syllable.semitones.len() - 1);
+            let weight = position - lower as f32;
+            let st =
+                syllable.semitones[lower] * (1.0 - weight) + syllable.semitones[upper] * weight;
+            let f0 = SPEAKER_HZ * 2f32.powf(st / 12.0);
+
+            phase += std::f32::consts::TAU * f0 / RATE as f32;
+            samples
+                .push(0.3 * phase.sin() + 0.15 * (2.0 * phase).sin() + 0.08 * (3.0 * phase).sin());
+        }
+    }
+    samples.extend(silence);
+    samples
+}
+
+fn pcm(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|s| ((s * i16::MAX as f32) as i16).to_le_bytes())
+        .collect()
+}
+
+fn wav(samples: &[f32]) -> Vec<u8> {
+    let data = pcm(samples);
+    let mut wav = Vec::new();
+    wav.extend(b"RIFF");
+    wav.extend((36 + data.len() as u32).to_le_bytes());
+    wav.extend(b"WAVEfmt ");
+    wav.extend(16u32.to_le_bytes());
+    wav.extend(1u16.to_le_bytes());
+    wav.extend(1u16.to_le_bytes());
+    wav.extend(RATE.to_le_bytes());
+    wav.extend((RATE * 2).to_le_bytes());
+    wav.extend(2u16.to_le_bytes());
+    wav.extend(16u16.to_le_bytes());
+    wav.extend(b"data");
+    wav.extend((data.len() as u32).to_le_bytes());
+    wav.extend(data);
+    wav
+}
+
+/// A word with a provider-style span in 10 ms units and no phonemes.
+fn word(text: &str, pinyin: &str, start: u32, end: u32) -> WordScore {
+    WordScore {
+        word: text.to_string(),
+        pinyin: Some(pinyin.to_string()),
+        tone: None,
+        scores: WordScores {
+            overall: 80.0,
+            pronunciation: 80.0,
+            tone: None,
+            prominence: None,
+        },
+        read_type: 0,
+        span: Some(TimeSpan { start, end }),
+        phonemes: None,
+    }
+}
+
+#[test]
+fn test_pitch_tracking_finds_f0_and_silence() {
+    let samples = synthesize(
+        &[Syllable {
+            ms: 500,
+            semitones: &[0.0],
+        }],
+        200,
+    );
+    let contour = track_pitch(&samples, RATE);
+
+    assert_eq!(contour.frame_ms, 10);
+    assert_eq!(contour.f0.len(), 90);
+    // Leading silence
+    assert!(contour.f0[..15].iter().all(Option::is_none));
+    for f0 in contour.f0[25..65].iter() {
+        let f0 = f0.expect("steady vowel is voiced");
+        assert!((f0 - SPEAKER_HZ).abs() < 2.0, "{} Hz", f0);
+    }
+    assert!(contour.f0[75..].iter().all(Option::is_none));
+}
+
+#[test]
+fn test_four_tones_are_told_apart() {
+    let samples = synthesize(
+        &[
+            Syllable {
+                ms: 300,
+                semitones: &[4.0, 4.0],
+            },
+            Syllable {
+                ms: 300,
+                semitones: &[-1.0, -0.5, 1.0, 4.0],
+            },
+            Syllable {
+                ms: 300,
+                semitones: &[5.0, 3.0, 0.0, -3.0, -6.0],
+            },
+            Syllable {
+                ms: 300,
+                semitones: &[-3.0, -5.0, -6.0, -5.0, -2.5],
+            },
+        ],
+        100,
+    );
+    // 妈麻骂马: the third tone is phrase-final so keeps its rise
+    let words = [
+        word("妈", "ma1", 10, 40),
+        word("麻", "ma2", 40, 70),
+        word("骂", "ma4", 70, 100),
+        word("马", "ma3", 100, 130),
+    ];
+
+    let analysis = analyze(&pcm(&samples), RATE, &words);
+
+    assert!((analysis.reference_hz.unwrap() - SPEAKER_HZ).abs() < 40.0);
+    let detected: Vec<Option<u8>> = analysis.syllables.iter().map(|s| s.detected_tone).collect();
+    assert_eq!(detected, [Some(1), Some(2), Some(4), Some(3)]);
+    for syllable in &analysis.syllables {
+        assert!(
+            syllable.score.unwrap() > 50.0,
+            "{} scored {:?}",
+            syllable.pinyin,
+            syllable.score
+        );
+        assert_eq!(syllable.contour.len(), 5);
+        assert_eq!(syllable.expected_contour.len(), 5);
+    }
+
+    // A falling contour read where a rising one was expected scores poorly
+    let swapped = [
+        word("妈", "ma1", 10, 40),
+        word("麻", "ma4", 40, 70),
+        word("骂", "ma2", 70, 100),
+        word("马", "ma3", 100, 130),
+    ];
+    let analysis = analyze(&pcm(&samples), RATE, &swapped);
+    assert!(analysis.syllables[1].score.unwrap() < 20.0);
+    assert_eq!(analysis.syllables[1].detected_tone, Some(2));
+}
+
+#[test]
+fn test_syllables_follow_phoneme_spans_and_sandhi() {
+    let samples = synthesize(
+        &[
+            Syllable {
+                ms: 300,
+                semitones: &[-1.0, -0.5, 1.0, 4.0],
+            },
+            Syllable {
+                ms: 300,
+                semitones: &[-3.0, -5.0, -6.0, -5.0, -2.5],
+            },
+        ],
+        100,
+    );
+    // One two-syllable word; each final carries its syllable's tone
+    let phoneme = |phoneme: &str, start: u32, end: u32| PhonemeScore {
+        phoneme: phoneme.to_string(),
+        pronunciation: 90.0,
+        span: Some(TimeSpan { start, end }),
+        tone_index: None,
+        phone: None,
+    };
+    let mut nihao = word("你好", "ni3 hao3", 10, 70);
+    nihao.phonemes = Some(vec![
+        phoneme("n", 10, 14),
+        phoneme("i", 14, 40),
+        phoneme("h", 40, 44),
+        phoneme("ao", 44, 70),
+    ]);
+    let mut omitted = word("吗", "ma5", 70, 80);
+    omitted.read_type = 2;
+
+    let analysis = analyze(&pcm(&samples), RATE, &[nihao, omitted]);
+
+    assert_eq!(analysis.syllables.len(), 2);
+    let ni = &analysis.syllables[0];
+    assert_eq!((ni.word_index, ni.pinyin.as_str()), (0, "ni3"));
+    assert_eq!((ni.span.start, ni.span.end), (14, 40));
+    // Third-tone sandhi: 你 is said with a second tone before 好
+    assert_eq!((ni.expected_tone, ni.target_tone), (3, 2));
+    assert_eq!(ni.detected_tone, Some(2));
+    assert!(ni.score.unwrap() > 50.0);
+    assert_eq!(analysis.syllables[1].target_tone, 3);
+    assert_eq!(analysis.syllables[1].detected_tone, Some(3));
+}
+
+#[test]
+fn test_silence_has_no_tone() {
+    let analysis = analyze(&vec![0u8; 16_000], RATE, &[word("妈", "ma1", 10, 40)]);
+    assert!(analysis.contour.f0.iter().all(Option::is_none));
+    assert!(analysis.reference_hz.is_none());
+    assert!(analysis.syllables.is_empty());
+}
+
+#[tokio::test]
+async fn test_evaluate_returns_tone_analysis() {
+    let (server, _db, _temp_dir) = support::test_server().await;
+
+    // The mock places word i at 200 + 360·i ms, its final in the second half;
+    // k and h are voiceless
+    let samples = synthesize(
+        &[
+            Syllable {
+                ms: 180,
+                semitones: &[],
+            },
+            Syllable {
+                ms: 180,
+                semitones: &[4.0, 4.0],
+            },
+            Syllable {
+                ms: 180,
+                semitones: &[],
+            },
+            Syllable {
+                ms: 180,
+                semitones: &[5.0, 3.0, 0.0, -3.0, -6.0],
+            },
+        ],
+        200,
+    );
+    let boundary = "----tone-boundary";
+    let mut body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"params\"\r\n\r\n{params}\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"audio\"; filename=\"a.wav\"\r\n\
+         Content-Type: audio/wav\r\n\r\n",
+        b = boundary,
+        params = json!({ "ref_text": "开会", "ref_pinyin": "kai1 hui4" }),
+    )
+    .into_bytes();
+    body.extend(wav(&samples));
+    body.extend(format!("\r\n--{}--\r\n", boundary).into_bytes());
+
+    let response = server
+        .post("/speech/evaluate")
+        .bytes(body.into())
+        .content_type(&format!("multipart/form-data; boundary={}", boundary))
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+
+    let analysis = &body["tone_analysis"];
+    assert_eq!(analysis["contour"]["frame_ms"], 10);
+    assert!(analysis["contour"]["f0"].as_array().unwrap().len() >= 90);
+    assert_eq!(analysis["syllables"][0]["pinyin"], "kai1");
+    assert_eq!(analysis["syllables"][0]["detected_tone"], 1);
+    assert_eq!(analysis["syllables"][1]["detected_tone"], 4);
+    assert!(analysis["syllables"][1]["score"].as_f64().unwrap() > 50.0);
+}
@@ -0,0 +1,337 @@
+//! A local stand-in for the iFlytek ISE WebSocket endpoint.
+//!
+//! It validates the signed `authorization` query parameter the same way the real
+//! service does, records every frame the client sends, and answers the final
+//! (`status: 2`) audio frame according to a [`Script`].
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+use base64::{
+    engine::general_purpose::STANDARD,
+    Engine as _,
+};
+use chrono::{
+    DateTime,
+    Utc,
+};
+use futures_util::{
+    SinkExt,
+    StreamExt,
+};
+use hmac::{
+    Hmac,
+    Mac,
+};
+use mandarinpath_backend::speech::iflytek::IFlytekConfig;
+use serde_json::{
+    json,
+    Value,
+};
+use sha2::Sha256;
+use tokio::{
+    net::{
+        TcpListener,
+        TcpStream,
+    },
+    task::JoinHandle,
+};
+use tokio_tungstenite::{
+    accept_hdr_async,
+    tungstenite::{
+        handshake::server::{
+            ErrorResponse,
+            Request,
+            Response,
+        },
+        http::StatusCode,
+        Message,
+    },
+};
+
+pub const APP_ID: &str = "standin_app";
+pub const API_KEY: &str = "standin_key";
+pub const API_SECRET: &str = "standin_secret";
+pub const WS_PATH: &str = "/v1/private/s8e098720";
+
+/// Maximum clock skew iFlytek tolerates on the signed `date` parameter.
+const MAX_DATE_SKEW_SECS: i64 = 300;
+const SID: &str = "ise000standin@dx0000000000000001";
+
+/// How the stand-in answers once the client has finished sending audio.
+#[derive(Debug, Clone)]
+pub enum Script {
+    /// Send these raw response frames verbatim, e.g. lines of a recorded fixture.
+    Replay(Vec<String>),
+    /// Answer with a single error frame carrying an iFlytek error code.
+    Error { code: i64, message: String },
+    /// Close the TCP connection without a WebSocket close handshake after the first frame.
+    DropAfterFirstFrame,
+    /// Accept every frame and never answer.
+    Silent,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    frames: Vec<Value>,
+    connections: usize,
+    auth_failures: usize,
+    /// Handshakes still to be turned away as if the service were overloaded
+    refusals_left: usize,
+}
+
+pub struct IFlytekStandIn {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    task: JoinHandle<()>,
+}
+
+impl IFlytekStandIn {
+    pub async fn start(script: Script) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind stand-in server");
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let task_state = state.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle_connection(
+                    stream,
+                    script.clone(),
+                    task_state.clone(),
+                ));
+            }
+        });
+
+        Self { addr, state, task }
+    }
+
+    /// Client configuration pointing at this server with valid credentials.
+    pub fn config(&self) -> IFlytekConfig {
+        IFlytekConfig {
+            app_id: APP_ID.to_string(),
+            api_key: API_KEY.to_string(),
+            api_secret: API_SECRET.to_string(),
+            ws_url: format!("ws://{}{}", self.addr, WS_PATH),
+        }
+    }
+
+    /// Every JSON frame received from clients, in arrival order.
+    pub fn frames(&self) -> Vec<Value> {
+        self.state.lock().unwrap().frames.clone()
+    }
+
+    /// Number of connections that passed the authorization check.
+    pub fn connections(&self) -> usize {
+        self.state.lock().unwrap().connections
+    }
+
+    pub fn auth_failures(&self) -> usize {
+        self.state.lock().unwrap().auth_failures
+    }
+
+    /// Answer the next `count` handshakes with 503 Service Unavailable.
+    pub fn refuse_handshakes(&self, count: usize) {
+        self.state.lock().unwrap().refusals_left = count;
+    }
+}
+
+impl Drop for IFlytekStandIn {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Load a recorded session: one response frame per line.
+pub fn recorded_frames(fixture: &str) -> Vec<String> {
+    let path = format!(
+        "{}/tests/fixtures/iflytek/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        fixture
+    );
+    std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read fixture {}: {}", path, e))
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Build a response frame wrapping a decoded result body.
+pub fn result_frame(result: &Value, status: u8, seq: u32) -> String {
+    json!({
+        "header": { "code": 0, "message": "success", "sid": SID, "status": status },
+        "payload": {
+            "result": {
+                "encoding": "utf8",
+                "compress": "raw",
+                "format": "plain",
+                "status": status,
+                "seq": seq,
+                "data": STANDARD.encode(result.to_string()),
+            }
+        }
+    })
+    .to_string()
+}
+
+fn error_frame(code: i64, message: &str) -> String {
+    json!({
+        "header": { "code": code, "message": message, "sid": SID, "status": 2 }
+    })
+    .to_string()
+}
+
+async fn handle_connection(stream: TcpStream, script: Script, state: Arc<Mutex<State>>) {
+    let auth_state = state.clone();
+    #[allow(clippy::result_large_err)]
+    let callback =
+        move |request: &Request, response: Response| authorize(&auth_state, request, response);
+
+    let Ok(ws_stream) = accept_hdr_async(stream, callback).await else {
+        return;
+    };
+    let (mut sender, mut receiver) = ws_stream.split();
+
+    while let Some(Ok(message)) = receiver.next().await {
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let frame: Value = match serde_json::from_str(&text) {
+            Ok(frame) => frame,
+            Err(_) => {
+                let _ = sender
+                    .send(Message::Text(error_frame(
+                        10160,
+                        "parse request json error",
+                    )))
+                    .await;
+                return;
+            }
+        };
+        let status = frame["header"]["status"].as_u64();
+        let app_id_ok = frame["header"]["app_id"] == APP_ID;
+        state.lock().unwrap().frames.push(frame);
+
+        if !app_id_ok {
+            let _ = sender
+                .send(Message::Text(error_frame(10313, "invalid appid")))
+                .await;
+            return;
+        }
+
+        if let Script::DropAfterFirstFrame = script {
+            // Dropping both halves closes the socket without a close frame
+            return;
+        }
+
+        if status == Some(2) {
+            let replies = match &script {
+                Script::Replay(frames) => frames.clone(),
+                Script::Error { code, message } => vec![error_frame(*code, message)],
+                Script::Silent => continue,
+                Script::DropAfterFirstFrame => unreachable!(),
+            };
+            for reply in replies {
+                if sender.send(Message::Text(reply)).await.is_err() {
+                    return;
+                }
+            }
+            let _ = sender.close().await;
+            return;
+        }
+    }
+}
+
+// The error type is dictated by tungstenite's handshake callback
+#[allow(clippy::result_large_err)]
+fn authorize(
+    state: &Mutex<State>,
+    request: &Request,
+    response: Response,
+) -> Result<Response, ErrorResponse> {
+    let mut state = state.lock().unwrap();
+    if state.refusals_left > 0 {
+        state.refusals_left -= 1;
+        let mut error = ErrorResponse::new(Some("service overloaded".to_string()));
+        *error.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+        return Err(error);
+    }
+
+    match verify_authorization(request) {
+        Ok(()) => {
+            state.connections += 1;
+            Ok(response)
+        }
+        Err(reason) => {
+            state.auth_failures += 1;
+            let mut error = ErrorResponse::new(Some(reason));
+            *error.status_mut() = StatusCode::UNAUTHORIZED;
+            Err(error)
+        }
+    }
+}
+
+/// Check the `host`/`date`/`authorization` query parameters produced by
+/// `IFlytekService::generate_auth_url`.
+fn verify_authorization(request: &Request) -> Result<(), String> {
+    let query = request.uri().query().unwrap_or_default();
+    let params: std::collections::HashMap<String, String> =
+        url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+
+    let host = params.get("host").ok_or("missing host")?;
+    let date = params.get("date").ok_or("missing date")?;
+    let authorization = params.get("authorization").ok_or("missing authorization")?;
+
+    let signed_at = DateTime::parse_from_rfc2822(date).map_err(|_| "malformed date")?;
+    if (Utc::now() - signed_at.with_timezone(&Utc))
+        .num_seconds()
+        .abs()
+        > MAX_DATE_SKEW_SECS
+    {
+        return Err("date outside allowed skew".to_string());
+    }
+
+    let decoded = STANDARD
+        .decode(authorization)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or("authorization is not base64 text")?;
+    let api_key = auth_field(&decoded, "api_key").ok_or("missing api_key")?;
+    let signature = auth_field(&decoded, "signature").ok_or("missing signature")?;
+
+    if api_key != API_KEY {
+        return Err("unknown api_key".to_string());
+    }
+
+    let signature_string = format!(
+        "host: {}\ndate: {}\nGET {} HTTP/1.1",
+        host,
+        date,
+        request.uri().path()
+    );
+    let mut mac = Hmac::<Sha256>::new_from_slice(API_SECRET.as_bytes()).unwrap();
+    mac.update(signature_string.as_bytes());
+    let expected = STANDARD.encode(mac.finalize().into_bytes());
+
+    if signature != expected {
+        return Err("signature mismatch".to_string());
+    }
+
+    Ok(())
+}
+
+fn auth_field<'a>(authorization: &'a str, name: &str) -> Option<&'a str> {
+    let start = authorization.find(&format!("{}=\"", name))? + name.len() + 2;
+    let len = authorization[start..].find('"')?;
+    Some(&authorization[start..start + len])
+}
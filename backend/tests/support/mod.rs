@@ -0,0 +1,157 @@
+// Each integration test binary uses a different subset of these helpers
+#![allow(dead_code)]
+
+pub mod iflytek_server;
+
+use std::net::SocketAddr;
+
+use axum::{
+    http::{
+        HeaderName,
+        HeaderValue,
+    },
+    Router,
+};
+use axum_test::TestServer;
+use bytes::Bytes;
+use mandarinpath_backend::{
+    config,
+    db,
+    routes,
+    speech::SpeechEvaluationRequest,
+};
+use serde_json::{
+    json,
+    Value,
+};
+use tempfile::TempDir;
+
+pub const PASSWORD: &str = "correct-horse-battery";
+
+/// Multipart boundary of [`evaluation_body`].
+pub const BOUNDARY: &str = "----evaluation-boundary";
+
+/// Configuration for tests: the mock speech provider and default rate limits.
+pub fn test_config(database_url: impl Into<String>) -> config::Config {
+    config::Config {
+        database_url: database_url.into(),
+        frontend_url: "http://localhost:5173".to_string(),
+        port: 3000,
+        debug_mode: true,
+        verbosity: 0,
+        jwt_secret: "test-jwt-secret-key-for-testing".to_string().into(),
+        speech: config::SpeechConfig {
+            provider: config::SpeechProvider::Mock,
+            ..Default::default()
+        },
+        rate_limits: Default::default(),
+        trusted_proxies: Vec::new(),
+    }
+}
+
+/// A database file in `temp_dir`.
+pub fn database_url(temp_dir: &TempDir) -> String {
+    format!("sqlite:{}", temp_dir.path().join("test.db").display())
+}
+
+/// The API routes over a fresh database, without the CSRF layer so that POSTs
+/// reach the handlers.
+pub async fn test_server() -> (TestServer, db::Database, TempDir) {
+    test_server_with(|_| {}).await
+}
+
+/// [`test_server`] with changes to the test configuration.
+pub async fn test_server_with(
+    configure: impl FnOnce(&mut config::Config),
+) -> (TestServer, db::Database, TempDir) {
+    let (app, db, temp_dir) = test_app(configure).await;
+    (TestServer::new(app).unwrap(), db, temp_dir)
+}
+
+/// [`test_server_with`] over real connections, so that requests have a peer
+/// address, which is always `127.0.0.1`.
+pub async fn connected_test_server_with(
+    configure: impl FnOnce(&mut config::Config),
+) -> (TestServer, db::Database, TempDir) {
+    let (app, db, temp_dir) = test_app(configure).await;
+    let server = TestServer::new(app.into_make_service_with_connect_info::<SocketAddr>()).unwrap();
+    (server, db, temp_dir)
+}
+
+async fn test_app(configure: impl FnOnce(&mut config::Config)) -> (Router, db::Database, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let mut config = test_config(database_url(&temp_dir));
+    configure(&mut config);
+    let db = db::Database::new(&config.database_url)
+        .await
+        .expect("Failed to connect to database");
+
+    (routes::create_routes(db.clone(), config), db, temp_dir)
+}
+
+/// Register, returning the response body with its tokens and user.
+pub async fn register(server: &TestServer, email: &str) -> Value {
+    let response = server
+        .post("/auth/register")
+        .json(&json!({"email": email, "password": PASSWORD}))
+        .await;
+    response.assert_status_ok();
+    response.json()
+}
+
+/// The `Authorization` header for the access token of a register, login or
+/// refresh response.
+pub fn bearer(auth: &Value) -> (HeaderName, HeaderValue) {
+    (
+        HeaderName::from_static("authorization"),
+        HeaderValue::from_str(&format!(
+            "Bearer {}",
+            auth["access_token"].as_str().unwrap()
+        ))
+        .unwrap(),
+    )
+}
+
+/// A sentence evaluation of 你好 with a tenth of a second of silent 16 kHz PCM.
+pub fn nihao_request() -> SpeechEvaluationRequest {
+    SpeechEvaluationRequest {
+        audio_data: Bytes::from(vec![0u8; 3200]),
+        ref_text: "你好".to_string(),
+        lang: "cn".to_string(),
+        core: "sent".to_string(),
+        ref_pinyin: Some("ni3 hao3".to_string()),
+        phoneme_output: true,
+        audio_encoding: "raw".to_string(),
+        sample_rate: 16000,
+        channels: 1,
+        bit_depth: 16,
+    }
+}
+
+/// Multipart body for `/speech/evaluate` with the given params and a quarter
+/// second of silent 16 kHz mono WAV, separated by [`BOUNDARY`].
+pub fn evaluation_body(params: &Value) -> Vec<u8> {
+    let data_len = 8_000u32;
+    let mut body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"params\"\r\n\r\n{params}\r\n\
+         --{b}\r\nContent-Disposition: form-data; name=\"audio\"; filename=\"a.wav\"\r\n\
+         Content-Type: audio/wav\r\n\r\n",
+        b = BOUNDARY,
+    )
+    .into_bytes();
+    body.extend(b"RIFF");
+    body.extend((36 + data_len).to_le_bytes());
+    body.extend(b"WAVEfmt ");
+    body.extend(16u32.to_le_bytes());
+    body.extend(1u16.to_le_bytes());
+    body.extend(1u16.to_le_bytes());
+    body.extend(16_000u32.to_le_bytes());
+    body.extend(32_000u32.to_le_bytes());
+    body.extend(2u16.to_le_bytes());
+    body.extend(16u16.to_le_bytes());
+    body.extend(b"data");
+    body.extend(data_len.to_le_bytes());
+    body.resize(body.len() + data_len as usize, 0);
+    body.extend(format!("\r\n--{}--\r\n", BOUNDARY).into_bytes());
+    body
+}
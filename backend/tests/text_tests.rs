@@ -0,0 +1,135 @@
+mod support;
+
+use mandarinpath_backend::text::{
+    SegmentKind,
+    Segmenter,
+};
+use serde_json::{
+    json,
+    Value,
+};
+
+fn split(text: &str) -> String {
+    Segmenter::bundled()
+        .segment(text)
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[test]
+fn test_segments_common_sentences() {
+    let cases = [
+        ("我们去银行取钱吧。", "我们/去/银行/取钱/吧/。"),
+        ("他长大了以后想当医生", "他/长大/了/以后/想/当/医生"),
+        ("你觉得这个问题怎么样？", "你/觉得/这个/问题/怎么样/？"),
+        ("中华人民共和国", "中华/人民/共和国"),
+        ("对不起，我不知道", "对不起/，/我/不/知道"),
+    ];
+    for (text, expected) in cases {
+        assert_eq!(split(text), expected);
+    }
+}
+
+#[test]
+fn test_frequency_resolves_ambiguity() {
+    // 研究生 and 和尚 are words, but the likelier split avoids them
+    assert_eq!(split("研究生命的起源"), "研究/生命/的/起源");
+    assert_eq!(split("结婚的和尚未结婚的"), "结婚/的/和/尚未/结婚/的");
+    assert_eq!(split("他是研究生"), "他/是/研究生");
+}
+
+#[test]
+fn test_segment_details() {
+    let segments = Segmenter::bundled().segment("我在iPhone 15上看电影…… 好");
+    let kinds: Vec<(&str, SegmentKind, usize)> = segments
+        .iter()
+        .map(|segment| (segment.text.as_str(), segment.kind, segment.start))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            ("我", SegmentKind::Word, 0),
+            ("在", SegmentKind::Word, 1),
+            ("iPhone", SegmentKind::Other, 2),
+            (" ", SegmentKind::Space, 8),
+            ("15", SegmentKind::Other, 9),
+            ("上", SegmentKind::Word, 11),
+            ("看", SegmentKind::Word, 12),
+            ("电影", SegmentKind::Word, 13),
+            ("……", SegmentKind::Punctuation, 15),
+            (" ", SegmentKind::Space, 17),
+            ("好", SegmentKind::Word, 18),
+        ]
+    );
+
+    let movie = &segments[7];
+    assert_eq!(movie.pinyin.as_deref(), Some("dian4 ying3"));
+    assert_eq!(movie.pinyin_marked.as_deref(), Some("diàn yǐng"));
+    assert_eq!(movie.english.as_deref(), Some("movie"));
+    assert!(movie.in_lexicon);
+
+    // Polyphones are read by the word they were segmented into
+    let bank = &Segmenter::bundled().segment("银行")[0];
+    assert_eq!(bank.pinyin.as_deref(), Some("yin2 hang2"));
+}
+
+#[test]
+fn test_custom_lexicon() {
+    let segmenter = Segmenter::from_tsv(
+        "# word\tfrequency\tenglish\n北京\t100\n大学\t100\tuniversity\n北京大学\t50\tPeking University\n京大\tmany\n",
+        &["学生\txue2 sheng5\tstudent\n大学\tda4 xue2\tcollege\n"],
+    );
+    // Fewer, longer words win unless the pieces are far more frequent
+    assert_eq!(segmenter.words("北京大学生"), ["北京大学", "生"]);
+    assert_eq!(segmenter.words("学生们"), ["学生", "们"]);
+
+    // The lexicon's gloss wins over the word lists', which fill in missing ones
+    assert_eq!(segmenter.english("大学"), Some("university"));
+    assert_eq!(segmenter.english("北京"), None);
+    assert_eq!(segmenter.english("学生"), Some("student"));
+    // Malformed frequencies are skipped
+    assert!(!segmenter.contains("京大"));
+}
+
+#[tokio::test]
+async fn test_segment_endpoint() {
+    let (server, _db, _temp_dir) = support::test_server().await;
+
+    let response = server
+        .post("/text/segment")
+        .json(&json!({"text": "我喜欢饕餮，饕餮！"}))
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["word_count"], 6);
+    assert_eq!(body["unknown_words"], json!(["饕", "餮"]));
+    assert_eq!(
+        body["segments"][1],
+        json!({
+            "text": "喜欢",
+            "kind": "word",
+            "start": 1,
+            "pinyin": "xi3 huan5",
+            "pinyin_marked": "xǐ huan",
+            "english": "to like",
+            "in_lexicon": true
+        })
+    );
+    assert_eq!(
+        body["segments"][4],
+        json!({"text": "，", "kind": "punctuation", "start": 5, "in_lexicon": false})
+    );
+
+    let response = server
+        .post("/text/segment")
+        .json(&json!({"text": "  "}))
+        .await;
+    response.assert_status_bad_request();
+    let response = server
+        .post("/text/segment")
+        .json(&json!({"text": "好".repeat(20_001)}))
+        .await;
+    response.assert_status_bad_request();
+}
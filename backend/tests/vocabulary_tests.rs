@@ -0,0 +1,308 @@
+mod support;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use mandarinpath_backend::dictionary::DictionaryService;
+use serde_json::{
+    json,
+    Value,
+};
+use support::{
+    bearer,
+    register,
+    test_server,
+};
+
+const SAMPLE: &str = "\
+你好 你好 [ni3 hao3] /hello/hi/
+銀行 银行 [yin2 hang2] /bank/CL:家[jia1],個|个[ge4]/
+綠色 绿色 [lu:4 se4] /green/
+";
+
+async fn add_word(server: &TestServer, auth: &Value, word: Value) -> Value {
+    let (name, value) = bearer(auth);
+    let response = server
+        .post("/words")
+        .add_header(name, value)
+        .json(&word)
+        .await;
+    response.assert_status_ok();
+    response.json()
+}
+
+#[tokio::test]
+async fn test_word_crud() {
+    let (server, _db, _temp_dir) = test_server().await;
+    let auth = register(&server, "words@example.com").await;
+    let (name, value) = bearer(&auth);
+
+    server
+        .get("/words")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let word = add_word(
+        &server,
+        &auth,
+        json!({"chinese": "你好", "pinyin": "ni3hao3", "definition": "hello"}),
+    )
+    .await;
+    let id = word["id"].as_str().unwrap().to_string();
+    assert_eq!(word["pinyin"], "nǐ hǎo");
+    assert_eq!(word["difficulty"], "beginner");
+    assert_eq!(word["strength"], 0);
+    assert_eq!(word["totalAttempts"], 0);
+    assert!(word["addedDate"].is_string());
+    assert!(word["nextReview"].is_null());
+    assert_eq!(
+        word["tasks"],
+        json!({
+            "speaking": {"completed": false, "lastScore": null, "attempts": 0},
+            "reading": {"completed": false, "timesEncountered": 0},
+            "writing": {"completed": false, "lastScore": null, "attempts": 0},
+        })
+    );
+
+    // The same word twice is a conflict, and bad pinyin is rejected
+    let response = server
+        .post("/words")
+        .add_header(name.clone(), value.clone())
+        .json(&json!({"chinese": "你好", "definition": "hi"}))
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+    assert_eq!(response.json::<Value>()["code"], "CONFLICT");
+    server
+        .post("/words")
+        .add_header(name.clone(), value.clone())
+        .json(&json!({"chinese": "谢谢", "pinyin": "xiex", "definition": "thanks"}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let response = server
+        .put(&format!("/words/{}", id))
+        .add_header(name.clone(), value.clone())
+        .json(&json!({
+            "strength": 40,
+            "correctAttempts": 2,
+            "totalAttempts": 3,
+            "lastReviewed": "2024-03-01T10:00:00Z",
+            "tasks": {"speaking": {"completed": true, "lastScore": 82.5, "attempts": 3}},
+        }))
+        .await;
+    response.assert_status_ok();
+    let updated: Value = response.json();
+    assert_eq!(updated["definition"], "hello");
+    assert_eq!(updated["strength"], 40);
+    assert_eq!(updated["correctAttempts"], 2);
+    assert_eq!(updated["lastReviewed"], "2024-03-01T10:00:00Z");
+    assert_eq!(updated["tasks"]["speaking"]["lastScore"], 82.5);
+    assert_eq!(updated["tasks"]["reading"]["timesEncountered"], 0);
+
+    server
+        .put(&format!("/words/{}", id))
+        .add_header(name.clone(), value.clone())
+        .json(&json!({"strength": 101}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Review times are kept when left out and cleared with null
+    let update = |body: Value| {
+        server
+            .put(&format!("/words/{}", id))
+            .add_header(name.clone(), value.clone())
+            .json(&body)
+    };
+    let updated: Value = update(json!({"strength": 45})).await.json();
+    assert_eq!(updated["lastReviewed"], "2024-03-01T10:00:00Z");
+    let updated: Value = update(json!({"lastReviewed": null})).await.json();
+    assert!(updated["lastReviewed"].is_null());
+    assert_eq!(updated["strength"], 45);
+
+    // Another user can neither see nor change the word
+    let other = register(&server, "other@example.com").await;
+    let (other_name, other_value) = bearer(&other);
+    server
+        .get(&format!("/words/{}", id))
+        .add_header(other_name.clone(), other_value.clone())
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    server
+        .delete(&format!("/words/{}", id))
+        .add_header(other_name, other_value)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    server
+        .delete(&format!("/words/{}", id))
+        .add_header(name.clone(), value.clone())
+        .await
+        .assert_status_ok();
+    server
+        .get(&format!("/words/{}", id))
+        .add_header(name, value)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_list_words_filters() {
+    let (server, _db, _temp_dir) = test_server().await;
+    let auth = register(&server, "filters@example.com").await;
+    let (name, value) = bearer(&auth);
+
+    for (chinese, difficulty) in [
+        ("你好", "beginner"),
+        ("银行", "intermediate"),
+        ("绿色", "beginner"),
+    ] {
+        add_word(
+            &server,
+            &auth,
+            json!({"chinese": chinese, "definition": "-", "difficulty": difficulty}),
+        )
+        .await;
+    }
+    let words: Value = server
+        .get("/words")
+        .add_header(name.clone(), value.clone())
+        .await
+        .json();
+    assert_eq!(words["total"], 3);
+    let id_of = |chinese: &str| {
+        words["words"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|word| word["chinese"] == chinese)
+            .unwrap()["id"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+
+    // Scheduled far ahead, so no longer due
+    server
+        .put(&format!("/words/{}", id_of("绿色")))
+        .add_header(name.clone(), value.clone())
+        .json(&json!({"nextReview": "2999-01-01T00:00:00Z"}))
+        .await
+        .assert_status_ok();
+
+    let list = |query: &'static [(&'static str, &'static str)]| {
+        let request = query.iter().fold(
+            server.get("/words").add_header(name.clone(), value.clone()),
+            |request, (key, value)| request.add_query_param(key, value),
+        );
+        async move {
+            let body: Value = request.await.json();
+            let mut words: Vec<String> = body["words"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|word| word["chinese"].as_str().unwrap().to_string())
+                .collect();
+            words.sort();
+            (words, body["total"].as_i64().unwrap())
+        }
+    };
+
+    assert_eq!(
+        list(&[("difficulty", "beginner")]).await,
+        (vec!["你好".to_string(), "绿色".to_string()], 2)
+    );
+    assert_eq!(
+        list(&[("due", "true")]).await,
+        (vec!["你好".to_string(), "银行".to_string()], 2)
+    );
+    assert_eq!(
+        list(&[("difficulty", "beginner"), ("due", "true")]).await,
+        (vec!["你好".to_string()], 1)
+    );
+    assert_eq!(list(&[("due_before", "3000-01-01T00:00:00Z")]).await.1, 3);
+
+    let page: Value = server
+        .get("/words")
+        .add_query_param("limit", 1)
+        .add_query_param("offset", 1)
+        .add_header(name, value)
+        .await
+        .json();
+    assert_eq!(page["words"].as_array().unwrap().len(), 1);
+    assert_eq!(page["total"], 3);
+}
+
+#[tokio::test]
+async fn test_bulk_add_from_dictionary() {
+    let (server, db, _temp_dir) = test_server().await;
+    DictionaryService::new(db.clone())
+        .import_cedict(SAMPLE)
+        .await
+        .unwrap();
+    let auth = register(&server, "bulk@example.com").await;
+    let (name, value) = bearer(&auth);
+
+    let search = |hanzi: &'static str| {
+        let request = server
+            .get("/dictionary/search")
+            .add_query_param("hanzi", hanzi);
+        async move {
+            request.await.json::<Value>()["entries"][0]["id"]
+                .as_i64()
+                .unwrap()
+        }
+    };
+    let (hello, bank, green) = (
+        search("你好").await,
+        search("银行").await,
+        search("绿色").await,
+    );
+
+    add_word(
+        &server,
+        &auth,
+        json!({"chinese": "你好", "definition": "hello there"}),
+    )
+    .await;
+
+    let response = server
+        .post("/words/bulk")
+        .add_header(name.clone(), value.clone())
+        .json(&json!({"entryIds": [bank, hello, green], "difficulty": "intermediate"}))
+        .await;
+    response.assert_status_ok();
+    let body: Value = response.json();
+    assert_eq!(body["skipped"], json!(["你好"]));
+    let added = body["added"].as_array().unwrap();
+    assert_eq!(added.len(), 2);
+    assert_eq!(added[0]["chinese"], "银行");
+    assert_eq!(added[0]["pinyin"], "yín háng");
+    assert_eq!(added[0]["definition"], "bank");
+    assert_eq!(added[0]["difficulty"], "intermediate");
+    assert_eq!(added[1]["pinyin"], "lǜ sè");
+
+    let response = server
+        .post("/words/bulk")
+        .add_header(name, value)
+        .json(&json!({"entryIds": [bank, 999_999]}))
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    // A word that fails to store leaves the rest of the batch out too
+    sqlx::query(
+        "CREATE TRIGGER refuse_green BEFORE INSERT ON user_words WHEN NEW.chinese = '绿色'
+         BEGIN SELECT RAISE(ABORT, 'refused'); END",
+    )
+    .execute(db.pool())
+    .await
+    .unwrap();
+    let other = register(&server, "bulk-other@example.com").await;
+    let (name, value) = bearer(&other);
+    server
+        .post("/words/bulk")
+        .add_header(name.clone(), value.clone())
+        .json(&json!({"entryIds": [bank, green]}))
+        .await
+        .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+    let page: Value = server.get("/words").add_header(name, value).await.json();
+    assert_eq!(page["total"], 0);
+}